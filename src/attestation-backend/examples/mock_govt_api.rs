@@ -0,0 +1,30 @@
+// Mock government API for running the whole pipeline offline.
+//
+//     MOCK_GOVT_API_SCENARIO=valid cargo run --example mock_govt_api --features mock-govt-api
+//
+// then start the server with GOVT_API_AUTH_URL=http://127.0.0.1:8089/authenticate and
+// GOVT_API_BASE_URL=http://127.0.0.1:8089. Scenarios: valid, name_mismatch, dob_mismatch,
+// deactivated, invalid_pan, rate_limited, unavailable. MOCK_GOVT_API_PANS=PAN=scenario,... overrides per PAN.
+use anyhow::{Result, anyhow};
+use attestation_server::mock_govt_api::{MockGovtApi, MockScenario};
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    tracing_subscriber::fmt::init();
+
+    let addr = std::env::var("MOCK_GOVT_API_ADDR").unwrap_or_else(|_| "127.0.0.1:8089".to_string());
+    let default = MockScenario::parse(&std::env::var("MOCK_GOVT_API_SCENARIO").unwrap_or_else(|_| "valid".to_string()))?;
+
+    let mut mock = MockGovtApi::new(default);
+    for entry in std::env::var("MOCK_GOVT_API_PANS").unwrap_or_default().split(',').filter(|e| !e.trim().is_empty()) {
+        let (pan, scenario) = entry
+            .split_once('=')
+            .ok_or_else(|| anyhow!("Invalid MOCK_GOVT_API_PANS entry (expected PAN=scenario): {}", entry))?;
+        mock = mock.with_pan(pan, MockScenario::parse(scenario)?);
+    }
+
+    let (local, server) = mock.serve(&addr).await?;
+    println!("Mock government API on http://{} (default scenario: {:?})", local, default);
+    server.await?;
+    Ok(())
+}
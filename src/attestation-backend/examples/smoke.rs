@@ -0,0 +1,64 @@
+// Smoke test for the signed-response flow: no Redis, Sui or government API needed.
+//
+//     cargo run --example smoke
+use attestation_server::api_error::ApiJson;
+use attestation_server::app::{process_kyc, KYCRequest};
+use attestation_server::common::{verify_signed_response, ProcessDataRequest};
+use attestation_server::content_negotiation::{Negotiated, ResponseFormat};
+use attestation_server::kyc_jobs::KycAttestation;
+use attestation_server::AppState;
+use axum::extract::State;
+use base64::{engine::general_purpose, Engine as _};
+use fastcrypto::ed25519::Ed25519KeyPair;
+use fastcrypto::traits::KeyPair;
+use std::sync::Arc;
+
+async fn run() -> Result<(), String> {
+    let state = Arc::new(AppState::new(Ed25519KeyPair::generate(&mut rand::thread_rng())));
+    let public_key = state.eph_kp.public().clone();
+
+    // The demo "encryption" is base64; five face frames are required to pass
+    let encrypt = |bytes: &[u8]| general_purpose::STANDARD.encode(bytes);
+    let request = ProcessDataRequest {
+        payload: KYCRequest {
+            encrypted_doc: encrypt(b"demo identity document"),
+            encrypted_faces: (0..5).map(|i| encrypt(format!("face frame {}", i).as_bytes())).collect(),
+            encrypted_session_key: encrypt(b"demo session key"),
+            wallet_address: format!("0x{}", "a".repeat(64)),
+            enc_scheme: None,
+        },
+    };
+
+    let Negotiated(_, attestation) = process_kyc(State(state), None, ResponseFormat::Json, ApiJson(request))
+        .await
+        .map_err(|e| format!("process_kyc failed: {:?}", e))?;
+
+    let KycAttestation::Verified(signed) = attestation else {
+        return Err("demo payload was not verified".to_string());
+    };
+    verify_signed_response(&public_key, &signed).map_err(|e| format!("{:?}", e))?;
+
+    // A tampered response must not verify
+    let mut tampered = signed.clone();
+    tampered.response.data.wallet_address = format!("0x{}", "b".repeat(64));
+    if verify_signed_response(&public_key, &tampered).is_ok() {
+        return Err("tampered response verified".to_string());
+    }
+    Ok(())
+}
+
+#[tokio::main]
+async fn main() {
+    match run().await {
+        Ok(()) => println!("PASS: signed response verifies against the enclave key"),
+        Err(e) => {
+            println!("FAIL: {}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+#[tokio::test]
+async fn smoke() {
+    run().await.unwrap();
+}
@@ -0,0 +1,141 @@
+// Uniform error body returned by every endpoint: { code, message, request_id, retryable }
+use axum::async_trait;
+use axum::extract::{FromRequest, Request};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+use crate::common::TimestampError;
+use crate::EnclaveError;
+
+/// Error body clients can parse without knowing which endpoint produced it. `code` is a stable
+/// machine-readable string; `message` is for humans and may change. `request_id` is filled in by
+/// [`crate::request_id::request_id_middleware`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ApiError {
+    #[serde(skip)]
+    pub status: StatusCode,
+    pub code: String,
+    pub message: String,
+    pub request_id: Option<String>,
+    /// Whether the same request may succeed if sent again later.
+    pub retryable: bool,
+}
+
+impl ApiError {
+    /// An error whose `retryable` flag follows the status: 429, 502, 503 and 504 are retryable.
+    pub fn new(status: StatusCode, code: &str, message: impl Into<String>) -> Self {
+        Self {
+            status,
+            code: code.to_string(),
+            message: message.into(),
+            request_id: None,
+            retryable: matches!(status.as_u16(), 429 | 502 | 503 | 504),
+        }
+    }
+
+    pub fn retryable(mut self, retryable: bool) -> Self {
+        self.retryable = retryable;
+        self
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        (self.status, Json(self)).into_response()
+    }
+}
+
+impl From<EnclaveError> for ApiError {
+    fn from(e: EnclaveError) -> Self {
+        match e {
+            EnclaveError::GenericError(m) => ApiError::new(StatusCode::BAD_REQUEST, "bad_request", m),
+            EnclaveError::InvalidBody(m) => ApiError::new(StatusCode::BAD_REQUEST, "invalid_body", m),
+            EnclaveError::DecryptionFailed(m) => ApiError::new(StatusCode::BAD_REQUEST, "decryption_failed", m),
+            EnclaveError::NotAcceptable(m) => ApiError::new(StatusCode::NOT_ACCEPTABLE, "not_acceptable", m),
+            EnclaveError::NotFound(m) => ApiError::new(StatusCode::NOT_FOUND, "not_found", m),
+            EnclaveError::Unauthorized(m) => ApiError::new(StatusCode::UNAUTHORIZED, "unauthorized", m),
+            EnclaveError::RateLimited(m) => ApiError::new(StatusCode::TOO_MANY_REQUESTS, "rate_limited", m),
+            // The key may sign again later, e.g. once a rotation completes
+            EnclaveError::SigningFailed(m) => {
+                ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, "signing_failed", m).retryable(true)
+            }
+            EnclaveError::Upstream(m) => ApiError::new(StatusCode::BAD_GATEWAY, "upstream_unavailable", m),
+            EnclaveError::Internal(m) => ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, "internal_error", m),
+            EnclaveError::Timestamp(e @ TimestampError::TooOld { .. }) => {
+                ApiError::new(StatusCode::BAD_REQUEST, "timestamp_too_old", e.to_string())
+            }
+            EnclaveError::Timestamp(e @ TimestampError::InFuture { .. }) => {
+                ApiError::new(StatusCode::BAD_REQUEST, "timestamp_in_future", e.to_string())
+            }
+        }
+    }
+}
+
+/// `Json` extractor whose rejections use the [`ApiError`] body instead of axum's plain text.
+pub struct ApiJson<T>(pub T);
+
+#[async_trait]
+impl<T, S> FromRequest<S> for ApiJson<T>
+where
+    T: DeserializeOwned,
+    S: Send + Sync,
+{
+    type Rejection = EnclaveError;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        match Json::<T>::from_request(req, state).await {
+            Ok(Json(value)) => Ok(ApiJson(value)),
+            Err(rejection) => Err(EnclaveError::InvalidBody(rejection.body_text())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::request_id::{request_id_middleware, REQUEST_ID_HEADER};
+    use axum::body::{to_bytes, Body};
+    use axum::middleware::from_fn;
+    use axum::routing::get;
+    use axum::Router;
+    use tower::Service;
+
+    async fn error_body(route: &'static str, error: fn() -> EnclaveError) -> (StatusCode, serde_json::Value) {
+        let mut app = Router::new()
+            .route(route, get(move || async move { Err::<(), _>(error()) }))
+            .layer(from_fn(request_id_middleware));
+        let request = axum::http::Request::get(route)
+            .header(REQUEST_ID_HEADER, "req-1")
+            .body(Body::empty())
+            .unwrap();
+        let response = app.call(request).await.unwrap();
+        let status = response.status();
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+
+        // Exactly the four documented fields, whatever the error
+        let mut keys: Vec<_> = json.as_object().unwrap().keys().cloned().collect();
+        keys.sort();
+        assert_eq!(keys, ["code", "message", "request_id", "retryable"], "{}", route);
+        assert_eq!(json["request_id"], "req-1");
+        (status, json)
+    }
+
+    #[tokio::test]
+    async fn test_error_schema_for_decryption_and_upstream_failures() {
+        let (status, json) =
+            error_body("/decrypt", || EnclaveError::DecryptionFailed("Decryption failed: bad base64".to_string())).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(json["code"], "decryption_failed");
+        assert_eq!(json["message"], "Decryption failed: bad base64");
+        assert_eq!(json["retryable"], false);
+
+        let (status, json) = error_body("/upstream", || EnclaveError::Upstream("NSM unavailable".to_string())).await;
+        assert_eq!(status, StatusCode::BAD_GATEWAY);
+        assert_eq!(json["code"], "upstream_unavailable");
+        assert_eq!(json["retryable"], true);
+    }
+}
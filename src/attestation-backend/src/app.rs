@@ -1,15 +1,27 @@
 // app.rs
-use crate::common::{to_signed_response, IntentScope, ProcessDataRequest, ProcessedDataResponse};
+use crate::api_error::{ApiError, ApiJson};
+use crate::common::{to_signed_response, IntentScope, ProcessDataRequest};
+use crate::content_negotiation::{Negotiated, ResponseFormat};
+use crate::kyc_jobs::{KycAttestation, KycJobStatus};
+use crate::negative_attestation::sign_negative_attestation;
+use crate::metrics;
+use crate::request_id::{new_request_id, RequestId};
+use crate::verification_types::validate_sui_address;
 use crate::{AppState, EnclaveError};
-use axum::extract::State;
-use axum::Json;
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::{Extension, Json};
+use serde_json::json;
 use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
 use std::sync::Arc;
+use sha2::{Digest, Sha256};
+use tracing::warn;
 use base64::{Engine as _, engine::general_purpose};
 use fastcrypto::ed25519::Ed25519KeyPair;
 use fastcrypto::traits::KeyPair as FcKeyPair;
 use fastcrypto::traits::ToFromBytes;
-use crate::common::IntentMessage;
 
 
 // Add KYC structures and functions
@@ -19,6 +31,51 @@ pub struct KYCRequest {
     pub encrypted_faces: Vec<String>,
     pub encrypted_session_key: String,
     pub wallet_address: String,
+    /// How the encrypted fields were produced, see [`EncryptionScheme`]. Absent means the
+    /// original demo scheme, so clients predating the field keep working.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub enc_scheme: Option<String>,
+}
+
+/// Encryption applied to a KYC request's fields, declared by the client in `enc_scheme`.
+/// New schemes are added as variants; the tags of existing ones never change.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EncryptionScheme {
+    /// `demo-base64-v1`: base64 only, no actual encryption.
+    DemoBase64V1,
+}
+
+impl EncryptionScheme {
+    pub const ALL: [EncryptionScheme; 1] = [EncryptionScheme::DemoBase64V1];
+
+    pub fn tag(self) -> &'static str {
+        match self {
+            EncryptionScheme::DemoBase64V1 => "demo-base64-v1",
+        }
+    }
+
+    /// The scheme named by `tag`, or the demo scheme if none was given.
+    pub fn resolve(tag: Option<&str>) -> Result<Self, EnclaveError> {
+        let Some(tag) = tag.map(str::trim) else { return Ok(EncryptionScheme::DemoBase64V1) };
+        Self::ALL
+            .into_iter()
+            .find(|scheme| scheme.tag().eq_ignore_ascii_case(tag))
+            .ok_or_else(|| {
+                metrics::increment("kyc_unsupported_enc_scheme_total");
+                let supported: Vec<&str> = Self::ALL.iter().map(|scheme| scheme.tag()).collect();
+                EnclaveError::InvalidBody(format!(
+                    "Unsupported enc_scheme '{}' (supported: {})",
+                    tag,
+                    supported.join(", ")
+                ))
+            })
+    }
+
+    pub fn decrypt(self, encrypted: &str) -> Result<Vec<u8>, EnclaveError> {
+        match self {
+            EncryptionScheme::DemoBase64V1 => decrypt_demo(encrypted),
+        }
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -28,56 +85,265 @@ pub struct KYCResponse {
     pub attestation_hash: String,
 }
 
+/// `did_id` recorded on the negative attestation for a rejected KYC request; KYC has no entry
+/// in the verification-type table, so this is kept clear of its ids.
+pub const KYC_DID_ID: u8 = u8::MAX;
+
+/// Outcome of a verification that ran. Either way it is signed and returned with 200; a
+/// request that never got this far is an unsigned [`EnclaveError`] instead.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum IdentityDecision {
+    Verified,
+    Rejected(String),
+}
+
+/// A request that can't be verified (malformed, undecryptable) fails with an unsigned
+/// [`crate::api_error::ApiError`]. Once verification runs, its result is signed and returned
+/// with 200, including a rejection: a [`crate::negative_attestation::NegativeAttestation`]
+/// signed under [`IntentScope::NegativeVerification`].
 pub async fn process_kyc(
     State(state): State<Arc<AppState>>,
-    Json(request): Json<ProcessDataRequest<KYCRequest>>,
-) -> Result<Json<ProcessedDataResponse<IntentMessage<KYCResponse>>>, EnclaveError>{
+    request_id: Option<Extension<RequestId>>,
+    format: ResponseFormat,
+    ApiJson(request): ApiJson<ProcessDataRequest<KYCRequest>>,
+) -> Result<Negotiated<KycAttestation>, EnclaveError>{
     let kyc_data = &request.payload;
-    
-    // For demo, simple decryption (in production, use proper crypto)
-    let doc_data = decrypt_demo(&kyc_data.encrypted_doc)?;
+    let (doc_data, face_frames) = decrypt_request(kyc_data)?;
+
+    let mut signed = attest_kyc(&state, &kyc_data.wallet_address, doc_data, face_frames)?;
+    signed.set_request_id(request_id.map(|Extension(RequestId(id))| id));
+    Ok(Negotiated(format, signed))
+}
+
+/// Like [`process_kyc`], but returns 202 with a polling token once the request is validated;
+/// the attestation is then served by [`get_verification_result`].
+pub async fn process_kyc_async(
+    State(state): State<Arc<AppState>>,
+    request_id: Option<Extension<RequestId>>,
+    ApiJson(request): ApiJson<ProcessDataRequest<KYCRequest>>,
+) -> Result<Response, EnclaveError> {
+    let kyc_data = request.payload;
+    // Undecryptable input is rejected now rather than surfacing only on poll
+    let (doc_data, face_frames) = decrypt_request(&kyc_data)?;
+
+    let token = new_request_id();
+    state
+        .kyc_jobs
+        .put(&token, &KycJobStatus::Pending)
+        .await
+        .map_err(|e| EnclaveError::Upstream(format!("KYC job store unavailable: {}", e)))?;
+    let request_id = request_id.map(|Extension(RequestId(id))| id);
+    let job_token = token.clone();
+    tokio::spawn(async move {
+        let status = match attest_kyc(&state, &kyc_data.wallet_address, doc_data, face_frames) {
+            Ok(mut signed) => {
+                signed.set_request_id(request_id);
+                KycJobStatus::Completed { attestation: signed }
+            }
+            Err(e) => KycJobStatus::failed(ApiError::from(e)),
+        };
+        if let Err(e) = state.kyc_jobs.put(&job_token, &status).await {
+            warn!("Failed to record the outcome of KYC job {}: {}", job_token, e);
+        }
+    });
+
+    let body = Json(json!({
+        "token": token,
+        "status_url": format!("/verification_result/{}", token),
+    }));
+    Ok((StatusCode::ACCEPTED, body).into_response())
+}
+
+/// Result of an asynchronous KYC job: the signed attestation once completed, 202 while pending,
+/// and the error [`process_kyc`] would have returned if it failed.
+pub async fn get_verification_result(
+    State(state): State<Arc<AppState>>,
+    Path(token): Path<String>,
+    format: ResponseFormat,
+) -> Result<Response, EnclaveError> {
+    let status = state
+        .kyc_jobs
+        .get(&token)
+        .await
+        .map_err(|e| EnclaveError::Upstream(format!("KYC job store unavailable: {}", e)))?;
+    match status {
+        Some(KycJobStatus::Completed { attestation }) => Ok(Negotiated(format, attestation).into_response()),
+        Some(KycJobStatus::Pending) => Ok((StatusCode::ACCEPTED, Json(KycJobStatus::Pending)).into_response()),
+        Some(KycJobStatus::Failed { http_status, mut error }) => {
+            error.status = StatusCode::from_u16(http_status).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+            Ok(error.into_response())
+        }
+        None => Err(EnclaveError::NotFound(format!("Unknown or expired token: {}", token))),
+    }
+}
+
+/// Smallest and largest decoded session key accepted: a 128-bit symmetric key up to one
+/// wrapped under RSA-4096.
+const SESSION_KEY_BYTES: std::ops::RangeInclusive<usize> = 16..=512;
+
+/// `value` must hold something other than whitespace.
+fn require_non_empty(field: &str, value: &str) -> Result<(), EnclaveError> {
+    if value.trim().is_empty() {
+        return Err(EnclaveError::InvalidBody(format!("{} is empty", field)));
+    }
+    Ok(())
+}
+
+fn decrypt_request(kyc_data: &KYCRequest) -> Result<(Vec<u8>, Vec<Vec<u8>>), EnclaveError> {
+    validate_sui_address(&kyc_data.wallet_address).map_err(|e| EnclaveError::InvalidBody(e.reason))?;
+    // Empty input decodes to empty bytes, which would otherwise be verified as if it were data
+    require_non_empty("encrypted_doc", &kyc_data.encrypted_doc)?;
+    require_non_empty("encrypted_session_key", &kyc_data.encrypted_session_key)?;
+    for (index, frame) in kyc_data.encrypted_faces.iter().enumerate() {
+        require_non_empty(&format!("encrypted_faces[{}]", index), frame)?;
+    }
+    let scheme = EncryptionScheme::resolve(kyc_data.enc_scheme.as_deref())?;
+    let session_key = scheme.decrypt(&kyc_data.encrypted_session_key)?;
+    if !SESSION_KEY_BYTES.contains(&session_key.len()) {
+        return Err(EnclaveError::InvalidBody(format!(
+            "encrypted_session_key is {} bytes, expected {} to {}",
+            session_key.len(), SESSION_KEY_BYTES.start(), SESSION_KEY_BYTES.end()
+        )));
+    }
+
+    let doc_data = scheme.decrypt(&kyc_data.encrypted_doc)?;
     let face_frames: Vec<Vec<u8>> = kyc_data.encrypted_faces
         .iter()
-        .map(|f| decrypt_demo(f))
+        .map(|f| scheme.decrypt(f))
         .collect::<Result<Vec<_>, _>>()?;
-    
+    Ok((doc_data, face_frames))
+}
+
+fn attest_kyc(
+    state: &AppState,
+    wallet_address: &str,
+    doc_data: Vec<u8>,
+    face_frames: Vec<Vec<u8>>,
+) -> Result<KycAttestation, EnclaveError> {
     // Verify faces match and liveness
-    let verification_result = verify_identity(doc_data, face_frames)?;
+    let decision = verify_identity(doc_data, face_frames, &LivenessRules::from_env(), state.face_detector.as_ref());
+    let verification_result = decision == IdentityDecision::Verified;
     
     // Generate attestation
     let attestation_hash = generate_attestation_hash(&state.eph_kp, &verification_result)?;
- 
-    
-    let response = KYCResponse {
-        verified: verification_result,
-        wallet_address: kyc_data.wallet_address.clone(),
-        attestation_hash,
-    };
+    let timestamp_ms = current_timestamp()?;
 
-    Ok(Json(to_signed_response(
-        &state.eph_kp,
-        response,
-        current_timestamp()?,
-        IntentScope::KYCVerification,
-    )))
+    match decision {
+        IdentityDecision::Verified => {
+            let response = KYCResponse {
+                verified: true,
+                wallet_address: wallet_address.to_string(),
+                attestation_hash,
+            };
+            Ok(KycAttestation::Verified(to_signed_response(
+                &state.eph_kp,
+                response,
+                timestamp_ms,
+                IntentScope::KYCVerification,
+            )?))
+        }
+        IdentityDecision::Rejected(reason) => {
+            let verified_at = i64::try_from(timestamp_ms)
+                .ok()
+                .and_then(chrono::DateTime::from_timestamp_millis)
+                .ok_or_else(|| EnclaveError::Internal(format!("Timestamp {} out of range", timestamp_ms)))?
+                .to_rfc3339_opts(chrono::SecondsFormat::Millis, true);
+            Ok(KycAttestation::Rejected(sign_negative_attestation(
+                &state.eph_kp,
+                wallet_address,
+                KYC_DID_ID,
+                &reason,
+                &attestation_hash,
+                &verified_at,
+                timestamp_ms,
+            )?))
+        }
+    }
 }
 
 fn decrypt_demo(encrypted: &str) -> Result<Vec<u8>, EnclaveError> {
     general_purpose::STANDARD
         .decode(encrypted)
-        .map_err(|e| EnclaveError::GenericError(format!("Decryption failed: {}", e)))
+        .map_err(|e| EnclaveError::DecryptionFailed(format!("Decryption failed: {}", e)))
+}
+
+/// Tells whether a decrypted selfie frame shows a face. Plugged into [`AppState`] so a real
+/// detector can be added without the default build carrying one.
+pub trait FaceDetector: Send + Sync {
+    fn detects_face(&self, frame: &[u8]) -> bool;
+}
+
+/// The default detector: takes every frame to show a face, leaving only the count and
+/// distinctness checks.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NoFaceDetection;
+
+impl FaceDetector for NoFaceDetection {
+    fn detects_face(&self, _frame: &[u8]) -> bool {
+        true
+    }
 }
 
-fn verify_identity(doc: Vec<u8>, faces: Vec<Vec<u8>>) -> Result<bool, EnclaveError> {
-    Ok(!doc.is_empty() && faces.len() >= 5)
+/// Liveness requirements on the selfie frames.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LivenessRules {
+    /// Frames that must differ from each other (`KYC_MIN_DISTINCT_FRAMES`, default 5).
+    pub min_distinct: usize,
+    /// Frames the [`FaceDetector`] must find a face in (`KYC_MIN_FACE_FRAMES`, default 5).
+    pub min_face_frames: usize,
 }
 
-fn generate_attestation_hash(
+impl LivenessRules {
+    pub fn from_env() -> Self {
+        let count = |name: &str| std::env::var(name).ok().and_then(|v| v.parse::<usize>().ok()).unwrap_or(5);
+        Self {
+            min_distinct: count("KYC_MIN_DISTINCT_FRAMES"),
+            min_face_frames: count("KYC_MIN_FACE_FRAMES"),
+        }
+    }
+}
+
+fn verify_identity(
+    doc: Vec<u8>,
+    faces: Vec<Vec<u8>>,
+    rules: &LivenessRules,
+    detector: &dyn FaceDetector,
+) -> IdentityDecision {
+    let min_distinct = rules.min_distinct;
+    if doc.is_empty() {
+        return IdentityDecision::Rejected("document is empty".to_string());
+    }
+    if faces.len() < 5 {
+        return IdentityDecision::Rejected(format!("{} face frames, at least 5 required", faces.len()));
+    }
+    // Copies of one frame are a static or replayed submission, not a moving face
+    let distinct = faces.iter().map(Sha256::digest).collect::<HashSet<_>>().len();
+    if distinct < min_distinct {
+        metrics::increment("kyc_liveness_duplicate_frames_total");
+        warn!("Liveness failed: {} of {} face frames distinct, {} required", distinct, faces.len(), min_distinct);
+        return IdentityDecision::Rejected(format!(
+            "liveness failed: {} distinct face frames, {} required",
+            distinct, min_distinct
+        ));
+    }
+    // A covered camera or one pointed away still yields distinct frames
+    let with_face = faces.iter().filter(|frame| detector.detects_face(frame)).count();
+    if with_face < rules.min_face_frames {
+        metrics::increment("kyc_liveness_no_face_total");
+        warn!("Liveness failed: a face in {} of {} frames, {} required", with_face, faces.len(), rules.min_face_frames);
+        return IdentityDecision::Rejected(format!(
+            "liveness failed: a face in {} frames, {} required",
+            with_face, rules.min_face_frames
+        ));
+    }
+    IdentityDecision::Verified
+}
+
+/// SHA-256 of the decision and the public key that signs it.
+pub(crate) fn generate_attestation_hash(
     keypair: &Ed25519KeyPair, 
     verified: &bool
 ) -> Result<String, EnclaveError> {
-    use sha2::{Sha256, Digest};
-    
     let mut hasher = Sha256::new();
     hasher.update(verified.to_string());
     hasher.update(keypair.public().as_bytes());
@@ -89,5 +355,242 @@ fn current_timestamp() -> Result<u64, EnclaveError> {
     std::time::SystemTime::now()
         .duration_since(std::time::UNIX_EPOCH)
         .map(|d| d.as_millis() as u64)
-        .map_err(|e| EnclaveError::GenericError(format!("Time error: {}", e)))
-}
\ No newline at end of file
+        .map_err(|e| EnclaveError::Internal(format!("Time error: {}", e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::verify_signed_response;
+    use axum::body::{to_bytes, Body};
+    use axum::http::Request;
+    use axum::routing::{get, post};
+    use axum::Router;
+    use fastcrypto::traits::KeyPair;
+    use tower::Service;
+
+    async fn json_body(response: Response) -> serde_json::Value {
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        serde_json::from_slice(&body).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_async_token_resolves_to_completed_attestation() {
+        let state = Arc::new(AppState::new(Ed25519KeyPair::generate(&mut rand::thread_rng())));
+        let public_key = state.eph_kp.public().clone();
+        let mut app = Router::new()
+            .route("/process_kyc_async", post(process_kyc_async))
+            .route("/verification_result/:token", get(get_verification_result))
+            .with_state(state);
+
+        let encrypt = |bytes: &[u8]| general_purpose::STANDARD.encode(bytes);
+        let wallet = format!("0x{}", "ab".repeat(32));
+        let request = |wallet: &str| {
+            let body = json!({
+                "payload": {
+                    "encrypted_doc": encrypt(b"document"),
+                    "encrypted_faces": (0..5).map(|i| encrypt(&[i])).collect::<Vec<_>>(),
+                    "encrypted_session_key": encrypt(&[7u8; 32]),
+                    "wallet_address": wallet,
+                }
+            });
+            Request::post("/process_kyc_async")
+                .header("content-type", "application/json")
+                .body(Body::from(body.to_string()))
+                .unwrap()
+        };
+
+        // A malformed wallet is refused before any job starts
+        let refused = app.call(request("0xabc")).await.unwrap();
+        assert_eq!(refused.status(), StatusCode::BAD_REQUEST);
+
+        let response = app.call(request(&wallet)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::ACCEPTED);
+        let status_url = json_body(response).await["status_url"].as_str().unwrap().to_string();
+
+        let mut completed = None;
+        for _ in 0..50 {
+            let response = app
+                .call(Request::get(status_url.as_str()).body(Body::empty()).unwrap())
+                .await
+                .unwrap();
+            if response.status() == StatusCode::OK {
+                completed = Some(json_body(response).await);
+                break;
+            }
+            assert_eq!(response.status(), StatusCode::ACCEPTED);
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+
+        let KycAttestation::Verified(signed) =
+            serde_json::from_value(completed.expect("job never completed")).unwrap()
+        else {
+            panic!("job was not verified");
+        };
+        assert!(signed.response.data.verified);
+        assert_eq!(signed.response.data.wallet_address, wallet);
+        assert!(verify_signed_response(&public_key, &signed).is_ok());
+
+        let unknown = app
+            .call(Request::get("/verification_result/nope").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(unknown.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_failed_job_returns_the_error_body() {
+        let state = Arc::new(AppState::new(Ed25519KeyPair::generate(&mut rand::thread_rng())));
+        let failed = KycJobStatus::failed(ApiError::from(EnclaveError::SigningFailed("key unavailable".to_string())));
+        state.kyc_jobs.put("job-1", &failed).await.unwrap();
+        let mut app = Router::new()
+            .route("/verification_result/:token", get(get_verification_result))
+            .with_state(state);
+
+        let response = app
+            .call(Request::get("/verification_result/job-1").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        let body = json_body(response).await;
+        assert_eq!(body["code"], "signing_failed");
+        assert_eq!(body["message"], "key unavailable");
+        assert_eq!(body["retryable"], true);
+    }
+
+    fn kyc_request() -> KYCRequest {
+        let encrypt = |bytes: &[u8]| general_purpose::STANDARD.encode(bytes);
+        KYCRequest {
+            encrypted_doc: encrypt(b"document"),
+            encrypted_faces: (0..5).map(|i| encrypt(&[i])).collect(),
+            encrypted_session_key: encrypt(&[7u8; 32]),
+            wallet_address: format!("0x{}", "ab".repeat(32)),
+            enc_scheme: None,
+        }
+    }
+
+    fn rejection(request: &KYCRequest) -> String {
+        match decrypt_request(request) {
+            Err(EnclaveError::InvalidBody(reason)) => reason,
+            other => panic!("expected InvalidBody, got {:?}", other.map(|_| ())),
+        }
+    }
+
+    #[test]
+    fn test_declared_enc_scheme_selects_decryptor() {
+        let (doc, faces) = decrypt_request(&kyc_request()).unwrap();
+        let declared = KYCRequest { enc_scheme: Some("demo-base64-v1".to_string()), ..kyc_request() };
+        assert_eq!(decrypt_request(&declared).unwrap(), (doc, faces));
+
+        let unsupported = KYCRequest { enc_scheme: Some("x25519-aesgcm-v9".to_string()), ..kyc_request() };
+        assert_eq!(
+            rejection(&unsupported),
+            "Unsupported enc_scheme 'x25519-aesgcm-v9' (supported: demo-base64-v1)"
+        );
+    }
+
+    #[test]
+    fn test_empty_encrypted_fields_are_rejected_before_decryption() {
+        assert!(decrypt_request(&kyc_request()).is_ok());
+
+        let empty_doc = KYCRequest { encrypted_doc: "  ".to_string(), ..kyc_request() };
+        assert_eq!(rejection(&empty_doc), "encrypted_doc is empty");
+
+        let empty_key = KYCRequest { encrypted_session_key: String::new(), ..kyc_request() };
+        assert_eq!(rejection(&empty_key), "encrypted_session_key is empty");
+        let short_key = KYCRequest { encrypted_session_key: general_purpose::STANDARD.encode(b"key"), ..kyc_request() };
+        assert_eq!(rejection(&short_key), "encrypted_session_key is 3 bytes, expected 16 to 512");
+
+        let mut empty_frame = kyc_request();
+        empty_frame.encrypted_faces[2] = "\n".to_string();
+        assert_eq!(rejection(&empty_frame), "encrypted_faces[2] is empty");
+    }
+
+    #[test]
+    fn test_duplicated_face_frames_fail_liveness() {
+        let frames = |count: u8, distinct: bool| -> Vec<Vec<u8>> {
+            (0..count).map(|i| vec![if distinct { i } else { 0 }; 64]).collect()
+        };
+        let verified = |faces, min_distinct| {
+            let rules = LivenessRules { min_distinct, min_face_frames: 5 };
+            verify_identity(b"document".to_vec(), faces, &rules, &NoFaceDetection)
+        };
+        assert_eq!(verified(frames(5, true), 5), IdentityDecision::Verified);
+        assert_eq!(
+            verified(frames(5, false), 5),
+            IdentityDecision::Rejected("liveness failed: 1 distinct face frames, 5 required".to_string())
+        );
+
+        // A lower requirement tolerates some repeats, but not a single static frame
+        let mut some_repeats = frames(5, true);
+        some_repeats[4] = some_repeats[3].clone();
+        assert_eq!(verified(some_repeats, 3), IdentityDecision::Verified);
+        assert_ne!(verified(frames(5, false), 3), IdentityDecision::Verified);
+    }
+
+    /// Finds a face only in frames starting with a non-zero byte.
+    struct MockDetector;
+
+    impl FaceDetector for MockDetector {
+        fn detects_face(&self, frame: &[u8]) -> bool {
+            frame.first().is_some_and(|byte| *byte != 0)
+        }
+    }
+
+    #[test]
+    fn test_frames_without_a_detected_face_fail_liveness() {
+        let rules = LivenessRules { min_distinct: 5, min_face_frames: 4 };
+        let frames = |first_bytes: [u8; 5]| -> Vec<Vec<u8>> {
+            first_bytes.iter().enumerate().map(|(i, first)| vec![*first, i as u8, 1, 2]).collect()
+        };
+        let verify = |faces| verify_identity(b"document".to_vec(), faces, &rules, &MockDetector);
+
+        // Camera covered: distinct frames, no face in any
+        assert_eq!(
+            verify(frames([0; 5])),
+            IdentityDecision::Rejected("liveness failed: a face in 0 frames, 4 required".to_string())
+        );
+        assert_ne!(verify(frames([1, 1, 1, 0, 0])), IdentityDecision::Verified);
+        assert_eq!(verify(frames([1, 1, 1, 1, 0])), IdentityDecision::Verified);
+        // The default detector never rejects
+        let undetected = verify_identity(b"document".to_vec(), frames([0; 5]), &rules, &NoFaceDetection);
+        assert_eq!(undetected, IdentityDecision::Verified);
+    }
+
+    #[tokio::test]
+    async fn test_unprocessable_request_is_unsigned_and_rejection_is_signed() {
+        let state = Arc::new(AppState::new(Ed25519KeyPair::generate(&mut rand::thread_rng())));
+        let public_key = state.eph_kp.public().clone();
+        let mut app = Router::new().route("/process_kyc", post(process_kyc)).with_state(state);
+        let mut call = |request: KYCRequest| {
+            let body = json!({ "payload": request }).to_string();
+            app.call(
+                Request::post("/process_kyc")
+                    .header("content-type", "application/json")
+                    .body(Body::from(body))
+                    .unwrap(),
+            )
+        };
+
+        // Couldn't be processed: a plain error, nothing signed
+        let undecryptable = KYCRequest { encrypted_doc: "not base64!".to_string(), ..kyc_request() };
+        let response = call(undecryptable).await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let body = json_body(response).await;
+        assert_eq!(body["code"], "decryption_failed");
+        assert!(body.get("signature").is_none());
+
+        // Processed and rejected: a negative attestation, signed under its own intent
+        let mut static_face = kyc_request();
+        static_face.encrypted_faces = vec![general_purpose::STANDARD.encode([9u8; 16]); 5];
+        let response = call(static_face).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let KycAttestation::Rejected(signed) = serde_json::from_value(json_body(response).await).unwrap() else {
+            panic!("rejection was not a negative attestation");
+        };
+        assert!(!signed.response.data.verified);
+        assert_eq!(signed.response.data.did_id, KYC_DID_ID);
+        assert_eq!(signed.response.intent, IntentScope::NegativeVerification);
+        assert!(verify_signed_response(&public_key, &signed).is_ok());
+    }
+}
@@ -0,0 +1,265 @@
+// Signed verification attestations, stored per wallet and type and served back with a signature check
+use anyhow::{Result, anyhow};
+use axum::async_trait;
+use axum::extract::{Query, State};
+use axum::Json;
+use fastcrypto::ed25519::Ed25519PublicKey;
+use fastcrypto::encoding::{Encoding, Hex};
+use fastcrypto::traits::{KeyPair, ToFromBytes};
+use redis::aio::Connection;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tracing::warn;
+
+use crate::common::{key_id, to_signed_response, verify_signed_response, IntentMessage, IntentScope, ProcessedDataResponse};
+use crate::redis_timeout::with_timeout;
+use crate::results::VerificationResultEvent;
+use crate::signing::{EnclaveSigner, SigningError};
+use crate::verification_processor::RedisConnector;
+use crate::{AppState, EnclaveError};
+
+/// Payload of a stored attestation: the outcome of one wallet's verification of one type.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct VerificationAttestation {
+    pub user_wallet: String,
+    pub verification_type: String,
+    pub did_id: u8,
+    pub result: String,
+    pub evidence_hash: String,
+    pub evidence_schema: String,
+    pub evidence_profile: String,
+    pub verified_at: String,
+}
+
+pub type SignedVerificationAttestation = ProcessedDataResponse<IntentMessage<VerificationAttestation>>;
+
+/// Sign a processed verification under [`IntentScope::VerificationResult`]. `verification_type`
+/// is the canonical type name, so aliases of one type share a single record.
+pub fn sign_verification_attestation<S: EnclaveSigner + ?Sized>(
+    signer: &S,
+    event: &VerificationResultEvent,
+    verification_type: &str,
+    timestamp_ms: u64,
+) -> Result<SignedVerificationAttestation, SigningError> {
+    let attestation = VerificationAttestation {
+        user_wallet: event.user_wallet.clone(),
+        verification_type: verification_type.to_string(),
+        did_id: event.did_id,
+        result: event.result.clone(),
+        evidence_hash: event.evidence_hash.clone(),
+        evidence_schema: event.evidence_schema.clone(),
+        evidence_profile: event.evidence_profile.clone(),
+        verified_at: event.verified_at.clone(),
+    };
+    to_signed_response(signer, attestation, timestamp_ms, IntentScope::VerificationResult)
+}
+
+/// A signed attestation with the hex public key that signed it, which may predate the current key.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StoredAttestation {
+    pub public_key: String,
+    pub attestation: SignedVerificationAttestation,
+}
+
+/// Lookup key: wallet and type, case-insensitive.
+fn record_key(wallet: &str, verification_type: &str) -> String {
+    format!("{}:{}", wallet.trim().to_lowercase(), verification_type.trim().to_lowercase())
+}
+
+/// Latest attestation per wallet and verification type.
+#[async_trait]
+pub trait AttestationStore: Send + Sync {
+    async fn put(&self, stored: &StoredAttestation) -> Result<()>;
+    async fn get(&self, wallet: &str, verification_type: &str) -> Result<Option<StoredAttestation>>;
+}
+
+/// In-process store, for tests and runs without Redis.
+#[derive(Default)]
+pub struct MemoryAttestationStore {
+    records: Mutex<HashMap<String, StoredAttestation>>,
+}
+
+#[async_trait]
+impl AttestationStore for MemoryAttestationStore {
+    async fn put(&self, stored: &StoredAttestation) -> Result<()> {
+        let data = &stored.attestation.response.data;
+        let key = record_key(&data.user_wallet, &data.verification_type);
+        self.records.lock().unwrap().insert(key, stored.clone());
+        Ok(())
+    }
+
+    async fn get(&self, wallet: &str, verification_type: &str) -> Result<Option<StoredAttestation>> {
+        Ok(self.records.lock().unwrap().get(&record_key(wallet, verification_type)).cloned())
+    }
+}
+
+/// Redis hash `ATTESTATION_STORE_KEY` (default `attestations`) keyed by `wallet:type`.
+pub struct RedisAttestationStore {
+    redis: RedisConnector,
+    conn: tokio::sync::Mutex<Option<Connection>>,
+    hash_key: String,
+}
+
+impl RedisAttestationStore {
+    pub fn from_env(redis: RedisConnector) -> Self {
+        Self {
+            redis,
+            conn: tokio::sync::Mutex::new(None),
+            hash_key: std::env::var("ATTESTATION_STORE_KEY").unwrap_or_else(|_| "attestations".to_string()),
+        }
+    }
+
+    /// Run `command` on the shared connection, dropping the connection if Redis fails.
+    async fn query<T: redis::FromRedisValue>(&self, command: &redis::Cmd) -> Result<T> {
+        let mut guard = self.conn.lock().await;
+        if guard.is_none() {
+            *guard = Some(self.redis.connect().await?);
+        }
+        let conn = guard.as_mut().expect("connection was just established");
+        let result = with_timeout("attestation store", self.redis.timeouts().command, command.query_async(conn)).await;
+        if result.is_err() {
+            *guard = None;
+        }
+        result
+    }
+}
+
+#[async_trait]
+impl AttestationStore for RedisAttestationStore {
+    async fn put(&self, stored: &StoredAttestation) -> Result<()> {
+        let data = &stored.attestation.response.data;
+        let mut command = redis::cmd("HSET");
+        command
+            .arg(&self.hash_key)
+            .arg(record_key(&data.user_wallet, &data.verification_type))
+            .arg(serde_json::to_string(stored)?);
+        self.query::<()>(&command).await
+    }
+
+    async fn get(&self, wallet: &str, verification_type: &str) -> Result<Option<StoredAttestation>> {
+        let mut command = redis::cmd("HGET");
+        command.arg(&self.hash_key).arg(record_key(wallet, verification_type));
+        let stored: Option<String> = self.query(&command).await?;
+        stored
+            .map(|json| serde_json::from_str(&json).map_err(|e| anyhow!("Corrupt stored attestation: {}", e)))
+            .transpose()
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AttestationQuery {
+    pub wallet: String,
+    #[serde(rename = "type")]
+    pub verification_type: String,
+}
+
+/// A stored attestation with the server's check of its signature.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AttestationLookup {
+    pub attestation: SignedVerificationAttestation,
+    /// Key id of the key that signed the attestation.
+    pub key_id: String,
+    pub signature_valid: bool,
+    /// Whether that key is the one this enclave holds now.
+    pub current_key: bool,
+}
+
+/// Endpoint `/attestation?wallet=0x...&type=pan`: the stored attestation, 404 if there is none.
+/// `type` may be an alias; records are kept under the canonical type name.
+pub async fn get_stored_attestation(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<AttestationQuery>,
+) -> Result<Json<AttestationLookup>, EnclaveError> {
+    let verification_type = match state.verification_types.get(&query.verification_type) {
+        Ok(spec) => spec.verification_type.as_str(),
+        // Not in the table: only a record stored under that exact name can match
+        Err(_) => query.verification_type.as_str(),
+    };
+    let stored = state
+        .attestations
+        .get(&query.wallet, verification_type)
+        .await
+        .map_err(|e| EnclaveError::Upstream(format!("Attestation store unavailable: {}", e)))?
+        .ok_or_else(|| {
+            EnclaveError::NotFound(format!(
+                "No {} attestation for wallet {}",
+                query.verification_type, query.wallet
+            ))
+        })?;
+
+    let signer = Hex::decode(&stored.public_key)
+        .ok()
+        .and_then(|bytes| Ed25519PublicKey::from_bytes(&bytes).ok());
+    let signature_valid = match &signer {
+        Some(pk) => verify_signed_response(pk, &stored.attestation).is_ok(),
+        None => {
+            warn!("Stored attestation for {} has an unreadable public key", query.wallet);
+            false
+        }
+    };
+
+    Ok(Json(AttestationLookup {
+        key_id: signer.as_ref().map(key_id).unwrap_or_default(),
+        current_key: signer.as_ref() == Some(state.eph_kp.public()),
+        signature_valid,
+        attestation: stored.attestation,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::results::sample_event;
+    use axum::body::{to_bytes, Body};
+    use axum::http::{Request, StatusCode};
+    use axum::routing::get;
+    use axum::Router;
+    use fastcrypto::ed25519::Ed25519KeyPair;
+    use tower::Service;
+
+    #[tokio::test]
+    async fn test_stored_attestation_is_retrieved_and_verified() {
+        let state = Arc::new(AppState::new(Ed25519KeyPair::generate(&mut rand::thread_rng())));
+        let event = VerificationResultEvent {
+            user_wallet: "0xABC".to_string(),
+            verification_type: "age".to_string(),
+            ..sample_event()
+        };
+        let signed = sign_verification_attestation(&state.eph_kp, &event, "pan", 1_000).unwrap();
+        let stored = StoredAttestation {
+            public_key: Hex::encode(state.eph_kp.public().as_bytes()),
+            attestation: signed,
+        };
+        state.attestations.put(&stored).await.unwrap();
+
+        let mut app = Router::new()
+            .route("/attestation", get(get_stored_attestation))
+            .with_state(state.clone());
+        let response = app
+            .call(Request::get("/attestation?wallet=0xabc&type=PAN").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let lookup: AttestationLookup = serde_json::from_slice(&body).unwrap();
+        assert!(lookup.signature_valid);
+        assert!(lookup.current_key);
+        assert_eq!(lookup.key_id, key_id(state.eph_kp.public()));
+        assert_eq!(lookup.attestation.response.intent, IntentScope::VerificationResult);
+        assert_eq!(lookup.attestation.response.data.result, "verified");
+
+        // An alias finds the record kept under its canonical type
+        let by_alias = app
+            .call(Request::get("/attestation?wallet=0xabc&type=age").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(by_alias.status(), StatusCode::OK);
+
+        let missing = app
+            .call(Request::get("/attestation?wallet=0xabc&type=citizenship").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(missing.status(), StatusCode::NOT_FOUND);
+    }
+}
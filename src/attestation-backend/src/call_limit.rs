@@ -0,0 +1,47 @@
+// Per-dependency caps on concurrent calls, so each is sized to its own rate limits
+use std::sync::Arc;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+use tracing::info;
+
+use crate::metrics;
+
+/// At most `max` calls to one dependency in flight at once. The government API and Sui each
+/// have their own, so a burst of API calls never waits behind Sui submissions serialized on
+/// the cap object, nor the other way round.
+#[derive(Debug, Clone)]
+pub struct CallLimit {
+    name: String,
+    max: usize,
+    permits: Arc<Semaphore>,
+}
+
+impl CallLimit {
+    pub fn new(name: &str, max: usize) -> Self {
+        let max = max.max(1);
+        metrics::set_gauge(&format!("{}_max_concurrency", name), max as f64);
+        Self { name: name.to_string(), max, permits: Arc::new(Semaphore::new(max)) }
+    }
+
+    /// From `{prefix}_MAX_CONCURRENCY`, else `default`.
+    pub fn from_env(name: &str, prefix: &str, default: usize) -> Self {
+        let max = std::env::var(format!("{}_MAX_CONCURRENCY", prefix))
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(default);
+        let limit = Self::new(name, max);
+        info!("{} calls: at most {} in flight", name, limit.max);
+        limit
+    }
+
+    pub fn max(&self) -> usize {
+        self.max
+    }
+
+    /// Wait for a free slot; the call holds it until the permit is dropped.
+    pub async fn acquire(&self) -> OwnedSemaphorePermit {
+        if self.permits.available_permits() == 0 {
+            metrics::increment(&format!("{}_concurrency_waits_total", self.name));
+        }
+        self.permits.clone().acquire_owned().await.expect("call limit semaphore is never closed")
+    }
+}
@@ -0,0 +1,124 @@
+// Canonical JSON (RFC 8785 style) for bytes that are hashed or compared across implementations
+use anyhow::Result;
+use serde::Serialize;
+use serde_json::{Number, Value};
+
+/// `value` as canonical JSON: object keys sorted by their UTF-16 code units, no whitespace,
+/// strings escaped minimally and numbers written the way ECMAScript prints them. Two values
+/// that compare equal always give the same bytes, whatever order their maps were built in.
+pub fn to_canonical_string<T: Serialize + ?Sized>(value: &T) -> Result<String> {
+    let mut out = String::new();
+    write_value(&mut out, &serde_json::to_value(value)?)?;
+    Ok(out)
+}
+
+fn write_value(out: &mut String, value: &Value) -> Result<()> {
+    match value {
+        Value::Null | Value::Bool(_) | Value::String(_) => out.push_str(&serde_json::to_string(value)?),
+        Value::Number(number) => out.push_str(&format_number(number)),
+        Value::Array(items) => {
+            out.push('[');
+            for (i, item) in items.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                write_value(out, item)?;
+            }
+            out.push(']');
+        }
+        Value::Object(map) => {
+            let mut entries: Vec<(&String, &Value)> = map.iter().collect();
+            entries.sort_by(|(a, _), (b, _)| a.encode_utf16().cmp(b.encode_utf16()));
+            out.push('{');
+            for (i, (key, item)) in entries.into_iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                out.push_str(&serde_json::to_string(key)?);
+                out.push(':');
+                write_value(out, item)?;
+            }
+            out.push('}');
+        }
+    }
+    Ok(())
+}
+
+/// Integers as they are; floats without a fractional part as integers, `-0` as `0`, and
+/// exponents with an explicit sign (`1e+21`), as ECMAScript's `Number.prototype.toString`.
+fn format_number(number: &Number) -> String {
+    let Some(float) = number.as_f64().filter(|_| number.is_f64()) else {
+        return number.to_string();
+    };
+    if float == 0.0 {
+        return "0".to_string();
+    }
+    if float.fract() == 0.0 && float.abs() < 1e21 {
+        return format!("{:.0}", float);
+    }
+    let text = number.to_string();
+    match text.split_once('e') {
+        Some((mantissa, exponent)) if !exponent.starts_with(['-', '+']) => format!("{}e+{}", mantissa, exponent),
+        _ => text,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_keys_are_sorted_at_every_level() {
+        let value = json!({ "b": 1, "a": { "z": [ { "y": true, "x": null } ], "c": "d" } });
+        assert_eq!(to_canonical_string(&value).unwrap(), r#"{"a":{"c":"d","z":[{"x":null,"y":true}]},"b":1}"#);
+
+        // Built in a different order, same bytes
+        let mut reordered = serde_json::Map::new();
+        reordered.insert("a".to_string(), json!({ "c": "d", "z": [ { "x": null, "y": true } ] }));
+        reordered.insert("b".to_string(), json!(1));
+        assert_eq!(to_canonical_string(&reordered).unwrap(), to_canonical_string(&value).unwrap());
+
+        // UTF-16 order: U+FB01 sorts before U+1F600, unlike in UTF-8
+        let keys = json!({ "\u{1F600}": 1, "\u{FB01}": 2, "é": 3, "Z": 4 });
+        assert_eq!(to_canonical_string(&keys).unwrap(), "{\"Z\":4,\"é\":3,\"\u{1F600}\":1,\"\u{FB01}\":2}");
+    }
+
+    #[test]
+    fn test_structs_serialize_with_sorted_keys() {
+        #[derive(Serialize)]
+        struct Record {
+            wallet: String,
+            did_id: u8,
+            evidence: Vec<&'static str>,
+        }
+        let record = Record { wallet: "0xabc".to_string(), did_id: 0, evidence: vec!["b", "a"] };
+        // Array order is data, so it is kept
+        assert_eq!(to_canonical_string(&record).unwrap(), r#"{"did_id":0,"evidence":["b","a"],"wallet":"0xabc"}"#);
+    }
+
+    #[test]
+    fn test_numbers_are_formatted_like_ecmascript() {
+        let cases = [
+            (json!(0), "0"),
+            (json!(-0.0), "0"),
+            (json!(1.0), "1"),
+            (json!(-42), "-42"),
+            (json!(u64::MAX), "18446744073709551615"),
+            (json!(1.5), "1.5"),
+            (json!(0.1), "0.1"),
+            (json!(1e21), "1e+21"),
+            (json!(1e-7), "1e-7"),
+            (json!(123456789012.0), "123456789012"),
+        ];
+        for (value, expected) in cases {
+            assert_eq!(to_canonical_string(&value).unwrap(), expected, "{:?}", value);
+        }
+    }
+
+    #[test]
+    fn test_strings_are_minimally_escaped() {
+        let value = json!({ "s": "a/b \"q\" \\ \n\t\u{1} é" });
+        assert_eq!(to_canonical_string(&value).unwrap(), "{\"s\":\"a/b \\\"q\\\" \\\\ \\n\\t\\u0001 é\"}");
+    }
+}
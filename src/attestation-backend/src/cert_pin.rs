@@ -0,0 +1,166 @@
+// Optional SPKI pinning for the government API's TLS certificate, on top of normal CA validation
+use anyhow::{Result, anyhow};
+use base64::{engine::general_purpose, Engine as _};
+use rustls::client::{ServerCertVerified, ServerCertVerifier, WebPkiVerifier};
+use rustls::{Certificate, ClientConfig, OwnedTrustAnchor, RootCertStore, ServerName};
+use sha2::{Digest, Sha256};
+use std::sync::Arc;
+use std::time::SystemTime;
+use tracing::warn;
+use x509_cert::der::{Decode, Encode};
+
+use crate::metrics;
+
+/// SHA-256 of a server certificate's SubjectPublicKeyInfo, as in `GOVT_API_CERT_PIN`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CertPin([u8; 32]);
+
+impl CertPin {
+    /// Base64 (optionally prefixed `sha256/`, as printed by
+    /// `openssl x509 -pubkey | openssl pkey -pubin -outform der | openssl dgst -sha256 -binary | base64`)
+    /// or 64 hex characters.
+    pub fn parse(value: &str) -> Result<Self> {
+        let value = value.trim();
+        let value = value.strip_prefix("sha256/").unwrap_or(value);
+        let bytes = if value.len() == 64 && value.chars().all(|c| c.is_ascii_hexdigit()) {
+            hex::decode(value)?
+        } else {
+            general_purpose::STANDARD
+                .decode(value)
+                .map_err(|e| anyhow!("Invalid certificate pin '{}': {}", value, e))?
+        };
+        let digest: [u8; 32] = bytes
+            .try_into()
+            .map_err(|_| anyhow!("Certificate pin must be a SHA-256 digest (32 bytes)"))?;
+        Ok(Self(digest))
+    }
+
+    /// The pin for a DER certificate.
+    pub fn of_certificate(der: &[u8]) -> Result<Self> {
+        let cert = x509_cert::Certificate::from_der(der).map_err(|e| anyhow!("Unreadable certificate: {}", e))?;
+        let spki = cert
+            .tbs_certificate
+            .subject_public_key_info
+            .to_der()
+            .map_err(|e| anyhow!("Unreadable public key: {}", e))?;
+        Ok(Self(Sha256::digest(spki).into()))
+    }
+
+    /// `GOVT_API_CERT_PIN`, when set.
+    pub fn from_env() -> Result<Option<Self>> {
+        match std::env::var("GOVT_API_CERT_PIN") {
+            Ok(value) if !value.trim().is_empty() => Ok(Some(Self::parse(&value)?)),
+            _ => Ok(None),
+        }
+    }
+}
+
+/// The public web roots reqwest trusts by default.
+pub fn webpki_roots() -> RootCertStore {
+    let mut roots = RootCertStore::empty();
+    roots.add_trust_anchors(webpki_roots::TLS_SERVER_ROOTS.iter().map(|ta| {
+        OwnedTrustAnchor::from_subject_spki_name_constraints(ta.subject, ta.spki, ta.name_constraints)
+    }));
+    roots
+}
+
+/// Usual chain and hostname validation, then the leaf's public key must match the pin.
+struct PinnedVerifier {
+    inner: WebPkiVerifier,
+    pin: CertPin,
+}
+
+impl ServerCertVerifier for PinnedVerifier {
+    fn verify_server_cert(
+        &self,
+        end_entity: &Certificate,
+        intermediates: &[Certificate],
+        server_name: &ServerName,
+        scts: &mut dyn Iterator<Item = &[u8]>,
+        ocsp_response: &[u8],
+        now: SystemTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        let verified = self.inner.verify_server_cert(end_entity, intermediates, server_name, scts, ocsp_response, now)?;
+        let presented = CertPin::of_certificate(&end_entity.0).map_err(|e| rustls::Error::General(e.to_string()))?;
+        if presented != self.pin {
+            metrics::increment("govt_api_cert_pin_mismatch_total");
+            warn!("🔒 Government API certificate does not match GOVT_API_CERT_PIN (presented sha256/{})",
+                  general_purpose::STANDARD.encode(presented.0));
+            return Err(rustls::Error::General("server certificate does not match the pinned public key".to_string()));
+        }
+        Ok(verified)
+    }
+}
+
+/// TLS settings for a reqwest client that only accepts servers chaining to `roots` whose
+/// certificate carries the pinned key.
+pub fn pinned_tls_config(pin: CertPin, roots: RootCertStore) -> ClientConfig {
+    ClientConfig::builder()
+        .with_safe_defaults()
+        .with_custom_certificate_verifier(Arc::new(PinnedVerifier { inner: WebPkiVerifier::new(roots, None), pin }))
+        .with_no_client_auth()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio_rustls::TlsAcceptor;
+
+    /// HTTPS server for `localhost` with a fresh self-signed certificate; returns its port and DER.
+    async fn tls_server() -> (u16, Vec<u8>) {
+        let generated = rcgen::generate_simple_self_signed(vec!["localhost".to_string()]).unwrap();
+        let cert = generated.serialize_der().unwrap();
+        let key = rustls::PrivateKey(generated.serialize_private_key_der());
+        let config = rustls::ServerConfig::builder()
+            .with_safe_defaults()
+            .with_no_client_auth()
+            .with_single_cert(vec![Certificate(cert.clone())], key)
+            .unwrap();
+        let acceptor = TlsAcceptor::from(Arc::new(config));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let acceptor = acceptor.clone();
+                tokio::spawn(async move {
+                    let Ok(mut tls) = acceptor.accept(stream).await else { return };
+                    let mut request = [0u8; 1024];
+                    let _ = tls.read(&mut request).await;
+                    let _ = tls
+                        .write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 2\r\nconnection: close\r\n\r\nok")
+                        .await;
+                    let _ = tls.shutdown().await;
+                });
+            }
+        });
+        (port, cert)
+    }
+
+    fn client(pin: CertPin, trusted: &[u8]) -> reqwest::Client {
+        let mut roots = RootCertStore::empty();
+        roots.add(&Certificate(trusted.to_vec())).unwrap();
+        reqwest::Client::builder()
+            .use_preconfigured_tls(pinned_tls_config(pin, roots))
+            .build()
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_mismatched_pin_is_rejected() {
+        let (port, cert) = tls_server().await;
+        let url = format!("https://localhost:{}/pan/verify", port);
+        let pin = CertPin::of_certificate(&cert).unwrap();
+
+        let pinned = client(pin, &cert).get(&url).send().await.unwrap();
+        assert_eq!(pinned.text().await.unwrap(), "ok");
+
+        // A trusted certificate with another key is refused
+        let other = CertPin::parse(&format!("sha256/{}", general_purpose::STANDARD.encode([7u8; 32]))).unwrap();
+        let error = client(other, &cert).get(&url).send().await.unwrap_err();
+        assert!(format!("{:?}", error).contains("pinned public key"), "{:?}", error);
+
+        assert_eq!(CertPin::parse(&hex::encode(pin.0)).unwrap(), pin);
+        assert!(CertPin::parse("sha256/c2hvcnQ=").is_err());
+    }
+}
@@ -0,0 +1,132 @@
+// Circuit breaker around the government API so an outage fails fast instead of timing out per message
+use std::fmt;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+
+use crate::government_api::{gov_api_error, GovApiError};
+use crate::metrics;
+
+/// The government API could not be reached, either because the circuit is open or
+/// because the request failed at the transport level. Retrying later can succeed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GovApiUnavailable {
+    pub reason: String,
+}
+
+impl fmt::Display for GovApiUnavailable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Government API unavailable: {}", self.reason)
+    }
+}
+
+impl std::error::Error for GovApiUnavailable {}
+
+/// True when `error` means the government API was unreachable (as opposed to a rejection or bad input).
+pub fn is_unavailable(error: &anyhow::Error) -> bool {
+    error.downcast_ref::<GovApiUnavailable>().is_some()
+        || gov_api_error(error).is_some_and(GovApiError::is_unavailable)
+}
+
+#[derive(Debug)]
+struct BreakerState {
+    consecutive_failures: u32,
+    opened_at: Option<Instant>,
+}
+
+/// Opens after `failure_threshold` consecutive failures and stays open for `open_duration`.
+/// After that a request is let through (half-open); its outcome closes or re-opens the circuit.
+#[derive(Debug)]
+pub struct CircuitBreaker {
+    name: String,
+    failure_threshold: u32,
+    open_duration: Duration,
+    state: Mutex<BreakerState>,
+}
+
+impl CircuitBreaker {
+    pub fn new(name: &str, failure_threshold: u32, open_duration: Duration) -> Self {
+        metrics::set_gauge(&format!("{}_circuit_open", name), 0.0);
+        Self {
+            name: name.to_string(),
+            failure_threshold: failure_threshold.max(1),
+            open_duration,
+            state: Mutex::new(BreakerState {
+                consecutive_failures: 0,
+                opened_at: None,
+            }),
+        }
+    }
+
+    /// Reads `{PREFIX}_CIRCUIT_FAILURE_THRESHOLD` (default 5) and `{PREFIX}_CIRCUIT_OPEN_SECS` (default 30).
+    pub fn from_env(name: &str, prefix: &str) -> Self {
+        let parse = |suffix: &str, default: u64| {
+            std::env::var(format!("{}_{}", prefix, suffix))
+                .ok()
+                .and_then(|v| v.parse::<u64>().ok())
+                .unwrap_or(default)
+        };
+        Self::new(
+            name,
+            parse("CIRCUIT_FAILURE_THRESHOLD", 5) as u32,
+            Duration::from_secs(parse("CIRCUIT_OPEN_SECS", 30)),
+        )
+    }
+
+    /// Whether requests are currently being refused.
+    pub fn is_open(&self) -> bool {
+        let state = self.state.lock().unwrap();
+        matches!(state.opened_at, Some(opened_at) if opened_at.elapsed() < self.open_duration)
+    }
+
+    /// Refuse the call while the circuit is open.
+    pub fn check(&self) -> Result<(), GovApiUnavailable> {
+        if self.is_open() {
+            return Err(GovApiUnavailable {
+                reason: format!("{} circuit is open", self.name),
+            });
+        }
+        Ok(())
+    }
+
+    pub fn record_success(&self) {
+        let mut state = self.state.lock().unwrap();
+        if state.opened_at.is_some() {
+            info!("{} circuit closed", self.name);
+        }
+        state.consecutive_failures = 0;
+        state.opened_at = None;
+        metrics::set_gauge(&format!("{}_circuit_open", self.name), 0.0);
+    }
+
+    pub fn record_failure(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.consecutive_failures += 1;
+        if state.consecutive_failures >= self.failure_threshold {
+            // Also re-opens a half-open circuit whose trial request failed
+            warn!("{} circuit opened after {} consecutive failures", self.name, state.consecutive_failures);
+            state.opened_at = Some(Instant::now());
+            metrics::set_gauge(&format!("{}_circuit_open", self.name), 1.0);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_opens_after_threshold_and_half_opens_after_cooldown() {
+        let breaker = CircuitBreaker::new("test_api", 2, Duration::from_millis(50));
+        breaker.record_failure();
+        assert!(breaker.check().is_ok());
+        breaker.record_failure();
+        assert!(breaker.is_open());
+        assert!(is_unavailable(&anyhow::Error::new(breaker.check().unwrap_err())));
+
+        std::thread::sleep(Duration::from_millis(60));
+        assert!(breaker.check().is_ok());
+        breaker.record_success();
+        assert!(!breaker.is_open());
+    }
+}
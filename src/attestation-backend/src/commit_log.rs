@@ -0,0 +1,271 @@
+// Crash-consistent progress record for the two-step start_verification + update_verification_status sequence
+use anyhow::Result;
+use redis::aio::Connection;
+use std::collections::HashMap;
+use std::time::Duration;
+
+use crate::redis_timeout::{with_timeout, RedisTimeouts};
+
+/// Progress of one logical verification through the two Sui calls. Stored as a Redis hash with
+/// the fields `started`, `started_with_object_id`, `updated` and `in_doubt`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SuiCommitState {
+    pub started: bool,
+    pub started_with_object_id: Option<String>,
+    pub updated: bool,
+    /// The call that was submitted with an unknown outcome, if one was.
+    pub in_doubt: Option<String>,
+}
+
+/// Where the processor should pick up for a (possibly redelivered) message.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ResumePoint {
+    /// Nothing recorded yet: run start_verification.
+    StartVerification,
+    /// start_verification already created the UserDID: go straight to the update.
+    UpdateVerificationStatus { user_did_id: String },
+    /// Both calls already committed: nothing left to do.
+    Completed,
+    /// `call` may or may not have executed. Nothing is resubmitted until someone has checked the
+    /// chain and deleted the record.
+    InDoubt { call: String },
+}
+
+impl SuiCommitState {
+    pub fn from_fields(fields: &HashMap<String, String>) -> Self {
+        let flag = |name: &str| fields.get(name).map(|v| v == "1" || v == "true").unwrap_or(false);
+        Self {
+            started: flag("started"),
+            started_with_object_id: fields
+                .get("started_with_object_id")
+                .filter(|v| !v.is_empty())
+                .cloned(),
+            updated: flag("updated"),
+            in_doubt: fields.get("in_doubt").filter(|v| !v.is_empty()).cloned(),
+        }
+    }
+
+    pub fn resume_point(&self) -> ResumePoint {
+        if self.updated {
+            return ResumePoint::Completed;
+        }
+        if let Some(call) = &self.in_doubt {
+            return ResumePoint::InDoubt { call: call.clone() };
+        }
+        match (self.started, &self.started_with_object_id) {
+            (true, Some(user_did_id)) => ResumePoint::UpdateVerificationStatus {
+                user_did_id: user_did_id.clone(),
+            },
+            // A start without a recorded object id cannot be resumed safely, so redo it
+            _ => ResumePoint::StartVerification,
+        }
+    }
+}
+
+/// Redis-backed store of [`SuiCommitState`] records, keyed per wallet, DID type and evidence hash
+/// so a redelivered message resumes while a genuinely new verification starts fresh.
+///
+/// Every write also resets the record's TTL, so Redis expires it on its own; there is no sweeper.
+/// The TTL only has to outlive redelivery: a message still pending after it runs out is treated
+/// as new and its Sui calls are made again.
+#[derive(Debug, Clone)]
+pub struct CommitLog {
+    key_prefix: String,
+    ttl: Duration,
+    command_timeout: Duration,
+}
+
+impl CommitLog {
+    /// `SUI_COMMIT_LOG_PREFIX` (default `sui_commit`) and `SUI_COMMIT_LOG_TTL_SECS`
+    /// (default 7 days; 0 or unparsable falls back to the default, never to no expiry).
+    pub fn from_env() -> Self {
+        let ttl_secs = std::env::var("SUI_COMMIT_LOG_TTL_SECS")
+            .ok()
+            .and_then(|v| v.parse::<u64>().ok())
+            .filter(|&secs| secs > 0)
+            .unwrap_or(7 * 24 * 3600);
+        Self {
+            key_prefix: std::env::var("SUI_COMMIT_LOG_PREFIX")
+                .unwrap_or_else(|_| "sui_commit".to_string()),
+            ttl: Duration::from_secs(ttl_secs),
+            command_timeout: RedisTimeouts::from_env().command,
+        }
+    }
+
+    pub fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = ttl.max(Duration::from_millis(1));
+        self
+    }
+
+    pub fn ttl(&self) -> Duration {
+        self.ttl
+    }
+
+    pub fn key(&self, user_wallet: &str, did_id: u8, evidence_hash: &str) -> String {
+        format!("{}:{}:{}:{}", self.key_prefix, user_wallet, did_id, evidence_hash)
+    }
+
+    /// Key for the status update of one re-verification round: the one re-checking the
+    /// verification indexed at `verified_at_ms`. The re-check can reproduce the original
+    /// evidence hash, so it is kept apart from [`Self::key`].
+    pub fn reverification_key(&self, user_wallet: &str, did_id: u8, verified_at_ms: u64) -> String {
+        format!("{}:reverify:{}:{}:{}", self.key_prefix, user_wallet, did_id, verified_at_ms)
+    }
+
+    pub async fn load(&self, conn: &mut Connection, key: &str) -> Result<SuiCommitState> {
+        let fields: HashMap<String, String> =
+            with_timeout("HGETALL", self.command_timeout, redis::cmd("HGETALL").arg(key).query_async(conn)).await?;
+        Ok(SuiCommitState::from_fields(&fields))
+    }
+
+    /// HSET `fields` and (re)set the TTL in one transaction, so no record is left without one.
+    async fn write(&self, conn: &mut Connection, key: &str, fields: &[(&str, &str)]) -> Result<()> {
+        let mut hset = redis::cmd("HSET");
+        hset.arg(key);
+        for (field, value) in fields {
+            hset.arg(*field).arg(*value);
+        }
+        let mut pipe = redis::pipe();
+        pipe.atomic()
+            .add_command(hset)
+            .ignore()
+            .cmd("PEXPIRE")
+            .arg(key)
+            .arg(self.ttl.as_millis() as u64)
+            .ignore();
+        with_timeout("HSET", self.command_timeout, pipe.query_async::<_, ()>(conn)).await?;
+        Ok(())
+    }
+
+    pub async fn mark_started(&self, conn: &mut Connection, key: &str, user_did_id: &str) -> Result<()> {
+        self.write(conn, key, &[("started", "1"), ("started_with_object_id", user_did_id)]).await
+    }
+
+    pub async fn mark_updated(&self, conn: &mut Connection, key: &str) -> Result<()> {
+        self.write(conn, key, &[("updated", "1")]).await
+    }
+
+    /// Record that `call` was submitted and its outcome is unknown, so a redelivery doesn't send it again.
+    pub async fn mark_in_doubt(&self, conn: &mut Connection, key: &str, call: &str) -> Result<()> {
+        self.write(conn, key, &[("in_doubt", call)]).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fields(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+    }
+
+    #[test]
+    fn test_fresh_record_starts_verification() {
+        let state = SuiCommitState::from_fields(&HashMap::new());
+        assert_eq!(state, SuiCommitState::default());
+        assert_eq!(state.resume_point(), ResumePoint::StartVerification);
+    }
+
+    #[test]
+    fn test_started_record_resumes_at_update() {
+        let state = SuiCommitState::from_fields(&fields(&[
+            ("started", "1"),
+            ("started_with_object_id", "0xabc"),
+        ]));
+        assert_eq!(
+            state.resume_point(),
+            ResumePoint::UpdateVerificationStatus { user_did_id: "0xabc".to_string() }
+        );
+    }
+
+    #[test]
+    fn test_updated_record_is_completed() {
+        let state = SuiCommitState::from_fields(&fields(&[
+            ("started", "1"),
+            ("started_with_object_id", "0xabc"),
+            ("updated", "1"),
+        ]));
+        assert_eq!(state.resume_point(), ResumePoint::Completed);
+    }
+
+    #[test]
+    fn test_call_in_doubt_is_not_resumed() {
+        let state = SuiCommitState::from_fields(&fields(&[("in_doubt", "start_verification")]));
+        assert_eq!(state.resume_point(), ResumePoint::InDoubt { call: "start_verification".to_string() });
+        let state = SuiCommitState::from_fields(&fields(&[
+            ("started", "1"),
+            ("started_with_object_id", "0xabc"),
+            ("in_doubt", "update_verification_status"),
+        ]));
+        assert_eq!(state.resume_point(), ResumePoint::InDoubt { call: "update_verification_status".to_string() });
+    }
+
+    #[test]
+    fn test_started_without_object_id_restarts() {
+        let state = SuiCommitState::from_fields(&fields(&[("started", "1")]));
+        assert_eq!(state.resume_point(), ResumePoint::StartVerification);
+    }
+
+    #[test]
+    fn test_reverification_rounds_are_keyed_apart_from_verifications() {
+        let log = CommitLog::from_env();
+        let round = log.reverification_key("0xabc", 0, 1_000);
+        assert_ne!(round, log.key("0xabc", 0, "1000"));
+        assert_eq!(round, log.reverification_key("0xabc", 0, 1_000));
+        assert_ne!(round, log.reverification_key("0xabc", 0, 2_000));
+    }
+
+    type Store = std::sync::Arc<std::sync::Mutex<HashMap<String, (HashMap<String, String>, Option<tokio::time::Instant>)>>>;
+
+    fn execute(store: &Store, args: &[String]) -> String {
+        let mut store = store.lock().unwrap();
+        let now = tokio::time::Instant::now();
+        store.retain(|_, (_, deadline)| deadline.is_none_or(|at| at > now));
+        match args[0].to_uppercase().as_str() {
+            "HSET" => {
+                let (hash, _) = store.entry(args[1].clone()).or_default();
+                for pair in args[2..].chunks(2) {
+                    hash.insert(pair[0].clone(), pair[1].clone());
+                }
+                format!(":{}\r\n", (args.len() - 2) / 2)
+            }
+            "PEXPIRE" => match store.get_mut(&args[1]) {
+                Some((_, deadline)) => {
+                    *deadline = Some(now + Duration::from_millis(args[2].parse().unwrap()));
+                    ":1\r\n".to_string()
+                }
+                None => ":0\r\n".to_string(),
+            },
+            "HGETALL" => {
+                let fields = store.get(&args[1]).map(|(hash, _)| hash.clone()).unwrap_or_default();
+                let mut reply = format!("*{}\r\n", fields.len() * 2);
+                for (field, value) in fields {
+                    reply += &format!("${}\r\n{}\r\n${}\r\n{}\r\n", field.len(), field, value.len(), value);
+                }
+                reply
+            }
+            _ => "+OK\r\n".to_string(),
+        }
+    }
+
+    /// Just enough of Redis for the commit log: HSET, PEXPIRE, HGETALL and MULTI/EXEC, with expiry.
+    async fn expiring_redis() -> String {
+        let store = Store::default();
+        crate::fake_redis::serve(move |args| Some(execute(&store, args))).await
+    }
+
+    #[tokio::test]
+    async fn test_processed_record_expires_after_its_ttl() {
+        let client = redis::Client::open(expiring_redis().await).unwrap();
+        let mut conn = client.get_async_connection().await.unwrap();
+        let log = CommitLog::from_env().with_ttl(Duration::from_millis(200));
+        let key = log.key("0xabc", 0, "ab");
+
+        log.mark_started(&mut conn, &key, "0xdid").await.unwrap();
+        log.mark_updated(&mut conn, &key).await.unwrap();
+        assert_eq!(log.load(&mut conn, &key).await.unwrap().resume_point(), ResumePoint::Completed);
+
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        assert_eq!(log.load(&mut conn, &key).await.unwrap(), SuiCommitState::default());
+    }
+}
@@ -1,9 +1,13 @@
+use crate::content_negotiation::{Negotiated, ResponseFormat};
+use crate::signing::{EnclaveSigner, SigningError};
 use crate::AppState;
 use crate::EnclaveError;
+use axum::response::{IntoResponse, Response};
 use axum::{extract::State, Json};
-use fastcrypto::traits::Signer;
 use fastcrypto::{encoding::Encoding, traits::ToFromBytes};
 use fastcrypto::{encoding::Hex, traits::KeyPair as FcKeyPair};
+use fastcrypto::encoding::Base64;
+use fastcrypto::hash::{Blake2b256, HashFunction};
 #[cfg(feature = "aws")]
 use aws_nitro_enclaves_nsm_api::api::{Request as NsmRequest, Response as NsmResponse};
 #[cfg(feature = "aws")]
@@ -20,25 +24,62 @@ use std::sync::Arc;
 use std::time::Duration;
 use tracing::info;
 
-use fastcrypto::ed25519::Ed25519KeyPair;
-/// ==== COMMON TYPES ====
+use fastcrypto::ed25519::{Ed25519PublicKey, Ed25519Signature};
+use fastcrypto::traits::VerifyingKey;
+// ==== COMMON TYPES ====
 
 /// Intent message wrapper struct containing the intent scope and timestamp.
 /// This standardizes the serialized payload for signing.
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct IntentMessage<T: Serialize> {
     pub intent: IntentScope,
     pub timestamp_ms: u64,
     pub data: T,
 }
 
-/// Intent scope enum. Add new scope here if needed, each corresponds to a
-/// scope for signing. Replace in with your own intent per message type being signed by the enclave.
-#[derive(Serialize_repr, Deserialize_repr, Debug)]
+/// Intent scope enum, the first byte of every signed message. Each variant corresponds to one
+/// kind of payload signed by the enclave, and its byte must match what the Move contract
+/// expects for that payload. Only ever append new scopes: renumbering a variant invalidates
+/// every signature already issued under it.
+#[derive(Serialize_repr, Deserialize_repr, Debug, Clone, Copy, PartialEq, Eq)]
 #[repr(u8)]
 pub enum IntentScope {
     Generic = 0,
-    KYCVerification = 1, 
+    /// Successful KYC verification result ([`crate::app::KYCResponse`]).
+    KYCVerification = 1,
+    /// Rejected verification ([`crate::negative_attestation::NegativeAttestation`]).
+    NegativeVerification = 2,
+    /// Liveness and key continuity ([`crate::heartbeat::Heartbeat`]).
+    Heartbeat = 3,
+    /// A set of verification results signed together.
+    BatchVerification = 4,
+    /// Stored verification outcome ([`crate::attestation_store::VerificationAttestation`]).
+    VerificationResult = 5,
+    /// Consumer-group lag crossing its threshold ([`crate::lag_alert::LagAlert`]).
+    LagAlert = 6,
+}
+
+impl IntentScope {
+    /// Every scope, in wire-byte order.
+    pub const ALL: [IntentScope; 7] = [
+        IntentScope::Generic,
+        IntentScope::KYCVerification,
+        IntentScope::NegativeVerification,
+        IntentScope::Heartbeat,
+        IntentScope::BatchVerification,
+        IntentScope::VerificationResult,
+        IntentScope::LagAlert,
+    ];
+
+    /// The byte this scope is encoded as.
+    pub fn as_byte(self) -> u8 {
+        self as u8
+    }
+
+    /// The scope encoded as `byte`, if there is one.
+    pub fn from_byte(byte: u8) -> Option<Self> {
+        Self::ALL.into_iter().find(|scope| scope.as_byte() == byte)
+    }
 }
 
 impl<T: Serialize + Debug> IntentMessage<T> {
@@ -52,10 +93,13 @@ impl<T: Serialize + Debug> IntentMessage<T> {
 }
 
 /// Wrapper struct containing the response (the intent message) and signature.
-#[derive(Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct ProcessedDataResponse<T> {
     pub response: T,
     pub signature: String,
+    /// Request id for log correlation. Metadata only, not covered by the signature.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
 }
 
 /// Wrapper struct containing the request payload.
@@ -65,12 +109,12 @@ pub struct ProcessDataRequest<T> {
 }
 
 /// Sign the bcs bytes of the the payload with keypair.
-pub fn to_signed_response<T: Serialize + Clone>(
-    kp: &Ed25519KeyPair,
+pub fn to_signed_response<T: Serialize + Clone, S: EnclaveSigner + ?Sized>(
+    kp: &S,
     payload: T,
     timestamp_ms: u64,
     intent: IntentScope,
-) -> ProcessedDataResponse<IntentMessage<T>> {
+) -> Result<ProcessedDataResponse<IntentMessage<T>>, SigningError> {
     let intent_msg = IntentMessage {
         intent,
         timestamp_ms,
@@ -78,14 +122,133 @@ pub fn to_signed_response<T: Serialize + Clone>(
     };
 
     let signing_payload = bcs::to_bytes(&intent_msg).expect("should not fail");
-    let sig = kp.sign(&signing_payload);
-    ProcessedDataResponse {
+    let sig = kp.try_sign(&signing_payload)?;
+    Ok(ProcessedDataResponse {
         response: intent_msg,
         signature: Hex::encode(sig),
+        request_id: None,
+    })
+}
+
+/// Check that `signed.signature` is `pk`'s signature over the bcs bytes of `signed.response`.
+pub fn verify_signed_response<T: Serialize>(
+    pk: &Ed25519PublicKey,
+    signed: &ProcessedDataResponse<IntentMessage<T>>,
+) -> Result<(), EnclaveError> {
+    let signing_payload = bcs::to_bytes(&signed.response)
+        .map_err(|e| EnclaveError::GenericError(format!("Failed to serialize response: {}", e)))?;
+    let sig_bytes = Hex::decode(&signed.signature)
+        .map_err(|e| EnclaveError::GenericError(format!("Signature is not hex: {}", e)))?;
+    let signature = Ed25519Signature::from_bytes(&sig_bytes)
+        .map_err(|e| EnclaveError::GenericError(format!("Malformed signature: {}", e)))?;
+    pk.verify(&signing_payload, &signature)
+        .map_err(|e| EnclaveError::GenericError(format!("Signature verification failed: {}", e)))
+}
+
+/// Fixed payload signed by [`signing_self_test`].
+#[derive(Debug, Clone, Serialize)]
+struct SelfTestPayload {
+    message: &'static str,
+}
+
+/// Sign a known payload with `signer` and check it against `pk` the way verifiers will, so a
+/// broken key, encoding or scheme mismatch stops the server before it signs anything real.
+pub fn signing_self_test<S: EnclaveSigner + ?Sized>(signer: &S, pk: &Ed25519PublicKey) -> Result<(), EnclaveError> {
+    let payload = SelfTestPayload { message: "nautilus attestation signing self-test" };
+    let signed = to_signed_response(signer, payload, 0, IntentScope::Generic)?;
+    verify_signed_response(pk, &signed)?;
+    info!("🔏 Signing self-test passed (scheme: ed25519, key id: {})", key_id(pk));
+    Ok(())
+}
+
+/// How far a signed `timestamp_ms` may trail or lead the verifier's clock. Read from
+/// `SIGNATURE_MAX_AGE_MS` (default 10 minutes) and `SIGNATURE_MAX_FUTURE_SKEW_MS` (default
+/// 30 seconds); keep the age in line with the Move contract's `signature_timestamp_ms` check.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SkewWindow {
+    pub max_age_ms: u64,
+    pub max_future_ms: u64,
+}
+
+impl Default for SkewWindow {
+    fn default() -> Self {
+        Self {
+            max_age_ms: 10 * 60 * 1000,
+            max_future_ms: 30 * 1000,
+        }
     }
 }
 
-/// ==== HEALTHCHECK, GET ATTESTASTION ENDPOINT IMPL ====
+impl SkewWindow {
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        let parse = |name: &str, default: u64| {
+            std::env::var(name)
+                .ok()
+                .and_then(|v| v.parse::<u64>().ok())
+                .unwrap_or(default)
+        };
+        Self {
+            max_age_ms: parse("SIGNATURE_MAX_AGE_MS", defaults.max_age_ms),
+            max_future_ms: parse("SIGNATURE_MAX_FUTURE_SKEW_MS", defaults.max_future_ms),
+        }
+    }
+
+    /// Accept `timestamp_ms` if it is at most `max_age_ms` behind and `max_future_ms` ahead of `now_ms`.
+    pub fn check(&self, timestamp_ms: u64, now_ms: u64) -> Result<(), TimestampError> {
+        if timestamp_ms > now_ms {
+            let ahead_ms = timestamp_ms - now_ms;
+            if ahead_ms > self.max_future_ms {
+                return Err(TimestampError::InFuture { ahead_ms, max_future_ms: self.max_future_ms });
+            }
+        } else {
+            let age_ms = now_ms - timestamp_ms;
+            if age_ms > self.max_age_ms {
+                return Err(TimestampError::TooOld { age_ms, max_age_ms: self.max_age_ms });
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A signed timestamp outside the [`SkewWindow`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TimestampError {
+    /// Signed too long ago: a stale or replayed response.
+    TooOld { age_ms: u64, max_age_ms: u64 },
+    /// Signed later than the verifier's clock allows: clock skew between signer and verifier.
+    InFuture { ahead_ms: u64, max_future_ms: u64 },
+}
+
+impl std::fmt::Display for TimestampError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TimestampError::TooOld { age_ms, max_age_ms } => {
+                write!(f, "Signed timestamp is {}ms old, more than the allowed {}ms", age_ms, max_age_ms)
+            }
+            TimestampError::InFuture { ahead_ms, max_future_ms } => {
+                write!(f, "Signed timestamp is {}ms in the future, more than the allowed {}ms", ahead_ms, max_future_ms)
+            }
+        }
+    }
+}
+
+impl std::error::Error for TimestampError {}
+
+/// [`verify_signed_response`], then check the signed timestamp against `window` at `now_ms`.
+pub fn verify_signed_response_within<T: Serialize>(
+    pk: &Ed25519PublicKey,
+    signed: &ProcessedDataResponse<IntentMessage<T>>,
+    now_ms: u64,
+    window: &SkewWindow,
+) -> Result<(), EnclaveError> {
+    verify_signed_response(pk, signed)?;
+    window
+        .check(signed.response.timestamp_ms, now_ms)
+        .map_err(EnclaveError::Timestamp)
+}
+
+// ==== HEALTHCHECK, GET ATTESTASTION ENDPOINT IMPL ====
 
 /// Response for get attestation.
 #[derive(Debug, Serialize, Deserialize)]
@@ -94,12 +257,27 @@ pub struct GetAttestationResponse {
     pub attestation: String,
 }
 
+/// Response for get attestation when CBOR is negotiated: the document as raw bytes.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct GetAttestationCborResponse {
+    #[serde(with = "serde_bytes")]
+    pub attestation: Vec<u8>,
+}
+
+fn attestation_response(format: ResponseFormat, document: Vec<u8>, json_encoded: String) -> Response {
+    match format {
+        ResponseFormat::Json => Negotiated(format, GetAttestationResponse { attestation: json_encoded }).into_response(),
+        ResponseFormat::Cbor => Negotiated(format, GetAttestationCborResponse { attestation: document }).into_response(),
+    }
+}
+
 /// Endpoint that returns an attestation committed
 /// to the enclave's public key.
 #[cfg(feature = "aws")]
 pub async fn get_attestation(
     State(state): State<Arc<AppState>>,
-) -> Result<Json<GetAttestationResponse>, EnclaveError> {
+    format: ResponseFormat,
+) -> Result<Response, EnclaveError> {
     info!("get attestation called");
 
     let pk = state.eph_kp.public();
@@ -116,14 +294,13 @@ pub async fn get_attestation(
     match response {
         NsmResponse::Attestation { document } => {
             driver::nsm_exit(fd);
-            Ok(Json(GetAttestationResponse {
-                attestation: Hex::encode(document),
-            }))
+            let encoded = Hex::encode(&document);
+            Ok(attestation_response(format, document, encoded))
         }
         _ => {
             driver::nsm_exit(fd);
-            Err(EnclaveError::GenericError(
-                "unexpected response".to_string(),
+            Err(EnclaveError::Upstream(
+                "unexpected response from NSM".to_string(),
             ))
         }
     }
@@ -133,13 +310,13 @@ pub async fn get_attestation(
 #[cfg(not(feature = "aws"))]
 pub async fn get_attestation(
     State(_state): State<Arc<AppState>>,
-) -> Result<Json<GetAttestationResponse>, EnclaveError> {
+    format: ResponseFormat,
+) -> Result<Response, EnclaveError> {
     info!("get attestation called (stub - AWS feature not enabled)");
     
     // Return a mock attestation for development/testing
-    Ok(Json(GetAttestationResponse {
-        attestation: "mock_attestation_document".to_string(),
-    }))
+    let mock = "mock_attestation_document".to_string();
+    Ok(attestation_response(format, mock.clone().into_bytes(), mock))
 }
 
 /// Health check response.
@@ -162,7 +339,7 @@ pub async fn health_check(
     let client = Client::builder()
         .timeout(Duration::from_secs(5))
         .build()
-        .map_err(|e| EnclaveError::GenericError(format!("Failed to create HTTP client: {}", e)))?;
+        .map_err(|e| EnclaveError::Internal(format!("Failed to create HTTP client: {}", e)))?;
 
     // Load allowed endpoints from YAML file
     let endpoints_status = match std::fs::read_to_string("allowed_endpoints.yaml") {
@@ -236,3 +413,256 @@ pub async fn health_check(
         endpoints_status,
     }))
 }
+
+// ==== KEYS ENDPOINT IMPL ====
+
+/// Sui signature scheme flag for Ed25519, prepended to the public key when deriving addresses.
+const ED25519_SCHEME_FLAG: u8 = 0x00;
+
+/// Short, stable identifier for a public key: hex of the first 8 bytes of its SHA-256.
+pub fn key_id(pk: &Ed25519PublicKey) -> String {
+    use sha2::{Digest, Sha256};
+    hex::encode(&Sha256::digest(pk.as_bytes())[..8])
+}
+
+/// Sui address derived from an Ed25519 public key: `blake2b256(flag || pk)`.
+pub fn sui_address(pk: &Ed25519PublicKey) -> String {
+    let mut hasher = Blake2b256::default();
+    hasher.update([ED25519_SCHEME_FLAG]);
+    hasher.update(pk.as_bytes());
+    format!("0x{}", hex::encode(hasher.finalize().digest))
+}
+
+/// Response for the keys endpoint.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct KeysResponse {
+    /// Signature scheme of the enclave key.
+    pub scheme: String,
+    /// Identifier of the key, see [`key_id`].
+    pub key_id: String,
+    /// Raw public key bytes, hex encoded.
+    pub public_key_hex: String,
+    /// Raw public key bytes, base64 encoded.
+    pub public_key_base64: String,
+    /// Sui address derived from the public key.
+    pub sui_address: String,
+}
+
+/// Endpoint that returns the enclave public key in the encodings integrators need.
+pub async fn get_keys(State(state): State<Arc<AppState>>, format: ResponseFormat) -> Negotiated<KeysResponse> {
+    let pk = state.eph_kp.public();
+
+    Negotiated(format, KeysResponse {
+        scheme: "ed25519".to_string(),
+        key_id: key_id(pk),
+        public_key_hex: Hex::encode(pk.as_bytes()),
+        public_key_base64: Base64::encode(pk.as_bytes()),
+        sui_address: sui_address(pk),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fastcrypto::ed25519::Ed25519KeyPair;
+
+    #[test]
+    fn test_intent_scope_wire_bytes_are_stable() {
+        // Changing any of these invalidates signatures the Move contract has already accepted
+        let expected = [
+            (IntentScope::Generic, 0u8),
+            (IntentScope::KYCVerification, 1),
+            (IntentScope::NegativeVerification, 2),
+            (IntentScope::Heartbeat, 3),
+            (IntentScope::BatchVerification, 4),
+            (IntentScope::VerificationResult, 5),
+            (IntentScope::LagAlert, 6),
+        ];
+        assert_eq!(expected.len(), IntentScope::ALL.len());
+        for (scope, byte) in expected {
+            assert_eq!(scope.as_byte(), byte);
+            assert_eq!(bcs::to_bytes(&scope).unwrap(), vec![byte]);
+            assert_eq!(serde_json::to_string(&scope).unwrap(), byte.to_string());
+            assert_eq!(IntentScope::from_byte(byte), Some(scope));
+        }
+        assert_eq!(IntentScope::from_byte(7), None);
+    }
+
+    /// Signing timestamp of every golden vector.
+    const GOLDEN_TIMESTAMP_MS: u64 = 1_700_000_000_000;
+
+    /// BCS of the signed intent message and the signature over it, both hex.
+    fn golden<T: Serialize + Clone>(kp: &Ed25519KeyPair, payload: T, intent: IntentScope) -> (String, String) {
+        let signed = to_signed_response(kp, payload, GOLDEN_TIMESTAMP_MS, intent).unwrap();
+        (Hex::encode(bcs::to_bytes(&signed.response).unwrap()), signed.signature)
+    }
+
+    #[test]
+    fn test_signed_bytes_match_golden_vectors() {
+        use crate::app::KYCResponse;
+        use crate::attestation_store::VerificationAttestation;
+        use crate::heartbeat::Heartbeat;
+        use crate::lag_alert::{LagAlert, LagState};
+        use crate::negative_attestation::NegativeAttestation;
+
+        // Ed25519 signing is deterministic, so a fixed key pins the signatures too. If this
+        // fails, signatures the contract and integrators verify have changed: don't update
+        // the vectors unless that is the intent.
+        let kp = Ed25519KeyPair::from_bytes(&[7u8; 32]).unwrap();
+        let wallet = format!("0x{}", "ab".repeat(32));
+        let evidence_hash = "cd".repeat(32);
+        let vectors = [
+            golden(&kp, "payload".to_string(), IntentScope::Generic),
+            golden(
+                &kp,
+                KYCResponse { verified: true, wallet_address: wallet.clone(), attestation_hash: "ef".repeat(32) },
+                IntentScope::KYCVerification,
+            ),
+            golden(
+                &kp,
+                NegativeAttestation {
+                    user_wallet: wallet.clone(),
+                    did_id: 0,
+                    verified: false,
+                    reason_hash: "12".repeat(32),
+                    evidence_hash: evidence_hash.clone(),
+                    verified_at: "2023-11-14T22:13:20Z".to_string(),
+                },
+                IntentScope::NegativeVerification,
+            ),
+            golden(
+                &kp,
+                Heartbeat { key_id: "key".to_string(), public_key: "34".repeat(32), sequence: 3, started_at_ms: 1_699_999_000_000 },
+                IntentScope::Heartbeat,
+            ),
+            golden(
+                &kp,
+                VerificationAttestation {
+                    user_wallet: wallet.clone(),
+                    verification_type: "pan".to_string(),
+                    did_id: 0,
+                    result: "verified".to_string(),
+                    evidence_hash,
+                    evidence_schema: "pan_v1".to_string(),
+                    evidence_profile: "full".to_string(),
+                    verified_at: "2023-11-14T22:13:20Z".to_string(),
+                },
+                IntentScope::VerificationResult,
+            ),
+            golden(
+                &kp,
+                LagAlert {
+                    state: LagState::Behind,
+                    lag: 250,
+                    threshold: 100,
+                    consumer_group: "attestation_processors".to_string(),
+                    streams: vec!["verification_stream".to_string()],
+                    key_id: "key".to_string(),
+                },
+                IntentScope::LagAlert,
+            ),
+        ];
+        // (BCS of the intent message, signature), per scope in wire-byte order. BatchVerification
+        // has no payload type yet; add its vector with one
+        let expected = [
+            // Generic
+            (
+                "000068e5cf8b010000077061796c6f6164",
+                "c18cf48c33f79d43514986535c06cb75411431f76ce16a59e944fae56a3c270bca3222d33a9f41b837d7f511ad6d889e986ca830e5da5acb00026fdfe6a66b04",
+            ),
+            // KYCVerification
+            (
+                "010068e5cf8b01000001423078616261626162616261626162616261626162616261626162616261626162616261626162616261626162616261626162616261626162616261626162616261624065666566656665666566656665666566656665666566656665666566656665666566656665666566656665666566656665666566656665666566656665666566",
+                "5845c467d79604c37a2364d54b6d5b72491156bf4361adcf7b6ba71cf0437fd499adee6b40bba731e9fb1998931bb8a6bda27765a206af492a4714ea537c5f02",
+            ),
+            // NegativeVerification
+            (
+                "020068e5cf8b0100004230786162616261626162616261626162616261626162616261626162616261626162616261626162616261626162616261626162616261626162616261626162616200004031323132313231323132313231323132313231323132313231323132313231323132313231323132313231323132313231323132313231323132313231323132406364636463646364636463646364636463646364636463646364636463646364636463646364636463646364636463646364636463646364636463646364636414323032332d31312d31345432323a31333a32305a",
+                "23db5098311e579bf0769ee7b9e17f79717398c19b7fd1082eaeb62e8affa418d47d88111e2dc5bb838576b5d17068ce6afdf037e4f0e447874cf281b8605f0f",
+            ),
+            // Heartbeat
+            (
+                "030068e5cf8b010000036b657940333433343334333433343334333433343334333433343334333433343334333433343334333433343334333433343334333433343334333433343334333433340300000000000000c025d6cf8b010000",
+                "3a09476366f4e26ba7db39f5f1ef4beaa2e0949805b1b47d4bf5454ae1f633031c72e84f6ca2140359eab5ccbf93e60b3a20b0661b37088031880602085bbf05",
+            ),
+            // VerificationResult
+            (
+                "050068e5cf8b010000423078616261626162616261626162616261626162616261626162616261626162616261626162616261626162616261626162616261626162616261626162616261620370616e0008766572696669656440636463646364636463646364636463646364636463646364636463646364636463646364636463646364636463646364636463646364636463646364636463640670616e5f76310466756c6c14323032332d31312d31345432323a31333a32305a",
+                "9188d673f50b62a8d5fad544e75e5c6b5f04e7c4d4d6a14de7f7c2b063101750e00cf9051d6f3723488a948b88f2ad322ad1e8a8afa7761dd290efd8a38bc507",
+            ),
+            // LagAlert
+            (
+                "060068e5cf8b01000000fa000000000000006400000000000000166174746573746174696f6e5f70726f636573736f72730113766572696669636174696f6e5f73747265616d036b6579",
+                "79bb211cd4624b5bbff94d87c24cd2de7e08b40a9025d90fd6aa09444b6e3ee5c860cd2cd1e3d1473239ba4b07fe148a6b40f3e0f3010c997315f130d1be5008",
+            ),
+        ];
+        assert_eq!(vectors.len(), expected.len());
+        for ((bytes, signature), (expected_bytes, expected_signature)) in vectors.iter().zip(expected) {
+            assert_eq!(bytes, expected_bytes);
+            assert_eq!(signature, expected_signature);
+        }
+    }
+
+    #[test]
+    fn test_signing_self_test_catches_a_mismatched_key() {
+        let kp = Ed25519KeyPair::generate(&mut rand::thread_rng());
+        assert!(signing_self_test(&kp, kp.public()).is_ok());
+
+        let other = Ed25519KeyPair::generate(&mut rand::thread_rng());
+        assert!(matches!(signing_self_test(&kp, other.public()), Err(EnclaveError::GenericError(_))));
+    }
+
+    #[test]
+    fn test_unknown_intent_scope_is_rejected() {
+        assert_eq!(IntentScope::from_byte(255), None);
+        // An unknown scope can't reach the signer through deserialization either
+        assert!(serde_json::from_str::<IntentScope>("7").is_err());
+    }
+
+    #[test]
+    fn test_signed_timestamp_skew_boundaries() {
+        let kp = Ed25519KeyPair::generate(&mut rand::thread_rng());
+        let window = SkewWindow { max_age_ms: 60_000, max_future_ms: 5_000 };
+        let now_ms = 1_700_000_000_000;
+        let signed_at = |timestamp_ms| to_signed_response(&kp, "payload".to_string(), timestamp_ms, IntentScope::Generic).unwrap();
+        let verify = |timestamp_ms| verify_signed_response_within(kp.public(), &signed_at(timestamp_ms), now_ms, &window);
+
+        // Exactly at either edge is accepted, one millisecond past is not
+        assert!(verify(now_ms).is_ok());
+        assert!(verify(now_ms - 60_000).is_ok());
+        assert!(verify(now_ms + 5_000).is_ok());
+        assert!(matches!(
+            verify(now_ms - 60_001),
+            Err(EnclaveError::Timestamp(TimestampError::TooOld { age_ms: 60_001, .. }))
+        ));
+        assert!(matches!(
+            verify(now_ms + 5_001),
+            Err(EnclaveError::Timestamp(TimestampError::InFuture { ahead_ms: 5_001, .. }))
+        ));
+
+        // A bad signature is still reported as such, whatever the timestamp
+        let mut tampered = signed_at(now_ms);
+        tampered.response.data = "other".to_string();
+        assert!(matches!(
+            verify_signed_response_within(kp.public(), &tampered, now_ms, &window),
+            Err(EnclaveError::GenericError(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_keys_match_enclave_public_key() {
+        let eph_kp = Ed25519KeyPair::generate(&mut rand::thread_rng());
+        let expected = eph_kp.public().clone();
+        let state = Arc::new(AppState::new(eph_kp));
+
+        let Negotiated(_, keys) = get_keys(State(state), ResponseFormat::Json).await;
+
+        assert_eq!(keys.scheme, "ed25519");
+        assert_eq!(Hex::decode(&keys.public_key_hex).unwrap(), expected.as_bytes());
+        assert_eq!(Base64::decode(&keys.public_key_base64).unwrap(), expected.as_bytes());
+        assert_eq!(keys.key_id, key_id(&expected));
+        assert_eq!(keys.key_id.len(), 16);
+        assert_eq!(keys.sui_address, sui_address(&expected));
+        assert_eq!(keys.sui_address.len(), 66);
+    }
+}
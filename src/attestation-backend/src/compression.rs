@@ -0,0 +1,107 @@
+// gzip/brotli compression of API responses, negotiated through Accept-Encoding
+use tower_http::compression::predicate::{NotForContentType, Predicate, SizeAbove};
+use tower_http::compression::CompressionLayer;
+use tracing::info;
+
+/// Default size below which a response is sent as is.
+pub const DEFAULT_MIN_COMPRESS_BYTES: u16 = 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CompressionConfig {
+    pub enabled: bool,
+    /// Responses with a body of at most this many bytes are not compressed.
+    pub min_size_bytes: u16,
+}
+
+impl Default for CompressionConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            min_size_bytes: DEFAULT_MIN_COMPRESS_BYTES,
+        }
+    }
+}
+
+impl CompressionConfig {
+    /// `RESPONSE_COMPRESSION` (default true) and `RESPONSE_COMPRESSION_MIN_BYTES` (default 1024,
+    /// capped at 65535).
+    pub fn from_env() -> Self {
+        let config = Self {
+            enabled: std::env::var("RESPONSE_COMPRESSION")
+                .map(|v| !v.eq_ignore_ascii_case("false"))
+                .unwrap_or(true),
+            min_size_bytes: std::env::var("RESPONSE_COMPRESSION_MIN_BYTES")
+                .ok()
+                .and_then(|v| v.parse::<u64>().ok())
+                .map(|bytes| bytes.min(u16::MAX as u64) as u16)
+                .unwrap_or(DEFAULT_MIN_COMPRESS_BYTES),
+        };
+        info!("🗜️  Response compression: enabled={}, min size={} bytes", config.enabled, config.min_size_bytes);
+        config
+    }
+
+    /// gzip and brotli for responses above the threshold, whichever the client prefers. Images
+    /// and event streams are never compressed. When disabled, every response passes through.
+    pub fn layer(&self) -> CompressionLayer<impl Predicate> {
+        let layer = if self.enabled {
+            CompressionLayer::new()
+        } else {
+            CompressionLayer::new().no_gzip().no_br()
+        };
+        layer.compress_when(
+            SizeAbove::new(self.min_size_bytes)
+                .and(NotForContentType::IMAGES)
+                .and(NotForContentType::SSE),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::{to_bytes, Body};
+    use axum::http::header::{ACCEPT_ENCODING, CONTENT_ENCODING};
+    use axum::http::{Request, Response};
+    use axum::routing::get;
+    use axum::{Json, Router};
+    use tower::Service;
+
+    fn app(config: CompressionConfig) -> Router {
+        Router::new()
+            .route("/large", get(|| async { Json(vec!["attestation document bytes"; 200]) }))
+            .route("/small", get(|| async { Json("ok") }))
+            .layer(config.layer())
+    }
+
+    async fn get_with(app: &mut Router, path: &str, accept_encoding: Option<&str>) -> Response<Body> {
+        let mut request = Request::get(path);
+        if let Some(encoding) = accept_encoding {
+            request = request.header(ACCEPT_ENCODING, encoding);
+        }
+        app.call(request.body(Body::empty()).unwrap()).await.unwrap()
+    }
+
+    fn encoding(response: &Response<Body>) -> Option<&str> {
+        response.headers().get(CONTENT_ENCODING).map(|v| v.to_str().unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_only_large_responses_are_compressed_for_supporting_clients() {
+        let mut app = app(CompressionConfig::default());
+
+        let large = get_with(&mut app, "/large", Some("gzip")).await;
+        assert_eq!(encoding(&large), Some("gzip"));
+        let compressed = to_bytes(large.into_body(), usize::MAX).await.unwrap();
+        assert!(compressed.len() < 1024, "{} bytes after gzip", compressed.len());
+
+        assert_eq!(encoding(&get_with(&mut app, "/large", Some("br")).await), Some("br"));
+        assert_eq!(encoding(&get_with(&mut app, "/large", None).await), None);
+        assert_eq!(encoding(&get_with(&mut app, "/small", Some("gzip, br")).await), None);
+
+        let mut disabled = self::app(CompressionConfig { enabled: false, ..CompressionConfig::default() });
+        let large = get_with(&mut disabled, "/large", Some("gzip")).await;
+        assert_eq!(encoding(&large), None);
+        let body = to_bytes(large.into_body(), usize::MAX).await.unwrap();
+        assert!(body.len() > 1024);
+    }
+}
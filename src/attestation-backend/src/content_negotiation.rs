@@ -0,0 +1,152 @@
+// Accept-header negotiation between JSON (default) and CBOR response bodies
+use axum::async_trait;
+use axum::extract::FromRequestParts;
+use axum::http::header::{ACCEPT, CONTENT_TYPE};
+use axum::http::request::Parts;
+use axum::http::HeaderValue;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use serde::Serialize;
+
+use crate::EnclaveError;
+
+pub const APPLICATION_JSON: &str = "application/json";
+pub const APPLICATION_CBOR: &str = "application/cbor";
+
+/// Body encoding chosen from the request's `Accept` header.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResponseFormat {
+    Json,
+    Cbor,
+}
+
+impl ResponseFormat {
+    /// Pick a format from an `Accept` value, honouring q-values. A missing header means JSON;
+    /// a header that names neither JSON, CBOR nor a matching wildcard is not acceptable.
+    pub fn from_accept(accept: Option<&str>) -> Result<Self, EnclaveError> {
+        let Some(accept) = accept.map(str::trim).filter(|a| !a.is_empty()) else {
+            return Ok(ResponseFormat::Json);
+        };
+
+        let mut best: Option<(f32, ResponseFormat)> = None;
+        for range in accept.split(',') {
+            let mut params = range.split(';');
+            let media_type = params.next().unwrap_or("").trim().to_lowercase();
+            let quality = params
+                .filter_map(|p| p.trim().strip_prefix("q="))
+                .find_map(|q| q.parse::<f32>().ok())
+                .unwrap_or(1.0);
+            if quality <= 0.0 {
+                continue;
+            }
+
+            let format = match media_type.as_str() {
+                APPLICATION_JSON | "application/*" | "*/*" => ResponseFormat::Json,
+                APPLICATION_CBOR => ResponseFormat::Cbor,
+                _ => continue,
+            };
+            // Earlier ranges win ties
+            if best.is_none_or(|(q, _)| quality > q) {
+                best = Some((quality, format));
+            }
+        }
+
+        best.map(|(_, format)| format).ok_or_else(|| {
+            EnclaveError::NotAcceptable(format!(
+                "Unsupported Accept: {} (supported: {}, {})",
+                accept, APPLICATION_JSON, APPLICATION_CBOR
+            ))
+        })
+    }
+}
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for ResponseFormat {
+    type Rejection = EnclaveError;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        ResponseFormat::from_accept(parts.headers.get(ACCEPT).and_then(|v| v.to_str().ok()))
+    }
+}
+
+/// A response body serialized in the negotiated format.
+pub struct Negotiated<T>(pub ResponseFormat, pub T);
+
+impl<T: Serialize> IntoResponse for Negotiated<T> {
+    fn into_response(self) -> Response {
+        match self.0 {
+            ResponseFormat::Json => Json(self.1).into_response(),
+            ResponseFormat::Cbor => {
+                let mut body = Vec::new();
+                if let Err(e) = ciborium::ser::into_writer(&self.1, &mut body) {
+                    return EnclaveError::Internal(format!("CBOR encoding failed: {}", e)).into_response();
+                }
+                let mut response = body.into_response();
+                response
+                    .headers_mut()
+                    .insert(CONTENT_TYPE, HeaderValue::from_static(APPLICATION_CBOR));
+                response
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::{get_attestation, GetAttestationCborResponse};
+    use crate::AppState;
+    use axum::body::{to_bytes, Body};
+    use axum::http::{Request, StatusCode};
+    use axum::{routing::get, Router};
+    use fastcrypto::ed25519::Ed25519KeyPair;
+    use fastcrypto::traits::KeyPair;
+    use std::sync::Arc;
+    use tower::Service;
+
+    fn app() -> Router {
+        let state = Arc::new(AppState::new(Ed25519KeyPair::generate(&mut rand::thread_rng())));
+        Router::new()
+            .route("/get_attestation", get(get_attestation))
+            .with_state(state)
+    }
+
+    #[test]
+    fn test_accept_parsing() {
+        assert_eq!(ResponseFormat::from_accept(None).unwrap(), ResponseFormat::Json);
+        assert_eq!(ResponseFormat::from_accept(Some("*/*")).unwrap(), ResponseFormat::Json);
+        assert_eq!(ResponseFormat::from_accept(Some("application/cbor")).unwrap(), ResponseFormat::Cbor);
+        assert_eq!(
+            ResponseFormat::from_accept(Some("application/json;q=0.5, application/cbor")).unwrap(),
+            ResponseFormat::Cbor
+        );
+        assert!(ResponseFormat::from_accept(Some("text/html, application/cbor;q=0")).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_get_attestation_as_cbor() {
+        let request = Request::builder()
+            .uri("/get_attestation")
+            .header(ACCEPT, APPLICATION_CBOR)
+            .body(Body::empty())
+            .unwrap();
+        let response = app().call(request).await.unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[CONTENT_TYPE], APPLICATION_CBOR);
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let decoded: GetAttestationCborResponse = ciborium::de::from_reader(body.as_ref()).unwrap();
+        assert!(!decoded.attestation.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_unsupported_accept_is_406() {
+        let request = Request::builder()
+            .uri("/get_attestation")
+            .header(ACCEPT, "text/html")
+            .body(Body::empty())
+            .unwrap();
+        let response = app().call(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_ACCEPTABLE);
+    }
+}
@@ -0,0 +1,424 @@
+// Decision policies that turn a government API response into a "verified" / "failed" result
+use anyhow::{Result, anyhow};
+use std::collections::HashMap;
+use tracing::info;
+
+use crate::government_api::PanVerificationData;
+
+/// Rule set used to decide whether a government API response counts as verified.
+///
+/// Different products need different guarantees: a full KYC check must match
+/// both name and date of birth, while an age check only cares about the DOB.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DecisionPolicy {
+    /// `status == "valid"` and both the name and date of birth match (default).
+    Full,
+    /// `status == "valid"` and the date of birth matches. Name is ignored.
+    DobOnly,
+    /// `status == "valid"` and the name matches. Date of birth is ignored.
+    NameOnly,
+    /// Only requires `status == "valid"`.
+    StatusOnly,
+}
+
+impl DecisionPolicy {
+    pub fn parse(value: &str) -> Result<Self> {
+        match value.trim().to_lowercase().as_str() {
+            "full" => Ok(DecisionPolicy::Full),
+            "dob_only" => Ok(DecisionPolicy::DobOnly),
+            "name_only" => Ok(DecisionPolicy::NameOnly),
+            "status_only" => Ok(DecisionPolicy::StatusOnly),
+            other => Err(anyhow!("Unknown decision policy: {}", other)),
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DecisionPolicy::Full => "full",
+            DecisionPolicy::DobOnly => "dob_only",
+            DecisionPolicy::NameOnly => "name_only",
+            DecisionPolicy::StatusOnly => "status_only",
+        }
+    }
+
+    /// Returns true when the response satisfies every rule of this policy.
+    pub fn is_verified(&self, data: &PanVerificationData) -> bool {
+        self.evaluate(data).verified
+    }
+
+    /// Evaluate the response, reporting the first rule that failed.
+    pub fn evaluate(&self, data: &PanVerificationData) -> Decision {
+        if data.status != "valid" {
+            return Decision::rejected(format!("PAN status is '{}'", data.status));
+        }
+        self.evaluate_matches(data)
+    }
+
+    /// The name and date of birth rules alone, for a status already known to be acceptable.
+    pub fn evaluate_matches(&self, data: &PanVerificationData) -> Decision {
+        let (check_name, check_dob) = match self {
+            DecisionPolicy::Full => (true, true),
+            DecisionPolicy::DobOnly => (false, true),
+            DecisionPolicy::NameOnly => (true, false),
+            DecisionPolicy::StatusOnly => (false, false),
+        };
+        if check_name && !data.name_as_per_pan_match {
+            return Decision::rejected("name does not match PAN records");
+        }
+        if check_dob && !data.date_of_birth_match {
+            return Decision::rejected("date of birth does not match PAN records");
+        }
+        Decision::verified()
+    }
+}
+
+/// What a PAN `status` from the government API means for the request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StatusOutcome {
+    /// Go on to the policy's name and date of birth rules.
+    Verified,
+    /// A final rejection.
+    Rejected,
+    /// Neither verified nor rejected: left for a person to decide.
+    Review,
+    /// The answer isn't final yet; the message is retried later.
+    Retry,
+}
+
+impl StatusOutcome {
+    pub fn parse(value: &str) -> Result<Self> {
+        match value.trim().to_lowercase().as_str() {
+            "verified" => Ok(StatusOutcome::Verified),
+            "rejected" => Ok(StatusOutcome::Rejected),
+            "review" => Ok(StatusOutcome::Review),
+            "retry" => Ok(StatusOutcome::Retry),
+            other => Err(anyhow!("Unknown status outcome: {}", other)),
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            StatusOutcome::Verified => "verified",
+            StatusOutcome::Rejected => "rejected",
+            StatusOutcome::Review => "review",
+            StatusOutcome::Retry => "retry",
+        }
+    }
+}
+
+/// Statuses with a known meaning; anything else the upstream sends goes to review.
+const DEFAULT_STATUS_OUTCOMES: [(&str, StatusOutcome); 5] = [
+    ("valid", StatusOutcome::Verified),
+    ("invalid", StatusOutcome::Rejected),
+    ("fake", StatusOutcome::Rejected),
+    ("deleted", StatusOutcome::Rejected),
+    ("deactivated", StatusOutcome::Rejected),
+];
+
+/// Outcome of each PAN status. `PAN_STATUS_OUTCOMES`, e.g. `not-present=retry,deleted=review`,
+/// adds to or overrides [`DEFAULT_STATUS_OUTCOMES`]; a status in neither is sent to review
+/// rather than failed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StatusHandling {
+    by_status: HashMap<String, StatusOutcome>,
+}
+
+impl Default for StatusHandling {
+    fn default() -> Self {
+        Self {
+            by_status: DEFAULT_STATUS_OUTCOMES
+                .iter()
+                .map(|(status, outcome)| (status.to_string(), *outcome))
+                .collect(),
+        }
+    }
+}
+
+impl StatusHandling {
+    pub fn from_env() -> Result<Self> {
+        Self::parse(&std::env::var("PAN_STATUS_OUTCOMES").unwrap_or_default())
+    }
+
+    /// Parse a comma separated `status=outcome` list on top of the defaults.
+    pub fn parse(table: &str) -> Result<Self> {
+        let mut handling = Self::default();
+        for entry in table.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+            let (status, outcome) = entry
+                .split_once('=')
+                .ok_or_else(|| anyhow!("Invalid status outcome entry (expected status=outcome): {}", entry))?;
+            handling.by_status.insert(status.trim().to_lowercase(), StatusOutcome::parse(outcome)?);
+        }
+        Ok(handling)
+    }
+
+    pub fn outcome(&self, status: &str) -> StatusOutcome {
+        self.by_status
+            .get(&status.trim().to_lowercase())
+            .copied()
+            .unwrap_or(StatusOutcome::Review)
+    }
+}
+
+/// Outcome of applying a policy, with the reason when verification failed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Decision {
+    pub verified: bool,
+    pub reason: Option<String>,
+    /// Neither verified nor rejected; see [`StatusOutcome::Review`].
+    pub review: bool,
+}
+
+impl Decision {
+    pub fn verified() -> Self {
+        Self {
+            verified: true,
+            reason: None,
+            review: false,
+        }
+    }
+
+    pub fn rejected(reason: impl Into<String>) -> Self {
+        Self {
+            verified: false,
+            reason: Some(reason.into()),
+            review: false,
+        }
+    }
+
+    pub fn review(reason: impl Into<String>) -> Self {
+        Self {
+            verified: false,
+            reason: Some(reason.into()),
+            review: true,
+        }
+    }
+}
+
+/// Per-`verification_type` policy table.
+///
+/// Configured through `VERIFICATION_DECISION_POLICIES`, e.g. `age=dob_only,pan=full`.
+/// Types without an entry fall back to `DECISION_POLICY_DEFAULT` (itself `full` by default).
+/// `REQUIRE_AADHAAR_SEEDING=true` additionally requires `aadhaar_seeding_status == "y"`
+/// for every type; it is off by default. The PAN status is classified first, by `status_handling`.
+#[derive(Debug, Clone)]
+pub struct DecisionPolicies {
+    default_policy: DecisionPolicy,
+    by_type: HashMap<String, DecisionPolicy>,
+    pub require_aadhaar_seeding: bool,
+    pub status_handling: StatusHandling,
+}
+
+impl Default for DecisionPolicies {
+    fn default() -> Self {
+        Self {
+            default_policy: DecisionPolicy::Full,
+            by_type: HashMap::new(),
+            require_aadhaar_seeding: false,
+            status_handling: StatusHandling::default(),
+        }
+    }
+}
+
+impl DecisionPolicies {
+    pub fn from_env() -> Result<Self> {
+        let default_policy = match std::env::var("DECISION_POLICY_DEFAULT") {
+            Ok(value) => DecisionPolicy::parse(&value)?,
+            Err(_) => DecisionPolicy::Full,
+        };
+        let table = std::env::var("VERIFICATION_DECISION_POLICIES").unwrap_or_default();
+
+        let mut policies = Self::parse(default_policy, &table)?;
+        policies.require_aadhaar_seeding = std::env::var("REQUIRE_AADHAAR_SEEDING")
+            .map(|v| v == "1" || v.eq_ignore_ascii_case("true"))
+            .unwrap_or(false);
+        policies.status_handling = StatusHandling::from_env()?;
+        info!("Decision policies: default={} overrides={:?} require_aadhaar_seeding={} status_outcomes={:?}",
+              policies.default_policy.as_str(), policies.by_type, policies.require_aadhaar_seeding,
+              policies.status_handling.by_status);
+        Ok(policies)
+    }
+
+    /// Parse a comma separated `type=policy` list.
+    pub fn parse(default_policy: DecisionPolicy, table: &str) -> Result<Self> {
+        let mut by_type = HashMap::new();
+        for entry in table.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+            let (verification_type, policy) = entry
+                .split_once('=')
+                .ok_or_else(|| anyhow!("Invalid decision policy entry (expected type=policy): {}", entry))?;
+            by_type.insert(
+                verification_type.trim().to_lowercase(),
+                DecisionPolicy::parse(policy)?,
+            );
+        }
+
+        Ok(Self {
+            default_policy,
+            by_type,
+            require_aadhaar_seeding: false,
+            status_handling: StatusHandling::default(),
+        })
+    }
+
+    /// Fill in policies from the verification type table; entries set through
+    /// `VERIFICATION_DECISION_POLICIES` take precedence.
+    pub fn with_type_policies<'a>(mut self, policies: impl Iterator<Item = (&'a str, DecisionPolicy)>) -> Self {
+        for (verification_type, policy) in policies {
+            self.by_type.entry(verification_type.trim().to_lowercase()).or_insert(policy);
+        }
+        self
+    }
+
+    pub fn for_type(&self, verification_type: &str) -> DecisionPolicy {
+        self.by_type
+            .get(&verification_type.trim().to_lowercase())
+            .copied()
+            .unwrap_or(self.default_policy)
+    }
+
+    /// Classify the PAN status, then apply the type's policy plus the global Aadhaar seeding
+    /// requirement. A [`StatusOutcome::Retry`] status is the caller's to retry; here it is a review.
+    pub fn evaluate(&self, verification_type: &str, data: &PanVerificationData) -> Decision {
+        match self.status_handling.outcome(&data.status) {
+            StatusOutcome::Verified => {}
+            StatusOutcome::Rejected => return Decision::rejected(format!("PAN status is '{}'", data.status)),
+            StatusOutcome::Review | StatusOutcome::Retry => {
+                return Decision::review(format!("PAN status '{}' needs manual review", data.status));
+            }
+        }
+        let decision = self.for_type(verification_type).evaluate_matches(data);
+        if decision.verified
+            && self.require_aadhaar_seeding
+            && !data.aadhaar_seeding_status.trim().eq_ignore_ascii_case("y")
+        {
+            return Decision::rejected(format!(
+                "PAN is not Aadhaar-seeded (aadhaar_seeding_status='{}')",
+                data.aadhaar_seeding_status
+            ));
+        }
+        decision
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn response(status: &str, name_match: bool, dob_match: bool) -> PanVerificationData {
+        seeded_response(status, name_match, dob_match, "y")
+    }
+
+    fn seeded_response(status: &str, name_match: bool, dob_match: bool, seeding: &str) -> PanVerificationData {
+        PanVerificationData {
+            entity: "in.co.sandbox.kyc.pan_verification.response".to_string(),
+            pan: "HJTPB9891M".to_string(),
+            status: status.to_string(),
+            remarks: None,
+            name_as_per_pan_match: name_match,
+            date_of_birth_match: dob_match,
+            category: "individual".to_string(),
+            aadhaar_seeding_status: seeding.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_full_policy_requires_all_fields() {
+        let policy = DecisionPolicy::Full;
+        assert!(policy.is_verified(&response("valid", true, true)));
+        assert!(!policy.is_verified(&response("valid", false, true)));
+        assert!(!policy.is_verified(&response("valid", true, false)));
+        assert!(!policy.is_verified(&response("invalid", true, true)));
+    }
+
+    #[test]
+    fn test_dob_only_policy_ignores_name() {
+        let policy = DecisionPolicy::DobOnly;
+        assert!(policy.is_verified(&response("valid", false, true)));
+        assert!(!policy.is_verified(&response("valid", true, false)));
+        assert!(!policy.is_verified(&response("invalid", true, true)));
+    }
+
+    #[test]
+    fn test_name_only_policy_ignores_dob() {
+        let policy = DecisionPolicy::NameOnly;
+        assert!(policy.is_verified(&response("valid", true, false)));
+        assert!(!policy.is_verified(&response("valid", false, true)));
+        assert!(!policy.is_verified(&response("invalid", true, true)));
+    }
+
+    #[test]
+    fn test_status_only_policy() {
+        let policy = DecisionPolicy::StatusOnly;
+        assert!(policy.is_verified(&response("valid", false, false)));
+        assert!(!policy.is_verified(&response("invalid", true, true)));
+    }
+
+    #[test]
+    fn test_policy_table_lookup() {
+        let policies = DecisionPolicies::parse(DecisionPolicy::Full, "age=dob_only, Citizenship = status_only").unwrap();
+        assert_eq!(policies.for_type("age"), DecisionPolicy::DobOnly);
+        assert_eq!(policies.for_type("AGE"), DecisionPolicy::DobOnly);
+        assert_eq!(policies.for_type("citizenship"), DecisionPolicy::StatusOnly);
+        assert_eq!(policies.for_type("pan"), DecisionPolicy::Full);
+    }
+
+    #[test]
+    fn test_policy_table_rejects_bad_entries() {
+        assert!(DecisionPolicies::parse(DecisionPolicy::Full, "age").is_err());
+        assert!(DecisionPolicies::parse(DecisionPolicy::Full, "age=lenient").is_err());
+    }
+
+    #[test]
+    fn test_rejection_reports_reason() {
+        let decision = DecisionPolicy::Full.evaluate(&response("valid", true, false));
+        assert!(!decision.verified);
+        assert_eq!(decision.reason.as_deref(), Some("date of birth does not match PAN records"));
+        assert_eq!(DecisionPolicy::Full.evaluate(&response("valid", true, true)), Decision::verified());
+    }
+
+    #[test]
+    fn test_aadhaar_seeding_not_required_by_default() {
+        let policies = DecisionPolicies::default();
+        assert!(policies.evaluate("pan", &seeded_response("valid", true, true, "y")).verified);
+        assert!(policies.evaluate("pan", &seeded_response("valid", true, true, "n")).verified);
+    }
+
+    #[test]
+    fn test_aadhaar_seeding_required() {
+        let policies = DecisionPolicies {
+            require_aadhaar_seeding: true,
+            ..DecisionPolicies::default()
+        };
+        assert!(policies.evaluate("pan", &seeded_response("valid", true, true, "y")).verified);
+
+        let decision = policies.evaluate("pan", &seeded_response("valid", true, true, "n"));
+        assert!(!decision.verified);
+        assert!(decision.reason.unwrap().contains("not Aadhaar-seeded"));
+
+        // Failures from the base policy keep their own reason
+        let decision = policies.evaluate("pan", &seeded_response("invalid", true, true, "n"));
+        assert_eq!(decision.reason.as_deref(), Some("PAN status is 'invalid'"));
+    }
+
+    #[test]
+    fn test_statuses_map_to_their_configured_outcomes() {
+        let handling = StatusHandling::parse("not-present=retry, Deleted=review, active=verified").unwrap();
+        assert_eq!(handling.outcome("valid"), StatusOutcome::Verified);
+        assert_eq!(handling.outcome("active"), StatusOutcome::Verified);
+        assert_eq!(handling.outcome("fake"), StatusOutcome::Rejected);
+        assert_eq!(handling.outcome("deactivated"), StatusOutcome::Rejected);
+        assert_eq!(handling.outcome("deleted"), StatusOutcome::Review);
+        assert_eq!(handling.outcome(" NOT-PRESENT "), StatusOutcome::Retry);
+        // Nobody said what this one means
+        assert_eq!(handling.outcome("suspended"), StatusOutcome::Review);
+        assert!(StatusHandling::parse("fake=ignore").is_err());
+
+        let policies = DecisionPolicies { status_handling: handling, ..DecisionPolicies::default() };
+        assert_eq!(policies.evaluate("pan", &response("active", true, true)), Decision::verified());
+        // Still subject to the policy's rules
+        assert!(!policies.evaluate("pan", &response("active", false, true)).verified);
+        assert_eq!(policies.evaluate("pan", &response("fake", true, true)), Decision::rejected("PAN status is 'fake'"));
+        let review = policies.evaluate("pan", &response("suspended", true, true));
+        assert!(review.review && !review.verified);
+        assert_eq!(review.reason.as_deref(), Some("PAN status 'suspended' needs manual review"));
+    }
+}
@@ -0,0 +1,330 @@
+// Deferred verifications: messages parked while the government API is unreachable, re-enqueued once it recovers
+use anyhow::{Result, anyhow};
+use redis::aio::Connection;
+use redis::streams::StreamRangeReply;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use tokio::time::{Duration, sleep};
+use tracing::{error, info};
+
+use crate::circuit_breaker::CircuitBreaker;
+use crate::government_api::VerificationRequest;
+use crate::message_source::stream_names_from_env;
+use crate::metrics;
+use crate::redis_timeout::{with_timeout, RedisTimeouts};
+use crate::sealed_blob::{open_text, seal_text, SealingKey, PII_FIELDS};
+use crate::verification_processor::RedisConnector;
+
+const ORIGINAL_ID_FIELD: &str = "deferred_original_id";
+const RETRY_AFTER_FIELD: &str = "deferred_retry_after_ms";
+const ORIGIN_STREAM_FIELD: &str = "deferred_origin_stream";
+
+/// A verification parked in the deferred stream with its original stream fields.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeferredEntry {
+    pub original_id: String,
+    pub fields: BTreeMap<String, String>,
+    pub retry_after_ms: u64,
+    /// The stream to re-enqueue to, when not the primary one.
+    pub origin_stream: Option<String>,
+}
+
+impl DeferredEntry {
+    pub fn from_request(original_id: &str, request: &VerificationRequest, retry_after_ms: u64) -> Self {
+        let mut fields = BTreeMap::new();
+        fields.insert("user_wallet".to_string(), request.user_wallet.clone());
+        fields.insert("did_id".to_string(), request.did_id.clone());
+        fields.insert("verification_type".to_string(), request.verification_type.clone());
+        fields.insert("document_data".to_string(), request.document_data.clone());
+        if let Some(extracted_data) = &request.extracted_data {
+            fields.insert("extracted_data".to_string(), extracted_data.clone());
+        }
+        if let Some(user_corrections) = &request.user_corrections {
+            fields.insert("user_corrections".to_string(), user_corrections.clone());
+        }
+        fields.insert("timestamp".to_string(), request.timestamp.clone());
+        fields.insert("status".to_string(), request.status.clone());
+
+        Self {
+            original_id: original_id.to_string(),
+            fields,
+            retry_after_ms,
+            origin_stream: None,
+        }
+    }
+
+    pub fn with_origin_stream(mut self, stream: Option<&str>) -> Self {
+        self.origin_stream = stream.map(str::to_string);
+        self
+    }
+
+    /// Fields as written to the deferred stream: the original fields, with the document data
+    /// sealed under `key`, plus the deferral metadata.
+    pub fn to_stream_fields(&self, key: &SealingKey) -> Vec<(String, String)> {
+        let mut fields: Vec<(String, String)> = self
+            .fields
+            .iter()
+            .map(|(name, value)| match PII_FIELDS.contains(&name.as_str()) {
+                true => (name.clone(), seal_text(key, value)),
+                false => (name.clone(), value.clone()),
+            })
+            .collect();
+        fields.push((ORIGINAL_ID_FIELD.to_string(), self.original_id.clone()));
+        fields.push((RETRY_AFTER_FIELD.to_string(), self.retry_after_ms.to_string()));
+        if let Some(stream) = &self.origin_stream {
+            fields.push((ORIGIN_STREAM_FIELD.to_string(), stream.clone()));
+        }
+        fields
+    }
+
+    /// The entry [`Self::to_stream_fields`] wrote, failing if its document data doesn't open under `key`.
+    pub fn from_stream_fields(mut fields: BTreeMap<String, String>, key: &SealingKey) -> Result<Self> {
+        for name in PII_FIELDS {
+            if let Some(sealed) = fields.get_mut(name) {
+                *sealed = open_text(key, sealed).map_err(|e| anyhow!("Deferred {} doesn't open: {}", name, e))?;
+            }
+        }
+        let original_id = fields.remove(ORIGINAL_ID_FIELD).unwrap_or_default();
+        let origin_stream = fields.remove(ORIGIN_STREAM_FIELD);
+        let retry_after_ms = fields
+            .remove(RETRY_AFTER_FIELD)
+            .and_then(|v| v.parse::<u64>().ok())
+            .ok_or_else(|| anyhow!("Deferred entry is missing {}", RETRY_AFTER_FIELD))?;
+        Ok(Self {
+            original_id,
+            fields,
+            retry_after_ms,
+            origin_stream,
+        })
+    }
+
+    pub fn is_due(&self, now_ms: u64) -> bool {
+        self.retry_after_ms <= now_ms
+    }
+}
+
+/// The deferred entries, in stream order, due for re-enqueueing: those up to the first one still
+/// waiting, as every entry after it was deferred later. Nothing is due while the circuit is open.
+pub fn due_entries<T>(entries: Vec<(T, DeferredEntry)>, now_ms: u64, circuit_open: bool) -> Vec<(T, DeferredEntry)> {
+    if circuit_open {
+        return Vec::new();
+    }
+    entries.into_iter().take_while(|(_, entry)| entry.is_due(now_ms)).collect()
+}
+
+/// Deferred stream settings: `GOVT_API_DEFER_ENABLED`, `VERIFICATION_DEFERRED_STREAM`
+/// and `GOVT_API_DEFER_DELAY_SECS`. Re-enqueued entries go back to the stream they came from,
+/// or the primary stream. Document data is sealed while parked, so deferral needs a `KEY_SEALING`
+/// key that outlives a restart. Entries that don't open are deleted.
+#[derive(Debug, Clone)]
+pub struct DeferredQueue {
+    pub enabled: bool,
+    deferred_stream: String,
+    target_stream: String,
+    delay: Duration,
+    sealing_key: SealingKey,
+    command_timeout: Duration,
+    // Last entry re-enqueued; the next scan starts after it
+    cursor: Option<String>,
+}
+
+impl DeferredQueue {
+    const SCAN_COUNT: usize = 100;
+    const SCAN_INTERVAL_SECS: u64 = 10;
+
+    pub fn from_env(sealing_key: SealingKey) -> Self {
+        let queue = Self {
+            enabled: std::env::var("GOVT_API_DEFER_ENABLED")
+                .map(|v| v == "1" || v.eq_ignore_ascii_case("true"))
+                .unwrap_or(false),
+            deferred_stream: std::env::var("VERIFICATION_DEFERRED_STREAM")
+                .unwrap_or_else(|_| "verification_deferred".to_string()),
+            target_stream: stream_names_from_env().remove(0),
+            delay: Duration::from_secs(
+                std::env::var("GOVT_API_DEFER_DELAY_SECS")
+                    .ok()
+                    .and_then(|v| v.parse::<u64>().ok())
+                    .unwrap_or(60),
+            ),
+            sealing_key,
+            command_timeout: RedisTimeouts::from_env().command,
+            cursor: None,
+        };
+        if queue.enabled {
+            info!("Deferral enabled: unreachable government API parks messages in {}", queue.deferred_stream);
+        }
+        queue
+    }
+
+    pub fn retry_after_ms(&self, now_ms: u64) -> u64 {
+        now_ms + self.delay.as_millis() as u64
+    }
+
+    pub async fn defer(&self, conn: &mut Connection, entry: &DeferredEntry) -> Result<()> {
+        let mut cmd = redis::cmd("XADD");
+        cmd.arg(&self.deferred_stream).arg("*");
+        for (key, value) in entry.to_stream_fields(&self.sealing_key) {
+            cmd.arg(key).arg(value);
+        }
+        with_timeout("XADD", self.command_timeout, cmd.query_async::<_, String>(conn)).await?;
+        metrics::increment("verifications_deferred_total");
+        info!("⏸️ Deferred message {} until {}", entry.original_id, entry.retry_after_ms);
+        Ok(())
+    }
+
+    /// Move due entries back to the verification stream, scanning on from the last one moved.
+    /// Returns how many were re-enqueued.
+    pub async fn requeue_due(&mut self, conn: &mut Connection, now_ms: u64, circuit_open: bool) -> Result<usize> {
+        let start = self.cursor.as_ref().map_or_else(|| "-".to_string(), |id| format!("({}", id));
+        let mut range = redis::cmd("XRANGE");
+        range.arg(&self.deferred_stream).arg(start).arg("+").arg("COUNT").arg(Self::SCAN_COUNT);
+        let reply: StreamRangeReply = with_timeout("XRANGE", self.command_timeout, range.query_async(conn)).await?;
+
+        let mut entries = Vec::new();
+        for stream_id in reply.ids {
+            let fields: BTreeMap<String, String> = stream_id
+                .map
+                .iter()
+                .filter_map(|(k, v)| redis::from_redis_value::<String>(v).ok().map(|v| (k.clone(), v)))
+                .collect();
+            match DeferredEntry::from_stream_fields(fields, &self.sealing_key) {
+                Ok(entry) => entries.push((stream_id.id, entry)),
+                // Malformed, or sealed under a key since lost: it can never be re-enqueued
+                Err(e) => {
+                    error!("Dropping deferred entry {} that can't be re-enqueued: {}", stream_id.id, e);
+                    let mut xdel = redis::cmd("XDEL");
+                    xdel.arg(&self.deferred_stream).arg(&stream_id.id);
+                    with_timeout("XDEL", self.command_timeout, xdel.query_async::<_, i64>(conn)).await?;
+                    metrics::increment("deferred_entries_dropped_total");
+                }
+            }
+        }
+
+        let due = due_entries(entries, now_ms, circuit_open);
+        for (deferred_id, entry) in &due {
+            let target_stream = entry.origin_stream.as_deref().unwrap_or(&self.target_stream);
+            let mut cmd = redis::cmd("XADD");
+            cmd.arg(target_stream).arg("*");
+            for (key, value) in &entry.fields {
+                cmd.arg(key).arg(value);
+            }
+            with_timeout("XADD", self.command_timeout, cmd.query_async::<_, String>(conn)).await?;
+            let mut xdel = redis::cmd("XDEL");
+            xdel.arg(&self.deferred_stream).arg(deferred_id);
+            with_timeout("XDEL", self.command_timeout, xdel.query_async::<_, i64>(conn)).await?;
+            self.cursor = Some(deferred_id.clone());
+            info!("▶️ Re-enqueued deferred message {} to {}", entry.original_id, target_stream);
+        }
+        metrics::increment_by("verifications_requeued_total", due.len() as u64);
+        Ok(due.len())
+    }
+}
+
+/// Background task: once the circuit closes, move due deferred entries back to the verification stream.
+pub async fn run_requeue_task(mut queue: DeferredQueue, redis: RedisConnector, breaker: Arc<CircuitBreaker>) -> Result<()> {
+    let mut conn = redis.connect().await?;
+    loop {
+        sleep(Duration::from_secs(DeferredQueue::SCAN_INTERVAL_SECS)).await;
+
+        let now_ms = chrono::Utc::now().timestamp_millis() as u64;
+        if let Err(e) = queue.requeue_due(&mut conn, now_ms, breaker.is_open()).await {
+            error!("Failed to re-enqueue deferred messages: {}", e);
+            conn = redis.connect().await?;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::circuit_breaker::is_unavailable;
+
+    fn request() -> VerificationRequest {
+        VerificationRequest {
+            user_wallet: "0xabc".to_string(),
+            did_id: "0".to_string(),
+            verification_type: "pan".to_string(),
+            document_data: r#"{"pan":"HJTPB9891M"}"#.to_string(),
+            extracted_data: None,
+            user_corrections: None,
+            timestamp: "2025-01-01T00:00:00Z".to_string(),
+            status: "pending".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_message_during_open_circuit_is_deferred_then_requeued() {
+        let breaker = CircuitBreaker::new("defer_test_api", 1, Duration::from_millis(50));
+        breaker.record_failure();
+
+        // The API call is refused while the circuit is open, which is what triggers deferral
+        let err = anyhow::Error::new(breaker.check().unwrap_err());
+        assert!(is_unavailable(&err));
+        let entry = DeferredEntry::from_request("1700000000000-0", &request(), 1_000);
+
+        // Round-trips through the deferred stream with its original fields intact, the document sealed
+        let key = SealingKey::new([3u8; 32]);
+        let stored: BTreeMap<String, String> = entry.to_stream_fields(&key).into_iter().collect();
+        assert!(!stored["document_data"].contains("HJTPB9891M"));
+        assert!(DeferredEntry::from_stream_fields(stored.clone(), &SealingKey::new([4u8; 32])).is_err());
+        let restored = DeferredEntry::from_stream_fields(stored, &key).unwrap();
+        assert_eq!(restored, entry);
+        assert_eq!(restored.fields["document_data"], r#"{"pan":"HJTPB9891M"}"#);
+        assert!(!restored.fields.contains_key(RETRY_AFTER_FIELD));
+
+        // Not re-enqueued while the circuit is open or before retry-after
+        let parked = vec![("d-1".to_string(), restored)];
+        assert!(due_entries(parked.clone(), 2_000, breaker.is_open()).is_empty());
+        std::thread::sleep(Duration::from_millis(60));
+        assert!(due_entries(parked.clone(), 500, breaker.is_open()).is_empty());
+
+        // Circuit closed (half-open) and retry-after passed: re-enqueued
+        let due = due_entries(parked, 2_000, breaker.is_open());
+        assert_eq!(due.len(), 1);
+        assert_eq!(due[0].1.original_id, "1700000000000-0");
+    }
+
+    #[tokio::test]
+    async fn test_unopenable_entries_are_dropped_and_scans_resume_after_the_last_requeued() {
+        use crate::fake_redis::{bulk, serve};
+        use std::sync::Mutex as StdMutex;
+
+        let key = SealingKey::new([3u8; 32]);
+        let stream_entry = |id: &str, retry_after_ms: u64, key: &SealingKey| {
+            let fields = DeferredEntry::from_request(id, &request(), retry_after_ms).to_stream_fields(key);
+            let encoded: String = fields.iter().map(|(name, value)| bulk(name) + &bulk(value)).collect();
+            format!("*2\r\n{}*{}\r\n{}", bulk(id), fields.len() * 2, encoded)
+        };
+        // Sealed under a lost key, due, and not due yet
+        let reply = format!(
+            "*3\r\n{}{}{}",
+            stream_entry("10-0", 1_000, &SealingKey::new([4u8; 32])),
+            stream_entry("11-0", 1_000, &key),
+            stream_entry("12-0", 5_000, &key)
+        );
+        let commands = Arc::new(StdMutex::new(Vec::new()));
+        let seen = commands.clone();
+        let url = serve(move |args| {
+            match args[0].as_str() {
+                "XRANGE" | "XDEL" => seen.lock().unwrap().push(format!("{} {}", args[0], args[2])),
+                _ => {}
+            }
+            match args[0].as_str() {
+                "XRANGE" => Some(reply.clone()),
+                "XADD" => Some(bulk("20-0")),
+                _ => Some(":1\r\n".to_string()),
+            }
+        })
+        .await;
+        let mut conn = redis::Client::open(url).unwrap().get_async_connection().await.unwrap();
+
+        let mut queue = DeferredQueue::from_env(key);
+        assert_eq!(queue.requeue_due(&mut conn, 2_000, false).await.unwrap(), 1);
+        assert_eq!(*commands.lock().unwrap(), vec!["XRANGE -", "XDEL 10-0", "XDEL 11-0"]);
+
+        // The next scan starts after what was re-enqueued, not at the head
+        commands.lock().unwrap().clear();
+        queue.requeue_due(&mut conn, 2_000, true).await.unwrap();
+        assert_eq!(commands.lock().unwrap()[0], "XRANGE (11-0");
+    }
+}
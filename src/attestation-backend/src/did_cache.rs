@@ -0,0 +1,190 @@
+// In-memory LRU of UserDID object ids per (wallet, DID type), to skip repeat start_verification calls
+use anyhow::Result;
+use std::collections::{BTreeMap, HashMap};
+use std::future::Future;
+use std::sync::Mutex;
+use tokio::time::{Duration, Instant};
+use tracing::info;
+
+use crate::metrics;
+
+/// Cache limits, from `USER_DID_CACHE_SIZE` (0 disables the cache) and `USER_DID_CACHE_TTL_SECS`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UserDidCacheConfig {
+    pub capacity: usize,
+    pub ttl: Duration,
+}
+
+impl Default for UserDidCacheConfig {
+    fn default() -> Self {
+        Self {
+            capacity: 10_000,
+            ttl: Duration::from_secs(3600),
+        }
+    }
+}
+
+impl UserDidCacheConfig {
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        let parse = |name: &str, default: u64| {
+            std::env::var(name)
+                .ok()
+                .and_then(|v| v.parse::<u64>().ok())
+                .unwrap_or(default)
+        };
+        Self {
+            capacity: parse("USER_DID_CACHE_SIZE", defaults.capacity as u64) as usize,
+            ttl: Duration::from_secs(parse("USER_DID_CACHE_TTL_SECS", defaults.ttl.as_secs())),
+        }
+    }
+}
+
+type CacheKey = (String, u8);
+
+struct CacheEntry {
+    user_did_id: String,
+    inserted_at: Instant,
+    /// Position in `CacheState::recency`.
+    tick: u64,
+}
+
+#[derive(Default)]
+struct CacheState {
+    entries: HashMap<CacheKey, CacheEntry>,
+    /// Oldest use first, for eviction.
+    recency: BTreeMap<u64, CacheKey>,
+    next_tick: u64,
+}
+
+/// Process-lifetime `(wallet, did_id) -> UserDID object id` map, bounded in size and age.
+/// A fast path in front of the Redis commit log: it only saves Sui calls, never decides them.
+pub struct UserDidCache {
+    config: UserDidCacheConfig,
+    state: Mutex<CacheState>,
+}
+
+impl UserDidCache {
+    pub fn new(config: UserDidCacheConfig) -> Self {
+        Self {
+            config,
+            state: Mutex::new(CacheState::default()),
+        }
+    }
+
+    pub fn from_env() -> Self {
+        Self::new(UserDidCacheConfig::from_env())
+    }
+
+    fn key(wallet: &str, did_id: u8) -> CacheKey {
+        (wallet.trim().to_lowercase(), did_id)
+    }
+
+    /// The cached object id, unless it is missing or older than the TTL.
+    pub fn get(&self, wallet: &str, did_id: u8) -> Option<String> {
+        let key = Self::key(wallet, did_id);
+        let mut state = self.state.lock().unwrap();
+        let state = &mut *state;
+        let entry = state.entries.get_mut(&key)?;
+        if entry.inserted_at.elapsed() > self.config.ttl {
+            state.recency.remove(&entry.tick);
+            state.entries.remove(&key);
+            return None;
+        }
+        state.recency.remove(&entry.tick);
+        entry.tick = state.next_tick;
+        state.recency.insert(state.next_tick, key);
+        state.next_tick += 1;
+        Some(entry.user_did_id.clone())
+    }
+
+    /// Remember `user_did_id`, evicting the least recently used entry when full.
+    pub fn insert(&self, wallet: &str, did_id: u8, user_did_id: &str) {
+        if self.config.capacity == 0 {
+            return;
+        }
+        let key = Self::key(wallet, did_id);
+        let mut state = self.state.lock().unwrap();
+        if let Some(previous) = state.entries.remove(&key) {
+            state.recency.remove(&previous.tick);
+        }
+        while state.entries.len() >= self.config.capacity {
+            let Some((_, oldest)) = state.recency.pop_first() else { break };
+            state.entries.remove(&oldest);
+        }
+        let tick = state.next_tick;
+        state.next_tick += 1;
+        state.recency.insert(tick, key.clone());
+        state.entries.insert(
+            key,
+            CacheEntry {
+                user_did_id: user_did_id.to_string(),
+                inserted_at: Instant::now(),
+                tick,
+            },
+        );
+    }
+
+    pub fn len(&self) -> usize {
+        self.state.lock().unwrap().entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The wallet's UserDID object id: from the cache when known, otherwise from `start`
+    /// (the `start_verification` call), whose result is cached. Hits are counted in
+    /// `user_did_cache_hits_total`.
+    pub async fn get_or_start<F, Fut>(&self, wallet: &str, did_id: u8, start: F) -> Result<String>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<String>>,
+    {
+        if let Some(user_did_id) = self.get(wallet, did_id) {
+            metrics::increment("user_did_cache_hits_total");
+            info!("⚡ Reusing cached UserDID {} for wallet: {}, skipping start_verification", user_did_id, wallet);
+            return Ok(user_did_id);
+        }
+        let user_did_id = start().await?;
+        self.insert(wallet, did_id, &user_did_id);
+        Ok(user_did_id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    #[tokio::test]
+    async fn test_second_call_for_wallet_skips_start_verification() {
+        let cache = UserDidCache::new(UserDidCacheConfig {
+            capacity: 2,
+            ttl: Duration::from_millis(200),
+        });
+        let calls = AtomicU32::new(0);
+        let start = || async {
+            calls.fetch_add(1, Ordering::SeqCst);
+            Ok("0xdid".to_string())
+        };
+
+        assert_eq!(cache.get_or_start("0xABC", 0, start).await.unwrap(), "0xdid");
+        assert_eq!(cache.get_or_start("0xabc", 0, start).await.unwrap(), "0xdid");
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+        // Another DID type is a different object
+        cache.get_or_start("0xabc", 1, start).await.unwrap();
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+
+        // Full: the least recently used entry (0xabc/0 was used before 0xabc/1) goes first
+        cache.insert("0xdef", 0, "0xother");
+        assert_eq!(cache.len(), 2);
+        assert_eq!(cache.get("0xabc", 0), None);
+        assert_eq!(cache.get("0xabc", 1).as_deref(), Some("0xdid"));
+
+        // Expired entries are not served
+        tokio::time::sleep(Duration::from_millis(250)).await;
+        assert_eq!(cache.get("0xdef", 0), None);
+    }
+}
@@ -0,0 +1,103 @@
+// Enclave key generation from NSM hardware entropy, with a loud (or fail-closed) software fallback
+use anyhow::{Result, anyhow};
+use fastcrypto::ed25519::Ed25519KeyPair;
+use fastcrypto::traits::{AllowedRng, KeyPair};
+use rand::SeedableRng;
+use tracing::{error, info};
+
+use crate::metrics;
+
+/// Whether a failed NSM `GetRandom` should stop startup, from `NSM_ENTROPY_FAIL_CLOSED`
+/// (default false: fall back to the software RNG, logging an error and counting the failure).
+pub fn fail_closed_from_env() -> bool {
+    std::env::var("NSM_ENTROPY_FAIL_CLOSED")
+        .map(|v| v == "1" || v.eq_ignore_ascii_case("true"))
+        .unwrap_or(false)
+}
+
+/// Pick the key seed: the NSM's when it produced one, otherwise an error when `fail_closed`,
+/// or `None` to signal the software RNG fallback. Every failure is logged and counted in
+/// `nsm_entropy_failures_total`; `key_hardware_entropy` records which source was used.
+pub fn choose_seed(nsm: Result<[u8; 32]>, fail_closed: bool) -> Result<Option<[u8; 32]>> {
+    match nsm {
+        Ok(seed) => {
+            metrics::set_gauge("key_hardware_entropy", 1.0);
+            Ok(Some(seed))
+        }
+        Err(e) => {
+            metrics::increment("nsm_entropy_failures_total");
+            metrics::set_gauge("key_hardware_entropy", 0.0);
+            if fail_closed {
+                error!("🚨 NSM entropy unavailable and NSM_ENTROPY_FAIL_CLOSED is set, refusing to start: {}", e);
+                return Err(anyhow!("NSM entropy unavailable: {}", e));
+            }
+            error!("🚨 NSM entropy unavailable, generating the enclave key from the software RNG: {}", e);
+            Ok(None)
+        }
+    }
+}
+
+/// The keypair drawn from `rng` (any `CryptoRng + RngCore` fastcrypto accepts): the NSM-seeded
+/// or thread RNG in production, a seeded one in tests that need a known key.
+pub fn keypair_from_rng<R: AllowedRng>(rng: &mut R) -> Ed25519KeyPair {
+    Ed25519KeyPair::generate(rng)
+}
+
+/// Generate the enclave keypair from `nsm` entropy, following [`choose_seed`] when it fails.
+pub fn generate_keypair(nsm: Result<[u8; 32]>, fail_closed: bool) -> Result<Ed25519KeyPair> {
+    Ok(match choose_seed(nsm, fail_closed)? {
+        Some(seed) => {
+            info!("🔑 Enclave key generated from NSM hardware entropy");
+            keypair_from_rng(&mut rand::rngs::StdRng::from_seed(seed))
+        }
+        None => keypair_from_rng(&mut rand::thread_rng()),
+    })
+}
+
+/// 32 bytes of entropy from the NSM's `GetRandom`.
+#[cfg(feature = "aws")]
+pub fn nsm_random() -> Result<[u8; 32]> {
+    use aws_nitro_enclaves_nsm_api::api::{Request, Response};
+    use aws_nitro_enclaves_nsm_api::driver;
+
+    let fd = driver::nsm_init();
+    let response = driver::nsm_process_request(fd, Request::GetRandom);
+    driver::nsm_exit(fd);
+    match response {
+        Response::GetRandom { random } if random.len() >= 32 => {
+            Ok(random[..32].try_into().expect("slice is 32 bytes"))
+        }
+        Response::GetRandom { random } => Err(anyhow!("NSM returned only {} random bytes", random.len())),
+        _ => Err(anyhow!("Unexpected NSM response to GetRandom")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_nsm_failure_falls_back_or_fails_closed() {
+        let failures = metrics::counter("nsm_entropy_failures_total");
+
+        // Healthy NSM: its seed is used, whatever the setting
+        assert_eq!(choose_seed(Ok([7u8; 32]), true).unwrap(), Some([7u8; 32]));
+        let a = generate_keypair(Ok([7u8; 32]), false).unwrap();
+        let b = generate_keypair(Ok([7u8; 32]), false).unwrap();
+        assert_eq!(a.public(), b.public());
+
+        // Broken NSM: fallback by default, refusal when fail-closed
+        assert_eq!(choose_seed(Err(anyhow!("no device")), false).unwrap(), None);
+        assert!(generate_keypair(Err(anyhow!("no device")), false).is_ok());
+        assert!(generate_keypair(Err(anyhow!("no device")), true).is_err());
+        assert!(metrics::counter("nsm_entropy_failures_total") >= failures + 3);
+    }
+
+    #[test]
+    fn test_seeded_rng_gives_a_known_key_and_attestation_hash() {
+        let keypair = keypair_from_rng(&mut rand::rngs::StdRng::seed_from_u64(42));
+        assert_eq!(hex::encode(keypair.public().as_ref()), "9bdb607f02802cdd126290cfa1e025e4c13bbdbb347a70edeace584159303454");
+        let hash = crate::app::generate_attestation_hash(&keypair, &true).unwrap();
+        assert_eq!(hash, "81c6b7835790e35c20b8e1e6fd83d6d873703765fb6a89d594626c22436e78e8");
+    }
+}
@@ -0,0 +1,317 @@
+// Evidence hash schemas, one per verification type
+use anyhow::{Result, anyhow};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+
+use crate::canonical_json::to_canonical_string;
+use crate::payload::InvalidMessage;
+
+/// PAN verification evidence (stable fields + actual verified data).
+/// The match booleans are `None` when the [`EvidenceProfile`] leaves them out of the hash.
+#[derive(Debug, Clone, Serialize)]
+pub struct PanEvidence {
+    pub pan: String,
+    pub status: String,
+    pub name_as_per_pan: String,
+    pub date_of_birth: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name_as_per_pan_match: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub date_of_birth_match: Option<bool>,
+    pub category: String,
+    pub aadhaar_seeding_status: String,
+}
+
+/// Aadhaar verification evidence. Only the last four digits are ever committed.
+#[derive(Debug, Clone, Serialize)]
+pub struct AadhaarEvidence {
+    pub aadhaar_last4: String,
+    pub status: String,
+    pub name: String,
+    pub date_of_birth: String,
+}
+
+/// Voter ID (EPIC) verification evidence.
+#[derive(Debug, Clone, Serialize)]
+pub struct VoterIdEvidence {
+    pub epic_number: String,
+    pub status: String,
+    pub name: String,
+}
+
+/// Driving licence verification evidence.
+#[derive(Debug, Clone, Serialize)]
+pub struct DrivingLicenceEvidence {
+    pub dl_number: String,
+    pub status: String,
+    pub name: String,
+    pub date_of_birth: String,
+    pub valid_till: String,
+}
+
+/// Evidence input for every supported verification type.
+///
+/// Hashed with a `schema` tag, so the tag is part of the hashed bytes: two types can never
+/// produce the same hash and the on-chain side can tell which schema a hash was computed over.
+/// The serde tags name the earlier schemas, whose preimage kept the fields in declaration
+/// order; `pan_v1` is the untagged PAN preimage that came before those.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "schema")]
+pub enum EvidenceInput {
+    #[serde(rename = "pan_v2")]
+    Pan(PanEvidence),
+    #[serde(rename = "aadhaar_v1")]
+    Aadhaar(AadhaarEvidence),
+    #[serde(rename = "voter_id_v1")]
+    VoterId(VoterIdEvidence),
+    #[serde(rename = "driving_licence_v1")]
+    DrivingLicence(DrivingLicenceEvidence),
+}
+
+/// Which evidence fields a hash commits to, from `EVIDENCE_HASH_PROFILE`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum EvidenceProfile {
+    /// Every field, including the match booleans (the original hash).
+    #[default]
+    Full,
+    /// Only what the API reports about the document. The match booleans depend on the
+    /// claimed name and date of birth, so they are left out.
+    Facts,
+}
+
+impl EvidenceProfile {
+    pub fn parse(name: &str) -> Result<Self> {
+        match name.trim().to_lowercase().as_str() {
+            "full" => Ok(Self::Full),
+            "facts" => Ok(Self::Facts),
+            other => Err(anyhow!("Unknown EVIDENCE_HASH_PROFILE: {}", other)),
+        }
+    }
+
+    pub fn from_env() -> Result<Self> {
+        match std::env::var("EVIDENCE_HASH_PROFILE") {
+            Ok(name) => Self::parse(&name),
+            Err(_) => Ok(Self::default()),
+        }
+    }
+
+    /// Id emitted next to the hash so verifiers know which fields it covers.
+    pub fn id(self) -> &'static str {
+        match self {
+            Self::Full => "full",
+            Self::Facts => "facts",
+        }
+    }
+}
+
+/// A computed evidence hash together with the schema and profile it was computed over.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EvidenceHash {
+    pub schema: &'static str,
+    pub profile: &'static str,
+    pub hash: String,
+}
+
+impl EvidenceInput {
+    /// Schema new hashes are computed under: the tagged fields as canonical JSON.
+    pub fn schema(&self) -> &'static str {
+        match self {
+            EvidenceInput::Pan(_) => "pan_v3",
+            EvidenceInput::Aadhaar(_) => "aadhaar_v2",
+            EvidenceInput::VoterId(_) => "voter_id_v2",
+            EvidenceInput::DrivingLicence(_) => "driving_licence_v2",
+        }
+    }
+
+    /// The serde tag: the schema before [`Self::schema`], whose preimage kept declaration order.
+    fn ordered_schema(&self) -> &'static str {
+        match self {
+            EvidenceInput::Pan(_) => "pan_v2",
+            EvidenceInput::Aadhaar(_) => "aadhaar_v1",
+            EvidenceInput::VoterId(_) => "voter_id_v1",
+            EvidenceInput::DrivingLicence(_) => "driving_licence_v1",
+        }
+    }
+
+    /// Preimage of the hash: the schema tag and the fields as [`crate::canonical_json`], so the
+    /// bytes don't depend on field order and other implementations can reproduce them.
+    pub fn preimage(&self) -> Result<String> {
+        let mut value = serde_json::to_value(self)?;
+        value["schema"] = self.schema().into();
+        to_canonical_string(&value)
+    }
+
+    /// Preimages of the schemas this input may have been hashed under, current first: the
+    /// tagged fields in declaration order before that, and for PAN the bare fields (`pan_v1`).
+    pub fn preimages(&self) -> Result<Vec<(&'static str, String)>> {
+        let mut preimages = vec![
+            (self.schema(), self.preimage()?),
+            (self.ordered_schema(), serde_json::to_string(self)?),
+        ];
+        if let EvidenceInput::Pan(pan) = self {
+            preimages.push(("pan_v1", serde_json::to_string(pan)?));
+        }
+        Ok(preimages)
+    }
+
+    /// The input with the fields `profile` leaves out removed.
+    pub fn with_profile(&self, profile: EvidenceProfile) -> Self {
+        let mut input = self.clone();
+        if let (EvidenceProfile::Facts, EvidenceInput::Pan(pan)) = (profile, &mut input) {
+            pan.name_as_per_pan_match = None;
+            pan.date_of_birth_match = None;
+        }
+        input
+    }
+
+    /// SHA-256 over the [`Self::preimage`], hex encoded, committing to every field.
+    pub fn hash(&self) -> Result<EvidenceHash> {
+        self.hash_with_profile(EvidenceProfile::Full)
+    }
+
+    /// SHA-256 over the [`Self::preimage`] of the fields `profile` selects, hex encoded.
+    pub fn hash_with_profile(&self, profile: EvidenceProfile) -> Result<EvidenceHash> {
+        let preimage = self.with_profile(profile).preimage()?;
+        Ok(EvidenceHash {
+            schema: self.schema(),
+            profile: profile.id(),
+            hash: hex::encode(Sha256::digest(preimage.as_bytes())),
+        })
+    }
+}
+
+/// Length of the evidence hash the contract stores: a SHA-256 digest.
+pub const EVIDENCE_HASH_BYTES: usize = 32;
+
+/// Decode a hex evidence hash into the 32 bytes passed to the contract as `vector<u8>`.
+/// Anything else is an [`InvalidMessage`]: retrying the same hash can't fix it.
+pub fn decode_evidence_hash(evidence_hash: &str) -> Result<Vec<u8>> {
+    let invalid = |reason: String| anyhow::Error::new(InvalidMessage { reason });
+    let bytes = hex::decode(evidence_hash)
+        .map_err(|e| invalid(format!("evidence hash {:?} is not valid hex: {}", evidence_hash, e)))?;
+    if bytes.len() != EVIDENCE_HASH_BYTES {
+        return Err(invalid(format!(
+            "evidence hash must be {} bytes, got {} ({:?})",
+            EVIDENCE_HASH_BYTES,
+            bytes.len(),
+            evidence_hash
+        )));
+    }
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_evidence_hash() {
+        let hash = pan().hash().unwrap().hash;
+        assert_eq!(decode_evidence_hash(&hash).unwrap().len(), 32);
+
+        assert!(decode_evidence_hash(&"ab".repeat(31)).unwrap_err().to_string().contains("32 bytes"));
+        assert!(decode_evidence_hash(&"a".repeat(63)).unwrap_err().to_string().contains("not valid hex"));
+        assert!(decode_evidence_hash(&"zz".repeat(32)).is_err());
+    }
+
+    #[test]
+    fn test_wrong_length_evidence_hash_is_rejected_before_submission() {
+        let err = decode_evidence_hash(&"ab".repeat(16)).unwrap_err();
+        assert!(crate::payload::is_invalid_message(&err));
+        assert!(err.to_string().contains("must be 32 bytes, got 16"), "{}", err);
+        assert_eq!(decode_evidence_hash(&"ab".repeat(32)).unwrap(), vec![0xab; 32]);
+    }
+
+    fn pan() -> EvidenceInput {
+        EvidenceInput::Pan(PanEvidence {
+            pan: "HJTPB9891M".to_string(),
+            status: "valid".to_string(),
+            name_as_per_pan: "Ashwin Balaguru".to_string(),
+            date_of_birth: "27/10/2004".to_string(),
+            name_as_per_pan_match: Some(true),
+            date_of_birth_match: Some(true),
+            category: "individual".to_string(),
+            aadhaar_seeding_status: "y".to_string(),
+        })
+    }
+
+    #[test]
+    fn test_preimage_is_canonical_json_with_the_schema_tag() {
+        let preimage = pan().preimage().unwrap();
+        assert!(preimage.starts_with(r#"{"aadhaar_seeding_status":"y","category":"individual""#), "{}", preimage);
+        assert!(preimage.ends_with(r#""schema":"pan_v3","status":"valid"}"#), "{}", preimage);
+    }
+
+    #[test]
+    fn test_preimages_include_the_earlier_schemas() {
+        let preimages = pan().preimages().unwrap();
+        let schemas: Vec<&str> = preimages.iter().map(|(schema, _)| *schema).collect();
+        assert_eq!(schemas, ["pan_v3", "pan_v2", "pan_v1"]);
+        assert_eq!(preimages[0].1, pan().preimage().unwrap());
+        assert!(preimages[1].1.starts_with(r#"{"schema":"pan_v2","pan":"HJTPB9891M""#));
+        assert!(preimages[2].1.starts_with(r#"{"pan":"HJTPB9891M","status":"valid""#));
+
+        let voter = EvidenceInput::VoterId(VoterIdEvidence {
+            epic_number: "ABC1234567".to_string(),
+            status: "valid".to_string(),
+            name: "Ashwin Balaguru".to_string(),
+        });
+        let schemas: Vec<&str> = voter.preimages().unwrap().into_iter().map(|(schema, _)| schema).collect();
+        assert_eq!(schemas, ["voter_id_v2", "voter_id_v1"]);
+    }
+
+    #[test]
+    fn test_types_with_overlapping_data_hash_differently() {
+        let aadhaar = EvidenceInput::Aadhaar(AadhaarEvidence {
+            aadhaar_last4: "1234".to_string(),
+            status: "valid".to_string(),
+            name: "Ashwin Balaguru".to_string(),
+            date_of_birth: "27/10/2004".to_string(),
+        });
+        let licence = EvidenceInput::DrivingLicence(DrivingLicenceEvidence {
+            dl_number: "1234".to_string(),
+            status: "valid".to_string(),
+            name: "Ashwin Balaguru".to_string(),
+            date_of_birth: "27/10/2004".to_string(),
+            valid_till: String::new(),
+        });
+
+        let pan_hash = pan().hash().unwrap();
+        let aadhaar_hash = aadhaar.hash().unwrap();
+        let licence_hash = licence.hash().unwrap();
+
+        assert_eq!(pan_hash.schema, "pan_v3");
+        assert_eq!(aadhaar_hash.schema, "aadhaar_v2");
+        assert_eq!(licence_hash.schema, "driving_licence_v2");
+        assert_ne!(pan_hash.hash, aadhaar_hash.hash);
+        assert_ne!(aadhaar_hash.hash, licence_hash.hash);
+        assert_eq!(pan_hash.hash.len(), 64);
+    }
+
+    #[test]
+    fn test_profiles_hash_differently_and_are_tagged() {
+        let full = pan().hash_with_profile(EvidenceProfile::Full).unwrap();
+        let facts = pan().hash_with_profile(EvidenceProfile::Facts).unwrap();
+        assert_eq!(full, pan().hash().unwrap());
+        assert_eq!((full.profile, facts.profile), ("full", "facts"));
+        assert_eq!(facts.schema, "pan_v3");
+        assert_ne!(full.hash, facts.hash);
+
+        // The facts hash does not depend on how the claimed name matched
+        let mut mismatched = pan();
+        if let EvidenceInput::Pan(evidence) = &mut mismatched {
+            evidence.name_as_per_pan_match = Some(false);
+        }
+        assert_eq!(mismatched.hash_with_profile(EvidenceProfile::Facts).unwrap(), facts);
+        assert_ne!(mismatched.hash().unwrap(), full);
+
+        let preimage = pan().with_profile(EvidenceProfile::Facts).preimage().unwrap();
+        assert!(!preimage.contains("_match"));
+        assert!(EvidenceProfile::parse("bogus").is_err());
+    }
+
+    #[test]
+    fn test_hash_is_deterministic() {
+        assert_eq!(pan().hash().unwrap(), pan().hash().unwrap());
+    }
+}
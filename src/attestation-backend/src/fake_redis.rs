@@ -0,0 +1,74 @@
+// Minimal RESP server for tests: parses commands and answers them with a caller-supplied handler
+use std::sync::Arc;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+/// One RESP command from the front of `buf`, and how many bytes it took.
+pub fn parse_command(buf: &[u8]) -> Option<(Vec<String>, usize)> {
+    let line = |from: usize| -> Option<(String, usize)> {
+        let end = buf[from..].windows(2).position(|w| w == b"\r\n")? + from;
+        Some((String::from_utf8_lossy(&buf[from + 1..end]).to_string(), end + 2))
+    };
+    let (count, mut pos) = line(0)?;
+    let mut args = Vec::new();
+    for _ in 0..count.parse::<usize>().ok()? {
+        let (len, start) = line(pos)?;
+        let end = start + len.parse::<usize>().ok()?;
+        if buf.len() < end + 2 {
+            return None;
+        }
+        args.push(String::from_utf8_lossy(&buf[start..end]).to_string());
+        pos = end + 2;
+    }
+    Some((args, pos))
+}
+
+/// RESP bulk string.
+pub fn bulk(text: &str) -> String {
+    format!("${}\r\n{}\r\n", text.len(), text)
+}
+
+/// Serve Redis on a local port, answering each command with `execute(args)`; `None` leaves it
+/// unanswered, like a hung server. MULTI/EXEC is handled here, so `execute` only sees the queued
+/// commands. Returns the `redis://` URL.
+pub async fn serve<F>(execute: F) -> String
+where
+    F: Fn(&[String]) -> Option<String> + Send + Sync + 'static,
+{
+    let execute = Arc::new(execute);
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        while let Ok((mut socket, _)) = listener.accept().await {
+            let execute = execute.clone();
+            tokio::spawn(async move {
+                let (mut buf, mut chunk) = (Vec::new(), [0u8; 4096]);
+                let mut queued: Option<Vec<String>> = None;
+                while let Ok(n) = socket.read(&mut chunk).await {
+                    if n == 0 {
+                        break;
+                    }
+                    buf.extend_from_slice(&chunk[..n]);
+                    let mut out = String::new();
+                    while let Some((args, used)) = parse_command(&buf) {
+                        buf.drain(..used);
+                        let name = args[0].to_uppercase();
+                        if name == "MULTI" {
+                            queued = Some(Vec::new());
+                            out += "+OK\r\n";
+                        } else if name == "EXEC" {
+                            let replies = queued.take().unwrap_or_default();
+                            out += &format!("*{}\r\n{}", replies.len(), replies.concat());
+                        } else if let Some(queue) = queued.as_mut() {
+                            queue.push(execute(&args).unwrap_or_else(|| "+OK\r\n".to_string()));
+                            out += "+QUEUED\r\n";
+                        } else if let Some(reply) = execute(&args) {
+                            out += &reply;
+                        }
+                    }
+                    let _ = socket.write_all(out.as_bytes()).await;
+                }
+            });
+        }
+    });
+    format!("redis://{}", addr)
+}
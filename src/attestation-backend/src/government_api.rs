@@ -3,10 +3,23 @@ use anyhow::{Result, anyhow};
 use serde::{Deserialize, Serialize};
 use reqwest::Client;
 use chrono::{DateTime, Utc, Duration};
-use sha2::{Sha256, Digest};
 use serde_json;
+use sha2::{Digest, Sha256};
 use tracing::{info, warn, error};
-use hex;
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::call_limit::CallLimit;
+use crate::cert_pin::{pinned_tls_config, webpki_roots, CertPin};
+use crate::circuit_breaker::{is_unavailable, CircuitBreaker};
+use crate::decision_policy::{DecisionPolicies, StatusOutcome};
+use crate::evidence::{EvidenceHash, EvidenceInput, EvidenceProfile, PanEvidence};
+use crate::metrics;
+use crate::payload::InvalidMessage;
+use crate::retry::{is_transient, retry_after_header, retry_with_backoff_if, RetryPolicy, TransientError};
+use crate::stage_timer::StageTimer;
+use crate::verification_types::VerificationTypes;
 
 // JWT token management
 #[derive(Debug, Clone)]
@@ -20,7 +33,7 @@ pub struct JwtManager {
 }
 
 // Government API response structures
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct GovernmentApiResponse {
     pub code: u16,
     pub timestamp: u64,
@@ -28,7 +41,7 @@ pub struct GovernmentApiResponse {
     pub transaction_id: String,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct PanVerificationData {
     #[serde(rename = "@entity")]
     pub entity: String,
@@ -41,21 +54,299 @@ pub struct PanVerificationData {
     pub aadhaar_seeding_status: String,
 }
 
-// Evidence hash input structure (stable fields + actual data)
-#[derive(Debug, Serialize)]
-pub struct EvidenceHashInput {
-    pub pan: String,
-    pub status: String,
-    pub name_as_per_pan: String,
-    pub date_of_birth: String,
-    pub name_as_per_pan_match: bool,
-    pub date_of_birth_match: bool,
-    pub category: String,
-    pub aadhaar_seeding_status: String,
+/// Response of the batch endpoint: one record per PAN, echoing the `reference_id` it was sent with.
+#[derive(Debug, Deserialize)]
+pub struct GovernmentApiBatchResponse {
+    pub code: u16,
+    pub timestamp: u64,
+    pub transaction_id: String,
+    pub data: Vec<BatchPanRecord>,
 }
 
-// Verification request from Redis
 #[derive(Debug, Deserialize)]
+pub struct BatchPanRecord {
+    #[serde(default)]
+    pub reference_id: Option<String>,
+    #[serde(flatten)]
+    pub data: PanVerificationData,
+}
+
+/// One PAN of a batch: the message it came from, its request's [`idempotency_key`] (the same
+/// one a call on its own would send) and its normalized document.
+#[derive(Debug, Clone)]
+pub struct PanBatchItem {
+    pub reference_id: String,
+    pub idempotency_key: String,
+    pub document: DocumentData,
+}
+
+impl PanBatchItem {
+    pub fn new(reference_id: &str, request: &VerificationRequest, document: DocumentData) -> Self {
+        Self {
+            reference_id: reference_id.to_string(),
+            idempotency_key: request.idempotency_key(&document.pan),
+            document,
+        }
+    }
+}
+
+/// Split a batch response back into one response per input, in input order. Records are matched
+/// by `reference_id`, or by PAN when the upstream does not echo it; an input with no record is an error.
+pub fn map_batch_response(
+    inputs: &[PanBatchItem],
+    response: GovernmentApiBatchResponse,
+) -> Vec<Result<GovernmentApiResponse>> {
+    let mut records: Vec<Option<BatchPanRecord>> = response.data.into_iter().map(Some).collect();
+    inputs
+        .iter()
+        .map(|PanBatchItem { reference_id, document, .. }| {
+            let position = records
+                .iter()
+                .position(|r| matches!(r, Some(r) if r.reference_id.as_deref() == Some(reference_id.as_str())))
+                .or_else(|| {
+                    records.iter().position(|r| {
+                        matches!(r, Some(r) if r.reference_id.is_none() && normalize_pan(&r.data.pan) == document.pan)
+                    })
+                });
+            let record = position
+                .and_then(|i| records[i].take())
+                .ok_or_else(|| anyhow!("Batch response has no record for {} (PAN {})", reference_id, mask_pan(&document.pan)))?;
+            Ok(GovernmentApiResponse {
+                code: response.code,
+                timestamp: response.timestamp,
+                data: record.data,
+                transaction_id: response.transaction_id.clone(),
+            })
+        })
+        .collect()
+}
+
+/// Batching of PAN calls, from `GOVT_API_BATCH_SIZE` (default 1: no batching) and
+/// `GOVT_API_BATCH_WINDOW_MS`, how long to wait for more queued messages to fill a batch.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PanBatchConfig {
+    pub size: usize,
+    pub window: std::time::Duration,
+}
+
+impl PanBatchConfig {
+    pub fn from_env() -> Self {
+        let parse = |name: &str, default: u64| {
+            std::env::var(name)
+                .ok()
+                .and_then(|v| v.parse::<u64>().ok())
+                .unwrap_or(default)
+        };
+        Self {
+            size: parse("GOVT_API_BATCH_SIZE", 1).max(1) as usize,
+            window: std::time::Duration::from_millis(parse("GOVT_API_BATCH_WINDOW_MS", 50)),
+        }
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.size > 1
+    }
+}
+
+/// How the JWT and API key are sent on verification calls. Sandbox wants the raw JWT in
+/// `authorization` and the key in `x-api-key`; other providers may want `Bearer <jwt>` or other
+/// header names. From `GOVT_API_AUTH_HEADER`, `GOVT_API_AUTH_BEARER` and `GOVT_API_KEY_HEADER`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AuthHeaderFormat {
+    pub token_header: String,
+    pub bearer_prefix: bool,
+    pub api_key_header: String,
+}
+
+impl Default for AuthHeaderFormat {
+    fn default() -> Self {
+        Self {
+            token_header: "authorization".to_string(),
+            bearer_prefix: false,
+            api_key_header: "x-api-key".to_string(),
+        }
+    }
+}
+
+impl AuthHeaderFormat {
+    pub fn from_env() -> Result<Self> {
+        let default = Self::default();
+        let header = |name: &str, default: String| -> Result<String> {
+            match std::env::var(name) {
+                Ok(value) if !value.trim().is_empty() => {
+                    let value = value.trim().to_ascii_lowercase();
+                    reqwest::header::HeaderName::from_bytes(value.as_bytes())
+                        .map_err(|_| anyhow!("{} is not a valid header name: '{}'", name, value))?;
+                    Ok(value)
+                }
+                _ => Ok(default),
+            }
+        };
+        Ok(Self {
+            token_header: header("GOVT_API_AUTH_HEADER", default.token_header)?,
+            bearer_prefix: std::env::var("GOVT_API_AUTH_BEARER")
+                .map(|v| v == "1" || v.eq_ignore_ascii_case("true"))
+                .unwrap_or(default.bearer_prefix),
+            api_key_header: header("GOVT_API_KEY_HEADER", default.api_key_header)?,
+        })
+    }
+
+    /// Value of the token header for `token`.
+    pub fn token_value(&self, token: &str) -> String {
+        if self.bearer_prefix { format!("Bearer {}", token) } else { token.to_string() }
+    }
+}
+
+/// Header carrying the idempotency key of a verification call, unless `GOVT_API_IDEMPOTENCY_HEADER`
+/// names another one (or is `off`).
+pub const DEFAULT_IDEMPOTENCY_HEADER: &str = "idempotency-key";
+
+/// Idempotency header name from `GOVT_API_IDEMPOTENCY_HEADER`; `None` when turned off.
+fn idempotency_header_from_env() -> Result<Option<String>> {
+    match std::env::var("GOVT_API_IDEMPOTENCY_HEADER") {
+        Ok(value) if value.trim().eq_ignore_ascii_case("off") => Ok(None),
+        Ok(value) if !value.trim().is_empty() => {
+            let value = value.trim().to_ascii_lowercase();
+            reqwest::header::HeaderName::from_bytes(value.as_bytes())
+                .map_err(|_| anyhow!("GOVT_API_IDEMPOTENCY_HEADER is not a valid header name: '{}'", value))?;
+            Ok(Some(value))
+        }
+        _ => Ok(Some(DEFAULT_IDEMPOTENCY_HEADER.to_string())),
+    }
+}
+
+/// Idempotency key of one logical verification: the same wallet, PAN and nonce (the request's
+/// timestamp) always give the same key, so retries and redeliveries of a message can be
+/// deduplicated upstream while a new request for the same PAN is not.
+pub fn idempotency_key(wallet: &str, pan: &str, nonce: &str) -> String {
+    let digest = Sha256::digest(format!("{}\n{}\n{}", wallet, normalize_pan(pan), nonce).as_bytes());
+    hex::encode(&digest[..16])
+}
+
+/// Default `@entity` of a PAN verification request.
+pub const PAN_REQUEST_ENTITY: &str = "in.co.sandbox.kyc.pan_verification.request";
+
+/// Document fields a payload template can map from.
+const PAN_PAYLOAD_SOURCES: [&str; 6] = ["pan", "name_as_per_pan", "date_of_birth", "phone_number", "consent", "reason"];
+
+/// Shape of the PAN verification request body: its `@entity` (`GOVT_API_PAN_ENTITY`) and which
+/// document field goes under which key (`GOVT_API_PAN_FIELDS`, comma separated `key=field`, e.g.
+/// `id_number=pan,full_name=name_as_per_pan`). Defaults to sandbox's request, field for field.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PanPayloadTemplate {
+    pub entity: String,
+    /// (upstream key, document field)
+    pub fields: Vec<(String, String)>,
+}
+
+impl Default for PanPayloadTemplate {
+    fn default() -> Self {
+        Self {
+            entity: PAN_REQUEST_ENTITY.to_string(),
+            fields: ["pan", "name_as_per_pan", "date_of_birth", "consent", "reason"]
+                .iter()
+                .map(|field| (field.to_string(), field.to_string()))
+                .collect(),
+        }
+    }
+}
+
+impl PanPayloadTemplate {
+    /// Parse a `key=field` list; every field must be a document field and the PAN must be sent.
+    pub fn new(entity: &str, fields: &str) -> Result<Self> {
+        let entity = entity.trim();
+        if entity.is_empty() {
+            return Err(anyhow!("PAN payload @entity must not be empty"));
+        }
+        let mut mapped: Vec<(String, String)> = Vec::new();
+        for entry in fields.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+            let (key, field) = entry
+                .split_once('=')
+                .map(|(key, field)| (key.trim(), field.trim()))
+                .ok_or_else(|| anyhow!("PAN payload field '{}' must be key=field", entry))?;
+            if key.is_empty() || key == "@entity" {
+                return Err(anyhow!("PAN payload field '{}' has an invalid key", entry));
+            }
+            if !PAN_PAYLOAD_SOURCES.contains(&field) {
+                return Err(anyhow!("Unknown document field '{}' in PAN payload (known: {})",
+                                   field, PAN_PAYLOAD_SOURCES.join(", ")));
+            }
+            if mapped.iter().any(|(existing, _)| existing == key) {
+                return Err(anyhow!("PAN payload key '{}' is mapped twice", key));
+            }
+            mapped.push((key.to_string(), field.to_string()));
+        }
+        if !mapped.iter().any(|(_, field)| field == "pan") {
+            return Err(anyhow!("PAN payload template must send the pan"));
+        }
+        Ok(Self { entity: entity.to_string(), fields: mapped })
+    }
+
+    pub fn from_env() -> Result<Self> {
+        let default = Self::default();
+        let entity = std::env::var("GOVT_API_PAN_ENTITY").unwrap_or(default.entity);
+        let fields = std::env::var("GOVT_API_PAN_FIELDS")
+            .ok()
+            .filter(|fields| !fields.trim().is_empty())
+            .unwrap_or_else(|| default.fields.iter().map(|(key, field)| format!("{}={}", key, field)).collect::<Vec<_>>().join(","));
+        Self::new(&entity, &fields)
+    }
+
+    /// The request body for `document`. An unset optional field is sent as null.
+    pub fn render(&self, document: &DocumentData) -> serde_json::Value {
+        let mut payload = serde_json::Map::new();
+        payload.insert("@entity".to_string(), self.entity.clone().into());
+        for (key, field) in &self.fields {
+            let value = match field.as_str() {
+                "pan" => document.pan.clone().into(),
+                "name_as_per_pan" => document.name_as_per_pan.clone().into(),
+                "date_of_birth" => document.date_of_birth.clone().into(),
+                "phone_number" => document.phone_number.clone().into(),
+                "consent" => document.consent.clone().into(),
+                "reason" => document.reason.clone().into(),
+                _ => serde_json::Value::Null,
+            };
+            payload.insert(key.clone(), value);
+        }
+        serde_json::Value::Object(payload)
+    }
+}
+
+/// Response bodies above this are refused unless `GOVT_API_MAX_RESPONSE_BYTES` says otherwise.
+pub const DEFAULT_MAX_RESPONSE_BYTES: usize = 1024 * 1024;
+
+/// Read a response body of at most `limit` bytes. A larger one, whether announced by
+/// `Content-Length` or found while streaming, is dropped as soon as it's known to be too big.
+pub async fn read_body_limited(mut response: reqwest::Response, limit: usize) -> Result<String, GovApiError> {
+    let status = response.status().as_u16();
+    let too_large = || {
+        metrics::increment("govt_api_oversized_responses_total");
+        GovApiError::ServerError { status, body: format!("response body exceeds {} bytes", limit) }
+    };
+    if response.content_length().is_some_and(|length| length > limit as u64) {
+        return Err(too_large());
+    }
+    let mut body = Vec::new();
+    while let Some(chunk) = response
+        .chunk()
+        .await
+        .map_err(|e| GovApiError::Transport { reason: e.to_string() })?
+    {
+        if body.len() + chunk.len() > limit {
+            return Err(too_large());
+        }
+        body.extend_from_slice(&chunk);
+    }
+    Ok(String::from_utf8_lossy(&body).into_owned())
+}
+
+/// Key of a prefetched response: the normalized inputs the API answer depends on.
+fn prefetch_key(document: &DocumentData) -> String {
+    format!("{}|{}|{}", document.pan, document.name_as_per_pan, document.date_of_birth)
+}
+
+// Verification request from Redis
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct VerificationRequest {
     pub user_wallet: String,
     pub did_id: String,
@@ -67,8 +358,25 @@ pub struct VerificationRequest {
     pub status: String,
 }
 
+impl VerificationRequest {
+    /// The [`idempotency_key`] of verifying `pan` for this request, its timestamp as the nonce.
+    pub fn idempotency_key(&self, pan: &str) -> String {
+        idempotency_key(&self.user_wallet, pan, &self.timestamp)
+    }
+}
+
+/// Outcome of a processed verification request.
+#[derive(Debug, Clone)]
+pub struct VerificationOutcome {
+    /// "verified", "failed", or "review" for a PAN status that needs a person to decide
+    pub result: String,
+    pub evidence: EvidenceHash,
+    /// Why the decision policy rejected the request, if it did.
+    pub rejection_reason: Option<String>,
+}
+
 // Document data structure from Redis message
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct DocumentData {
     #[serde(rename = "@entity")]
     pub entity: Option<String>,
@@ -80,6 +388,344 @@ pub struct DocumentData {
     pub reason: String,
 }
 
+/// Canonical PAN: trimmed and uppercased.
+pub fn normalize_pan(pan: &str) -> String {
+    pan.trim().to_uppercase()
+}
+
+/// A PAN fit for error messages, which end up in the DLQ: the first five and the last
+/// character kept, the digits masked (`ABCDE****F`). Anything too short is masked whole.
+pub fn mask_pan(pan: &str) -> String {
+    let chars: Vec<char> = pan.chars().collect();
+    if chars.len() < 6 {
+        return "*".repeat(chars.len());
+    }
+    let (head, tail) = (&chars[..5], chars[chars.len() - 1]);
+    format!("{}{}{}", head.iter().collect::<String>(), "*".repeat(chars.len() - 6), tail)
+}
+
+/// Canonical name: trimmed, internal whitespace collapsed to single spaces, uppercased.
+pub fn normalize_name(name: &str) -> String {
+    name.split_whitespace().collect::<Vec<_>>().join(" ").to_uppercase()
+}
+
+/// PAN read by OCR, if `extracted_data` carries one (as `pan` or `pan_number`).
+pub fn extracted_pan(extracted_data: &str) -> Option<String> {
+    let extracted: serde_json::Value = match serde_json::from_str(extracted_data) {
+        Ok(value) => value,
+        Err(e) => {
+            warn!("Ignoring unparseable extracted_data: {}", e);
+            return None;
+        }
+    };
+    ["pan", "pan_number"]
+        .iter()
+        .find_map(|key| extracted.get(key).and_then(|v| v.as_str()))
+        .map(normalize_pan)
+        .filter(|pan| !pan.is_empty())
+}
+
+/// The submitted and OCR-read PANs, normalized, when the request has both and they differ.
+fn pan_mismatch(request: &VerificationRequest, document_pan: &str) -> Option<(String, String)> {
+    let ocr_pan = request.extracted_data.as_deref().and_then(extracted_pan)?;
+    let submitted_pan = normalize_pan(document_pan);
+    (ocr_pan != submitted_pan).then_some((submitted_pan, ocr_pan))
+}
+
+/// Reject a request whose submitted PAN differs from the one OCR read off the document,
+/// so we never verify a PAN the user did not actually present. The discrepancy is written
+/// to the `audit` log target and counted in `pan_mismatch_total`.
+pub fn check_pan_consistency(request: &VerificationRequest, document_pan: &str) -> Result<(), InvalidMessage> {
+    let Some((submitted_pan, ocr_pan)) = pan_mismatch(request, document_pan) else {
+        return Ok(());
+    };
+    metrics::increment("pan_mismatch_total");
+    warn!(
+        target: "audit",
+        wallet = %request.user_wallet,
+        submitted_pan = %submitted_pan,
+        extracted_pan = %ocr_pan,
+        "PAN in document_data does not match the PAN extracted from the document"
+    );
+    Err(InvalidMessage {
+        reason: format!(
+            "PAN mismatch: document_data has {} but the document reads {}",
+            mask_pan(&submitted_pan),
+            mask_pan(&ocr_pan)
+        ),
+    })
+}
+
+/// A request without explicit consent, or without a stated purpose. It must never reach the
+/// government API, and retrying can't fix it.
+#[derive(Debug)]
+pub struct ConsentMissing {
+    pub reason: String,
+}
+
+impl std::fmt::Display for ConsentMissing {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Consent missing: {}", self.reason)
+    }
+}
+
+impl std::error::Error for ConsentMissing {}
+
+pub fn is_consent_missing(error: &anyhow::Error) -> bool {
+    error.downcast_ref::<ConsentMissing>().is_some()
+}
+
+/// The only `consent` value the API accepts as explicit consent.
+pub const CONSENT_GIVEN: &str = "Y";
+
+/// How a government API call failed. Retrying, the circuit breaker and the decision act on
+/// the variant, never on the message text.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GovApiError {
+    /// No response at all: connection, TLS or timeout failure, a 408 (the server gave up
+    /// waiting for the request), or the circuit is open.
+    Transport { reason: String },
+    /// 429; `retry_after` is the server's `Retry-After`, when it sent one.
+    RateLimited { retry_after: Option<std::time::Duration> },
+    /// 5xx, or a success response that can't be read.
+    ServerError { status: u16, body: String },
+    /// 401/403: our credentials were refused, usually an expired token. Nothing is wrong with
+    /// the message, so it is retried once the token is refreshed.
+    Unauthorized { status: u16, body: String },
+    /// 4xx: the request we sent is wrong, so resending it can't help.
+    ClientError { status: u16, body: String },
+    /// The API processed the request and rejected the PAN. This is a verification result.
+    BusinessReject { remarks: String },
+}
+
+impl std::fmt::Display for GovApiError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GovApiError::Transport { reason } => write!(f, "Government API unreachable: {}", reason),
+            GovApiError::RateLimited { retry_after } => {
+                write!(f, "Government API rate limited (Retry-After: {:?})", retry_after)
+            }
+            GovApiError::ServerError { status, body } => write!(f, "Government API server error: {} - {}", status, body),
+            GovApiError::Unauthorized { status, body } => write!(f, "Government API refused our credentials: {} - {}", status, body),
+            GovApiError::ClientError { status, body } => write!(f, "Government API rejected the request: {} - {}", status, body),
+            GovApiError::BusinessReject { remarks } => write!(f, "Government API rejected the PAN: {}", remarks),
+        }
+    }
+}
+
+impl std::error::Error for GovApiError {}
+
+impl GovApiError {
+    /// Worth trying again later.
+    pub fn is_retryable(&self) -> bool {
+        matches!(
+            self,
+            GovApiError::Transport { .. }
+                | GovApiError::RateLimited { .. }
+                | GovApiError::ServerError { .. }
+                | GovApiError::Unauthorized { .. }
+        )
+    }
+
+    /// The API is down rather than answering; what the circuit breaker and deferral act on.
+    pub fn is_unavailable(&self) -> bool {
+        matches!(self, GovApiError::Transport { .. } | GovApiError::ServerError { .. })
+    }
+
+    /// Classify a completed PAN verification exchange. A 422 is how the API rejects a PAN.
+    /// Errors keep only a [`body_summary`]: bodies can echo the submitted document.
+    pub fn from_response(status: reqwest::StatusCode, body: &str) -> Result<GovernmentApiResponse, GovApiError> {
+        let code = status.as_u16();
+        if status.is_success() {
+            // The serde error alone: its message can quote the response
+            return serde_json::from_str(body).map_err(|e| GovApiError::ServerError {
+                status: code,
+                body: format!("unreadable response ({:?} error at line {} column {})", e.classify(), e.line(), e.column()),
+            });
+        }
+        if status == reqwest::StatusCode::TOO_MANY_REQUESTS {
+            return Err(GovApiError::RateLimited { retry_after: None });
+        }
+        if status == reqwest::StatusCode::UNPROCESSABLE_ENTITY {
+            return Err(GovApiError::BusinessReject { remarks: body_summary(body) });
+        }
+        if status.is_server_error() {
+            return Err(GovApiError::ServerError { status: code, body: body_summary(body) });
+        }
+        if status == reqwest::StatusCode::REQUEST_TIMEOUT {
+            return Err(GovApiError::Transport { reason: format!("{} - {}", status, body_summary(body)) });
+        }
+        if status == reqwest::StatusCode::UNAUTHORIZED || status == reqwest::StatusCode::FORBIDDEN {
+            return Err(GovApiError::Unauthorized { status: code, body: body_summary(body) });
+        }
+        Err(GovApiError::ClientError { status: code, body: body_summary(body) })
+    }
+
+    /// Classify the error a call gave up with, after its retries.
+    fn from_call_error(error: anyhow::Error) -> GovApiError {
+        let error = match error.downcast::<GovApiError>() {
+            Ok(e) => return e,
+            Err(error) => error,
+        };
+        match error.downcast::<TransientError>() {
+            Ok(e) => GovApiError::RateLimited { retry_after: e.retry_after },
+            Err(error) => GovApiError::Transport { reason: format!("{:#}", error) },
+        }
+    }
+}
+
+/// Longest upstream message kept in an error.
+const ERROR_MESSAGE_CHARS: usize = 200;
+
+/// What an error keeps of an upstream error body: its `message`, cut to [`ERROR_MESSAGE_CHARS`],
+/// or just its size. The rest may echo the submitted name, date of birth and PAN, and errors are
+/// logged, retried and dead-lettered.
+pub fn body_summary(body: &str) -> String {
+    serde_json::from_str::<serde_json::Value>(body)
+        .ok()
+        .and_then(|v| v["message"].as_str().map(|m| m.trim().chars().take(ERROR_MESSAGE_CHARS).collect()))
+        .unwrap_or_else(|| format!("<{} byte body>", body.len()))
+}
+
+/// The [`GovApiError`] behind `error`, if it came from the government API client.
+pub fn gov_api_error(error: &anyhow::Error) -> Option<&GovApiError> {
+    error.downcast_ref::<GovApiError>()
+}
+
+impl PanVerificationData {
+    /// The record for a PAN the API rejected outright, which the decision policies then fail.
+    pub fn rejected(pan: &str, remarks: &str) -> Self {
+        Self {
+            entity: "in.co.sandbox.kyc.pan_verification.response".to_string(),
+            pan: pan.to_string(),
+            status: "invalid".to_string(),
+            remarks: Some(remarks.to_string()),
+            name_as_per_pan_match: false,
+            date_of_birth_match: false,
+            category: String::new(),
+            aadhaar_seeding_status: String::new(),
+        }
+    }
+}
+
+impl DocumentData {
+    /// Canonical form of the user input. This is what gets validated, sent to the API
+    /// and hashed, so the same human input always yields the same evidence hash.
+    pub fn normalized(mut self) -> Self {
+        self.pan = normalize_pan(&self.pan);
+        self.name_as_per_pan = normalize_name(&self.name_as_per_pan);
+        self.date_of_birth = self.date_of_birth.trim().to_string();
+        self
+    }
+
+    /// PAN format check: five letters, four digits, one letter (e.g. ABCDE1234F).
+    pub fn validate(&self) -> Result<()> {
+        let bytes = self.pan.as_bytes();
+        let well_formed = bytes.len() == 10
+            && bytes[..5].iter().all(u8::is_ascii_uppercase)
+            && bytes[5..9].iter().all(u8::is_ascii_digit)
+            && bytes[9].is_ascii_uppercase();
+        if !well_formed {
+            return Err(anyhow!("Invalid PAN format: {}", mask_pan(&self.pan)));
+        }
+        if self.name_as_per_pan.is_empty() {
+            return Err(anyhow!("name_as_per_pan is empty"));
+        }
+        Ok(())
+    }
+
+    /// Why this document lacks consent: `consent` other than [`CONSENT_GIVEN`], or no `reason`.
+    fn missing_consent(&self) -> Option<String> {
+        let consent = self.consent.trim();
+        if !consent.eq_ignore_ascii_case(CONSENT_GIVEN) {
+            Some(format!("consent is '{}', expected '{}'", consent, CONSENT_GIVEN))
+        } else if self.reason.trim().is_empty() {
+            Some("reason is empty".to_string())
+        } else {
+            None
+        }
+    }
+
+    /// Require `consent` to be [`CONSENT_GIVEN`] and `reason` to be stated before any upstream
+    /// call. The consent given (or refused) is written to the `audit` log target either way.
+    pub fn check_consent(&self, wallet: &str) -> Result<(), ConsentMissing> {
+        let consent = self.consent.trim();
+        let reason = self.reason.trim();
+        match self.missing_consent() {
+            None => {
+                info!(target: "audit", wallet = %wallet, consent = %consent, reason = %reason, "Consent recorded");
+                Ok(())
+            }
+            Some(missing) => {
+                metrics::increment("consent_missing_total");
+                warn!(target: "audit", wallet = %wallet, consent = %consent, reason = %reason, "Verification refused: {}", missing);
+                Err(ConsentMissing { reason: missing })
+            }
+        }
+    }
+}
+
+/// Where the government API is reached and how its certificate is checked.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GovtApiEndpoints {
+    pub auth_url: String,
+    pub base_url: String,
+    /// Only the enclave's localhost:8443 proxy (forwarded via VSOCK) is trusted without a valid cert.
+    pub accept_invalid_certs: bool,
+    /// Where PAN verifications are posted; the batch endpoint is under it (see [`Self::batch_url`]).
+    pub verify_url: String,
+    /// Verifications go through the host proxy, which authenticates them: no token or API key is sent.
+    pub via_host_proxy: bool,
+}
+
+impl GovtApiEndpoints {
+    /// In enclave mode the localhost proxy is forced and the configured URLs are ignored;
+    /// outside it `auth_url`/`base_url` (`GOVT_API_AUTH_URL`/`GOVT_API_BASE_URL`) or the direct API.
+    pub fn resolve(enclave_mode: bool, auth_url: Option<String>, base_url: Option<String>) -> Self {
+        if enclave_mode {
+            Self {
+                auth_url: "https://localhost:8443/authenticate".to_string(),
+                base_url: "https://localhost:8443".to_string(),
+                accept_invalid_certs: true,
+                verify_url: "http://localhost:9999/govt-api/pan/verify".to_string(),
+                via_host_proxy: true,
+            }
+        } else {
+            let base_url = base_url.unwrap_or_else(|| "https://api.sandbox.co.in".to_string());
+            let base_url = base_url.trim_end_matches('/').to_string();
+            Self {
+                auth_url: auth_url.unwrap_or_else(|| "https://api.sandbox.co.in/authenticate".to_string()),
+                verify_url: format!("{}/kyc/pan/verify", base_url),
+                base_url,
+                accept_invalid_certs: false,
+                via_host_proxy: false,
+            }
+        }
+    }
+
+    /// The batch endpoint, next to the single one.
+    pub fn batch_url(&self) -> String {
+        format!("{}/batch", self.verify_url)
+    }
+
+    /// HTTP client for these endpoints. With `GOVT_API_CERT_PIN` set, direct connections also
+    /// require the server certificate's key to match the pin; the enclave's localhost proxy is
+    /// exempt, as its certificate isn't validated at all.
+    pub fn client(&self, timeout: std::time::Duration) -> Result<Client> {
+        let builder = Client::builder().timeout(timeout);
+        if self.accept_invalid_certs {
+            return Ok(builder.danger_accept_invalid_certs(true).build()?);
+        }
+        match CertPin::from_env()? {
+            Some(pin) => {
+                info!("🔒 Government API certificate pinned");
+                Ok(builder.use_preconfigured_tls(pinned_tls_config(pin, webpki_roots())).build()?)
+            }
+            None => Ok(builder.build()?),
+        }
+    }
+}
+
 impl JwtManager {
     pub fn new() -> Result<Self> {
         // Check if running in enclave mode
@@ -88,36 +734,17 @@ impl JwtManager {
         let enclave_mode = enclave_mode_str.parse::<bool>().unwrap_or(false);
         info!("🔧 JwtManager ENCLAVE_MODE: '{}' -> {}", enclave_mode_str, enclave_mode);
             
-        let auth_url = if enclave_mode {
-            // In enclave: force localhost:8443 (forwarded via VSOCK)
-            let url = "https://localhost:8443/authenticate".to_string();
-            info!("🔧 ENCLAVE_MODE=true: Forcing auth URL: {}", url);
-            url
-        } else {
-            // Outside enclave: use env var or direct API
-            let url = std::env::var("GOVT_API_AUTH_URL")
-                .unwrap_or_else(|_| "https://api.sandbox.co.in/authenticate".to_string());
-            info!("🔧 ENCLAVE_MODE=false: Using auth URL: {}", url);
-            url
-        };
+        let endpoints = GovtApiEndpoints::resolve(enclave_mode, std::env::var("GOVT_API_AUTH_URL").ok(), None);
+        let auth_url = endpoints.auth_url.clone();
+        info!("🔧 ENCLAVE_MODE={}: Using auth URL: {}", enclave_mode, auth_url);
         
         let api_key = std::env::var("GOVT_API_KEY")
             .map_err(|_| anyhow!("GOVT_API_KEY environment variable not set"))?;
         let api_secret = std::env::var("GOVT_API_SECRET")
             .map_err(|_| anyhow!("GOVT_API_SECRET environment variable not set"))?;
 
-        let client = if enclave_mode {
-            // In enclave: disable SSL verification for localhost proxy
-            Client::builder()
-                .timeout(std::time::Duration::from_secs(30))
-                .danger_accept_invalid_certs(true)
-                .build()?
-        } else {
-            // Outside enclave: normal SSL verification
-            Client::builder()
-                .timeout(std::time::Duration::from_secs(30))
-                .build()?
-        };
+        // In enclave the localhost proxy presents a self-signed cert
+        let client = endpoints.client(std::time::Duration::from_secs(30))?;
 
         Ok(Self {
             client,
@@ -178,6 +805,12 @@ impl JwtManager {
         Ok(token)
     }
 
+    /// Forget the current token, after the API refused it, so the next call authenticates again.
+    pub fn invalidate(&mut self) {
+        self.current_token = None;
+        self.token_expires_at = None;
+    }
+
     // Get valid token (authenticate if needed)
     pub async fn get_valid_token(&mut self) -> Result<String> {
         if !self.is_token_valid() {
@@ -192,7 +825,61 @@ impl JwtManager {
 pub struct GovernmentApiClient {
     client: Client,
     jwt_manager: JwtManager,
-    api_base_url: String,
+    endpoints: GovtApiEndpoints,
+    decision_policies: DecisionPolicies,
+    circuit_breaker: Arc<CircuitBreaker>,
+    retry_policy: RetryPolicy,
+    evidence_profile: EvidenceProfile,
+    batch_config: PanBatchConfig,
+    // Cleared once the upstream answers the batch endpoint with 404/405
+    batch_supported: bool,
+    // Batch results waiting for their message to be processed
+    prefetched: HashMap<String, GovernmentApiResponse>,
+    // Accepted verification types, once the table is attached
+    verification_types: Option<VerificationTypes>,
+    auth_header: AuthHeaderFormat,
+    payload_template: PanPayloadTemplate,
+    // Larger response bodies are refused rather than buffered
+    max_response_bytes: usize,
+    // Header the idempotency key goes in, if the upstream takes one
+    idempotency_header: Option<String>,
+    // Verification calls in flight, sized apart from Sui's
+    call_limit: CallLimit,
+}
+
+/// The evidence a PAN verification commits to: stable fields of the API response plus the
+/// claimed name and date of birth, in canonical form so input casing and spacing never change
+/// the hash. Shared by [`GovernmentApiClient::generate_evidence_hash`] and [`verify_evidence_hash`].
+pub fn pan_evidence_input(api_response: &GovernmentApiResponse, user_name: &str, user_dob: &str) -> EvidenceInput {
+    EvidenceInput::Pan(PanEvidence {
+        pan: normalize_pan(&api_response.data.pan),
+        status: api_response.data.status.clone(),
+        name_as_per_pan: normalize_name(user_name),
+        date_of_birth: user_dob.trim().to_string(),
+        name_as_per_pan_match: Some(api_response.data.name_as_per_pan_match),
+        date_of_birth_match: Some(api_response.data.date_of_birth_match),
+        category: api_response.data.category.clone(),
+        aadhaar_seeding_status: api_response.data.aadhaar_seeding_status.clone(),
+    })
+}
+
+/// Offline audit of an evidence hash: whether `expected_hash` is what we derive from a stored
+/// government API response and the claimed name and date of birth, without calling the API.
+/// The hash may have been computed under any [`EvidenceProfile`] and any earlier schema version,
+/// so each is tried; a response that doesn't parse never verifies.
+pub fn verify_evidence_hash(api_response_json: &str, claimed_name: &str, claimed_dob: &str, expected_hash: &str) -> bool {
+    let Ok(api_response) = serde_json::from_str::<GovernmentApiResponse>(api_response_json) else {
+        return false;
+    };
+    let input = pan_evidence_input(&api_response, claimed_name, claimed_dob);
+    let expected = expected_hash.trim().trim_start_matches("0x");
+    [EvidenceProfile::Full, EvidenceProfile::Facts].into_iter().any(|profile| {
+        input.with_profile(profile).preimages().is_ok_and(|preimages| {
+            preimages
+                .iter()
+                .any(|(_, preimage)| hex::encode(Sha256::digest(preimage.as_bytes())).eq_ignore_ascii_case(expected))
+        })
+    })
 }
 
 impl GovernmentApiClient {
@@ -203,111 +890,396 @@ impl GovernmentApiClient {
         let enclave_mode = enclave_mode_str.parse::<bool>().unwrap_or(false);
         info!("🔧 GovernmentApiClient ENCLAVE_MODE: '{}' -> {}", enclave_mode_str, enclave_mode);
             
-        let api_base_url = if enclave_mode {
-            // In enclave: force localhost:8443 (forwarded via VSOCK)
-            let url = "https://localhost:8443".to_string();
-            info!("🔧 ENCLAVE_MODE=true: Forcing base URL: {}", url);
-            url
-        } else {
-            // Outside enclave: use env var or direct API
-            let url = std::env::var("GOVT_API_BASE_URL")
-                .unwrap_or_else(|_| "https://api.sandbox.co.in".to_string());
-            info!("🔧 ENCLAVE_MODE=false: Using base URL: {}", url);
-            url
-        };
+        let endpoints = GovtApiEndpoints::resolve(
+            enclave_mode,
+            std::env::var("GOVT_API_AUTH_URL").ok(),
+            std::env::var("GOVT_API_BASE_URL").ok(),
+        );
+        info!("🔧 ENCLAVE_MODE={}: Using base URL: {}, verify URL: {}", enclave_mode, endpoints.base_url, endpoints.verify_url);
 
-        let client = if enclave_mode {
-            // In enclave: disable SSL verification for localhost proxy
-            Client::builder()
-                .timeout(std::time::Duration::from_secs(60))
-                .danger_accept_invalid_certs(true)
-                .build()?
-        } else {
-            // Outside enclave: normal SSL verification
-            Client::builder()
-                .timeout(std::time::Duration::from_secs(60))
-                .build()?
-        };
+        // In enclave the localhost proxy presents a self-signed cert
+        let client = endpoints.client(std::time::Duration::from_secs(60))?;
 
         let jwt_manager = JwtManager::new()?;
+        let decision_policies = DecisionPolicies::from_env()?;
 
         Ok(Self {
             client,
             jwt_manager,
-            api_base_url,
+            endpoints,
+            decision_policies,
+            circuit_breaker: Arc::new(CircuitBreaker::from_env("govt_api", "GOVT_API")),
+            retry_policy: RetryPolicy::from_env("GOVT_API_RETRY"),
+            evidence_profile: EvidenceProfile::from_env()?,
+            batch_config: PanBatchConfig::from_env(),
+            batch_supported: true,
+            prefetched: HashMap::new(),
+            verification_types: None,
+            auth_header: AuthHeaderFormat::from_env()?,
+            payload_template: PanPayloadTemplate::from_env()?,
+            max_response_bytes: std::env::var("GOVT_API_MAX_RESPONSE_BYTES")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(DEFAULT_MAX_RESPONSE_BYTES),
+            idempotency_header: idempotency_header_from_env()?,
+            call_limit: CallLimit::from_env("govt_api", "GOVT_API", 8),
         })
     }
 
-    // Verify PAN with government API
-    pub async fn verify_pan(&mut self, document_data: &DocumentData) -> Result<GovernmentApiResponse> {
+    /// Take per-type decision policies from the verification type table, and refuse any
+    /// type it doesn't accept instead of applying the default policy.
+    pub fn with_verification_types(mut self, types: &VerificationTypes) -> Self {
+        self.decision_policies = self.decision_policies.with_type_policies(types.policies());
+        self.verification_types = Some(types.clone());
+        self
+    }
+
+    /// Point the client at other endpoints, e.g. the mock in [`crate::mock_govt_api`].
+    /// Outside the enclave this is what `GOVT_API_AUTH_URL` and `GOVT_API_BASE_URL` set.
+    pub fn with_endpoints(mut self, auth_url: &str, api_base_url: &str) -> Self {
+        self.jwt_manager.auth_url = auth_url.to_string();
+        self.endpoints = GovtApiEndpoints::resolve(false, Some(auth_url.to_string()), Some(api_base_url.to_string()));
+        self
+    }
+
+    /// Choose which evidence fields the hash commits to (`EVIDENCE_HASH_PROFILE`).
+    pub fn with_evidence_profile(mut self, profile: EvidenceProfile) -> Self {
+        self.evidence_profile = profile;
+        self
+    }
+
+    pub fn with_auth_header(mut self, format: AuthHeaderFormat) -> Self {
+        self.auth_header = format;
+        self
+    }
+
+    pub fn with_payload_template(mut self, template: PanPayloadTemplate) -> Self {
+        self.payload_template = template;
+        self
+    }
+
+    /// Cap on a response body (`GOVT_API_MAX_RESPONSE_BYTES`, default 1 MiB).
+    pub fn with_max_response_bytes(mut self, max: usize) -> Self {
+        self.max_response_bytes = max;
+        self
+    }
+
+    /// Header to send each call's [`idempotency_key`] in, or `None` to leave it out.
+    pub fn with_idempotency_header(mut self, header: Option<&str>) -> Self {
+        self.idempotency_header = header.map(str::to_ascii_lowercase);
+        self
+    }
+
+    /// Cap on concurrent verification calls (`GOVT_API_MAX_CONCURRENCY`, default 8).
+    pub fn with_call_limit(mut self, limit: CallLimit) -> Self {
+        self.call_limit = limit;
+        self
+    }
+
+    /// The cap on concurrent verification calls, for another client to share.
+    pub fn call_limit(&self) -> CallLimit {
+        self.call_limit.clone()
+    }
+
+    /// Trip and check `breaker` instead of a circuit of this client's own.
+    pub fn with_circuit_breaker(mut self, breaker: Arc<CircuitBreaker>) -> Self {
+        self.circuit_breaker = breaker;
+        self
+    }
+
+    pub fn with_batch_config(mut self, config: PanBatchConfig) -> Self {
+        self.batch_config = config;
+        self
+    }
+
+    pub fn batch_config(&self) -> PanBatchConfig {
+        self.batch_config
+    }
+
+    /// Shared so the deferred-message task can tell when the API has recovered.
+    pub fn circuit_breaker(&self) -> Arc<CircuitBreaker> {
+        self.circuit_breaker.clone()
+    }
+
+    /// Verify a PAN with the government API, classifying any failure as a [`GovApiError`].
+    /// Every attempt carries `idempotency_key`, so the upstream can tell a retry from a new request.
+    pub async fn verify_pan(
+        &mut self,
+        document_data: &DocumentData,
+        idempotency_key: &str,
+    ) -> Result<GovernmentApiResponse, GovApiError> {
+        self.verify_pan_timed(document_data, idempotency_key, &StageTimer::default()).await
+    }
+
+    /// [`Self::verify_pan`], timing the token fetch as `govt_auth` and the call as `govt_verify`.
+    async fn verify_pan_timed(
+        &mut self,
+        document_data: &DocumentData,
+        idempotency_key: &str,
+        timer: &StageTimer,
+    ) -> Result<GovernmentApiResponse, GovApiError> {
         info!("Starting PAN verification for PAN: {}", document_data.pan);
 
         // Get valid JWT token (only needed for direct API calls, not proxy)
-        let token = if std::env::var("ENCLAVE_MODE").unwrap_or_else(|_| "false".to_string()).parse::<bool>().unwrap_or(false) {
-            // In enclave: using proxy, no token needed
-            "".to_string()
+        let token = if self.endpoints.via_host_proxy {
+            String::new()
         } else {
-            // Outside enclave: direct API call, need token
-            self.jwt_manager.get_valid_token().await?
+            timer.time("govt_auth", self.jwt_manager.get_valid_token()).await.map_err(GovApiError::from_call_error)?
         };
 
-        // Prepare PAN verification payload (match exact API format)
-        let verification_payload = serde_json::json!({
-            "@entity": "in.co.sandbox.kyc.pan_verification.request",
-            "pan": document_data.pan,
-            "name_as_per_pan": document_data.name_as_per_pan,
-            "date_of_birth": document_data.date_of_birth,
-            "consent": document_data.consent,
-            "reason": document_data.reason
+        // Prepare PAN verification payload in the upstream's format
+        let verification_payload = self.payload_template.render(document_data);
+        let url = self.endpoints.verify_url.clone();
+
+        info!("Making PAN verification API call to: {}", url);
+
+        let (status, response_text) = timer
+            .time("govt_verify", self.send_with_retry(&url, &token, &verification_payload, Some(idempotency_key)))
+            .await
+            .map_err(GovApiError::from_call_error)?;
+
+        info!("Government API response status: {}", status);
+
+        let mut result = GovApiError::from_response(status, &response_text);
+        // The token expired or was revoked early: authenticate again and send it once more
+        if let (Err(GovApiError::Unauthorized { .. }), false) = (&result, token.is_empty()) {
+            warn!("Government API refused the token ({}), re-authenticating", status);
+            self.jwt_manager.invalidate();
+            let token = timer.time("govt_auth", self.jwt_manager.get_valid_token()).await.map_err(GovApiError::from_call_error)?;
+            let (status, response_text) = timer
+                .time("govt_verify", self.send_with_retry(&url, &token, &verification_payload, Some(idempotency_key)))
+                .await
+                .map_err(GovApiError::from_call_error)?;
+            result = GovApiError::from_response(status, &response_text);
+        }
+        let api_response = result.inspect_err(|e| {
+            error!("Government API call failed: {}", e);
+        })?;
+
+        info!("PAN verification completed successfully. Status: {}", api_response.data.status);
+
+        Ok(api_response)
+    }
+
+    /// Verify several PANs, in one call to the batch endpoint when the upstream has one and with
+    /// one call per PAN otherwise. Results are in input order; `reference_id`s tie them to messages.
+    pub async fn verify_pan_batch(&mut self, inputs: &[PanBatchItem]) -> Result<Vec<Result<GovernmentApiResponse>>> {
+        if let Some(results) = self.try_verify_pan_batch(inputs).await? {
+            return Ok(results);
+        }
+        Ok(self.verify_pan_each(inputs).await)
+    }
+
+    /// Verify each of `inputs` with a call of its own, all sent at once: the call limit decides
+    /// how many are in flight. Any refused for the token are sent again, one at a time, after
+    /// authenticating anew. Results are in input order.
+    async fn verify_pan_each(&mut self, inputs: &[PanBatchItem]) -> Vec<Result<GovernmentApiResponse>> {
+        let token = match self.endpoints.via_host_proxy {
+            true => String::new(),
+            false => match self.jwt_manager.get_valid_token().await {
+                Ok(token) => token,
+                Err(e) => {
+                    let e = GovApiError::from_call_error(e);
+                    return inputs.iter().map(|_| Err(e.clone().into())).collect();
+                }
+            },
+        };
+        let (client, url, token) = (&*self, &self.endpoints.verify_url, &token);
+        let calls = inputs.iter().map(|item| async move {
+            let payload = client.payload_template.render(&item.document);
+            client
+                .send_with_retry(url, token, &payload, Some(&item.idempotency_key))
+                .await
+                .map_err(GovApiError::from_call_error)
+                .and_then(|(status, text)| GovApiError::from_response(status, &text))
         });
+        let results = futures::future::join_all(calls).await;
 
-        let url = if std::env::var("ENCLAVE_MODE").unwrap_or_else(|_| "false".to_string()).parse::<bool>().unwrap_or(false) {
-            // In enclave: use host proxy via VSOCK
-            "http://localhost:9999/govt-api/pan/verify".to_string()
-        } else {
-            // Outside enclave: direct API call
-            format!("{}/kyc/pan/verify", self.api_base_url)
+        let refused = |result: &Result<GovernmentApiResponse, GovApiError>| {
+            matches!(result, Err(GovApiError::Unauthorized { .. })) && !token.is_empty()
         };
+        if results.iter().any(refused) {
+            self.jwt_manager.invalidate();
+        }
+        let mut verified = Vec::with_capacity(inputs.len());
+        for (item, result) in inputs.iter().zip(results) {
+            let result = match refused(&result) {
+                true => self.verify_pan(&item.document, &item.idempotency_key).await,
+                false => result,
+            };
+            verified.push(result.map_err(Into::into));
+        }
+        verified
+    }
 
-        info!("Making PAN verification API call to: {}", url);
+    /// One call to the batch endpoint, or `None` if the upstream doesn't have one.
+    async fn try_verify_pan_batch(
+        &mut self,
+        inputs: &[PanBatchItem],
+    ) -> Result<Option<Vec<Result<GovernmentApiResponse>>>> {
+        if !self.batch_supported {
+            return Ok(None);
+        }
+        let token = if self.endpoints.via_host_proxy { String::new() } else { self.jwt_manager.get_valid_token().await? };
+        let url = self.endpoints.batch_url();
+        let requests: Vec<serde_json::Value> = inputs
+            .iter()
+            .map(|item| {
+                let mut request = self.payload_template.render(&item.document);
+                request["reference_id"] = item.reference_id.clone().into();
+                request
+            })
+            .collect();
+        let payload = serde_json::json!({ "requests": requests });
+        // Derived from the keys of the calls it stands for, so a retried batch is recognised too
+        let keys: Vec<&str> = inputs.iter().map(|item| item.idempotency_key.as_str()).collect();
+        let key = idempotency_key("", "batch", &keys.join(","));
+
+        info!("Making batch PAN verification API call for {} PANs to: {}", inputs.len(), url);
+        let (status, response_text) = self.send_with_retry(&url, &token, &payload, Some(&key)).await?;
+        if status == reqwest::StatusCode::NOT_FOUND || status == reqwest::StatusCode::METHOD_NOT_ALLOWED {
+            warn!("Government API has no batch endpoint ({}), verifying PANs one at a time", status);
+            self.batch_supported = false;
+            return Ok(None);
+        }
+        if !status.is_success() {
+            return Err(anyhow!("Government API batch call failed: {} - {}", status, response_text));
+        }
+        let response: GovernmentApiBatchResponse = serde_json::from_str(&response_text)
+            .map_err(|e| anyhow!("Failed to parse government API batch response: {} - Response: {}", e, response_text))?;
+        metrics::increment("govt_api_batch_calls_total");
+        Ok(Some(map_batch_response(inputs, response)))
+    }
+
+    /// Verify the PANs of `requests` ahead of processing them one by one: in one batch call when
+    /// batching is configured, otherwise with concurrent calls up to the call limit.
+    /// [`Self::process_verification_request`] then uses the stored answers. Requests that fail
+    /// to parse, or get no answer, are simply verified on their own later.
+    pub async fn prefetch(&mut self, requests: &[(String, VerificationRequest)]) {
+        self.prefetched.clear();
+        let mut inputs: Vec<PanBatchItem> = Vec::new();
+        for (reference_id, request) in requests {
+            let Ok(document) = serde_json::from_str::<DocumentData>(&request.document_data) else { continue };
+            let document = document.normalized();
+            // Left for the one-by-one path to reject and audit
+            if document.validate().is_err()
+                || document.missing_consent().is_some()
+                || pan_mismatch(request, &document.pan).is_some() {
+                continue;
+            }
+            // Identical inputs get the same answer; ask once
+            if !inputs.iter().any(|item| prefetch_key(&item.document) == prefetch_key(&document)) {
+                inputs.push(PanBatchItem::new(reference_id, request, document));
+            }
+        }
+        if inputs.len() < 2 {
+            return;
+        }
+        let results = match self.batch_config.enabled() {
+            true => self.verify_pan_batch(&inputs).await,
+            false => Ok(self.verify_pan_each(&inputs).await),
+        };
+        match results {
+            Ok(results) => {
+                for (item, result) in inputs.iter().zip(results) {
+                    if let Ok(response) = result {
+                        self.prefetched.insert(prefetch_key(&item.document), response);
+                    }
+                }
+            }
+            Err(e) => warn!("Batch PAN verification failed, verifying one at a time: {}", e),
+        }
+    }
+
+    /// [`Self::send_verification`] under the retry policy. Transport failures and 5xx are retried
+    /// while the circuit stays closed; a 429 waits for its `Retry-After` when it has one.
+    /// Every attempt sends the same `idempotency_key`.
+    async fn send_with_retry(
+        &self,
+        url: &str,
+        token: &str,
+        verification_payload: &serde_json::Value,
+        idempotency_key: Option<&str>,
+    ) -> Result<(reqwest::StatusCode, String)> {
+        retry_with_backoff_if(
+            &self.retry_policy,
+            "Government API call",
+            |e| (is_unavailable(e) || is_transient(e)) && !self.circuit_breaker.is_open(),
+            |_| self.send_verification(url, token, verification_payload, idempotency_key),
+        )
+        .await
+    }
 
-        let response = if std::env::var("ENCLAVE_MODE").unwrap_or_else(|_| "false".to_string()).parse::<bool>().unwrap_or(false) {
+    /// One attempt at the PAN verification call. Transport failures and 5xx are recorded against
+    /// the circuit and returned as [`GovApiError`]s; a 429 is a [`TransientError`] carrying the
+    /// server's `Retry-After`; any other response is returned as-is.
+    async fn send_verification(
+        &self,
+        url: &str,
+        token: &str,
+        verification_payload: &serde_json::Value,
+        idempotency_key: Option<&str>,
+    ) -> Result<(reqwest::StatusCode, String)> {
+        // Fail fast while the API is known to be down
+        self.circuit_breaker.check()?;
+        let _permit = self.call_limit.acquire().await;
+
+        let mut request = if self.endpoints.via_host_proxy {
             // In enclave: call host proxy (no auth headers needed)
             self.client
-                .post(&url)
+                .post(url)
                 .header("Content-Type", "application/json")
-                .json(&verification_payload)
-                .send()
-                .await?
         } else {
             // Outside enclave: direct API call with auth headers
             self.client
-                .post(&url)
-                .header("authorization", token)  // Use raw JWT token without "Bearer" prefix
+                .post(url)
+                .header(self.auth_header.token_header.as_str(), self.auth_header.token_value(token))
                 .header("Content-Type", "application/json")
-                .header("x-api-key", &self.jwt_manager.api_key)  // Add missing API key header
-                .json(&verification_payload)
-                .send()
-                .await?
+                .header(self.auth_header.api_key_header.as_str(), &self.jwt_manager.api_key)
         };
+        if let (Some(header), Some(key)) = (&self.idempotency_header, idempotency_key) {
+            request = request.header(header.as_str(), key);
+        }
+        let sent = request.json(verification_payload).send().await;
 
+        // Transport failures and 5xx count against the circuit; anything else means the API is up
+        let response = match sent {
+            Ok(response) => response,
+            Err(e) => {
+                self.circuit_breaker.record_failure();
+                return Err(GovApiError::Transport { reason: e.to_string() }.into());
+            }
+        };
         let status = response.status();
-        let response_text = response.text().await?;
-
-        info!("Government API response status: {}", status);
+        let retry_after = retry_after_header(response.headers());
+        let response_text = match read_body_limited(response, self.max_response_bytes).await {
+            Ok(text) => text,
+            Err(e) => {
+                self.circuit_breaker.record_failure();
+                error!("Government API response refused: {}", e);
+                return Err(e.into());
+            }
+        };
 
-        if !status.is_success() {
-            error!("Government API call failed: {} - {}", status, response_text);
-            return Err(anyhow!("Government API call failed: {} - {}", status, response_text));
+        // Rate limiting means the API is up; wait as asked rather than counting it as an outage
+        if status == reqwest::StatusCode::TOO_MANY_REQUESTS {
+            warn!("Government API rate limited us (Retry-After: {:?})", retry_after);
+            return Err(TransientError { reason: format!("{} - {}", status, body_summary(&response_text)), retry_after }.into());
         }
-
-        // Parse response
-        let api_response: GovernmentApiResponse = serde_json::from_str(&response_text)
-            .map_err(|e| anyhow!("Failed to parse government API response: {} - Response: {}", e, response_text))?;
-
-        info!("PAN verification completed successfully. Status: {}", api_response.data.status);
-
-        Ok(api_response)
+        if status.is_server_error() {
+            self.circuit_breaker.record_failure();
+            let body = body_summary(&response_text);
+            error!("Government API call failed: {} - {}", status, body);
+            return Err(GovApiError::ServerError { status: status.as_u16(), body }.into());
+        }
+        // The server timed out waiting for the request: the API is up, so it doesn't count as an outage
+        if status == reqwest::StatusCode::REQUEST_TIMEOUT {
+            let body = body_summary(&response_text);
+            warn!("Government API timed out receiving the request: {}", body);
+            return Err(TransientError { reason: format!("{} - {}", status, body), retry_after: None }.into());
+        }
+        self.circuit_breaker.record_success();
+        Ok((status, response_text))
     }
 
     // Generate evidence hash from government API response and user data
@@ -316,67 +1288,114 @@ impl GovernmentApiClient {
         api_response: &GovernmentApiResponse,
         user_name: &str,
         user_dob: &str,
-    ) -> Result<String> {
-        // Create evidence hash input with stable fields + actual verified data
-        let evidence_input = EvidenceHashInput {
-            pan: api_response.data.pan.clone(),
-            status: api_response.data.status.clone(),
-            name_as_per_pan: user_name.to_string(),
-            date_of_birth: user_dob.to_string(),
-            name_as_per_pan_match: api_response.data.name_as_per_pan_match,
-            date_of_birth_match: api_response.data.date_of_birth_match,
-            category: api_response.data.category.clone(),
-            aadhaar_seeding_status: api_response.data.aadhaar_seeding_status.clone(),
-        };
+    ) -> Result<EvidenceHash> {
+        let evidence_input = pan_evidence_input(api_response, user_name, user_dob);
 
-        // Serialize to JSON with consistent ordering
-        let json_string = serde_json::to_string(&evidence_input)?;
-        
-        info!("Evidence hash input: {}", json_string);
+        // Serialize to tagged canonical JSON
+        let evidence_input = evidence_input.with_profile(self.evidence_profile);
+        info!("Evidence hash input: {}", evidence_input.preimage()?);
 
         // Generate SHA256 hash
-        let mut hasher = Sha256::new();
-        hasher.update(json_string.as_bytes());
-        let hash_bytes = hasher.finalize();
-        let evidence_hash = hex::encode(hash_bytes);
+        let evidence_hash = evidence_input.hash_with_profile(self.evidence_profile)?;
 
-        info!("Generated evidence hash: {}", evidence_hash);
+        info!("Generated evidence hash: {} (schema: {}, profile: {})",
+              evidence_hash.hash, evidence_hash.schema, evidence_hash.profile);
 
         Ok(evidence_hash)
     }
 
-    // Process verification request from Redis
-    pub async fn process_verification_request(&mut self, request: &VerificationRequest) -> Result<(String, String)> {
+    // Process verification request from Redis, timing its stages on `timer`
+    pub async fn process_verification_request(
+        &mut self,
+        request: &VerificationRequest,
+        timer: &StageTimer,
+    ) -> Result<VerificationOutcome> {
         info!("Processing verification request for wallet: {}", request.user_wallet);
+        if let Some(types) = &self.verification_types {
+            types.get(&request.verification_type)?;
+        }
 
-        // Parse document data from JSON string
-        info!("Raw document_data JSON: {}", request.document_data);
-        let document_data: DocumentData = serde_json::from_str(&request.document_data)
-            .map_err(|e| anyhow!("Failed to parse document_data: {} - JSON: {}", e, request.document_data))?;
+        // Parse document data from JSON string; never logged, it is the user's PII
+        let document_data: DocumentData = timer
+            .time_sync("parse", || serde_json::from_str(&request.document_data))
+            .map_err(|e| anyhow!("Failed to parse document_data at line {} column {}", e.line(), e.column()))?;
 
-        // Make government API call
-        let api_response = self.verify_pan(&document_data).await?;
+        // Normalize before validation, the API call and the evidence hash
+        let document_data = document_data.normalized();
+        document_data.validate()?;
+        document_data.check_consent(&request.user_wallet)?;
+        check_pan_consistency(request, &document_data.pan)?;
 
-        // Determine verification result
-        let verification_result = if api_response.data.status == "valid" 
-            && api_response.data.name_as_per_pan_match 
-            && api_response.data.date_of_birth_match {
+        // Make government API call, unless a batch call already answered it
+        let mut rejected_with = None;
+        let api_response = match self.prefetched.remove(&prefetch_key(&document_data)) {
+            Some(response) => response,
+            None => match self
+                .verify_pan_timed(
+                    &document_data,
+                    &request.idempotency_key(&document_data.pan),
+                    timer,
+                )
+                .await
+            {
+                Ok(response) => response,
+                // A rejected PAN is a result, not a failure to verify
+                Err(GovApiError::BusinessReject { remarks }) => {
+                    metrics::increment("govt_api_business_rejects_total");
+                    rejected_with = Some(remarks.clone());
+                    GovernmentApiResponse {
+                        code: reqwest::StatusCode::UNPROCESSABLE_ENTITY.as_u16(),
+                        timestamp: Utc::now().timestamp_millis() as u64,
+                        data: PanVerificationData::rejected(&document_data.pan, &remarks),
+                        transaction_id: String::new(),
+                    }
+                }
+                Err(e) => return Err(e.into()),
+            },
+        };
+
+        // A status the upstream may still change is retried rather than decided
+        if self.decision_policies.status_handling.outcome(&api_response.data.status) == StatusOutcome::Retry {
+            metrics::increment("govt_api_status_retries_total");
+            return Err(TransientError {
+                reason: format!("PAN status '{}' is not final yet", api_response.data.status),
+                retry_after: None,
+            }
+            .into());
+        }
+
+        // Determine verification result using the policy configured for this verification type
+        let policy = self.decision_policies.for_type(&request.verification_type);
+        let mut decision = self.decision_policies.evaluate(&request.verification_type, &api_response.data);
+        if let Some(remarks) = rejected_with {
+            decision.reason = Some(format!("PAN rejected by the government API: {}", remarks));
+        }
+        let verification_result = if decision.verified {
             "verified"
+        } else if decision.review {
+            metrics::increment("verification_reviews_total");
+            "review"
         } else {
             "failed"
         };
+        info!("Decision policy '{}' applied for verification type '{}'", policy.as_str(), request.verification_type);
+        if let Some(reason) = &decision.reason {
+            warn!("Verification rejected for wallet: {} - Reason: {}", request.user_wallet, reason);
+        }
 
         // Generate evidence hash
-        let evidence_hash = self.generate_evidence_hash(
-            &api_response,
-            &document_data.name_as_per_pan,
-            &document_data.date_of_birth,
-        )?;
+        let evidence_hash = timer.time_sync("hash", || {
+            self.generate_evidence_hash(&api_response, &document_data.name_as_per_pan, &document_data.date_of_birth)
+        })?;
 
         info!("Verification completed for wallet: {} - Result: {} - Evidence Hash: {}", 
-               request.user_wallet, verification_result, evidence_hash);
+               request.user_wallet, verification_result, evidence_hash.hash);
 
-        Ok((verification_result.to_string(), evidence_hash))
+        Ok(VerificationOutcome {
+            result: verification_result.to_string(),
+            evidence: evidence_hash,
+            rejection_reason: decision.reason,
+        })
     }
 }
 
@@ -384,6 +1403,268 @@ impl GovernmentApiClient {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_batch_response_records_map_back_to_their_inputs() {
+        let inputs = vec![
+            batch_item("1-0", "HJTPB9891M"),
+            batch_item("2-0", "ABCDE1234F"),
+            batch_item("3-0", "PQRST6789Z"),
+            batch_item("4-0", "LMNOP4321K"),
+        ];
+        let record = |reference_id: Option<&str>, pan: &str, status: &str| {
+            serde_json::json!({
+                "reference_id": reference_id,
+                "@entity": "in.co.sandbox.kyc.pan_verification.response",
+                "pan": pan,
+                "status": status,
+                "remarks": null,
+                "name_as_per_pan_match": true,
+                "date_of_birth_match": true,
+                "category": "individual",
+                "aadhaar_seeding_status": "y",
+            })
+        };
+        // Out of order; 3-0 matched by PAN alone; 4-0 missing
+        let response: GovernmentApiBatchResponse = serde_json::from_value(serde_json::json!({
+            "code": 200,
+            "timestamp": 1760865505809u64,
+            "transaction_id": "batch-1",
+            "data": [
+                record(Some("2-0"), "ABCDE1234F", "deactivated"),
+                record(None, "pqrst6789z", "valid"),
+                record(Some("1-0"), "HJTPB9891M", "valid"),
+            ],
+        }))
+        .unwrap();
+
+        let results = map_batch_response(&inputs, response);
+        assert_eq!(results.len(), 4);
+        assert_eq!(results[0].as_ref().unwrap().data.pan, "HJTPB9891M");
+        assert_eq!(results[1].as_ref().unwrap().data.status, "deactivated");
+        assert_eq!(results[2].as_ref().unwrap().data.pan, "pqrst6789z");
+        assert_eq!(results[2].as_ref().unwrap().transaction_id, "batch-1");
+        assert!(results[3].as_ref().unwrap_err().to_string().contains("4-0"));
+    }
+
+    #[tokio::test]
+    async fn test_batch_falls_back_to_single_calls_without_batch_endpoint() {
+        use crate::mock_govt_api::{MockGovtApi, MockScenario};
+        let mock = MockGovtApi::new(MockScenario::Valid).with_pan("ABCDE1234F", MockScenario::Deactivated);
+        let (addr, _server) = mock.serve("127.0.0.1:0").await.unwrap();
+        let mut client = GovernmentApiClient::new()
+            .unwrap()
+            .with_endpoints(&format!("http://{}/authenticate", addr), &format!("http://{}", addr));
+
+        let inputs = vec![batch_item("1-0", "HJTPB9891M"), batch_item("2-0", "ABCDE1234F")];
+        // Each item carries the key its request would send on its own
+        assert_eq!(inputs[0].idempotency_key, idempotency_key("0xabc", "hjtpb9891m", "2025-01-01T00:00:00Z#1-0"));
+        let results = client.verify_pan_batch(&inputs).await.unwrap();
+        assert_eq!(results[0].as_ref().unwrap().data.status, "valid");
+        assert_eq!(results[1].as_ref().unwrap().data.status, "deactivated");
+        assert!(!client.batch_supported);
+    }
+
+    #[test]
+    fn test_responses_map_to_gov_api_error_variants() {
+        use reqwest::StatusCode;
+        let ok = serde_json::to_string(&api_response("HJTPB9891M")).unwrap();
+        assert_eq!(GovApiError::from_response(StatusCode::OK, &ok).unwrap().data.pan, "HJTPB9891M");
+
+        let classify = |status: StatusCode, body: &str| GovApiError::from_response(status, body).unwrap_err();
+        assert_eq!(
+            classify(StatusCode::UNPROCESSABLE_ENTITY, r#"{"code":422,"message":"Invalid PAN pattern"}"#),
+            GovApiError::BusinessReject { remarks: "Invalid PAN pattern".to_string() }
+        );
+        assert_eq!(
+            classify(StatusCode::BAD_REQUEST, r#"{"message":"Invalid request body"}"#),
+            GovApiError::ClientError { status: 400, body: "Invalid request body".to_string() }
+        );
+        assert!(matches!(classify(StatusCode::UNAUTHORIZED, "Unauthorized"), GovApiError::Unauthorized { status: 401, .. }));
+        assert!(matches!(classify(StatusCode::FORBIDDEN, "Forbidden"), GovApiError::Unauthorized { status: 403, .. }));
+        assert!(matches!(classify(StatusCode::REQUEST_TIMEOUT, ""), GovApiError::Transport { .. }));
+        assert!(matches!(classify(StatusCode::GATEWAY_TIMEOUT, "timeout"), GovApiError::ServerError { status: 504, .. }));
+        assert!(matches!(classify(StatusCode::OK, "<html>"), GovApiError::ServerError { status: 200, .. }));
+
+        // Neither an unreadable success nor an error body carries the document along
+        let pii = r#"{"data":{"pan":"HJTPB9891M","name_as_per_pan":"Ravi Kumar","date_of_birth":"15/08/1990""#;
+        for status in [StatusCode::OK, StatusCode::BAD_REQUEST, StatusCode::UNAUTHORIZED, StatusCode::BAD_GATEWAY] {
+            let error = classify(status, pii).to_string();
+            for field in ["HJTPB9891M", "Ravi Kumar", "15/08/1990"] {
+                assert!(!error.contains(field), "{} in {}", field, error);
+            }
+        }
+        assert_eq!(classify(StatusCode::TOO_MANY_REQUESTS, ""), GovApiError::RateLimited { retry_after: None });
+
+        // Errors the retried call gave up with, wrapped as the retry loop wraps them
+        let gave_up = |e: anyhow::Error| GovApiError::from_call_error(e.context("Government API call failed after 3 attempts"));
+        let throttled = TransientError { reason: "429".to_string(), retry_after: Some(std::time::Duration::from_secs(2)) };
+        assert_eq!(
+            gave_up(throttled.into()),
+            GovApiError::RateLimited { retry_after: Some(std::time::Duration::from_secs(2)) }
+        );
+        let down = GovApiError::ServerError { status: 503, body: "upstream unavailable".to_string() };
+        assert_eq!(gave_up(down.clone().into()), down);
+        assert!(matches!(gave_up(anyhow!("connection refused")), GovApiError::Transport { .. }));
+
+        // Only outages drive the circuit and deferral; nothing but rejections and bad requests is final
+        assert!(is_unavailable(&down.into()));
+        assert!(!is_unavailable(&GovApiError::RateLimited { retry_after: None }.into()));
+        assert!(!GovApiError::BusinessReject { remarks: String::new() }.is_retryable());
+        assert!(!GovApiError::ClientError { status: 400, body: String::new() }.is_retryable());
+        assert!(GovApiError::Transport { reason: String::new() }.is_retryable());
+        assert!(GovApiError::Unauthorized { status: 401, body: String::new() }.is_retryable());
+    }
+
+    #[test]
+    fn test_pan_mismatch_with_extracted_data_is_rejected() {
+        let request = |extracted: Option<&str>| VerificationRequest {
+            user_wallet: "0xabc".to_string(),
+            did_id: "0".to_string(),
+            verification_type: "pan".to_string(),
+            document_data: "{}".to_string(),
+            extracted_data: extracted.map(str::to_string),
+            user_corrections: None,
+            timestamp: "0".to_string(),
+            status: "pending".to_string(),
+        };
+
+        let err = check_pan_consistency(&request(Some(r#"{"pan":"ABCDE1234F"}"#)), "HJTPB9891M").unwrap_err();
+        assert!(err.reason.contains("ABCDE****F") && err.reason.contains("HJTPB****M"), "{}", err.reason);
+        assert!(!err.reason.contains("1234") && !err.reason.contains("9891"), "{}", err.reason);
+        assert!(crate::payload::is_invalid_message(&err.into()));
+
+        // Agreeing after normalization, or nothing to compare against
+        assert!(check_pan_consistency(&request(Some(r#"{"pan_number":" hjtpb9891m "}"#)), "HJTPB9891M").is_ok());
+        assert!(check_pan_consistency(&request(Some(r#"{"name":"Ashwin"}"#)), "HJTPB9891M").is_ok());
+        assert!(check_pan_consistency(&request(None), "HJTPB9891M").is_ok());
+
+        // The predicate prefetch filters with, which neither counts nor audits
+        let mismatched = request(Some(r#"{"pan":"abcde1234f"}"#));
+        assert_eq!(pan_mismatch(&mismatched, "HJTPB9891M"), Some(("HJTPB9891M".to_string(), "ABCDE1234F".to_string())));
+        assert_eq!(pan_mismatch(&mismatched, " abcde1234f "), None);
+
+        assert_eq!(mask_pan("ABC"), "***");
+    }
+
+    fn api_response(pan: &str) -> GovernmentApiResponse {
+        GovernmentApiResponse {
+            code: 200,
+            timestamp: 1760865505809,
+            data: PanVerificationData {
+                entity: "in.co.sandbox.kyc.pan_verification.response".to_string(),
+                pan: pan.to_string(),
+                status: "valid".to_string(),
+                remarks: None,
+                name_as_per_pan_match: true,
+                date_of_birth_match: true,
+                category: "individual".to_string(),
+                aadhaar_seeding_status: "y".to_string(),
+            },
+            transaction_id: "2bfc9f4c-e3c9-43d0-aef6-27c9082d7ce0".to_string(),
+        }
+    }
+
+    fn batch_item(reference_id: &str, pan: &str) -> PanBatchItem {
+        let request = VerificationRequest {
+            user_wallet: "0xabc".to_string(),
+            did_id: "0".to_string(),
+            verification_type: "pan".to_string(),
+            document_data: String::new(),
+            extracted_data: None,
+            user_corrections: None,
+            timestamp: format!("2025-01-01T00:00:00Z#{}", reference_id),
+            status: "pending".to_string(),
+        };
+        PanBatchItem::new(reference_id, &request, document(pan, "ASHWIN BALAGURU"))
+    }
+
+    fn document(pan: &str, name: &str) -> DocumentData {
+        DocumentData {
+            entity: None,
+            pan: pan.to_string(),
+            name_as_per_pan: name.to_string(),
+            date_of_birth: "27/10/2004".to_string(),
+            phone_number: None,
+            consent: "Y".to_string(),
+            reason: "KYC".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_missing_or_negative_consent_is_rejected() {
+        assert!(document("HJTPB9891M", "Ashwin").check_consent("0xabc").is_ok());
+        let lowercase = DocumentData { consent: " y ".to_string(), ..document("HJTPB9891M", "Ashwin") };
+        assert!(lowercase.check_consent("0xabc").is_ok());
+
+        for consent in ["", "N", "no", "yes"] {
+            let refused = DocumentData { consent: consent.to_string(), ..document("HJTPB9891M", "Ashwin") };
+            let err = refused.check_consent("0xabc").unwrap_err();
+            assert!(err.reason.contains("consent"), "{}: {}", consent, err);
+            assert!(is_consent_missing(&err.into()));
+        }
+        let no_reason = DocumentData { reason: "  ".to_string(), ..document("HJTPB9891M", "Ashwin") };
+        assert_eq!(no_reason.check_consent("0xabc").unwrap_err().reason, "reason is empty");
+    }
+
+    #[test]
+    fn test_pan_and_name_normalize_identically() {
+        let messy = document(" hjtpb9891m ", "  ashwin \t balaguru ").normalized();
+        let clean = document("HJTPB9891M", "Ashwin Balaguru").normalized();
+
+        assert_eq!(messy.pan, "HJTPB9891M");
+        assert_eq!(messy.pan, clean.pan);
+        assert_eq!(messy.name_as_per_pan, "ASHWIN BALAGURU");
+        assert_eq!(messy.name_as_per_pan, clean.name_as_per_pan);
+        assert!(messy.validate().is_ok());
+        assert!(document("HJTPB98911", "A").normalized().validate().is_err());
+    }
+
+    #[test]
+    fn test_normalized_inputs_produce_same_evidence_hash() {
+        let client = GovernmentApiClient::new().unwrap();
+
+        let messy = client.generate_evidence_hash(&api_response(" hjtpb9891m "), "  ashwin   balaguru ", "27/10/2004").unwrap();
+        let clean = client.generate_evidence_hash(&api_response("HJTPB9891M"), "Ashwin Balaguru", "27/10/2004").unwrap();
+
+        assert_eq!(messy, clean);
+    }
+
+    #[test]
+    fn test_stored_response_verifies_its_evidence_hash_and_tampered_one_fails() {
+        let client = GovernmentApiClient::new().unwrap();
+        let response = api_response("HJTPB9891M");
+        let evidence = client.generate_evidence_hash(&response, "Ashwin Balaguru", "27/10/2004").unwrap();
+        let stored = serde_json::to_string(&response).unwrap();
+
+        assert!(verify_evidence_hash(&stored, "Ashwin Balaguru", "27/10/2004", &evidence.hash));
+        // The same claim written differently, and the hash with a 0x prefix
+        assert!(verify_evidence_hash(&stored, " ashwin  BALAGURU", "27/10/2004", &format!("0x{}", evidence.hash)));
+
+        // A hash computed under the facts profile verifies too
+        let facts = client.with_evidence_profile(EvidenceProfile::Facts);
+        let facts_hash = facts.generate_evidence_hash(&response, "Ashwin Balaguru", "27/10/2004").unwrap().hash;
+        assert!(verify_evidence_hash(&stored, "Ashwin Balaguru", "27/10/2004", &facts_hash));
+
+        // Any change to the response or the claim breaks it
+        let mut tampered = response.clone();
+        tampered.data.status = "invalid".to_string();
+        let tampered = serde_json::to_string(&tampered).unwrap();
+        assert!(!verify_evidence_hash(&tampered, "Ashwin Balaguru", "27/10/2004", &evidence.hash));
+        assert!(!verify_evidence_hash(&stored, "Ashwin Balaguru", "28/10/2004", &evidence.hash));
+        assert!(!verify_evidence_hash("not json", "Ashwin Balaguru", "27/10/2004", &evidence.hash));
+
+        // Hashes under the earlier schemas still verify: fields in declaration order (pan_v2)
+        // and before the schema tag (pan_v1)
+        let input = pan_evidence_input(&response, "Ashwin Balaguru", "27/10/2004");
+        let ordered = hex::encode(Sha256::digest(serde_json::to_string(&input).unwrap().as_bytes()));
+        let EvidenceInput::Pan(fields) = input else { unreachable!() };
+        let untagged = hex::encode(Sha256::digest(serde_json::to_string(&fields).unwrap().as_bytes()));
+        for earlier in [&ordered, &untagged] {
+            assert_ne!(earlier, &evidence.hash);
+            assert!(verify_evidence_hash(&stored, "Ashwin Balaguru", "27/10/2004", earlier));
+        }
+    }
+
     #[test]
     fn test_evidence_hash_generation() {
         let client = GovernmentApiClient::new().unwrap();
@@ -411,7 +1692,365 @@ mod tests {
         ).unwrap();
 
         // Verify hash is generated and is 64 characters (SHA256 hex)
-        assert_eq!(evidence_hash.len(), 64);
-        assert!(evidence_hash.chars().all(|c| c.is_ascii_hexdigit()));
+        assert_eq!(evidence_hash.hash.len(), 64);
+        assert!(evidence_hash.hash.chars().all(|c| c.is_ascii_hexdigit()));
+        assert_eq!(evidence_hash.schema, "pan_v3");
+    }
+
+    #[tokio::test]
+    async fn test_rate_limited_call_waits_for_retry_after() {
+        use axum::http::{header::RETRY_AFTER, StatusCode};
+        use axum::response::IntoResponse;
+        use std::sync::atomic::{AtomicU32, Ordering};
+
+        // First call is throttled with Retry-After: 2, the second succeeds
+        let calls = Arc::new(AtomicU32::new(0));
+        let served = calls.clone();
+        let app = axum::Router::new().route(
+            "/kyc/pan/verify",
+            axum::routing::post(move || {
+                let served = served.clone();
+                async move {
+                    if served.fetch_add(1, Ordering::SeqCst) == 0 {
+                        (StatusCode::TOO_MANY_REQUESTS, [(RETRY_AFTER, "2")], "slow down").into_response()
+                    } else {
+                        "{}".into_response()
+                    }
+                }
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/kyc/pan/verify", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        let mut client = GovernmentApiClient::new().unwrap();
+        client.retry_policy = RetryPolicy {
+            max_attempts: 3,
+            base_delay: std::time::Duration::ZERO,
+            max_delay: std::time::Duration::ZERO,
+        };
+
+        let started = std::time::Instant::now();
+        let (status, _) = client.send_with_retry(&url, "", &serde_json::json!({}), None).await.unwrap();
+        assert_eq!(status, reqwest::StatusCode::OK);
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+        assert!(started.elapsed() >= std::time::Duration::from_secs(2), "waited only {:?}", started.elapsed());
+        assert!(!client.circuit_breaker.is_open());
+    }
+
+    #[tokio::test]
+    async fn test_configured_auth_header_format_is_sent() {
+        use axum::http::HeaderMap;
+        use std::sync::Mutex;
+
+        let seen: Arc<Mutex<Vec<HeaderMap>>> = Arc::default();
+        let recorded = seen.clone();
+        let app = axum::Router::new().route(
+            "/kyc/pan/verify",
+            axum::routing::post(move |headers: HeaderMap| {
+                recorded.lock().unwrap().push(headers);
+                async { "{}" }
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/kyc/pan/verify", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        let client = GovernmentApiClient::new().unwrap();
+        client.send_with_retry(&url, "jwt-1", &serde_json::json!({}), None).await.unwrap();
+        let bearer = AuthHeaderFormat {
+            token_header: "x-access-token".to_string(),
+            bearer_prefix: true,
+            api_key_header: "x-client-id".to_string(),
+        };
+        let client = client.with_auth_header(bearer);
+        client.send_with_retry(&url, "jwt-2", &serde_json::json!({}), None).await.unwrap();
+
+        let seen = seen.lock().unwrap();
+        let api_key = client.jwt_manager.api_key.as_str();
+        // Default: the raw JWT, as sandbox expects
+        assert_eq!(seen[0]["authorization"], "jwt-1");
+        assert_eq!(seen[0]["x-api-key"], api_key);
+        assert_eq!(seen[1]["x-access-token"], "Bearer jwt-2");
+        assert_eq!(seen[1]["x-client-id"], api_key);
+        assert!(!seen[1].contains_key("authorization") && !seen[1].contains_key("x-api-key"));
+    }
+
+    #[tokio::test]
+    async fn test_refused_token_is_refreshed_and_the_call_sent_again() {
+        use axum::http::{HeaderMap, StatusCode};
+        use std::sync::atomic::{AtomicU32, Ordering};
+
+        let logins = Arc::new(AtomicU32::new(0));
+        let issued = logins.clone();
+        let response = serde_json::to_string(&api_response("HJTPB9891M")).unwrap();
+        let app = axum::Router::new()
+            .route(
+                "/authenticate",
+                axum::routing::post(move || {
+                    let n = issued.fetch_add(1, Ordering::SeqCst) + 1;
+                    async move { format!(r#"{{"access_token":"jwt-{}"}}"#, n) }
+                }),
+            )
+            .route(
+                "/kyc/pan/verify",
+                axum::routing::post(move |headers: HeaderMap| {
+                    // The first token was revoked before its expiry
+                    let status = match headers["authorization"].to_str().unwrap() {
+                        "jwt-1" => StatusCode::UNAUTHORIZED,
+                        _ => StatusCode::OK,
+                    };
+                    let response = response.clone();
+                    async move { (status, response) }
+                }),
+            );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        let mut client = GovernmentApiClient::new()
+            .unwrap()
+            .with_endpoints(&format!("http://{}/authenticate", addr), &format!("http://{}", addr));
+        let document = document("HJTPB9891M", "ASHWIN BALAGURU");
+        let response = client.verify_pan(&document, "key").await.unwrap();
+        assert_eq!(response.data.pan, "HJTPB9891M");
+        assert_eq!(logins.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_retries_reuse_the_idempotency_key_and_new_requests_get_a_new_one() {
+        use axum::http::{HeaderMap, StatusCode};
+        use std::sync::Mutex;
+
+        let keys: Arc<Mutex<Vec<String>>> = Arc::default();
+        let recorded = keys.clone();
+        let response = serde_json::to_string(&api_response("HJTPB9891M")).unwrap();
+        let app = axum::Router::new()
+            .route("/authenticate", axum::routing::post(|| async { r#"{"access_token":"jwt"}"# }))
+            .route(
+                "/kyc/pan/verify",
+                axum::routing::post(move |headers: HeaderMap| {
+                    let mut keys = recorded.lock().unwrap();
+                    keys.push(headers[DEFAULT_IDEMPOTENCY_HEADER].to_str().unwrap().to_string());
+                    // The first attempt hits an outage
+                    let status = if keys.len() == 1 { StatusCode::SERVICE_UNAVAILABLE } else { StatusCode::OK };
+                    let response = response.clone();
+                    async move { (status, response) }
+                }),
+            );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        let mut client = GovernmentApiClient::new()
+            .unwrap()
+            .with_endpoints(&format!("http://{}/authenticate", addr), &format!("http://{}", addr))
+            .with_idempotency_header(Some(DEFAULT_IDEMPOTENCY_HEADER));
+        client.retry_policy = RetryPolicy {
+            max_attempts: 3,
+            base_delay: std::time::Duration::ZERO,
+            max_delay: std::time::Duration::ZERO,
+        };
+        let document = document("HJTPB9891M", "ASHWIN BALAGURU");
+        let first = idempotency_key("0xabc", &document.pan, "2025-01-01T00:00:00Z");
+        let second = idempotency_key("0xabc", &document.pan, "2025-01-02T00:00:00Z");
+        client.verify_pan(&document, &first).await.unwrap();
+        client.verify_pan(&document, &second).await.unwrap();
+
+        // Same inputs, same key; the PAN's casing doesn't matter
+        assert_eq!(first, idempotency_key("0xabc", "hjtpb9891m", "2025-01-01T00:00:00Z"));
+        assert_ne!(first, second);
+        assert_eq!(*keys.lock().unwrap(), vec![first.clone(), first, second]);
+    }
+
+    #[tokio::test]
+    async fn test_government_calls_run_wider_than_the_sui_limit() {
+        use crate::sui_proxy::{SuiEndpoints, CALL_PATH};
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        /// Calls in flight now, and the most seen at once.
+        #[derive(Default)]
+        struct InFlight {
+            now: AtomicUsize,
+            peak: AtomicUsize,
+        }
+
+        async fn slow_call(in_flight: Arc<InFlight>, response: serde_json::Value) -> axum::Json<serde_json::Value> {
+            let now = in_flight.now.fetch_add(1, Ordering::SeqCst) + 1;
+            in_flight.peak.fetch_max(now, Ordering::SeqCst);
+            tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+            in_flight.now.fetch_sub(1, Ordering::SeqCst);
+            axum::Json(response)
+        }
+
+        let (govt, sui) = (Arc::new(InFlight::default()), Arc::new(InFlight::default()));
+        let (govt_seen, sui_seen) = (govt.clone(), sui.clone());
+        let app = axum::Router::new()
+            .route("/authenticate", axum::routing::post(|| async { r#"{"access_token":"jwt"}"# }))
+            .route(
+                "/kyc/pan/verify",
+                axum::routing::post(move |axum::Json(body): axum::Json<serde_json::Value>| {
+                    let pan = body["pan"].as_str().unwrap_or_default().to_string();
+                    slow_call(govt_seen.clone(), serde_json::to_value(api_response(&pan)).unwrap())
+                }),
+            )
+            .route(
+                CALL_PATH,
+                axum::routing::post(move || slow_call(sui_seen.clone(), serde_json::json!({ "success": true, "stdout": "ok" }))),
+            );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        let mut client = GovernmentApiClient::new()
+            .unwrap()
+            .with_endpoints(&format!("{}/authenticate", base), &base)
+            .with_call_limit(CallLimit::new("govt_api", 4));
+        let endpoints = SuiEndpoints::new(vec![base.clone()], std::time::Duration::from_secs(60)).unwrap();
+        let (http, policy) = (Client::new(), RetryPolicy::from_env("SUI_PROXY_RETRY"));
+        let body = serde_json::json!({});
+
+        // The pipeline's prefetch of six queued requests, while Sui calls go on
+        let requests: Vec<(String, VerificationRequest)> = (0..6)
+            .map(|i| {
+                let document = document(&format!("ABCDE123{}F", i), "ASHWIN BALAGURU");
+                let request = VerificationRequest {
+                    user_wallet: format!("0x{}", i),
+                    did_id: "0".to_string(),
+                    verification_type: "pan".to_string(),
+                    document_data: serde_json::to_string(&document).unwrap(),
+                    extracted_data: None,
+                    user_corrections: None,
+                    timestamp: "2025-01-01T00:00:00Z".to_string(),
+                    status: "pending".to_string(),
+                };
+                (format!("{}-0", i), request)
+            })
+            .collect();
+        let sui_calls = futures::future::join_all((0..3).map(|_| endpoints.post(&http, &policy, CALL_PATH, &body)));
+        let ((), sui_results) = tokio::join!(client.prefetch(&requests), sui_calls);
+        assert_eq!(client.prefetched.len(), 6);
+        assert!(sui_results.iter().all(Result::is_ok));
+
+        // Each capped at its own limit, independently of the other
+        assert_eq!(govt.peak.load(Ordering::SeqCst), 4);
+        assert_eq!(sui.peak.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_oversized_response_is_refused() {
+        use axum::body::Body;
+
+        let app = axum::Router::new()
+            .route("/small", axum::routing::post(|| async { "{}" }))
+            .route("/large", axum::routing::post(|| async { "x".repeat(4096) }))
+            // No Content-Length, so the cap is only hit while streaming
+            .route(
+                "/streamed",
+                axum::routing::post(|| async {
+                    let chunks = (0..8).map(|_| Ok::<_, std::io::Error>("y".repeat(1024)));
+                    Body::from_stream(futures::stream::iter(chunks))
+                }),
+            );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        let mut client = GovernmentApiClient::new().unwrap().with_max_response_bytes(1024);
+        client.retry_policy = RetryPolicy {
+            max_attempts: 1,
+            base_delay: std::time::Duration::ZERO,
+            max_delay: std::time::Duration::ZERO,
+        };
+        let call = |path: &str| format!("{}{}", base, path);
+
+        let (status, body) = client.send_with_retry(&call("/small"), "", &serde_json::json!({}), None).await.unwrap();
+        assert_eq!((status, body.as_str()), (reqwest::StatusCode::OK, "{}"));
+        for path in ["/large", "/streamed"] {
+            let error = client.send_with_retry(&call(path), "", &serde_json::json!({}), None).await.unwrap_err();
+            assert_eq!(
+                GovApiError::from_call_error(error),
+                GovApiError::ServerError { status: 200, body: "response body exceeds 1024 bytes".to_string() },
+                "{}",
+                path
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_configured_entity_appears_in_outgoing_payload() {
+        use std::sync::Mutex;
+
+        let bodies: Arc<Mutex<Vec<serde_json::Value>>> = Arc::default();
+        let recorded = bodies.clone();
+        let response = serde_json::to_string(&api_response("HJTPB9891M")).unwrap();
+        let app = axum::Router::new()
+            .route("/authenticate", axum::routing::post(|| async { r#"{"access_token":"jwt"}"# }))
+            .route(
+                "/kyc/pan/verify",
+                axum::routing::post(move |axum::Json(body): axum::Json<serde_json::Value>| {
+                    recorded.lock().unwrap().push(body);
+                    async move { response }
+                }),
+            );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        let template = PanPayloadTemplate::new(
+            "in.co.example.kyc.pan.v2",
+            "id_number=pan, full_name=name_as_per_pan, consent=consent, reason=reason",
+        )
+        .unwrap();
+        let mut client = GovernmentApiClient::new()
+            .unwrap()
+            .with_endpoints(&format!("http://{}/authenticate", addr), &format!("http://{}", addr))
+            .with_payload_template(template);
+        client.verify_pan(&document("HJTPB9891M", "ASHWIN BALAGURU"), "key").await.unwrap();
+
+        let sent = bodies.lock().unwrap()[0].clone();
+        assert_eq!(
+            sent,
+            serde_json::json!({
+                "@entity": "in.co.example.kyc.pan.v2",
+                "id_number": "HJTPB9891M",
+                "full_name": "ASHWIN BALAGURU",
+                "consent": "Y",
+                "reason": "KYC",
+            })
+        );
+
+        // Templates are checked up front
+        let sandbox = "pan=pan,name_as_per_pan=name_as_per_pan,date_of_birth=date_of_birth,consent=consent,reason=reason";
+        assert_eq!(PanPayloadTemplate::new(PAN_REQUEST_ENTITY, sandbox).unwrap(), PanPayloadTemplate::default());
+        assert!(PanPayloadTemplate::new("x", "pan=pan,name=full_name").is_err());
+        assert!(PanPayloadTemplate::new("x", "name=name_as_per_pan").is_err());
+        assert!(PanPayloadTemplate::new("x", "id=pan,id=reason").is_err());
+        assert!(PanPayloadTemplate::new(" ", "pan=pan").is_err());
+    }
+
+    #[test]
+    fn test_enclave_mode_forces_the_localhost_proxy() {
+        let configured = || Some("https://govt.example.com".to_string());
+
+        let enclave = GovtApiEndpoints::resolve(true, configured(), configured());
+        assert_eq!(enclave.auth_url, "https://localhost:8443/authenticate");
+        assert_eq!(enclave.base_url, "https://localhost:8443");
+        assert!(enclave.accept_invalid_certs);
+        assert_eq!(enclave.verify_url, "http://localhost:9999/govt-api/pan/verify");
+        assert_eq!(enclave.batch_url(), "http://localhost:9999/govt-api/pan/verify/batch");
+        assert!(enclave.via_host_proxy);
+
+        let local = GovtApiEndpoints::resolve(false, configured(), configured());
+        assert_eq!(local.auth_url, "https://govt.example.com");
+        assert_eq!(local.base_url, "https://govt.example.com");
+        assert!(!local.accept_invalid_certs);
+        assert_eq!(local.batch_url(), "https://govt.example.com/kyc/pan/verify/batch");
+        assert!(!local.via_host_proxy);
+
+        let direct = GovtApiEndpoints::resolve(false, None, None);
+        assert_eq!(direct.auth_url, "https://api.sandbox.co.in/authenticate");
+        assert_eq!(direct.base_url, "https://api.sandbox.co.in");
+        assert!(!direct.accept_invalid_certs);
     }
 }
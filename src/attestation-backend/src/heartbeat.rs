@@ -0,0 +1,159 @@
+// Signed heartbeat: periodic proof that the enclave is alive and still holds the same ephemeral key
+use anyhow::Result;
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use fastcrypto::ed25519::Ed25519KeyPair;
+use fastcrypto::encoding::{Encoding, Hex};
+use fastcrypto::traits::{KeyPair, ToFromBytes};
+use serde::{Deserialize, Serialize};
+use std::sync::{Mutex, OnceLock};
+use tokio::time::{Duration, sleep};
+use tracing::{info, warn};
+
+use crate::api_error::ApiError;
+use crate::common::{key_id, to_signed_response, IntentMessage, IntentScope, ProcessedDataResponse};
+use crate::content_negotiation::{Negotiated, ResponseFormat};
+use crate::metrics;
+use crate::redis_timeout::with_timeout;
+use crate::signing::SigningError;
+use crate::verification_processor::RedisConnector;
+
+/// Payload of a heartbeat. A monitor compares `key_id` across heartbeats to catch a silent
+/// restart with a new key, and `sequence`/timestamp to catch a stalled process. The key itself
+/// is bound to the enclave by the NSM document from `/get_attestation`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Heartbeat {
+    pub key_id: String,
+    /// Hex of the raw public key that signed this heartbeat.
+    pub public_key: String,
+    /// Heartbeats emitted since start, starting at 0.
+    pub sequence: u64,
+    pub started_at_ms: u64,
+}
+
+pub type SignedHeartbeat = ProcessedDataResponse<IntentMessage<Heartbeat>>;
+
+/// Sign a heartbeat under [`IntentScope::Heartbeat`].
+pub fn sign_heartbeat(
+    kp: &Ed25519KeyPair,
+    sequence: u64,
+    started_at_ms: u64,
+    timestamp_ms: u64,
+) -> Result<SignedHeartbeat, SigningError> {
+    let heartbeat = Heartbeat {
+        key_id: key_id(kp.public()),
+        public_key: Hex::encode(kp.public().as_bytes()),
+        sequence,
+        started_at_ms,
+    };
+    to_signed_response(kp, heartbeat, timestamp_ms, IntentScope::Heartbeat)
+}
+
+fn latest() -> &'static Mutex<Option<SignedHeartbeat>> {
+    static LATEST: OnceLock<Mutex<Option<SignedHeartbeat>>> = OnceLock::new();
+    LATEST.get_or_init(|| Mutex::new(None))
+}
+
+/// The most recent heartbeat emitted by this process, if any.
+pub fn latest_heartbeat() -> Option<SignedHeartbeat> {
+    latest().lock().unwrap().clone()
+}
+
+/// Heartbeat settings: `HEARTBEAT_REDIS_KEY` and `HEARTBEAT_INTERVAL_SECS`.
+#[derive(Debug, Clone)]
+pub struct HeartbeatConfig {
+    pub redis_key: String,
+    pub interval: Duration,
+}
+
+impl HeartbeatConfig {
+    pub fn from_env() -> Self {
+        Self {
+            redis_key: std::env::var("HEARTBEAT_REDIS_KEY").unwrap_or_else(|_| "enclave_heartbeat".to_string()),
+            interval: Duration::from_secs(
+                std::env::var("HEARTBEAT_INTERVAL_SECS")
+                    .ok()
+                    .and_then(|v| v.parse::<u64>().ok())
+                    .filter(|secs| *secs > 0)
+                    .unwrap_or(30),
+            ),
+        }
+    }
+}
+
+/// Background task: sign a heartbeat every interval, keep it for `/heartbeat` and write it to Redis.
+/// The Redis key expires after three missed intervals, so a stalled enclave's heartbeat disappears.
+pub async fn run_heartbeat_task(kp: Ed25519KeyPair, redis: RedisConnector) -> Result<()> {
+    let config = HeartbeatConfig::from_env();
+    let started_at_ms = chrono::Utc::now().timestamp_millis() as u64;
+    let expiry_secs = config.interval.as_secs() * 3;
+    info!("💓 Emitting heartbeats to {} every {}s", config.redis_key, config.interval.as_secs());
+
+    let mut conn = None;
+    let mut sequence = 0u64;
+    loop {
+        let now_ms = chrono::Utc::now().timestamp_millis() as u64;
+        let heartbeat = match sign_heartbeat(&kp, sequence, started_at_ms, now_ms) {
+            Ok(heartbeat) => heartbeat,
+            // A missing heartbeat is exactly what monitors should see when the key can't sign
+            Err(e) => {
+                warn!("Failed to sign heartbeat: {}", e);
+                sleep(config.interval).await;
+                continue;
+            }
+        };
+        let payload = serde_json::to_string(&heartbeat)?;
+        *latest().lock().unwrap() = Some(heartbeat);
+        metrics::set_gauge("heartbeat_last_ms", now_ms as f64);
+        sequence += 1;
+
+        if conn.is_none() {
+            conn = redis.connect().await.map_err(|e| warn!("Heartbeat Redis connect failed: {}", e)).ok();
+        }
+        if let Some(c) = conn.as_mut() {
+            let mut set = redis::cmd("SET");
+            set.arg(&config.redis_key).arg(&payload).arg("EX").arg(expiry_secs);
+            let written = with_timeout("SET", redis.timeouts().command, set.query_async::<_, ()>(c)).await;
+            if let Err(e) = written {
+                warn!("Failed to write heartbeat to Redis: {}", e);
+                conn = None;
+            }
+        }
+
+        sleep(config.interval).await;
+    }
+}
+
+/// Endpoint returning the latest signed heartbeat; 503 until the first one is emitted.
+pub async fn get_heartbeat(format: ResponseFormat) -> Response {
+    match latest_heartbeat() {
+        Some(heartbeat) => Negotiated(format, heartbeat).into_response(),
+        None => ApiError::new(StatusCode::SERVICE_UNAVAILABLE, "heartbeat_unavailable", "No heartbeat emitted yet")
+            .into_response(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::verify_signed_response;
+
+    #[test]
+    fn test_heartbeat_signature_verifies_and_key_id_is_stable() {
+        let kp = Ed25519KeyPair::generate(&mut rand::thread_rng());
+        let first = sign_heartbeat(&kp, 0, 1_000, 1_000).unwrap();
+        let second = sign_heartbeat(&kp, 1, 1_000, 31_000).unwrap();
+
+        assert!(verify_signed_response(kp.public(), &first).is_ok());
+        assert!(verify_signed_response(kp.public(), &second).is_ok());
+        assert_eq!(first.response.intent, IntentScope::Heartbeat);
+        assert_eq!(first.response.data.key_id, second.response.data.key_id);
+        assert_eq!(second.response.data.sequence, 1);
+
+        // A restarted enclave has a new key, which the monitor sees as a changed key id
+        let restarted = Ed25519KeyPair::generate(&mut rand::thread_rng());
+        let after_restart = sign_heartbeat(&restarted, 0, 40_000, 40_000).unwrap();
+        assert_ne!(after_restart.response.data.key_id, first.response.data.key_id);
+        assert!(verify_signed_response(kp.public(), &after_restart).is_err());
+    }
+}
@@ -0,0 +1,379 @@
+// Optional sealed persistence of the ephemeral keypair, so an enclave restart recovers the same key
+use anyhow::{Result, anyhow};
+use fastcrypto::ed25519::Ed25519KeyPair;
+use fastcrypto::traits::{KeyPair, ToFromBytes};
+use hkdf::Hkdf;
+use hmac::{Hmac, Mac};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::fmt;
+use tracing::{info, warn};
+
+use crate::common::key_id;
+use crate::redis_timeout::with_timeout;
+use crate::verification_processor::RedisConnector;
+
+/// `KEY_SEALING`: `off` (default, a new key every boot), `dev` or `kms`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeySealingMode {
+    Off,
+    /// Wrapping key derived from `KEY_SEALING_DEV_SECRET`; for exercising restarts outside an enclave.
+    Dev,
+    /// Wrapping key from KMS, released only to an enclave whose attestation matches the key policy.
+    Kms,
+}
+
+impl KeySealingMode {
+    pub fn from_env() -> Result<Self> {
+        match std::env::var("KEY_SEALING").unwrap_or_else(|_| "off".to_string()).trim().to_lowercase().as_str() {
+            "" | "off" => Ok(Self::Off),
+            "dev" => Ok(Self::Dev),
+            "kms" => Ok(Self::Kms),
+            other => Err(anyhow!("Unknown KEY_SEALING mode: {}", other)),
+        }
+    }
+}
+
+/// Fail unless `KEY_SEALING` keeps the keypair across restarts. `setting` enables something that
+/// leaves data in Redis sealed under a key derived from it, which a new key could never open.
+pub fn require_persistent_key(setting: &str) -> Result<()> {
+    match KeySealingMode::from_env()? {
+        KeySealingMode::Off => Err(anyhow!(
+            "{} requires KEY_SEALING=dev or kms: what it keeps in Redis can't be opened after a restart otherwise",
+            setting
+        )),
+        KeySealingMode::Dev | KeySealingMode::Kms => Ok(()),
+    }
+}
+
+/// A stored sealed key that can never be unsealed here: corrupt, tampered with, or wrapped under
+/// a key this enclave is refused. Only then is it replaced; any other failure may clear up.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SealedKeyUnusable {
+    pub reason: String,
+}
+
+impl fmt::Display for SealedKeyUnusable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Sealed key unusable: {}", self.reason)
+    }
+}
+
+impl std::error::Error for SealedKeyUnusable {}
+
+pub fn is_sealed_key_unusable(error: &anyhow::Error) -> bool {
+    error.downcast_ref::<SealedKeyUnusable>().is_some()
+}
+
+fn unusable(reason: impl fmt::Display) -> anyhow::Error {
+    SealedKeyUnusable { reason: reason.to_string() }.into()
+}
+
+/// Source of the key that wraps the enclave keypair at rest.
+pub trait WrappingKeySource {
+    /// A fresh wrapping key and its wrapped form, which is stored with the sealed keypair.
+    fn new_key(&self) -> Result<([u8; 32], Vec<u8>)>;
+    /// Recover a wrapping key from its wrapped form. A wrapped key that will never be released
+    /// is a [`SealedKeyUnusable`].
+    fn unwrap_key(&self, wrapped: &[u8]) -> Result<[u8; 32]>;
+}
+
+/// Development stub. Anyone holding the secret can unseal, so it gives no protection at rest.
+pub struct DevWrappingKey {
+    secret: Vec<u8>,
+}
+
+impl DevWrappingKey {
+    pub fn new(secret: &[u8]) -> Self {
+        Self { secret: secret.to_vec() }
+    }
+
+    pub fn from_env() -> Result<Self> {
+        let secret = std::env::var("KEY_SEALING_DEV_SECRET")
+            .map_err(|_| anyhow!("KEY_SEALING_DEV_SECRET is required when KEY_SEALING=dev"))?;
+        Ok(Self::new(secret.as_bytes()))
+    }
+
+    fn derive(&self) -> [u8; 32] {
+        let mut key = [0u8; 32];
+        Hkdf::<Sha256>::new(None, &self.secret)
+            .expand(b"enclave-key-sealing-dev", &mut key)
+            .expect("32 bytes is a valid HKDF-SHA256 output length");
+        key
+    }
+}
+
+impl WrappingKeySource for DevWrappingKey {
+    fn new_key(&self) -> Result<([u8; 32], Vec<u8>)> {
+        Ok((self.derive(), Vec::new()))
+    }
+
+    fn unwrap_key(&self, _wrapped: &[u8]) -> Result<[u8; 32]> {
+        Ok(self.derive())
+    }
+}
+
+/// Data keys from KMS via `kmstool_enclave_cli`. KMS attaches the enclave's attestation document
+/// to each call, so a key policy conditioned on the PCRs only releases the wrapping key to this image.
+#[cfg(feature = "aws")]
+pub struct KmsWrappingKey {
+    key_id: String,
+    region: String,
+    proxy_port: String,
+}
+
+#[cfg(feature = "aws")]
+impl KmsWrappingKey {
+    /// `KEY_SEALING_KMS_KEY_ID` (required), `AWS_REGION` and `KMS_PROXY_PORT` (vsock proxy, default 8000).
+    pub fn from_env() -> Result<Self> {
+        Ok(Self {
+            key_id: std::env::var("KEY_SEALING_KMS_KEY_ID")
+                .map_err(|_| anyhow!("KEY_SEALING_KMS_KEY_ID is required when KEY_SEALING=kms"))?,
+            region: std::env::var("AWS_REGION").unwrap_or_else(|_| "us-east-1".to_string()),
+            proxy_port: std::env::var("KMS_PROXY_PORT").unwrap_or_else(|_| "8000".to_string()),
+        })
+    }
+
+    /// Run a `kmstool_enclave_cli` subcommand and return its `NAME: value` output lines.
+    fn run(&self, subcommand: &str, args: &[&str]) -> Result<std::collections::HashMap<String, String>> {
+        let credential = |name: &str| std::env::var(name).unwrap_or_default();
+        let output = std::process::Command::new("kmstool_enclave_cli")
+            .arg(subcommand)
+            .args(["--region", self.region.as_str(), "--proxy-port", self.proxy_port.as_str()])
+            .args(["--aws-access-key-id", credential("AWS_ACCESS_KEY_ID").as_str()])
+            .args(["--aws-secret-access-key", credential("AWS_SECRET_ACCESS_KEY").as_str()])
+            .args(["--aws-session-token", credential("AWS_SESSION_TOKEN").as_str()])
+            .args(args)
+            .output()?;
+        if !output.status.success() {
+            return Err(anyhow!(
+                "kmstool_enclave_cli {} failed: {}",
+                subcommand,
+                String::from_utf8_lossy(&output.stderr).trim()
+            ));
+        }
+        Ok(String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .filter_map(|line| line.split_once(':'))
+            .map(|(name, value)| (name.trim().to_string(), value.trim().to_string()))
+            .collect())
+    }
+
+    fn decode_field(fields: &std::collections::HashMap<String, String>, name: &str) -> Result<Vec<u8>> {
+        use base64::{engine::general_purpose, Engine as _};
+        let value = fields.get(name).ok_or_else(|| anyhow!("kmstool_enclave_cli output has no {}", name))?;
+        Ok(general_purpose::STANDARD.decode(value)?)
+    }
+}
+
+#[cfg(feature = "aws")]
+impl WrappingKeySource for KmsWrappingKey {
+    fn new_key(&self) -> Result<([u8; 32], Vec<u8>)> {
+        let fields = self.run("genkey", &["--key-id", self.key_id.as_str(), "--key-spec", "AES-256"])?;
+        let plaintext = Self::decode_field(&fields, "PLAINTEXT")?;
+        let key = plaintext.try_into().map_err(|_| anyhow!("KMS data key is not 32 bytes"))?;
+        Ok((key, Self::decode_field(&fields, "CIPHERTEXT")?))
+    }
+
+    fn unwrap_key(&self, wrapped: &[u8]) -> Result<[u8; 32]> {
+        use base64::{engine::general_purpose, Engine as _};
+        // Refused by the key policy (e.g. the PCRs changed) or not a ciphertext of this key: final.
+        // Anything else, such as the vsock proxy being down, may work on the next boot.
+        let fields = self
+            .run("decrypt", &["--ciphertext", general_purpose::STANDARD.encode(wrapped).as_str()])
+            .map_err(|e| {
+                let message = e.to_string();
+                if message.contains("AccessDeniedException") || message.contains("InvalidCiphertextException") {
+                    unusable(message)
+                } else {
+                    e
+                }
+            })?;
+        let plaintext = Self::decode_field(&fields, "PLAINTEXT")?;
+        plaintext.try_into().map_err(|_| anyhow!("KMS data key is not 32 bytes"))
+    }
+}
+
+/// A keypair encrypted under a wrapping key, safe to store outside the enclave. All fields are hex.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SealedKey {
+    pub public_key: String,
+    pub wrapped_key: String,
+    pub nonce: String,
+    pub ciphertext: String,
+    /// HMAC-SHA256 over the public key and ciphertext.
+    pub tag: String,
+}
+
+/// Per-seal keystream and MAC key, derived from the wrapping key and nonce.
+fn seal_keys(wrapping_key: &[u8; 32], nonce: &[u8], len: usize) -> (Vec<u8>, Hmac<Sha256>) {
+    let hkdf = Hkdf::<Sha256>::new(Some(nonce), wrapping_key);
+    let mut keystream = vec![0u8; len];
+    let mut mac_key = [0u8; 32];
+    hkdf.expand(b"enclave-key-sealing-enc", &mut keystream)
+        .expect("keypair length is a valid HKDF-SHA256 output length");
+    hkdf.expand(b"enclave-key-sealing-mac", &mut mac_key)
+        .expect("32 bytes is a valid HKDF-SHA256 output length");
+    let mac = <Hmac<Sha256> as Mac>::new_from_slice(&mac_key).expect("HMAC accepts any key length");
+    (keystream, mac)
+}
+
+fn xor(data: &[u8], keystream: &[u8]) -> Vec<u8> {
+    data.iter().zip(keystream).map(|(a, b)| a ^ b).collect()
+}
+
+pub fn seal(source: &dyn WrappingKeySource, kp: &Ed25519KeyPair) -> Result<SealedKey> {
+    let (wrapping_key, wrapped_key) = source.new_key()?;
+    let mut nonce = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut nonce);
+
+    let secret = kp.as_bytes();
+    let public_key = kp.public().as_bytes().to_vec();
+    let (keystream, mut mac) = seal_keys(&wrapping_key, &nonce, secret.len());
+    let ciphertext = xor(secret, &keystream);
+    mac.update(&public_key);
+    mac.update(&ciphertext);
+
+    Ok(SealedKey {
+        public_key: hex::encode(public_key),
+        wrapped_key: hex::encode(wrapped_key),
+        nonce: hex::encode(nonce),
+        ciphertext: hex::encode(ciphertext),
+        tag: hex::encode(mac.finalize().into_bytes()),
+    })
+}
+
+/// Recover a sealed keypair. A wrong wrapping key or any tampering is a [`SealedKeyUnusable`];
+/// a failure to reach the wrapping key source is returned as it is.
+pub fn unseal(source: &dyn WrappingKeySource, sealed: &SealedKey) -> Result<Ed25519KeyPair> {
+    let decode = |field: &str| hex::decode(field).map_err(|e| unusable(format!("not hex: {}", e)));
+    let public_key = decode(&sealed.public_key)?;
+    let ciphertext = decode(&sealed.ciphertext)?;
+    let wrapping_key = source.unwrap_key(&decode(&sealed.wrapped_key)?)?;
+
+    let (keystream, mut mac) = seal_keys(&wrapping_key, &decode(&sealed.nonce)?, ciphertext.len());
+    mac.update(&public_key);
+    mac.update(&ciphertext);
+    mac.verify_slice(&decode(&sealed.tag)?)
+        .map_err(|_| unusable("failed authentication"))?;
+
+    let kp = Ed25519KeyPair::from_bytes(&xor(&ciphertext, &keystream))
+        .map_err(|e| unusable(format!("not a valid keypair: {}", e)))?;
+    if kp.public().as_bytes() != public_key.as_slice() {
+        return Err(unusable("does not match its public key"));
+    }
+    Ok(kp)
+}
+
+/// The keypair in a stored sealed key, or `None` if it can never be unsealed and should be replaced.
+fn recover(source: &dyn WrappingKeySource, stored: &str) -> Result<Option<Ed25519KeyPair>> {
+    let unsealed = serde_json::from_str::<SealedKey>(stored)
+        .map_err(|e| unusable(format!("not a sealed key: {}", e)))
+        .and_then(|sealed| unseal(source, &sealed));
+    match unsealed {
+        Ok(kp) => Ok(Some(kp)),
+        Err(e) if is_sealed_key_unusable(&e) => {
+            warn!("Could not unseal stored enclave key, sealing a new one: {}", e);
+            Ok(None)
+        }
+        Err(e) => Err(anyhow!("Could not unseal stored enclave key, leaving it in place: {}", e)),
+    }
+}
+
+fn wrapping_key_source(mode: KeySealingMode) -> Result<Box<dyn WrappingKeySource>> {
+    match mode {
+        KeySealingMode::Off => Err(anyhow!("Key sealing is off")),
+        KeySealingMode::Dev => Ok(Box::new(DevWrappingKey::from_env()?)),
+        #[cfg(feature = "aws")]
+        KeySealingMode::Kms => Ok(Box::new(KmsWrappingKey::from_env()?)),
+        #[cfg(not(feature = "aws"))]
+        KeySealingMode::Kms => Err(anyhow!("KEY_SEALING=kms requires the aws feature")),
+    }
+}
+
+/// With `KEY_SEALING` off, return `fresh`. Otherwise recover the keypair sealed in Redis under
+/// `KEY_SEALING_REDIS_KEY`, or seal `fresh` there if none is stored or it can never be unsealed
+/// (e.g. after an image upgrade changed the PCRs the KMS policy allows). Any other unseal
+/// failure fails startup rather than overwriting a key that may still be recoverable.
+pub async fn load_or_seal(fresh: Ed25519KeyPair) -> Result<Ed25519KeyPair> {
+    let mode = KeySealingMode::from_env()?;
+    if mode == KeySealingMode::Off {
+        return Ok(fresh);
+    }
+    let source = wrapping_key_source(mode)?;
+    let redis_key = std::env::var("KEY_SEALING_REDIS_KEY").unwrap_or_else(|_| "enclave_sealed_key".to_string());
+    let redis = RedisConnector::from_env()?;
+    let mut conn = redis.connect().await?;
+
+    let mut get = redis::cmd("GET");
+    get.arg(&redis_key);
+    let stored: Option<String> = with_timeout("GET", redis.timeouts().command, get.query_async(&mut conn)).await?;
+    if let Some(stored) = stored {
+        if let Some(kp) = recover(source.as_ref(), &stored)? {
+            info!("🔐 Recovered sealed enclave key {}", key_id(kp.public()));
+            return Ok(kp);
+        }
+    }
+
+    let sealed = serde_json::to_string(&seal(source.as_ref(), &fresh)?)?;
+    let mut set = redis::cmd("SET");
+    set.arg(&redis_key).arg(sealed);
+    with_timeout("SET", redis.timeouts().command, set.query_async::<_, ()>(&mut conn)).await?;
+    info!("🔐 Sealed new enclave key {} to {}", key_id(fresh.public()), redis_key);
+    Ok(fresh)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unseal_of_seal_round_trips_the_key() {
+        let kp = Ed25519KeyPair::generate(&mut rand::thread_rng());
+        let source = DevWrappingKey::new(b"dev secret");
+
+        let sealed = seal(&source, &kp).unwrap();
+        let unsealed = unseal(&source, &sealed).unwrap();
+        assert_eq!(unsealed.as_bytes(), kp.as_bytes());
+        assert_eq!(unsealed.public(), kp.public());
+
+        // Wrong wrapping key or a modified ciphertext must not unseal
+        assert!(unseal(&DevWrappingKey::new(b"other secret"), &sealed).is_err());
+        let mut tampered = sealed.clone();
+        tampered.ciphertext.replace_range(0..2, if &sealed.ciphertext[0..2] == "00" { "01" } else { "00" });
+        assert!(unseal(&source, &tampered).is_err());
+    }
+
+    /// A wrapping key source that can't be reached.
+    struct Unreachable;
+
+    impl WrappingKeySource for Unreachable {
+        fn new_key(&self) -> Result<([u8; 32], Vec<u8>)> {
+            Err(anyhow!("connection refused"))
+        }
+
+        fn unwrap_key(&self, _wrapped: &[u8]) -> Result<[u8; 32]> {
+            Err(anyhow!("connection refused"))
+        }
+    }
+
+    #[test]
+    fn test_only_an_unusable_sealed_key_is_replaced() {
+        let kp = Ed25519KeyPair::generate(&mut rand::thread_rng());
+        let source = DevWrappingKey::new(b"dev secret");
+        let stored = serde_json::to_string(&seal(&source, &kp).unwrap()).unwrap();
+
+        let recovered = recover(&source, &stored).unwrap().unwrap();
+        assert_eq!(recovered.as_bytes(), kp.as_bytes());
+
+        // Corrupt, or sealed under another key: replaced
+        assert!(recover(&source, "not json").unwrap().is_none());
+        assert!(recover(&DevWrappingKey::new(b"other secret"), &stored).unwrap().is_none());
+
+        // The wrapping key source is down: startup fails and the stored key stays
+        let err = recover(&Unreachable, &stored).unwrap_err();
+        assert!(!is_sealed_key_unusable(&err));
+        assert!(err.to_string().contains("leaving it in place"), "{}", err);
+    }
+}
@@ -0,0 +1,166 @@
+// Asynchronous /process_kyc jobs, kept in Redis next to the other results and looked up by polling token
+use anyhow::{Result, anyhow};
+use axum::async_trait;
+use redis::aio::Connection;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::api_error::ApiError;
+use crate::app::KYCResponse;
+use crate::common::{IntentMessage, ProcessedDataResponse};
+use crate::metrics;
+use crate::negative_attestation::SignedNegativeAttestation;
+use crate::redis_timeout::with_timeout;
+use crate::verification_processor::RedisConnector;
+
+pub type SignedKycResponse = ProcessedDataResponse<IntentMessage<KYCResponse>>;
+
+/// What a processed KYC request yields: a signed [`KYCResponse`] when verified, otherwise a
+/// signed negative attestation, so a rejection can't be replayed under the KYC intent.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum KycAttestation {
+    Verified(SignedKycResponse),
+    Rejected(SignedNegativeAttestation),
+}
+
+impl KycAttestation {
+    /// Echo the caller's request id; it sits outside the signed bytes.
+    pub fn set_request_id(&mut self, request_id: Option<String>) {
+        match self {
+            KycAttestation::Verified(signed) => signed.request_id = request_id,
+            KycAttestation::Rejected(signed) => signed.request_id = request_id,
+        }
+    }
+}
+
+/// Where an asynchronous KYC job is.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum KycJobStatus {
+    Pending,
+    Completed { attestation: KycAttestation },
+    /// The error body the synchronous endpoint would have returned, with its HTTP status.
+    Failed { http_status: u16, error: ApiError },
+}
+
+impl KycJobStatus {
+    pub fn failed(error: ApiError) -> Self {
+        KycJobStatus::Failed { http_status: error.status.as_u16(), error }
+    }
+}
+
+/// How long a job stays retrievable after it was last written, from `KYC_RESULT_TTL_SECS` (default 600).
+fn result_ttl() -> Duration {
+    Duration::from_secs(
+        std::env::var("KYC_RESULT_TTL_SECS")
+            .ok()
+            .and_then(|v| v.parse::<u64>().ok())
+            .unwrap_or(600)
+            .max(1),
+    )
+}
+
+/// Status of each job by polling token. Entries expire a TTL after they were last written.
+#[async_trait]
+pub trait KycJobStore: Send + Sync {
+    async fn put(&self, token: &str, status: &KycJobStatus) -> Result<()>;
+    /// Current status of a job, or `None` for an unknown or expired token.
+    async fn get(&self, token: &str) -> Result<Option<KycJobStatus>>;
+}
+
+/// In-process store, for tests and runs without Redis.
+pub struct MemoryKycJobStore {
+    ttl: Duration,
+    jobs: Mutex<HashMap<String, (KycJobStatus, Instant)>>,
+}
+
+impl Default for MemoryKycJobStore {
+    fn default() -> Self {
+        Self {
+            ttl: result_ttl(),
+            jobs: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+#[async_trait]
+impl KycJobStore for MemoryKycJobStore {
+    async fn put(&self, token: &str, status: &KycJobStatus) -> Result<()> {
+        let mut jobs = self.jobs.lock().unwrap();
+        jobs.retain(|_, (_, written_at)| written_at.elapsed() < self.ttl);
+        jobs.insert(token.to_string(), (status.clone(), Instant::now()));
+        metrics::set_gauge("kyc_jobs_tracked", jobs.len() as f64);
+        Ok(())
+    }
+
+    async fn get(&self, token: &str) -> Result<Option<KycJobStatus>> {
+        let jobs = self.jobs.lock().unwrap();
+        Ok(jobs
+            .get(token)
+            .filter(|(_, written_at)| written_at.elapsed() < self.ttl)
+            .map(|(status, _)| status.clone()))
+    }
+}
+
+/// One Redis key per job, `KYC_JOB_KEY_PREFIX:<token>` (default prefix `kyc_jobs`), so any
+/// instance can answer a poll and a restart doesn't lose finished results.
+pub struct RedisKycJobStore {
+    redis: RedisConnector,
+    conn: tokio::sync::Mutex<Option<Connection>>,
+    key_prefix: String,
+    ttl: Duration,
+}
+
+impl RedisKycJobStore {
+    pub fn from_env(redis: RedisConnector) -> Self {
+        Self {
+            redis,
+            conn: tokio::sync::Mutex::new(None),
+            key_prefix: std::env::var("KYC_JOB_KEY_PREFIX").unwrap_or_else(|_| "kyc_jobs".to_string()),
+            ttl: result_ttl(),
+        }
+    }
+
+    fn key(&self, token: &str) -> String {
+        format!("{}:{}", self.key_prefix, token)
+    }
+
+    /// Run `command` on the shared connection, dropping the connection if Redis fails.
+    async fn query<T: redis::FromRedisValue>(&self, command: &redis::Cmd) -> Result<T> {
+        let mut guard = self.conn.lock().await;
+        if guard.is_none() {
+            *guard = Some(self.redis.connect().await?);
+        }
+        let conn = guard.as_mut().expect("connection was just established");
+        let result = with_timeout("KYC job store", self.redis.timeouts().command, command.query_async(conn)).await;
+        if result.is_err() {
+            *guard = None;
+        }
+        result
+    }
+}
+
+#[async_trait]
+impl KycJobStore for RedisKycJobStore {
+    async fn put(&self, token: &str, status: &KycJobStatus) -> Result<()> {
+        let mut command = redis::cmd("SET");
+        command
+            .arg(self.key(token))
+            .arg(serde_json::to_string(status)?)
+            .arg("PX")
+            .arg(self.ttl.as_millis() as u64);
+        self.query::<()>(&command).await
+    }
+
+    async fn get(&self, token: &str) -> Result<Option<KycJobStatus>> {
+        let mut command = redis::cmd("GET");
+        command.arg(self.key(token));
+        let stored: Option<String> = self.query(&command).await?;
+        stored
+            .map(|json| serde_json::from_str(&json).map_err(|e| anyhow!("Corrupt KYC job {}: {}", token, e)))
+            .transpose()
+    }
+}
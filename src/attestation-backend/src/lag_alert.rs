@@ -0,0 +1,270 @@
+// Consumer-group lag monitor that pushes a signed alert when the pipeline falls behind and recovers
+use anyhow::{Result, anyhow};
+use fastcrypto::ed25519::Ed25519KeyPair;
+use fastcrypto::traits::KeyPair;
+use redis::aio::Connection;
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use tokio::time::{Duration, sleep};
+use tracing::{info, warn};
+
+use crate::common::{key_id, to_signed_response, IntentMessage, IntentScope, ProcessedDataResponse};
+use crate::message_source::stream_names_from_env;
+use crate::metrics;
+use crate::redis_timeout::{with_timeout, RedisTimeouts};
+use crate::signing::SigningError;
+use crate::verification_processor::RedisConnector;
+
+/// Which side of the threshold the lag settled on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LagState {
+    Behind,
+    Recovered,
+}
+
+/// Payload of a lag alert. `lag` is summed over every stream the processor consumes.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LagAlert {
+    pub state: LagState,
+    pub lag: u64,
+    pub threshold: u64,
+    pub consumer_group: String,
+    pub streams: Vec<String>,
+    pub key_id: String,
+}
+
+pub type SignedLagAlert = ProcessedDataResponse<IntentMessage<LagAlert>>;
+
+/// Sign an alert under [`IntentScope::LagAlert`], so a receiver can tell it came from the enclave.
+pub fn sign_lag_alert(kp: &Ed25519KeyPair, alert: LagAlert, timestamp_ms: u64) -> Result<SignedLagAlert, SigningError> {
+    let alert = LagAlert { key_id: key_id(kp.public()), ..alert };
+    to_signed_response(kp, alert, timestamp_ms, IntentScope::LagAlert)
+}
+
+/// Debounced threshold crossing: the lag must stay on the other side for `debounce_ms` before
+/// the state flips and an alert is due, so a lag hovering at the threshold doesn't page anyone.
+#[derive(Debug, Clone)]
+pub struct LagAlarm {
+    threshold: u64,
+    debounce_ms: u64,
+    behind: bool,
+    /// When the lag first disagreed with `behind`, while it keeps disagreeing.
+    crossing_since_ms: Option<u64>,
+}
+
+impl LagAlarm {
+    pub fn new(threshold: u64, debounce_ms: u64) -> Self {
+        Self { threshold, debounce_ms, behind: false, crossing_since_ms: None }
+    }
+
+    /// Record a lag sample; the alert to send, if the state just flipped.
+    pub fn observe(&mut self, lag: u64, now_ms: u64) -> Option<LagState> {
+        if (lag >= self.threshold) == self.behind {
+            self.crossing_since_ms = None;
+            return None;
+        }
+        let since = *self.crossing_since_ms.get_or_insert(now_ms);
+        if now_ms.saturating_sub(since) < self.debounce_ms {
+            return None;
+        }
+        self.behind = !self.behind;
+        self.crossing_since_ms = None;
+        Some(if self.behind { LagState::Behind } else { LagState::Recovered })
+    }
+}
+
+/// Lag alert settings. Off unless `LAG_ALERT_THRESHOLD` is set; alerts go to
+/// `LAG_ALERT_WEBHOOK_URL` and/or the `LAG_ALERT_STREAM` stream.
+#[derive(Debug, Clone)]
+pub struct LagAlertConfig {
+    pub threshold: u64,
+    pub debounce: Duration,
+    pub interval: Duration,
+    pub webhook_url: Option<String>,
+    pub alert_stream: Option<String>,
+    pub consumer_group: String,
+    pub streams: Vec<String>,
+    pub command_timeout: Duration,
+}
+
+impl LagAlertConfig {
+    pub fn from_env() -> Result<Option<Self>> {
+        let Some(threshold) = std::env::var("LAG_ALERT_THRESHOLD").ok().filter(|v| !v.trim().is_empty()) else {
+            return Ok(None);
+        };
+        let threshold = threshold
+            .trim()
+            .parse::<u64>()
+            .map_err(|e| anyhow!("Invalid LAG_ALERT_THRESHOLD '{}': {}", threshold, e))?;
+        let secs = |name: &str, default: u64| {
+            Duration::from_secs(std::env::var(name).ok().and_then(|v| v.parse::<u64>().ok()).unwrap_or(default))
+        };
+        let optional = |name: &str| std::env::var(name).ok().filter(|v| !v.trim().is_empty());
+        Ok(Some(Self {
+            threshold: threshold.max(1),
+            debounce: secs("LAG_ALERT_DEBOUNCE_SECS", 60),
+            interval: secs("LAG_ALERT_INTERVAL_SECS", 15).max(Duration::from_secs(1)),
+            webhook_url: optional("LAG_ALERT_WEBHOOK_URL"),
+            alert_stream: optional("LAG_ALERT_STREAM"),
+            consumer_group: std::env::var("REDIS_CONSUMER_GROUP").unwrap_or_else(|_| "attestation_processors".to_string()),
+            streams: stream_names_from_env(),
+            command_timeout: RedisTimeouts::from_env().command,
+        }))
+    }
+}
+
+/// Entries `group` hasn't processed yet on `stream_name`: the `lag` XINFO reports (Redis 7+),
+/// or just the pending count where it doesn't. A missing group counts as no lag.
+pub async fn group_lag(conn: &mut Connection, stream_name: &str, group: &str, timeout: Duration) -> Result<u64> {
+    let mut xinfo = redis::cmd("XINFO");
+    xinfo.arg("GROUPS").arg(stream_name);
+    let groups: Vec<HashMap<String, redis::Value>> = with_timeout("XINFO", timeout, xinfo.query_async(conn)).await?;
+    let count = |info: &HashMap<String, redis::Value>, field: &str| match info.get(field) {
+        Some(redis::Value::Int(n)) => u64::try_from(*n).ok(),
+        _ => None,
+    };
+    let Some(info) = groups
+        .iter()
+        .find(|info| matches!(info.get("name"), Some(redis::Value::Data(name)) if name == group.as_bytes()))
+    else {
+        return Ok(0);
+    };
+    let pending = count(info, "pending").unwrap_or(0);
+    Ok(count(info, "lag").map(|lag| lag + pending).unwrap_or(pending))
+}
+
+/// Deliver a signed alert to every configured target; failures are logged and counted.
+async fn emit(client: &Client, conn: Option<&mut Connection>, config: &LagAlertConfig, alert: &SignedLagAlert) {
+    let payload = match serde_json::to_string(alert) {
+        Ok(payload) => payload,
+        Err(e) => return warn!("Failed to encode lag alert: {}", e),
+    };
+    if let Some(url) = &config.webhook_url {
+        let posted = client.post(url).header("content-type", "application/json").body(payload.clone()).send().await;
+        match posted {
+            Ok(response) if response.status().is_success() => {}
+            Ok(response) => {
+                warn!("Lag alert webhook returned {}", response.status());
+                metrics::increment("lag_alert_failures_total");
+            }
+            Err(e) => {
+                warn!("Lag alert webhook failed: {}", e);
+                metrics::increment("lag_alert_failures_total");
+            }
+        }
+    }
+    if let (Some(stream), Some(conn)) = (&config.alert_stream, conn) {
+        let mut xadd = redis::cmd("XADD");
+        xadd.arg(stream).arg("*").arg("alert").arg(&payload);
+        let added = with_timeout("XADD", config.command_timeout, xadd.query_async::<_, String>(conn)).await;
+        if let Err(e) = added {
+            warn!("Failed to write lag alert to {}: {}", stream, e);
+            metrics::increment("lag_alert_failures_total");
+        }
+    }
+}
+
+/// Background task: sample the consumer-group lag every interval, publish it as
+/// `consumer_group_lag`, and push an alert whenever it settles above or back below the threshold.
+pub async fn run_lag_alert_task(kp: Ed25519KeyPair, redis: RedisConnector) -> Result<()> {
+    let Some(config) = LagAlertConfig::from_env()? else {
+        info!("Lag alerts are off (LAG_ALERT_THRESHOLD unset)");
+        return Ok(());
+    };
+    info!("🚨 Alerting when {} lags {}+ entries on {} (debounce {}s)",
+          config.consumer_group, config.threshold, config.streams.join(","), config.debounce.as_secs());
+
+    let client = Client::new();
+    let mut alarm = LagAlarm::new(config.threshold, config.debounce.as_millis() as u64);
+    let mut conn = None;
+    loop {
+        sleep(config.interval).await;
+        if conn.is_none() {
+            conn = redis.connect().await.map_err(|e| warn!("Lag monitor Redis connect failed: {}", e)).ok();
+        }
+        let Some(c) = conn.as_mut() else { continue };
+        let mut lag = 0;
+        for stream_name in &config.streams {
+            match group_lag(c, stream_name, &config.consumer_group, config.command_timeout).await {
+                Ok(stream_lag) => lag += stream_lag,
+                Err(e) => {
+                    warn!("Failed to read lag of {}: {}", stream_name, e);
+                    conn = None;
+                    break;
+                }
+            }
+        }
+        if conn.is_none() {
+            continue;
+        }
+        metrics::set_gauge("consumer_group_lag", lag as f64);
+
+        let now_ms = chrono::Utc::now().timestamp_millis() as u64;
+        let Some(state) = alarm.observe(lag, now_ms) else { continue };
+        warn!("🚨 Consumer group {} is {:?}: lag {} (threshold {})", config.consumer_group, state, lag, config.threshold);
+        metrics::increment("lag_alerts_total");
+        let alert = LagAlert {
+            state,
+            lag,
+            threshold: config.threshold,
+            consumer_group: config.consumer_group.clone(),
+            streams: config.streams.clone(),
+            key_id: String::new(),
+        };
+        match sign_lag_alert(&kp, alert, now_ms) {
+            Ok(signed) => emit(&client, conn.as_mut(), &config, &signed).await,
+            Err(e) => warn!("Failed to sign lag alert: {}", e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::verify_signed_response;
+
+    #[test]
+    fn test_crossing_and_recovering_alert_once_each_after_debounce() {
+        let mut alarm = LagAlarm::new(100, 30_000);
+        let samples = [
+            (0, 10),
+            // A spike shorter than the debounce
+            (10_000, 500),
+            (20_000, 20),
+            // Stays behind: one alert once it has for 30s
+            (30_000, 150),
+            (50_000, 180),
+            (60_000, 200),
+            (70_000, 400),
+            // Dips back under briefly, then behind again: nothing
+            (80_000, 50),
+            (90_000, 120),
+            // Recovered for good
+            (100_000, 40),
+            (120_000, 30),
+            (130_000, 0),
+            (200_000, 0),
+        ];
+        let alerts: Vec<(u64, LagState)> = samples
+            .iter()
+            .filter_map(|(at, lag)| alarm.observe(*lag, *at).map(|state| (*at, state)))
+            .collect();
+        assert_eq!(alerts, vec![(60_000, LagState::Behind), (130_000, LagState::Recovered)]);
+
+        // The alert is signed by, and names, the enclave key
+        let kp = Ed25519KeyPair::generate(&mut rand::thread_rng());
+        let alert = LagAlert {
+            state: LagState::Behind,
+            lag: 200,
+            threshold: 100,
+            consumer_group: "attestation_processors".to_string(),
+            streams: vec!["verification_stream".to_string()],
+            key_id: String::new(),
+        };
+        let signed = sign_lag_alert(&kp, alert, 60_000).unwrap();
+        assert_eq!(signed.response.data.key_id, key_id(kp.public()));
+        assert!(verify_signed_response(kp.public(), &signed).is_ok());
+    }
+}
@@ -1,40 +1,146 @@
-use axum::http::StatusCode;
 use axum::response::IntoResponse;
 use axum::response::Response;
-use axum::Json;
-use fastcrypto::ed25519::Ed25519KeyPair;
-use serde_json::json;
+use fastcrypto::ed25519::{Ed25519KeyPair, Ed25519PublicKey};
+use std::sync::Arc;
 
+use crate::app::{FaceDetector, NoFaceDetection};
+use crate::attestation_store::{AttestationStore, MemoryAttestationStore};
+use crate::kyc_jobs::{KycJobStore, MemoryKycJobStore};
+use crate::live_results::{LiveResultsConfig, ResultFeed};
+use crate::sui_transaction::{GasMode, TransactionAccess};
+use crate::verification_types::VerificationTypes;
+
+pub mod api_error;
 pub mod app;
+pub mod attestation_store;
+pub mod call_limit;
+pub mod canonical_json;
+pub mod commit_log;
+pub mod cert_pin;
+pub mod circuit_breaker;
 pub mod common;
+pub mod compression;
+pub mod content_negotiation;
+pub mod decision_policy;
+pub mod deferred;
+pub mod diagnostics;
+pub mod did_cache;
+pub mod entropy;
+pub mod evidence;
+#[cfg(test)]
+mod fake_redis;
 pub mod government_api;
-// pub mod kafka_sui_processor; // Commented out - not using Kafka
+pub mod heartbeat;
+pub mod key_sealing;
+pub mod kyc_jobs;
+pub mod lag_alert;
+pub mod lifetime_stats;
+pub mod live_results;
+pub mod logging;
+pub mod message_source;
+pub mod metrics;
+#[cfg(any(test, feature = "mock-govt-api"))]
+pub mod mock_govt_api;
+pub mod negative_attestation;
+pub mod payload;
+pub mod pcr_check;
+#[cfg(feature = "kafka")]
+pub mod kafka_sui_processor;
 pub mod redis_sui_processor;
+pub mod redis_timeout;
+pub mod request_id;
+pub mod sealed_blob;
+pub mod stage_timer;
+pub mod result_store;
+pub mod results;
+pub mod retry;
+pub mod reverification;
+pub mod signing;
+pub mod stream_trim;
+pub mod sui_clock;
+pub mod sui_gas;
+pub mod sui_output;
+pub mod sui_proxy;
+pub mod sui_transaction;
 pub mod verification_processor;
+pub mod verification_types;
+pub mod verify_attestation;
+pub mod work_queue;
 pub mod zklogin;
 
 /// App state, at minimum needs to maintain the ephemeral keypair.  
 pub struct AppState {
     /// Ephemeral keypair on boot
     pub eph_kp: Ed25519KeyPair,
+    /// Signed verification results served by `/attestation`
+    pub attestations: Arc<dyn AttestationStore>,
+    /// Results streamed to `/ws/results` subscribers as they are published
+    pub result_feed: ResultFeed,
+    /// Who pays for transactions built by `/verification_transaction`
+    pub gas_mode: GasMode,
+    /// API keys and per-wallet rate limit for the transaction endpoints
+    pub transaction_access: TransactionAccess,
+    /// Keys of earlier boots that `/verify_attestation` still accepts
+    pub retired_keys: Vec<Ed25519PublicKey>,
+    /// Checks selfie frames for a face during KYC liveness
+    pub face_detector: Arc<dyn FaceDetector>,
+    /// `/process_kyc_async` jobs, polled through `/verification_result`
+    pub kyc_jobs: Arc<dyn KycJobStore>,
+    /// Resolves type aliases in `/attestation` lookups
+    pub verification_types: VerificationTypes,
+}
+
+impl AppState {
+    /// State with in-memory attestation and KYC job stores and the built-in type table.
+    pub fn new(eph_kp: Ed25519KeyPair) -> Self {
+        Self {
+            eph_kp,
+            attestations: Arc::new(MemoryAttestationStore::default()),
+            result_feed: ResultFeed::new(LiveResultsConfig::default()),
+            gas_mode: GasMode::SelfGas,
+            transaction_access: TransactionAccess::default(),
+            retired_keys: Vec::new(),
+            face_detector: Arc::new(NoFaceDetection),
+            kyc_jobs: Arc::new(MemoryKycJobStore::default()),
+            verification_types: VerificationTypes::default_table(),
+        }
+    }
 }
 
 /// Enclave errors enum.
 #[derive(Debug)]
 pub enum EnclaveError {
     GenericError(String),
+    /// The request body is not the JSON the endpoint expects.
+    InvalidBody(String),
+    /// An encrypted request field could not be decrypted.
+    DecryptionFailed(String),
+    /// The client's Accept header names no format we can produce.
+    NotAcceptable(String),
+    NotFound(String),
+    /// The caller didn't present a valid API key.
+    Unauthorized(String),
+    /// The caller made too many requests, or what they need is busy; they may retry later.
+    RateLimited(String),
+    /// The enclave key failed to sign the response.
+    SigningFailed(String),
+    /// A dependency (NSM, government API, Sui proxy) failed or answered unexpectedly.
+    Upstream(String),
+    Internal(String),
+    /// A signed timestamp fell outside the allowed [`common::SkewWindow`].
+    Timestamp(common::TimestampError),
+}
+
+impl From<signing::SigningError> for EnclaveError {
+    fn from(e: signing::SigningError) -> Self {
+        EnclaveError::SigningFailed(e.to_string())
+    }
 }
 
-/// Implement IntoResponse for EnclaveError.
+/// Implement IntoResponse for EnclaveError, as an [`api_error::ApiError`] body.
 impl IntoResponse for EnclaveError {
     fn into_response(self) -> Response {
-        let (status, error_message) = match self {
-            EnclaveError::GenericError(e) => (StatusCode::BAD_REQUEST, e),
-        };
-        let body = Json(json!({
-            "error": error_message,
-        }));
-        (status, body).into_response()
+        api_error::ApiError::from(self).into_response()
     }
 }
 
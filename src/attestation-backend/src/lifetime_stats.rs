@@ -0,0 +1,136 @@
+// Processed/succeeded/failed message counts kept in Redis, so they survive restarts
+use anyhow::Result;
+use redis::aio::Connection;
+use std::collections::HashMap;
+use std::time::Duration;
+use tracing::info;
+
+use crate::metrics;
+use crate::redis_timeout::{with_timeout, RedisTimeouts};
+
+/// Cumulative counts since the stats key was created.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LifetimeTotals {
+    pub processed: u64,
+    pub succeeded: u64,
+    pub failed: u64,
+}
+
+impl LifetimeTotals {
+    fn from_fields(fields: &HashMap<String, u64>) -> Self {
+        let field = |name: &str| fields.get(name).copied().unwrap_or(0);
+        Self {
+            processed: field("processed"),
+            succeeded: field("succeeded"),
+            failed: field("failed"),
+        }
+    }
+
+    /// Shown on /metrics as `messages_{processed,succeeded,failed}_lifetime`.
+    pub fn publish(&self) {
+        metrics::set_gauge("messages_processed_lifetime", self.processed as f64);
+        metrics::set_gauge("messages_succeeded_lifetime", self.succeeded as f64);
+        metrics::set_gauge("messages_failed_lifetime", self.failed as f64);
+    }
+}
+
+/// Lifetime counts in the Redis hash `LIFETIME_STATS_KEY` (default `verification:lifetime_stats`).
+/// Every instance increments the same hash, so the totals cover the whole deployment; the
+/// in-memory throughput tracker still reports the current rate.
+#[derive(Debug, Clone)]
+pub struct LifetimeStats {
+    key: String,
+    command_timeout: Duration,
+}
+
+impl LifetimeStats {
+    pub fn new(key: &str) -> Self {
+        Self { key: key.to_string(), command_timeout: RedisTimeouts::from_env().command }
+    }
+
+    pub fn from_env() -> Self {
+        Self::new(&std::env::var("LIFETIME_STATS_KEY").unwrap_or_else(|_| "verification:lifetime_stats".to_string()))
+    }
+
+    /// Count one processed message (and whether it succeeded) and return the new totals.
+    /// The increments are one MULTI/EXEC, so concurrent writers never see a partial update.
+    pub async fn record(&self, conn: &mut Connection, succeeded: bool) -> Result<LifetimeTotals> {
+        let outcome = if succeeded { "succeeded" } else { "failed" };
+        let mut pipe = redis::pipe();
+        pipe.atomic()
+            .hincr(&self.key, "processed", 1)
+            .hincr(&self.key, outcome, 1)
+            .hgetall(&self.key);
+        let (_, _, fields): (u64, u64, HashMap<String, u64>) =
+            with_timeout("HINCRBY", self.command_timeout, pipe.query_async(conn)).await?;
+        let totals = LifetimeTotals::from_fields(&fields);
+        totals.publish();
+        Ok(totals)
+    }
+
+    pub async fn load(&self, conn: &mut Connection) -> Result<LifetimeTotals> {
+        let fields: HashMap<String, u64> =
+            with_timeout("HGETALL", self.command_timeout, redis::cmd("HGETALL").arg(&self.key).query_async(conn)).await?;
+        let totals = LifetimeTotals::from_fields(&fields);
+        totals.publish();
+        info!("📈 Lifetime totals: {} processed ({} succeeded, {} failed)",
+              totals.processed, totals.succeeded, totals.failed);
+        Ok(totals)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    type Store = Arc<Mutex<HashMap<String, HashMap<String, u64>>>>;
+
+    fn execute(store: &Store, args: &[String]) -> String {
+        let mut store = store.lock().unwrap();
+        match args[0].to_uppercase().as_str() {
+            "HINCRBY" => {
+                let value = store.entry(args[1].clone()).or_default().entry(args[2].clone()).or_insert(0);
+                *value += args[3].parse::<u64>().unwrap();
+                format!(":{}\r\n", value)
+            }
+            "HGETALL" => {
+                let fields = store.get(&args[1]).cloned().unwrap_or_default();
+                let mut reply = format!("*{}\r\n", fields.len() * 2);
+                for (field, value) in fields {
+                    let value = value.to_string();
+                    reply += &format!("${}\r\n{}\r\n${}\r\n{}\r\n", field.len(), field, value.len(), value);
+                }
+                reply
+            }
+            _ => "+OK\r\n".to_string(),
+        }
+    }
+
+    /// HINCRBY, HGETALL and MULTI/EXEC over `store`, which outlives any one connection.
+    async fn counting_redis(store: Store) -> String {
+        crate::fake_redis::serve(move |args| Some(execute(&store, args))).await
+    }
+
+    #[tokio::test]
+    async fn test_lifetime_totals_survive_a_restart() {
+        let url = counting_redis(Store::default()).await;
+        let client = redis::Client::open(url).unwrap();
+
+        let mut conn = client.get_async_connection().await.unwrap();
+        let stats = LifetimeStats::new("lifetime_test");
+        stats.record(&mut conn, true).await.unwrap();
+        stats.record(&mut conn, true).await.unwrap();
+        let before = stats.record(&mut conn, false).await.unwrap();
+        assert_eq!(before, LifetimeTotals { processed: 3, succeeded: 2, failed: 1 });
+        drop(conn);
+
+        // A new process: fresh connection and tracker, same Redis
+        let mut conn = client.get_async_connection().await.unwrap();
+        let restarted = LifetimeStats::new("lifetime_test");
+        assert_eq!(restarted.load(&mut conn).await.unwrap(), before);
+        let after = restarted.record(&mut conn, true).await.unwrap();
+        assert_eq!(after, LifetimeTotals { processed: 4, succeeded: 3, failed: 1 });
+        assert_eq!(metrics::gauge("messages_processed_lifetime"), Some(4.0));
+    }
+}
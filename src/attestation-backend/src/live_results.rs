@@ -0,0 +1,261 @@
+// Live verification results over WebSocket (/ws/results) for dashboards
+use axum::extract::ws::{CloseFrame, Message, WebSocket, WebSocketUpgrade};
+use axum::extract::State;
+use axum::response::Response;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tokio::sync::broadcast::{self, error::RecvError};
+use tokio::time::{timeout, Duration};
+use tracing::{info, warn};
+
+use crate::metrics;
+use crate::results::VerificationResultEvent;
+use crate::AppState;
+
+/// Policy-violation close code, sent for a missing or wrong subscribe token.
+const CLOSE_UNAUTHORIZED: u16 = 1008;
+/// Try-again-later close code, sent to a slow consumer under [`SlowConsumerPolicy::Disconnect`].
+const CLOSE_SLOW_CONSUMER: u16 = 1013;
+
+/// What happens when a subscriber falls more than the buffer behind.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SlowConsumerPolicy {
+    /// Skip the events it missed and tell it how many (default).
+    Drop,
+    /// Close the connection; the client reconnects and catches up from the results stream.
+    Disconnect,
+}
+
+#[derive(Debug, Clone)]
+pub struct LiveResultsConfig {
+    /// Token a client must present to subscribe. Without one the endpoint refuses everyone.
+    pub token: Option<String>,
+    /// Events buffered per subscriber before the slow-consumer policy applies.
+    pub buffer: usize,
+    pub slow_consumer: SlowConsumerPolicy,
+    /// How long a client has to send its subscribe message after connecting.
+    pub subscribe_timeout: Duration,
+}
+
+impl Default for LiveResultsConfig {
+    fn default() -> Self {
+        Self {
+            token: None,
+            buffer: 256,
+            slow_consumer: SlowConsumerPolicy::Drop,
+            subscribe_timeout: Duration::from_secs(10),
+        }
+    }
+}
+
+impl LiveResultsConfig {
+    /// `RESULTS_WS_TOKEN`, `RESULTS_WS_BUFFER` (default 256) and `RESULTS_WS_SLOW_CONSUMER`
+    /// (`drop` or `disconnect`).
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        let config = Self {
+            token: std::env::var("RESULTS_WS_TOKEN").ok().filter(|t| !t.is_empty()),
+            buffer: std::env::var("RESULTS_WS_BUFFER")
+                .ok()
+                .and_then(|v| v.parse::<usize>().ok())
+                .filter(|n| *n > 0)
+                .unwrap_or(defaults.buffer),
+            slow_consumer: match std::env::var("RESULTS_WS_SLOW_CONSUMER") {
+                Ok(v) if v.trim().eq_ignore_ascii_case("disconnect") => SlowConsumerPolicy::Disconnect,
+                _ => SlowConsumerPolicy::Drop,
+            },
+            ..defaults
+        };
+        info!("📡 Live results: {}, buffer {} events, slow consumers: {:?}",
+              if config.token.is_some() { "enabled" } else { "disabled (no RESULTS_WS_TOKEN)" },
+              config.buffer, config.slow_consumer);
+        config
+    }
+}
+
+/// Fan-out of published results to WebSocket subscribers. Cheap to clone; every clone feeds the
+/// same subscribers. Publishing with no subscriber connected is a no-op.
+#[derive(Debug, Clone)]
+pub struct ResultFeed {
+    sender: broadcast::Sender<VerificationResultEvent>,
+    config: Arc<LiveResultsConfig>,
+}
+
+impl ResultFeed {
+    pub fn new(config: LiveResultsConfig) -> Self {
+        let (sender, _) = broadcast::channel(config.buffer.max(1));
+        Self { sender, config: Arc::new(config) }
+    }
+
+    pub fn from_env() -> Self {
+        Self::new(LiveResultsConfig::from_env())
+    }
+
+    pub fn publish(&self, event: &VerificationResultEvent) {
+        // Err only means nobody is subscribed
+        let _ = self.sender.send(event.clone());
+    }
+
+    fn authorized(&self, token: &str) -> bool {
+        self.config.token.as_deref().is_some_and(|expected| expected == token)
+    }
+}
+
+/// First message a client sends: its token, and optionally the one wallet it wants results for.
+#[derive(Debug, Deserialize)]
+pub struct SubscribeRequest {
+    pub token: String,
+    #[serde(default)]
+    pub wallet: Option<String>,
+}
+
+/// Messages sent to a subscriber.
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum LiveMessage<'a> {
+    Subscribed { wallet: Option<&'a str> },
+    Result { event: &'a VerificationResultEvent },
+    /// The subscriber fell behind and `missed` events were skipped.
+    Lagged { missed: u64 },
+}
+
+/// Endpoint `/ws/results`: after a [`SubscribeRequest`] with the right token, every result
+/// published from then on (for the requested wallet, if any) is sent as it happens.
+pub async fn ws_results(State(state): State<Arc<AppState>>, upgrade: WebSocketUpgrade) -> Response {
+    let feed = state.result_feed.clone();
+    upgrade.on_upgrade(move |socket| serve_subscriber(socket, feed))
+}
+
+async fn send(socket: &mut WebSocket, message: &LiveMessage<'_>) -> bool {
+    let text = serde_json::to_string(message).unwrap_or_default();
+    socket.send(Message::Text(text)).await.is_ok()
+}
+
+async fn close(mut socket: WebSocket, code: u16, reason: &'static str) {
+    let _ = socket.send(Message::Close(Some(CloseFrame { code, reason: reason.into() }))).await;
+}
+
+async fn serve_subscriber(mut socket: WebSocket, feed: ResultFeed) {
+    let request = match timeout(feed.config.subscribe_timeout, socket.recv()).await {
+        Ok(Some(Ok(Message::Text(text)))) => serde_json::from_str::<SubscribeRequest>(&text).ok(),
+        _ => None,
+    };
+    let Some(request) = request.filter(|r| feed.authorized(&r.token)) else {
+        warn!("Refused /ws/results subscription without a valid token");
+        return close(socket, CLOSE_UNAUTHORIZED, "unauthorized").await;
+    };
+    let wallet = request.wallet.map(|w| w.trim().to_lowercase());
+
+    // Subscribe before acknowledging, so nothing published after the ack is missed
+    let mut events = feed.sender.subscribe();
+    if !send(&mut socket, &LiveMessage::Subscribed { wallet: wallet.as_deref() }).await {
+        return;
+    }
+    metrics::increment("results_ws_subscriptions_total");
+
+    loop {
+        tokio::select! {
+            received = events.recv() => match received {
+                Ok(event) => {
+                    if wallet.as_ref().is_some_and(|w| !event.user_wallet.eq_ignore_ascii_case(w)) {
+                        continue;
+                    }
+                    if !send(&mut socket, &LiveMessage::Result { event: &event }).await {
+                        return;
+                    }
+                }
+                Err(RecvError::Lagged(missed)) => {
+                    metrics::increment_by("results_ws_dropped_total", missed);
+                    match feed.config.slow_consumer {
+                        SlowConsumerPolicy::Drop => {
+                            if !send(&mut socket, &LiveMessage::Lagged { missed }).await {
+                                return;
+                            }
+                        }
+                        SlowConsumerPolicy::Disconnect => {
+                            warn!("Disconnecting /ws/results subscriber {} events behind", missed);
+                            return close(socket, CLOSE_SLOW_CONSUMER, "too slow").await;
+                        }
+                    }
+                }
+                Err(RecvError::Closed) => return,
+            },
+            // Only watched for the client going away; anything it sends is ignored
+            incoming = socket.recv() => match incoming {
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => return,
+                Some(Ok(_)) => {}
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::results::sample_event;
+    use axum::routing::get;
+    use axum::Router;
+    use fastcrypto::ed25519::Ed25519KeyPair;
+    use fastcrypto::traits::KeyPair;
+    use futures::{SinkExt, StreamExt};
+    use tokio_tungstenite::tungstenite::Message as ClientMessage;
+
+    fn event(wallet: &str) -> VerificationResultEvent {
+        VerificationResultEvent {
+            user_wallet: wallet.to_string(),
+            did_id: 1,
+            verification_type: "citizenship".to_string(),
+            ..sample_event()
+        }
+    }
+
+    async fn serve(feed: ResultFeed) -> String {
+        let mut state = AppState::new(Ed25519KeyPair::generate(&mut rand::thread_rng()));
+        state.result_feed = feed;
+        let app = Router::new().route("/ws/results", get(ws_results)).with_state(Arc::new(state));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        format!("ws://{}/ws/results", addr)
+    }
+
+    async fn next_json<S>(client: &mut S) -> serde_json::Value
+    where
+        S: StreamExt<Item = Result<ClientMessage, tokio_tungstenite::tungstenite::Error>> + Unpin,
+    {
+        match timeout(Duration::from_secs(5), client.next()).await.unwrap().unwrap().unwrap() {
+            ClientMessage::Text(text) => serde_json::from_str(&text).unwrap(),
+            other => panic!("unexpected message: {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_subscriber_receives_results_published_after_subscribing() {
+        let feed = ResultFeed::new(LiveResultsConfig {
+            token: Some("dashboard-token".to_string()),
+            ..LiveResultsConfig::default()
+        });
+        let url = serve(feed.clone()).await;
+
+        let (mut client, _) = tokio_tungstenite::connect_async(url.as_str()).await.unwrap();
+        let subscribe = r#"{"token":"dashboard-token","wallet":"0xABC"}"#;
+        client.send(ClientMessage::Text(subscribe.to_string())).await.unwrap();
+        assert_eq!(next_json(&mut client).await, serde_json::json!({ "type": "subscribed", "wallet": "0xabc" }));
+
+        // Another wallet's result is filtered out; ours arrives
+        feed.publish(&event("0xdef"));
+        feed.publish(&event("0xabc"));
+        let received = next_json(&mut client).await;
+        assert_eq!(received["type"], "result");
+        assert_eq!(received["event"]["user_wallet"], "0xabc");
+        assert_eq!(received["event"]["evidence_hash"], "ab".repeat(32));
+
+        // A wrong token gets a policy-violation close and no events
+        let (mut intruder, _) = tokio_tungstenite::connect_async(url.as_str()).await.unwrap();
+        intruder.send(ClientMessage::Text(r#"{"token":"guess"}"#.to_string())).await.unwrap();
+        match timeout(Duration::from_secs(5), intruder.next()).await.unwrap().unwrap().unwrap() {
+            ClientMessage::Close(Some(frame)) => assert_eq!(u16::from(frame.code), CLOSE_UNAUTHORIZED),
+            other => panic!("unexpected message: {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,67 @@
+// Tracing subscriber setup: per-module level filtering and an optional log file target
+use anyhow::{Result, anyhow};
+use std::fs::OpenOptions;
+use std::sync::Mutex;
+use tracing_subscriber::{EnvFilter, fmt, layer::SubscriberExt, util::SubscriberInitExt};
+
+/// Info for this crate, warn for everything else (redis, reqwest, hyper, ...).
+pub const DEFAULT_LOG_FILTER: &str = "warn,attestation_server=info";
+
+/// Filter directives from a `RUST_LOG`-style value, falling back to [`DEFAULT_LOG_FILTER`] when unset or empty.
+pub fn log_filter(spec: Option<&str>) -> Result<EnvFilter> {
+    let spec = spec
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .unwrap_or(DEFAULT_LOG_FILTER);
+    EnvFilter::try_new(spec).map_err(|e| anyhow!("Invalid log filter '{}': {}", spec, e))
+}
+
+/// Install the global subscriber.
+///
+/// `RUST_LOG` controls levels per module (e.g. `warn,attestation_server::message_source=debug`).
+/// If `LOG_FILE` is set, the same events are also appended to that file without ANSI colours,
+/// which is how logs are collected from inside the enclave.
+pub fn init_logging() -> Result<()> {
+    let filter = log_filter(std::env::var("RUST_LOG").ok().as_deref())?;
+
+    let file_layer = match std::env::var("LOG_FILE").ok().filter(|p| !p.is_empty()) {
+        Some(path) => {
+            let file = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&path)
+                .map_err(|e| anyhow!("Failed to open log file {}: {}", path, e))?;
+            Some(fmt::layer().with_ansi(false).with_writer(Mutex::new(file)))
+        }
+        None => None,
+    };
+
+    tracing_subscriber::registry()
+        .with(filter)
+        .with(fmt::layer())
+        .with(file_layer)
+        .try_init()
+        .map_err(|e| anyhow!("Failed to initialize logging: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_filter_when_unset_or_empty() {
+        assert_eq!(log_filter(None).unwrap().to_string(), log_filter(Some(DEFAULT_LOG_FILTER)).unwrap().to_string());
+        assert_eq!(log_filter(Some("  ")).unwrap().to_string(), log_filter(None).unwrap().to_string());
+    }
+
+    #[test]
+    fn test_per_module_directives_are_accepted() {
+        let filter = log_filter(Some("warn,attestation_server::message_source=debug")).unwrap();
+        assert!(filter.to_string().contains("attestation_server::message_source=debug"));
+    }
+
+    #[test]
+    fn test_invalid_directive_is_rejected() {
+        assert!(log_filter(Some("attestation_server=loud")).is_err());
+    }
+}
@@ -1,18 +1,35 @@
 // main.rs
 use anyhow::Result;
-use axum::{routing::get, routing::post, Router};
+use axum::{middleware, routing::get, routing::post, Router};
 use fastcrypto::{ed25519::Ed25519KeyPair, traits::{KeyPair, ToFromBytes}};
-use attestation_server::common::{get_attestation, health_check};
-use attestation_server::app::{process_kyc};
+use attestation_server::attestation_store::{get_stored_attestation, RedisAttestationStore};
+use attestation_server::common::{get_attestation, get_keys, health_check, signing_self_test};
+use attestation_server::compression::CompressionConfig;
+use attestation_server::logging::init_logging;
+use attestation_server::app::{get_verification_result, process_kyc, process_kyc_async, NoFaceDetection};
+use attestation_server::diagnostics::get_diagnostics;
+use attestation_server::sui_transaction::{
+    serialize_verification_transaction, submit_sponsored_transaction, GasMode, TransactionAccess,
+};
+use attestation_server::heartbeat::{get_heartbeat, run_heartbeat_task};
+use attestation_server::key_sealing::load_or_seal;
+use attestation_server::kyc_jobs::RedisKycJobStore;
+use attestation_server::lag_alert::run_lag_alert_task;
+use attestation_server::entropy::keypair_from_rng;
+use attestation_server::live_results::{ws_results, ResultFeed};
+use attestation_server::message_source::stream_names_from_env;
+use attestation_server::metrics::metrics_handler;
+use attestation_server::request_id::request_id_middleware;
+use attestation_server::stream_trim::run_stream_trim_task;
+use attestation_server::verification_processor::{start_verification_processor, RedisConnector};
+use attestation_server::verification_types::VerificationTypes;
+use attestation_server::verify_attestation::{retired_keys_from_env, verify_attestation};
 // use attestation_server::zklogin::{get_salt, get_zk_proof}; // COMMENTED OUT - No longer using zkLogin
 use attestation_server::AppState;
 use std::sync::Arc;
 // CORS imports moved to function scope
 use tracing::{info, error};
 
-mod verification_processor;
-mod government_api;
-use verification_processor::start_verification_processor;
 // use rand::SeedableRng;
 
 #[tokio::main]
@@ -27,62 +44,103 @@ async fn main() -> Result<()> {
         info!("No local .env file found, using system environment variables");
     }
     
-    // Initialize tracing
-    tracing_subscriber::fmt::init();
+    // Initialize tracing (RUST_LOG per-module filter, optional LOG_FILE target)
+    init_logging()?;
     
     // Debug: Log key environment variables (without sensitive data)
     info!("Environment variables loaded (.env files only, no secrets.json):");
     info!("  REDIS_URL: {}", if std::env::var("REDIS_URL").is_ok() { "✅ Set" } else { "❌ Not set" });
-    info!("  REDIS_STREAM_NAME: {}", std::env::var("REDIS_STREAM_NAME").unwrap_or("default".to_string()));
+    info!("  REDIS_STREAM_NAME(S): {}", stream_names_from_env().join(","));
     info!("  SUI_PACKAGE_ID: {}", if std::env::var("SUI_PACKAGE_ID").is_ok() { "✅ Set" } else { "❌ Using default" });
 
     
 
     // Use NSM hardware entropy for key generation in enclave
     let eph_kp = if std::env::var("ENCLAVE_MODE").is_ok() {
-        // In enclave: use NSM hardware entropy
+        // In enclave: use NSM hardware entropy; a failure is logged and counted, or fatal if fail-closed
         #[cfg(feature = "aws")]
         {
-            use aws_nitro_enclaves_nsm_api::driver;
-            use aws_nitro_enclaves_nsm_api::api::{Request, Response};
-            
-            // Get entropy from NSM
-            let fd = driver::nsm_init();
-            let request = Request::GetRandom;
-            match driver::nsm_process_request(fd, request) {
-                Response::GetRandom { random } => {
-                    driver::nsm_exit(fd);
-                    let seed: [u8; 32] = random[..32].try_into().expect("Invalid entropy length");
-                    use rand::SeedableRng;
-                    let mut rng = rand::rngs::StdRng::from_seed(seed);
-                    Ed25519KeyPair::generate(&mut rng)
-                }
-                _ => {
-                    driver::nsm_exit(fd);
-                    // Fallback to thread_rng if NSM fails
-                    Ed25519KeyPair::generate(&mut rand::thread_rng())
-                }
-            }
+            use attestation_server::entropy::{fail_closed_from_env, generate_keypair, nsm_random};
+            use attestation_server::pcr_check::{enforce_from_env, nsm_pcrs, verify_measurements, ExpectedPcrs};
+            // Refuse to run a tampered image before any key exists
+            verify_measurements(&ExpectedPcrs::from_env()?, nsm_pcrs(), enforce_from_env())?;
+            generate_keypair(nsm_random(), fail_closed_from_env())?
         }
         #[cfg(not(feature = "aws"))]
         {
             // Fallback if aws feature not available
-            Ed25519KeyPair::generate(&mut rand::thread_rng())
+            keypair_from_rng(&mut rand::thread_rng())
         }
     } else {
         // Local development: use standard RNG
-        Ed25519KeyPair::generate(&mut rand::thread_rng())
+        keypair_from_rng(&mut rand::thread_rng())
     };
 
+    // Opt-in key continuity: reuse the keypair sealed by a previous boot, if any
+    let eph_kp = load_or_seal(eph_kp).await?;
+
+    // Refuse to start with a key whose signatures wouldn't verify
+    signing_self_test(&eph_kp, eph_kp.public())
+        .map_err(|e| anyhow::anyhow!("Signing self-test failed, not starting: {:?}", e))?;
+
     // Clone the keypair for the Redis processor
     let redis_keypair = Ed25519KeyPair::from_bytes(eph_kp.as_bytes())?;
-    let state = Arc::new(AppState { eph_kp });
+    let heartbeat_keypair = Ed25519KeyPair::from_bytes(eph_kp.as_bytes())?;
+    let lag_alert_keypair = Ed25519KeyPair::from_bytes(eph_kp.as_bytes())?;
+    #[cfg(feature = "kafka")]
+    let kafka_keypair = Ed25519KeyPair::from_bytes(eph_kp.as_bytes())?;
+    let result_feed = ResultFeed::from_env();
+    let state = Arc::new(AppState {
+        eph_kp,
+        attestations: Arc::new(RedisAttestationStore::from_env(RedisConnector::from_env()?)),
+        result_feed: result_feed.clone(),
+        gas_mode: GasMode::from_env()?,
+        transaction_access: TransactionAccess::from_env(),
+        retired_keys: retired_keys_from_env()?,
+        face_detector: Arc::new(NoFaceDetection),
+        kyc_jobs: Arc::new(RedisKycJobStore::from_env(RedisConnector::from_env()?)),
+        verification_types: VerificationTypes::from_env()?,
+    });
 
     info!("Starting attestation server with API and Verification processor");
 
     // Start both API server and Verification processor concurrently
     let api_handle = tokio::spawn(run_api_server(state));
-    let verification_handle = tokio::spawn(start_verification_processor(redis_keypair));
+    let verification_handle = tokio::spawn(start_verification_processor(redis_keypair, result_feed));
+
+    // Signed liveness/key-continuity heartbeat; failures are logged, never fatal
+    let heartbeat_redis = RedisConnector::from_env()?;
+    tokio::spawn(async move {
+        if let Err(e) = run_heartbeat_task(heartbeat_keypair, heartbeat_redis).await {
+            error!("Heartbeat task stopped: {}", e);
+        }
+    });
+
+    // Bound stream growth; entries a consumer group still needs are never trimmed
+    let trim_redis = RedisConnector::from_env()?;
+    tokio::spawn(async move {
+        if let Err(e) = run_stream_trim_task(trim_redis).await {
+            error!("Stream trim task stopped: {}", e);
+        }
+    });
+
+    // Push a signed alert when the consumer group falls behind, and when it catches up
+    let lag_redis = RedisConnector::from_env()?;
+    tokio::spawn(async move {
+        if let Err(e) = run_lag_alert_task(lag_alert_keypair, lag_redis).await {
+            error!("Lag alert task stopped: {}", e);
+        }
+    });
+
+    // Upstream verification results from Kafka feed the same pipeline as the Redis stream
+    #[cfg(feature = "kafka")]
+    if std::env::var("KAFKA_HOST").is_ok_and(|host| !host.trim().is_empty()) {
+        tokio::spawn(async move {
+            if let Err(e) = attestation_server::kafka_sui_processor::start_kafka_sui_processor(kafka_keypair).await {
+                error!("Kafka processor stopped: {}", e);
+            }
+        });
+    }
 
     // Wait for either to complete (or fail)
     tokio::select! {
@@ -117,12 +175,25 @@ async fn run_api_server(state: Arc<AppState>) -> Result<()> {
     let app = Router::new()
         .route("/", get(ping))
         .route("/health", get(health_check))
+        .route("/metrics", get(metrics_handler))
+        .route("/diagnostics", get(get_diagnostics))
         .route("/get_attestation", get(get_attestation))
+        .route("/keys", get(get_keys))
+        .route("/heartbeat", get(get_heartbeat))
+        .route("/attestation", get(get_stored_attestation))
+        .route("/verify_attestation", post(verify_attestation))
+        .route("/verification_transaction", post(serialize_verification_transaction))
+        .route("/submit_sponsored_transaction", post(submit_sponsored_transaction))
         .route("/process_kyc", post(process_kyc))
+        .route("/process_kyc_async", post(process_kyc_async))
+        .route("/verification_result/:token", get(get_verification_result))
+        .route("/ws/results", get(ws_results))
         // zkLogin endpoints - COMMENTED OUT - No longer using zkLogin for now
         // .route("/get_salt", post(get_salt))
         // .route("/get_zk_proof", post(get_zk_proof))
         .with_state(state)
+        .layer(middleware::from_fn(request_id_middleware))
+        .layer(CompressionConfig::from_env().layer())
         .layer(cors);
 
     let listener = tokio::net::TcpListener::bind("0.0.0.0:4000").await?;
@@ -0,0 +1,1951 @@
+// Message sources feeding the verification pipeline (Redis streams, Kafka)
+use anyhow::{Result, anyhow};
+use redis::streams::{StreamClaimReply, StreamId, StreamInfoGroupsReply, StreamReadReply};
+use redis::{RedisResult, Value, aio::Connection};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use tokio::sync::{Mutex, MutexGuard};
+use tracing::{error, info, warn};
+
+use crate::government_api::VerificationRequest;
+use crate::metrics;
+use crate::payload::{is_invalid_message, InvalidMessage};
+use crate::redis_timeout::with_timeout;
+use crate::sealed_blob::{seal_text, SealingKey};
+use crate::verification_types::{parse_did_id, validate_sui_address};
+use crate::verification_processor::RedisConnector;
+
+/// An already-decided verification result, ready for the Sui contract calls.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct VerifiedResult {
+    pub user_wallet: String,
+    #[serde(deserialize_with = "deserialize_did_id")]
+    pub did_id: u8,
+    pub result: String,
+    pub evidence_hash: String,
+    pub verified_at: String,
+    /// Why the verification was rejected, when `result` is not "verified".
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rejection_reason: Option<String>,
+}
+
+/// `did_id` as a string or an integer, parsed the same way for every source: strings go
+/// through [`parse_did_id`], so `" 1 "`, `"1"` and `1` are all accepted and `256` never is.
+fn deserialize_did_id<'de, D>(deserializer: D) -> Result<u8, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    use serde::de::{self, Visitor};
+
+    struct DidIdVisitor;
+
+    impl<'de> Visitor<'de> for DidIdVisitor {
+        type Value = u8;
+
+        fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+            formatter.write_str("a string or integer that can be converted to u8")
+        }
+
+        fn visit_str<E>(self, value: &str) -> Result<Self::Value, E>
+        where
+            E: de::Error,
+        {
+            parse_did_id(value).map_err(de::Error::custom)
+        }
+
+        fn visit_u64<E>(self, value: u64) -> Result<Self::Value, E>
+        where
+            E: de::Error,
+        {
+            u8::try_from(value).map_err(|_| de::Error::custom(format!("u64 value {} is too large for u8", value)))
+        }
+
+        fn visit_i64<E>(self, value: i64) -> Result<Self::Value, E>
+        where
+            E: de::Error,
+        {
+            u8::try_from(value).map_err(|_| de::Error::custom(format!("i64 value {} is out of range for u8", value)))
+        }
+    }
+
+    deserializer.deserialize_any(DidIdVisitor)
+}
+
+/// Milliseconds since epoch from any timestamp format a source sends: RFC 3339 (`...Z`,
+/// `...+05:30`), ISO 8601 without an offset (`T` or space separated, taken as UTC), or
+/// epoch digits (seconds below 10^11, milliseconds above). Times before the epoch are rejected.
+pub fn parse_timestamp_to_ms(timestamp_str: &str) -> Result<u64> {
+    let value = timestamp_str.trim();
+    if !value.is_empty() && value.bytes().all(|b| b.is_ascii_digit()) {
+        let epoch: u64 = value
+            .parse()
+            .map_err(|e| anyhow!("Failed to parse timestamp '{}': {}", timestamp_str, e))?;
+        return Ok(if epoch < 100_000_000_000 { epoch * 1000 } else { epoch });
+    }
+    let millis = match chrono::DateTime::parse_from_rfc3339(value) {
+        Ok(dt) => dt.timestamp_millis(),
+        Err(_) => ["%Y-%m-%dT%H:%M:%S%.f", "%Y-%m-%d %H:%M:%S%.f"]
+            .iter()
+            .find_map(|format| chrono::NaiveDateTime::parse_from_str(value, format).ok())
+            .map(|naive| naive.and_utc().timestamp_millis())
+            .ok_or_else(|| anyhow!("Failed to parse timestamp '{}'", timestamp_str))?,
+    };
+    u64::try_from(millis).map_err(|_| anyhow!("Timestamp '{}' is before the epoch", timestamp_str))
+}
+
+/// What a source delivers: either a raw request that still needs the government API,
+/// or a result that an upstream service has already verified.
+#[derive(Debug, Clone)]
+pub enum MessagePayload {
+    Request(VerificationRequest),
+    Verified(VerifiedResult),
+}
+
+/// One message from a source. `id` is whatever the source needs to ack it
+/// (a stream entry id for Redis, an offset for Kafka).
+#[derive(Debug, Clone)]
+pub struct VerificationMessage {
+    pub id: String,
+    pub payload: MessagePayload,
+    /// The stream the message was read from, for sources that read several.
+    pub stream: Option<String>,
+}
+
+impl VerificationMessage {
+    /// `id` qualified by its stream, unique across every stream a source reads.
+    pub fn key(&self) -> String {
+        match &self.stream {
+            Some(stream) => format!("{}/{}", stream, self.id),
+            None => self.id.clone(),
+        }
+    }
+}
+
+/// A transport that yields verification messages and is told how each one ended.
+pub trait MessageSource: Send + Sync {
+    fn name(&self) -> &str;
+
+    /// Fetch up to `max` new messages. An empty batch means the source is idle.
+    fn next_batch(&self, max: usize) -> impl Future<Output = Result<Vec<VerificationMessage>>> + Send;
+
+    /// The message was fully processed and must not be delivered again.
+    fn ack(&self, message: &VerificationMessage) -> impl Future<Output = Result<()>> + Send;
+
+    /// Ack several processed messages at once. Sources that can ack in one round trip override this.
+    fn ack_many(&self, messages: &[VerificationMessage]) -> impl Future<Output = Result<()>> + Send {
+        async move {
+            for message in messages {
+                self.ack(message).await?;
+            }
+            Ok(())
+        }
+    }
+
+    /// The message could not be processed; the source decides whether it is redelivered.
+    fn nack(&self, message: &VerificationMessage, reason: &str) -> impl Future<Output = Result<()>> + Send;
+}
+
+/// Receives each message from the pipeline. Success acks the message, failure nacks it.
+pub trait MessageHandler: Send {
+    fn handle(&mut self, message: &VerificationMessage) -> impl Future<Output = Result<()>> + Send;
+
+    /// Set aside a message whose handling panicked, so it isn't redelivered into the same panic.
+    /// Success acks it; the default has nowhere to put it, so it is nacked instead.
+    fn quarantine(&mut self, message: &VerificationMessage, reason: &str) -> impl Future<Output = Result<()>> + Send {
+        let _ = reason;
+        async move { Err(anyhow!("No quarantine for message {}", message.id)) }
+    }
+}
+
+// catch_unwind catches nothing when panics abort, and one poison message would take the process down
+#[cfg(panic = "abort")]
+compile_error!("the message loop isolates panicking messages, so it must be built with panic = \"unwind\"");
+
+/// [`MessageHandler::handle`] behind a panic boundary: a panic is logged with the message id and
+/// the message quarantined, and the caller carries on with the next one. Ok means ack it.
+async fn handle_guarded<H: MessageHandler>(handler: &mut H, message: &VerificationMessage) -> Result<()> {
+    use futures::FutureExt;
+    let panic = match std::panic::AssertUnwindSafe(handler.handle(message)).catch_unwind().await {
+        Ok(result) => return result,
+        Err(panic) => panic,
+    };
+    let detail = panic
+        .downcast_ref::<&str>()
+        .map(|s| s.to_string())
+        .or_else(|| panic.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "non-string panic payload".to_string());
+    metrics::increment("message_handler_panics_total");
+    error!("💥 Handling message {} panicked: {}", message.key(), detail);
+    let reason = format!("handler panicked: {}", detail);
+    handler.quarantine(message, &reason).await.map_err(|e| anyhow!("{} (not quarantined: {})", reason, e))
+}
+
+/// Run one message through `handler` and report the outcome back to its source.
+/// The returned error is only ever an ack/nack failure.
+pub async fn dispatch<S: MessageSource, H: MessageHandler>(
+    source: &S,
+    handler: &mut H,
+    message: &VerificationMessage,
+) -> Result<()> {
+    match handle_guarded(handler, message).await {
+        Ok(()) => source.ack(message).await,
+        Err(e) => {
+            warn!("Failed to process message {} from {}: {}", message.id, source.name(), e);
+            source.nack(message, &e.to_string()).await
+        }
+    }
+}
+
+/// Messages that were fully processed (including their Sui transactions) and await one
+/// multi-id ack. A crash before [`AckBatch::flush`] only leaves them pending, so they are
+/// reclaimed once idle (`REDIS_RECLAIM_MIN_IDLE_MS`) and resumed from the commit log; nothing
+/// is acked before it is committed.
+pub struct AckBatch {
+    pending: Vec<VerificationMessage>,
+    max: usize,
+}
+
+impl AckBatch {
+    pub fn new(max: usize) -> Self {
+        Self {
+            pending: Vec::new(),
+            max: max.max(1),
+        }
+    }
+
+    /// Batch size from `ACK_BATCH_SIZE` (default 16; 1 acks each message on its own).
+    pub fn from_env() -> Self {
+        Self::new(
+            std::env::var("ACK_BATCH_SIZE")
+                .ok()
+                .and_then(|v| v.parse::<usize>().ok())
+                .unwrap_or(16),
+        )
+    }
+
+    pub fn push(&mut self, message: &VerificationMessage) {
+        self.pending.push(message.clone());
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.pending.len() >= self.max
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pending.is_empty()
+    }
+
+    /// Ack everything collected so far. On failure the batch is dropped anyway: the messages
+    /// stay pending at the source until it reclaims them.
+    pub async fn flush<S: MessageSource>(&mut self, source: &S) -> Result<()> {
+        if self.pending.is_empty() {
+            return Ok(());
+        }
+        let batch = std::mem::take(&mut self.pending);
+        metrics::increment_by("ack_batch_messages_total", batch.len() as u64);
+        source.ack_many(&batch).await
+    }
+}
+
+/// Like [`dispatch`], but a success is added to `batch` instead of being acked right away;
+/// the batch is flushed once full. Failures are nacked immediately.
+pub async fn dispatch_batched<S: MessageSource, H: MessageHandler>(
+    source: &S,
+    handler: &mut H,
+    message: &VerificationMessage,
+    batch: &mut AckBatch,
+) -> Result<()> {
+    match handle_guarded(handler, message).await {
+        Ok(()) => {
+            batch.push(message);
+            if batch.is_full() {
+                batch.flush(source).await?;
+            }
+            Ok(())
+        }
+        Err(e) => {
+            warn!("Failed to process message {} from {}: {}", message.id, source.name(), e);
+            source.nack(message, &e.to_string()).await
+        }
+    }
+}
+
+/// What a source does with a message that just failed.
+#[cfg(any(test, feature = "kafka"))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FailureAction {
+    /// Deliver it again; `attempt` failures so far.
+    Retry { attempt: u32 },
+    /// Out of attempts: move it aside and go on with the next message.
+    GiveUp { attempts: u32 },
+}
+
+/// Failed attempts per message id, for sources with no delivery count of their own (Kafka),
+/// so a poison message is retried a bounded number of times instead of blocking its partition.
+#[cfg(any(test, feature = "kafka"))]
+pub struct AttemptTracker {
+    max_attempts: u32,
+    failures: std::sync::Mutex<HashMap<String, u32>>,
+}
+
+#[cfg(any(test, feature = "kafka"))]
+impl AttemptTracker {
+    pub fn new(max_attempts: u32) -> Self {
+        Self {
+            max_attempts: max_attempts.max(1),
+            failures: std::sync::Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Attempts per message from `var` (default 3; 1 gives up on the first failure).
+    pub fn from_env(var: &str) -> Self {
+        Self::new(std::env::var(var).ok().and_then(|v| v.parse::<u32>().ok()).unwrap_or(3))
+    }
+
+    pub fn record_failure(&self, id: &str) -> FailureAction {
+        let mut failures = self.failures.lock().unwrap();
+        let attempt = failures.entry(id.to_string()).or_insert(0);
+        *attempt += 1;
+        if *attempt < self.max_attempts {
+            return FailureAction::Retry { attempt: *attempt };
+        }
+        let attempts = *attempt;
+        failures.remove(id);
+        FailureAction::GiveUp { attempts }
+    }
+
+    /// Forget `id`, once it has been processed.
+    pub fn clear(&self, id: &str) {
+        self.failures.lock().unwrap().remove(id);
+    }
+}
+
+/// A message used up its [`RetryBudget`]; it goes to the DLQ whichever stage was failing.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BudgetExhausted {
+    pub attempts: u32,
+    pub elapsed: std::time::Duration,
+    pub last_error: String,
+}
+
+impl std::fmt::Display for BudgetExhausted {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Retry budget exhausted after {} attempts over {:?}: {}", self.attempts, self.elapsed, self.last_error)
+    }
+}
+
+impl std::error::Error for BudgetExhausted {}
+
+pub fn is_budget_exhausted(error: &anyhow::Error) -> bool {
+    error.downcast_ref::<BudgetExhausted>().is_some()
+}
+
+#[derive(Debug, Clone, Copy)]
+struct BudgetEntry {
+    first_seen: tokio::time::Instant,
+    failures: u32,
+}
+
+/// When a message's budget runs out, handed to each handling so it can stop between stages. A
+/// stage already running is never cut off: abandoning one between `start_verification` and its
+/// commit-log record would leave a UserDID nothing knows about.
+#[derive(Debug, Clone, Copy)]
+pub struct StageDeadline {
+    at: tokio::time::Instant,
+    deadline: std::time::Duration,
+}
+
+impl StageDeadline {
+    /// Fail if the deadline has passed, instead of starting `next_stage`.
+    pub fn check(&self, next_stage: &str) -> Result<()> {
+        if tokio::time::Instant::now() >= self.at {
+            return Err(anyhow!("still failing at the {:?} deadline, before {}", self.deadline, next_stage));
+        }
+        Ok(())
+    }
+}
+
+/// Overall budget per message across every stage and redelivery: at most `max_attempts`
+/// failed handlings and `deadline` from when it was first handled. The deadline is checked
+/// between stages ([`StageDeadline`]); the retries within a stage are bounded by its own policy.
+/// Kept in memory: a restart gives pending messages a fresh budget, and entries are dropped
+/// once twice the deadline old, by when a nacked message has been reclaimed or given up on.
+#[derive(Debug)]
+pub struct RetryBudget {
+    max_attempts: u32,
+    deadline: std::time::Duration,
+    entries: std::sync::Mutex<HashMap<String, BudgetEntry>>,
+}
+
+impl RetryBudget {
+    pub fn new(max_attempts: u32, deadline: std::time::Duration) -> Self {
+        Self {
+            max_attempts: max_attempts.max(1),
+            deadline,
+            entries: std::sync::Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// `MESSAGE_RETRY_MAX_ATTEMPTS` (default 10) and `MESSAGE_RETRY_DEADLINE_SECS` (default 600).
+    pub fn from_env() -> Self {
+        let var = |name: &str, default: u64| std::env::var(name).ok().and_then(|v| v.parse::<u64>().ok()).unwrap_or(default);
+        Self::new(
+            var("MESSAGE_RETRY_MAX_ATTEMPTS", 10) as u32,
+            std::time::Duration::from_secs(var("MESSAGE_RETRY_DEADLINE_SECS", 600)),
+        )
+    }
+
+    /// Run one handling of message `id` within what is left of its budget. A failure that
+    /// uses up the budget, or ends past the deadline, is a [`BudgetExhausted`]; any other
+    /// failure is returned as is, to be retried.
+    pub async fn run<T, F, Fut>(&self, id: &str, handling: F) -> Result<T>
+    where
+        F: FnOnce(StageDeadline) -> Fut,
+        Fut: Future<Output = Result<T>>,
+    {
+        let entry = {
+            let mut entries = self.entries.lock().unwrap();
+            let stale = self.deadline * 2;
+            entries.retain(|_, entry| entry.first_seen.elapsed() < stale);
+            *entries
+                .entry(id.to_string())
+                .or_insert(BudgetEntry { first_seen: tokio::time::Instant::now(), failures: 0 })
+        };
+        let deadline = StageDeadline { at: entry.first_seen + self.deadline, deadline: self.deadline };
+
+        let error = match handling(deadline).await {
+            Ok(value) => {
+                self.clear(id);
+                return Ok(value);
+            }
+            Err(e) => e,
+        };
+        let failures = entry.failures + 1;
+        let elapsed = entry.first_seen.elapsed();
+        if failures < self.max_attempts && elapsed < self.deadline {
+            if let Some(entry) = self.entries.lock().unwrap().get_mut(id) {
+                entry.failures = failures;
+            }
+            return Err(error);
+        }
+        self.clear(id);
+        metrics::increment("message_retry_budget_exhausted_total");
+        Err(BudgetExhausted { attempts: failures, elapsed, last_error: error.to_string() }.into())
+    }
+
+    /// Whether `id` is already past its deadline, so its next handling won't get to call anything.
+    pub fn past_deadline(&self, id: &str) -> bool {
+        self.entries
+            .lock()
+            .unwrap()
+            .get(id)
+            .is_some_and(|entry| entry.first_seen.elapsed() >= self.deadline)
+    }
+
+    /// Forget `id`, once it has been processed or moved aside.
+    pub fn clear(&self, id: &str) {
+        self.entries.lock().unwrap().remove(id);
+    }
+}
+
+/// What a verification stream entry field must hold.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FieldShape {
+    Text,
+    /// A full Sui address, as accepted by [`validate_sui_address`].
+    SuiAddress,
+    /// A `did_id`, as accepted by [`parse_did_id`].
+    DidId,
+    /// Text holding a JSON object.
+    JsonObject,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct FieldSpec {
+    pub name: &'static str,
+    pub shape: FieldShape,
+    pub required: bool,
+}
+
+const fn field(name: &'static str, shape: FieldShape, required: bool) -> FieldSpec {
+    FieldSpec { name, shape, required }
+}
+
+/// The declared shape of a verification stream entry. `extracted_data` and `user_corrections`
+/// stay plain text: an unreadable OCR payload is ignored downstream rather than rejected.
+pub const VERIFICATION_REQUEST_FIELDS: &[FieldSpec] = &[
+    field("user_wallet", FieldShape::SuiAddress, true),
+    field("did_id", FieldShape::DidId, true),
+    field("verification_type", FieldShape::Text, true),
+    field("document_data", FieldShape::JsonObject, true),
+    field("extracted_data", FieldShape::Text, false),
+    field("user_corrections", FieldShape::Text, false),
+    field("timestamp", FieldShape::Text, true),
+    field("status", FieldShape::Text, true),
+];
+
+/// Stream field names for producers that don't use ours, from `REDIS_STREAM_FIELD_MAP`
+/// (`logical=actual`, comma separated, e.g. `user_wallet=wallet`). Logical names are those of
+/// [`VERIFICATION_REQUEST_FIELDS`]; fields not listed keep their name.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StreamFieldNames {
+    renamed: HashMap<String, String>,
+}
+
+impl StreamFieldNames {
+    pub fn parse(spec: &str) -> Result<Self> {
+        let mut renamed = HashMap::new();
+        for pair in spec.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+            let (logical, actual) = pair
+                .split_once('=')
+                .map(|(l, a)| (l.trim(), a.trim()))
+                .filter(|(_, a)| !a.is_empty())
+                .ok_or_else(|| anyhow!("Invalid stream field mapping '{}', expected logical=actual", pair))?;
+            if !VERIFICATION_REQUEST_FIELDS.iter().any(|spec| spec.name == logical) {
+                return Err(anyhow!("Unknown stream field '{}' in mapping '{}'", logical, pair));
+            }
+            renamed.insert(logical.to_string(), actual.to_string());
+        }
+        let names = Self { renamed };
+        let mut actual: Vec<&str> = VERIFICATION_REQUEST_FIELDS.iter().map(|spec| names.actual(spec.name)).collect();
+        actual.sort_unstable();
+        if let Some(pair) = actual.windows(2).find(|pair| pair[0] == pair[1]) {
+            return Err(anyhow!("Stream field '{}' is mapped to more than one field", pair[0]));
+        }
+        Ok(names)
+    }
+
+    pub fn from_env() -> Result<Self> {
+        let names = Self::parse(&std::env::var("REDIS_STREAM_FIELD_MAP").unwrap_or_default())?;
+        if !names.renamed.is_empty() {
+            info!("Stream field names: {:?}", names.renamed);
+        }
+        Ok(names)
+    }
+
+    /// The field a producer puts `logical` in.
+    pub fn actual<'a>(&'a self, logical: &'a str) -> &'a str {
+        self.renamed.get(logical).map(String::as_str).unwrap_or(logical)
+    }
+
+    /// `fields` keyed by logical name, ready for [`parse_stream_fields`].
+    pub fn to_logical<'a>(&self, fields: &'a HashMap<String, Value>) -> std::borrow::Cow<'a, HashMap<String, Value>> {
+        if self.renamed.is_empty() {
+            return std::borrow::Cow::Borrowed(fields);
+        }
+        let logical = VERIFICATION_REQUEST_FIELDS
+            .iter()
+            .filter_map(|spec| Some((spec.name.to_string(), fields.get(self.actual(spec.name))?.clone())))
+            .collect();
+        std::borrow::Cow::Owned(logical)
+    }
+}
+
+/// A stream field value as text. Bulk strings must be UTF-8.
+fn field_text(value: &Value) -> Option<String> {
+    match value {
+        Value::Data(bytes) => String::from_utf8(bytes.clone()).ok(),
+        Value::Int(i) => Some(i.to_string()),
+        Value::Status(s) => Some(s.clone()),
+        _ => None,
+    }
+}
+
+/// Check `fields` against `schema`, naming the first field that doesn't fit.
+pub fn validate_fields(schema: &[FieldSpec], fields: &HashMap<String, Value>) -> Result<(), InvalidMessage> {
+    let invalid = |spec: &FieldSpec, problem: String| InvalidMessage {
+        reason: format!("field '{}' {}", spec.name, problem),
+    };
+    for spec in schema {
+        let Some(value) = fields.get(spec.name) else {
+            if spec.required {
+                return Err(invalid(spec, "is missing".to_string()));
+            }
+            continue;
+        };
+        let text = field_text(value).ok_or_else(|| invalid(spec, "is not a UTF-8 string".to_string()))?;
+        match spec.shape {
+            FieldShape::Text => {}
+            FieldShape::SuiAddress => {
+                validate_sui_address(&text).map_err(|e| invalid(spec, format!("is invalid: {}", e.reason)))?;
+            }
+            FieldShape::DidId => {
+                parse_did_id(&text).map_err(|e| invalid(spec, format!("is invalid: {}", e.reason)))?;
+            }
+            FieldShape::JsonObject => match serde_json::from_str::<serde_json::Value>(&text) {
+                Ok(serde_json::Value::Object(_)) => {}
+                Ok(_) => return Err(invalid(spec, "is not a JSON object".to_string())),
+                Err(e) => return Err(invalid(spec, format!("is not valid JSON: {}", e))),
+            },
+        }
+    }
+    Ok(())
+}
+
+/// Build a message from the fields of a verification stream entry. Entries that don't match
+/// [`VERIFICATION_REQUEST_FIELDS`] fail with an [`InvalidMessage`] naming the bad field.
+pub fn parse_stream_fields(id: &str, fields: &HashMap<String, Value>) -> Result<VerificationMessage> {
+    validate_fields(VERIFICATION_REQUEST_FIELDS, fields)?;
+    let get_field = |key: &str| -> Result<String> {
+        fields.get(key)
+            .and_then(field_text)
+            .ok_or_else(|| anyhow!("Missing or invalid field: {}", key))
+    };
+
+    let request = VerificationRequest {
+        user_wallet: get_field("user_wallet")?,
+        did_id: get_field("did_id")?,
+        verification_type: get_field("verification_type")?,
+        document_data: get_field("document_data")?,
+        extracted_data: get_field("extracted_data").ok(),
+        user_corrections: get_field("user_corrections").ok(),
+        timestamp: get_field("timestamp")?,
+        status: get_field("status")?,
+    };
+
+    Ok(VerificationMessage {
+        id: id.to_string(),
+        payload: MessagePayload::Request(request),
+        stream: None,
+    })
+}
+
+/// Build a message from a decoded Kafka record carrying an upstream verification result.
+pub fn parse_record_payload(offset: i64, payload: &str) -> Result<VerificationMessage> {
+    let result: VerifiedResult = serde_json::from_str(payload)
+        .map_err(|e| anyhow!("Invalid verification record at offset {}: {}", offset, e))?;
+    Ok(VerificationMessage {
+        id: offset.to_string(),
+        payload: MessagePayload::Verified(result),
+        stream: None,
+    })
+}
+
+pub async fn create_consumer_group(conn: &mut Connection, stream_name: &str, consumer_group: &str) -> Result<()> {
+    // Try to create consumer group (ignore if it already exists)
+    let result: RedisResult<String> = redis::cmd("XGROUP")
+        .arg("CREATE")
+        .arg(stream_name)
+        .arg(consumer_group)
+        .arg("0")
+        .arg("MKSTREAM")
+        .query_async(conn)
+        .await;
+
+    let mut create_error = None;
+    match result {
+        Ok(_) => info!("Created consumer group: {}", consumer_group),
+        Err(e) => {
+            if e.to_string().contains("BUSYGROUP") {
+                info!("Consumer group already exists: {}", consumer_group);
+            } else {
+                warn!("Failed to create consumer group: {}", e);
+                create_error = Some(e.to_string());
+            }
+        }
+    }
+
+    // Confirm the group is really there, so a failed create can't leave us reading NOGROUP forever
+    let groups: RedisResult<StreamInfoGroupsReply> =
+        redis::cmd("XINFO").arg("GROUPS").arg(stream_name).query_async(conn).await;
+    let group_names = match groups {
+        Ok(reply) => reply.groups.into_iter().map(|g| g.name).collect::<Vec<_>>(),
+        // No such key: the stream (and so the group) does not exist
+        Err(e) if e.kind() == redis::ErrorKind::ResponseError => Vec::new(),
+        Err(e) => return Err(anyhow!("Failed to list consumer groups on {}: {}", stream_name, e)),
+    };
+    ensure_consumer_group(stream_name, consumer_group, &group_names, create_error.as_deref())
+}
+
+/// Fail unless `consumer_group` is among the groups listed for `stream_name`.
+pub fn ensure_consumer_group(
+    stream_name: &str,
+    consumer_group: &str,
+    existing_groups: &[String],
+    create_error: Option<&str>,
+) -> Result<()> {
+    if existing_groups.iter().any(|g| g == consumer_group) {
+        return Ok(());
+    }
+    Err(anyhow!(
+        "Consumer group {} does not exist on stream {} (XGROUP CREATE: {}); check REDIS_STREAM_NAME(S) and REDIS_CONSUMER_GROUP",
+        consumer_group,
+        stream_name,
+        create_error.unwrap_or("succeeded")
+    ))
+}
+
+/// XREADGROUP tuning, read from `REDIS_READ_COUNT`, `REDIS_READ_MAX_COUNT`,
+/// `REDIS_READ_BLOCK_MS` and `REDIS_READ_ADAPTIVE`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StreamReadConfig {
+    /// COUNT used at startup, and the floor when adaptive.
+    pub count: usize,
+    /// Ceiling for COUNT when adaptive.
+    pub max_count: usize,
+    pub block_ms: u64,
+    /// Grow COUNT while reads come back full, shrink it while they come back empty.
+    pub adaptive: bool,
+}
+
+impl Default for StreamReadConfig {
+    fn default() -> Self {
+        Self {
+            count: 10,
+            max_count: 100,
+            block_ms: 1000,
+            adaptive: false,
+        }
+    }
+}
+
+impl StreamReadConfig {
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        let parse = |name: &str, default: u64| {
+            std::env::var(name)
+                .ok()
+                .and_then(|v| v.parse::<u64>().ok())
+                .unwrap_or(default)
+        };
+        let count = parse("REDIS_READ_COUNT", defaults.count as u64).max(1) as usize;
+        let max_count = (parse("REDIS_READ_MAX_COUNT", defaults.max_count as u64) as usize).max(count);
+        let config = Self {
+            count,
+            max_count,
+            block_ms: parse("REDIS_READ_BLOCK_MS", defaults.block_ms),
+            adaptive: std::env::var("REDIS_READ_ADAPTIVE")
+                .map(|v| v == "1" || v.eq_ignore_ascii_case("true"))
+                .unwrap_or(defaults.adaptive),
+        };
+        info!("Stream reads: COUNT={} (max {}), BLOCK={}ms, adaptive={}",
+              config.count, config.max_count, config.block_ms, config.adaptive);
+        config
+    }
+
+    /// COUNT to use after a read that asked for `requested` and got `received` entries.
+    pub fn next_count(&self, current: usize, requested: usize, received: usize) -> usize {
+        if !self.adaptive {
+            return current;
+        }
+        if received > 0 && received >= requested {
+            // The stream is backed up: fetch bigger batches
+            (current * 2).min(self.max_count)
+        } else if received == 0 {
+            // Idle: fall back towards the configured COUNT
+            (current / 2).max(self.count)
+        } else {
+            current
+        }
+    }
+}
+
+/// Streams to consume: `REDIS_STREAM_NAMES` (comma separated, e.g. one per tenant or region),
+/// else `REDIS_STREAM_NAME` (default `verification_stream`). The first is the primary stream.
+pub fn stream_names_from_env() -> Vec<String> {
+    let names: Vec<String> = std::env::var("REDIS_STREAM_NAMES")
+        .unwrap_or_default()
+        .split(',')
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .map(str::to_string)
+        .collect();
+    if !names.is_empty() {
+        return names;
+    }
+    vec![std::env::var("REDIS_STREAM_NAME").unwrap_or_else(|_| "verification_stream".to_string())]
+}
+
+/// How long ago a stream entry was added, from the millisecond timestamp in its id (`<ms>-<seq>`).
+pub fn entry_age(id: &str, now_ms: u64) -> Option<std::time::Duration> {
+    let (millis, _) = id.split_once('-')?;
+    Some(std::time::Duration::from_millis(now_ms.saturating_sub(millis.parse().ok()?)))
+}
+
+/// An entry's fields as one JSON object, sealed into the DLQ and expired streams.
+fn fields_json(fields: &HashMap<String, Value>) -> String {
+    let payload: serde_json::Map<String, serde_json::Value> = fields
+        .iter()
+        .map(|(key, value)| {
+            let text = field_text(value).unwrap_or_else(|| format!("{:?}", value));
+            (key.clone(), serde_json::Value::String(text))
+        })
+        .collect();
+    serde_json::Value::Object(payload).to_string()
+}
+
+/// The entries claimed by an XAUTOCLAIM, from its `[cursor, entries, deleted ids]` reply.
+fn parse_autoclaim_reply(reply: &Value) -> RedisResult<Vec<StreamId>> {
+    match reply {
+        Value::Bulk(items) if items.len() >= 2 => Ok(redis::from_redis_value::<StreamClaimReply>(&items[1])?.ids),
+        _ => Err((redis::ErrorKind::TypeError, "unexpected XAUTOCLAIM reply").into()),
+    }
+}
+
+/// Redis stream consumer group source over one or more streams, read together in a single
+/// XREADGROUP. Reads and acks use separate connections so the fetch stage never waits behind
+/// an ack from the execute stage. Entries left pending (nacked, or read by a consumer that went
+/// away) are taken back with XAUTOCLAIM once idle for `reclaim_min_idle`, and handled again.
+pub struct RedisStreamSource {
+    redis: RedisConnector,
+    read_conn: Mutex<Option<Connection>>,
+    ack_conn: Mutex<Option<Connection>>,
+    streams: Vec<String>,
+    /// The streams, comma separated, for logs.
+    label: String,
+    consumer_group: String,
+    consumer_name: String,
+    /// Where entries failing [`VERIFICATION_REQUEST_FIELDS`] are moved.
+    dlq_stream: String,
+    field_names: StreamFieldNames,
+    read_config: StreamReadConfig,
+    current_count: AtomicUsize,
+    /// Entries older than this are acked without processing (`REDIS_MAX_ENTRY_AGE_SECS`).
+    max_entry_age: Option<std::time::Duration>,
+    /// Where expired entries are copied first, if anywhere (`REDIS_EXPIRED_STREAM`).
+    expired_stream: Option<String>,
+    /// Seals the entry fields copied to the DLQ and expired streams.
+    sealing_key: SealingKey,
+    /// How long an entry must sit pending before it is reclaimed (`REDIS_RECLAIM_MIN_IDLE_MS`,
+    /// default 300000; 0 disables). Longer than any one handling, so nothing in flight is taken.
+    reclaim_min_idle: Option<std::time::Duration>,
+    /// When pending entries were last reclaimed; they are looked for once per `reclaim_min_idle`.
+    last_reclaim: std::sync::Mutex<Option<std::time::Instant>>,
+}
+
+impl RedisStreamSource {
+    pub fn from_env(redis: RedisConnector, sealing_key: SealingKey) -> Result<Self> {
+        let read_config = StreamReadConfig::from_env();
+        metrics::set_gauge("redis_read_count", read_config.count as f64);
+        metrics::set_gauge("redis_read_block_ms", read_config.block_ms as f64);
+        Ok(Self {
+            redis,
+            current_count: AtomicUsize::new(read_config.count),
+            read_config,
+            read_conn: Mutex::new(None),
+            ack_conn: Mutex::new(None),
+            label: stream_names_from_env().join(","),
+            streams: stream_names_from_env(),
+            consumer_group: std::env::var("REDIS_CONSUMER_GROUP")
+                .unwrap_or_else(|_| "attestation_processors".to_string()),
+            consumer_name: std::env::var("REDIS_CONSUMER_NAME")
+                .unwrap_or_else(|_| "rust_processor_1".to_string()),
+            dlq_stream: std::env::var("VERIFICATION_DLQ_STREAM")
+                .unwrap_or_else(|_| "verification_dlq".to_string()),
+            field_names: StreamFieldNames::from_env()?,
+            max_entry_age: std::env::var("REDIS_MAX_ENTRY_AGE_SECS")
+                .ok()
+                .and_then(|v| v.parse::<u64>().ok())
+                .filter(|secs| *secs > 0)
+                .map(std::time::Duration::from_secs),
+            expired_stream: std::env::var("REDIS_EXPIRED_STREAM").ok().filter(|v| !v.trim().is_empty()),
+            sealing_key,
+            reclaim_min_idle: Some(
+                std::env::var("REDIS_RECLAIM_MIN_IDLE_MS")
+                    .ok()
+                    .and_then(|v| v.parse::<u64>().ok())
+                    .unwrap_or(300_000),
+            )
+            .filter(|ms| *ms > 0)
+            .map(std::time::Duration::from_millis),
+            last_reclaim: std::sync::Mutex::new(None),
+        })
+    }
+
+    pub async fn init(&self) -> Result<()> {
+        info!("   Stream: {}", self.label);
+        info!("   Consumer Group: {}", self.consumer_group);
+        info!("   Consumer Name: {}", self.consumer_name);
+
+        // Create consumer group if it doesn't exist
+        let mut guard = self.connection(&self.read_conn).await?;
+        let conn = guard.as_mut().expect("connection was just established");
+        let result = self.create_consumer_groups(conn).await;
+        if result.is_err() {
+            *guard = None;
+        }
+        result
+    }
+
+    /// The consumer group on every stream.
+    async fn create_consumer_groups(&self, conn: &mut Connection) -> Result<()> {
+        for stream in &self.streams {
+            with_timeout(
+                "XGROUP",
+                self.redis.timeouts().command,
+                create_consumer_group(conn, stream, &self.consumer_group),
+            )
+            .await?;
+        }
+        Ok(())
+    }
+
+    /// The stream `message` is acked on; messages without one belong to the primary stream.
+    fn stream_of<'a>(&'a self, message: &'a VerificationMessage) -> &'a str {
+        message.stream.as_deref().unwrap_or(&self.streams[0])
+    }
+
+    /// Lock a connection slot, reconnecting if a previous error dropped it.
+    async fn connection<'a>(&self, slot: &'a Mutex<Option<Connection>>) -> Result<MutexGuard<'a, Option<Connection>>> {
+        let mut guard = slot.lock().await;
+        if guard.is_none() {
+            *guard = Some(self.redis.connect().await?);
+        }
+        Ok(guard)
+    }
+
+    fn read_command(&self, count: usize) -> redis::Cmd {
+        let mut cmd = redis::cmd("XREADGROUP");
+        cmd.arg("GROUP")
+            .arg(&self.consumer_group)
+            .arg(&self.consumer_name)
+            .arg("COUNT")
+            .arg(count)
+            .arg("BLOCK")
+            .arg(self.read_config.block_ms)
+            .arg("STREAMS")
+            .arg(&self.streams)
+            .arg(vec![">"; self.streams.len()]); // Only new messages
+        cmd
+    }
+
+    /// XAUTOCLAIM up to `count` entries of `stream` pending for at least `min_idle`, for this consumer.
+    fn reclaim_command(&self, stream: &str, min_idle: std::time::Duration, count: usize) -> redis::Cmd {
+        let mut cmd = redis::cmd("XAUTOCLAIM");
+        cmd.arg(stream)
+            .arg(&self.consumer_group)
+            .arg(&self.consumer_name)
+            .arg(min_idle.as_millis() as u64)
+            .arg("0-0")
+            .arg("COUNT")
+            .arg(count);
+        cmd
+    }
+
+    /// Pending entries idle past `reclaim_min_idle` on every stream, claimed for this consumer,
+    /// if it is time to look for them.
+    async fn reclaim_entries(&self, conn: &mut Connection, count: usize) -> Result<Vec<(String, StreamId)>> {
+        let Some(min_idle) = self.reclaim_min_idle else {
+            return Ok(Vec::new());
+        };
+        {
+            let mut last = self.last_reclaim.lock().unwrap();
+            if last.is_some_and(|at| at.elapsed() < min_idle) {
+                return Ok(Vec::new());
+            }
+            *last = Some(std::time::Instant::now());
+        }
+        let mut reclaimed = Vec::new();
+        for stream in &self.streams {
+            let reply = with_timeout(
+                "XAUTOCLAIM",
+                self.redis.timeouts().command,
+                self.reclaim_command(stream, min_idle, count).query_async::<_, Value>(conn),
+            )
+            .await?;
+            let entries = parse_autoclaim_reply(&reply)?;
+            reclaimed.extend(entries.into_iter().map(|entry| (stream.clone(), entry)));
+        }
+        if !reclaimed.is_empty() {
+            metrics::increment_by("stream_entries_reclaimed_total", reclaimed.len() as u64);
+            info!("♻️ Reclaimed {} stream entries pending for over {:?}", reclaimed.len(), min_idle);
+        }
+        Ok(reclaimed)
+    }
+
+    /// XADD to the DLQ stream, with the entry's fields as a sealed JSON object, and XACK the entry.
+    fn dead_letter_command(
+        &self,
+        stream: &str,
+        id: &str,
+        fields: &HashMap<String, Value>,
+        error: &anyhow::Error,
+    ) -> redis::Pipeline {
+        let entry = dead_letter_entry(&self.dlq_stream, id, stream, &self.sealing_key, &fields_json(fields), error);
+        let mut pipe = redis::pipe();
+        pipe.atomic()
+            .add_command(entry)
+            .ignore()
+            .cmd("XACK")
+            .arg(stream)
+            .arg(&self.consumer_group)
+            .arg(id)
+            .ignore();
+        pipe
+    }
+
+    /// Move an entry that failed schema validation to the DLQ. If that fails it stays pending.
+    async fn dead_letter_entry(
+        &self,
+        conn: &mut Connection,
+        stream: &str,
+        id: &str,
+        fields: &HashMap<String, Value>,
+        error: &anyhow::Error,
+    ) {
+        let pipe = self.dead_letter_command(stream, id, fields, error);
+        match with_timeout("XADD", self.redis.timeouts().command, pipe.query_async::<_, ()>(conn)).await {
+            Ok(()) => {
+                metrics::increment("verification_dlq_total");
+                warn!("☠️  Stream entry {} from {} moved to {}: {}", id, stream, self.dlq_stream, error);
+            }
+            Err(e) => warn!("Failed to dead-letter stream entry {} from {} ({}): {}", id, stream, error, e),
+        }
+    }
+
+    /// Ack an entry past `max_entry_age` without processing it, copying it to the expired stream
+    /// if there is one. If that fails it stays pending.
+    async fn expire_entry(&self, conn: &mut Connection, stream: &str, id: &str, fields: &HashMap<String, Value>) {
+        let mut pipe = redis::pipe();
+        pipe.atomic();
+        if let Some(expired_stream) = &self.expired_stream {
+            pipe.cmd("XADD")
+                .arg(expired_stream)
+                .arg("*")
+                .arg("message_id")
+                .arg(id)
+                .arg("stream")
+                .arg(stream)
+                .arg("payload")
+                .arg(seal_text(&self.sealing_key, &fields_json(fields)))
+                .arg("expired_at")
+                .arg(chrono::Utc::now().to_rfc3339())
+                .ignore();
+        }
+        pipe.cmd("XACK").arg(stream).arg(&self.consumer_group).arg(id).ignore();
+        match with_timeout("XACK", self.redis.timeouts().command, pipe.query_async::<_, ()>(conn)).await {
+            Ok(()) => {
+                metrics::increment("stream_entries_expired_total");
+                warn!("⌛ Stream entry {} from {} is too old, discarded unprocessed", id, stream);
+            }
+            Err(e) => warn!("Failed to discard expired stream entry {} from {}: {}", id, stream, e),
+        }
+    }
+
+    /// Turn read entries into messages, expiring the stale ones and dead-lettering the invalid ones.
+    async fn admit_entries(&self, conn: &mut Connection, entries: Vec<(String, StreamId)>) -> Vec<VerificationMessage> {
+        let mut messages = Vec::new();
+        let now_ms = chrono::Utc::now().timestamp_millis() as u64;
+        for (stream, stream_id) in entries {
+            // Left over from a long outage: the user has likely moved on
+            let age = entry_age(&stream_id.id, now_ms);
+            if self.max_entry_age.is_some_and(|max_age| age.is_some_and(|age| age > max_age)) {
+                self.expire_entry(conn, &stream, &stream_id.id, &stream_id.map).await;
+                continue;
+            }
+            match parse_stream_fields(&stream_id.id, &self.field_names.to_logical(&stream_id.map)) {
+                Ok(message) => messages.push(VerificationMessage { stream: Some(stream), ..message }),
+                // Structurally invalid: no upstream call is spent on it
+                Err(e) if is_invalid_message(&e) => {
+                    self.dead_letter_entry(conn, &stream, &stream_id.id, &stream_id.map, &e).await
+                }
+                // Left pending so it shows up in XPENDING for inspection
+                Err(e) => warn!("Skipping malformed stream entry {} from {}: {}", stream_id.id, stream, e),
+            }
+        }
+        messages
+    }
+
+    async fn read_entries(&self, conn: &mut Connection, max: usize) -> Result<Vec<VerificationMessage>> {
+        let current = self.current_count.load(Ordering::Relaxed);
+        let count = current.min(max).max(1);
+
+        // Entries left pending go ahead of new ones
+        let reclaimed = self.reclaim_entries(conn, count).await?;
+        if !reclaimed.is_empty() {
+            return Ok(self.admit_entries(conn, reclaimed).await);
+        }
+
+        // Read messages from the stream
+        let deadline = self.redis.timeouts().blocking(self.read_config.block_ms);
+        let result = with_timeout(
+            "XREADGROUP",
+            deadline,
+            self.read_command(count).query_async::<_, StreamReadReply>(conn),
+        )
+        .await;
+
+        match result {
+            Ok(reply) => {
+                let entries: Vec<_> = reply
+                    .keys
+                    .into_iter()
+                    .flat_map(|stream_key| {
+                        let stream = stream_key.key;
+                        stream_key.ids.into_iter().map(move |entry| (stream.clone(), entry))
+                    })
+                    .collect();
+
+                // Only adapt on reads the worker queue didn't cap
+                if count == current {
+                    let next = self.read_config.next_count(current, count, entries.len());
+                    if next != current {
+                        self.current_count.store(next, Ordering::Relaxed);
+                        metrics::set_gauge("redis_read_count", next as f64);
+                    }
+                }
+
+                Ok(self.admit_entries(conn, entries).await)
+            }
+            Err(e) => {
+                if e.to_string().contains("NOGROUP") {
+                    warn!("Consumer group doesn't exist, recreating...");
+                    self.create_consumer_groups(conn).await?;
+                    Ok(Vec::new())
+                } else {
+                    Err(anyhow!("Redis stream read error: {}", e))
+                }
+            }
+        }
+    }
+}
+
+impl MessageSource for RedisStreamSource {
+    fn name(&self) -> &str {
+        &self.label
+    }
+
+    async fn next_batch(&self, max: usize) -> Result<Vec<VerificationMessage>> {
+        let mut guard = self.connection(&self.read_conn).await?;
+        let conn = guard.as_mut().expect("connection was just established");
+        let result = self.read_entries(conn, max).await;
+        if result.is_err() {
+            // Reconnect on the next read
+            *guard = None;
+        }
+        result
+    }
+
+    async fn ack(&self, message: &VerificationMessage) -> Result<()> {
+        self.ack_many(std::slice::from_ref(message)).await
+    }
+
+    async fn ack_many(&self, messages: &[VerificationMessage]) -> Result<()> {
+        if messages.is_empty() {
+            return Ok(());
+        }
+        let mut guard = self.connection(&self.ack_conn).await?;
+        let conn = guard.as_mut().expect("connection was just established");
+        // One XACK per stream, in a single round trip
+        let mut by_stream: std::collections::BTreeMap<&str, Vec<&str>> = std::collections::BTreeMap::new();
+        for message in messages {
+            by_stream.entry(self.stream_of(message)).or_default().push(&message.id);
+        }
+        let mut pipe = redis::pipe();
+        for (stream, ids) in &by_stream {
+            pipe.cmd("XACK").arg(*stream).arg(&self.consumer_group).arg(ids).ignore();
+        }
+        let result = with_timeout("XACK", self.redis.timeouts().command, pipe.query_async::<_, ()>(conn)).await;
+        if let Err(e) = result {
+            *guard = None;
+            let ids: Vec<&str> = messages.iter().map(|m| m.id.as_str()).collect();
+            return Err(anyhow!("XACK of {} failed: {}", ids.join(","), e));
+        }
+        Ok(())
+    }
+
+    async fn nack(&self, message: &VerificationMessage, _reason: &str) -> Result<()> {
+        // Don't acknowledge failed messages - they stay pending and are reclaimed once idle
+        warn!("Leaving message {} unacked on {}", message.id, self.stream_of(message));
+        Ok(())
+    }
+}
+
+/// XADD of a message that can never be processed to `dlq_stream`. Every DLQ entry has the same
+/// fields: `message_id`, `stream`, `payload` and `error` (both sealed, as either can carry the
+/// document's contents) and `failed_at`.
+pub fn dead_letter_entry(
+    dlq_stream: &str,
+    message_id: &str,
+    stream: &str,
+    sealing_key: &SealingKey,
+    payload: &str,
+    error: &anyhow::Error,
+) -> redis::Cmd {
+    let mut xadd = redis::cmd("XADD");
+    xadd.arg(dlq_stream)
+        .arg("*")
+        .arg("message_id")
+        .arg(message_id)
+        .arg("stream")
+        .arg(stream)
+        .arg("payload")
+        .arg(seal_text(sealing_key, payload))
+        .arg("error")
+        .arg(seal_text(sealing_key, &error.to_string()))
+        .arg("failed_at")
+        .arg(chrono::Utc::now().to_rfc3339());
+    xadd
+}
+
+/// Source with nothing to fetch, recording what it acks and nacks.
+#[cfg(test)]
+#[derive(Default)]
+pub(crate) struct RecordingSource {
+    pub acked: std::sync::Mutex<Vec<String>>,
+    pub nacked: std::sync::Mutex<Vec<String>>,
+    pub ack_calls: std::sync::Mutex<usize>,
+}
+
+#[cfg(test)]
+impl MessageSource for RecordingSource {
+    fn name(&self) -> &str {
+        "recording"
+    }
+
+    async fn next_batch(&self, _max: usize) -> Result<Vec<VerificationMessage>> {
+        Ok(Vec::new())
+    }
+
+    async fn ack(&self, message: &VerificationMessage) -> Result<()> {
+        self.ack_many(std::slice::from_ref(message)).await
+    }
+
+    async fn ack_many(&self, messages: &[VerificationMessage]) -> Result<()> {
+        *self.ack_calls.lock().unwrap() += 1;
+        self.acked.lock().unwrap().extend(messages.iter().map(|m| m.id.clone()));
+        Ok(())
+    }
+
+    async fn nack(&self, message: &VerificationMessage, _reason: &str) -> Result<()> {
+        self.nacked.lock().unwrap().push(message.id.clone());
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fake_redis::{self, bulk};
+    use crate::signing::{EnclaveSigner, SigningError};
+    use crate::redis_timeout::{is_redis_timeout, RedisTimeouts};
+    use crate::verification_processor::sign_verification;
+    use fastcrypto::ed25519::{Ed25519KeyPair, Ed25519Signature};
+    use fastcrypto::traits::KeyPair;
+    use std::sync::{Arc, Mutex as StdMutex};
+    use tokio::time::Duration;
+
+    /// Stand-in for the processor: accepts messages for one wallet and records what it saw.
+    struct RecordingHandler {
+        seen: Vec<(String, String)>,
+    }
+
+    impl MessageHandler for RecordingHandler {
+        async fn handle(&mut self, message: &VerificationMessage) -> Result<()> {
+            let wallet = match &message.payload {
+                MessagePayload::Request(request) => request.user_wallet.clone(),
+                MessagePayload::Verified(result) => result.user_wallet.clone(),
+            };
+            self.seen.push((message.id.clone(), wallet.clone()));
+            if wallet.ends_with("bad") {
+                return Err(anyhow!("rejected"));
+            }
+            Ok(())
+        }
+    }
+
+    /// Signs every verified result it handles, like the processor does before the Sui call.
+    struct SigningHandler<S: EnclaveSigner> {
+        signer: S,
+    }
+
+    impl<S: EnclaveSigner + Send> MessageHandler for SigningHandler<S> {
+        async fn handle(&mut self, message: &VerificationMessage) -> Result<()> {
+            if let MessagePayload::Verified(result) = &message.payload {
+                sign_verification(&self.signer, result)?;
+            }
+            Ok(())
+        }
+    }
+
+    struct FailingSigner;
+
+    impl EnclaveSigner for FailingSigner {
+        fn try_sign(&self, _msg: &[u8]) -> Result<Ed25519Signature, SigningError> {
+            Err(SigningError { reason: "key unavailable".to_string() })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_signing_failure_leaves_message_unacked() {
+        let message = VerificationMessage {
+            id: "1700000000000-0".to_string(),
+            payload: MessagePayload::Verified(VerifiedResult {
+                user_wallet: "0xabc".to_string(),
+                did_id: 0,
+                result: "verified".to_string(),
+                evidence_hash: "ab".repeat(32),
+                verified_at: "2025-01-01T00:00:00+00:00".to_string(),
+                rejection_reason: None,
+            }),
+            stream: None,
+        };
+
+        let source = RecordingSource::default();
+        let mut failing = SigningHandler { signer: FailingSigner };
+        assert!(dispatch(&source, &mut failing, &message).await.is_ok());
+        assert!(source.acked.lock().unwrap().is_empty());
+        assert_eq!(*source.nacked.lock().unwrap(), vec!["1700000000000-0".to_string()]);
+
+        let source = RecordingSource::default();
+        let mut working = SigningHandler { signer: Ed25519KeyPair::generate(&mut rand::thread_rng()) };
+        dispatch(&source, &mut working, &message).await.unwrap();
+        assert_eq!(*source.acked.lock().unwrap(), vec!["1700000000000-0".to_string()]);
+    }
+
+    /// A full Sui address ending in `short`'s digits, e.g. `0x00…0abc` for `0xabc`.
+    fn address(short: &str) -> String {
+        format!("0x{:0>64}", short.trim_start_matches("0x"))
+    }
+
+    fn stream_fields(wallet: &str) -> HashMap<String, Value> {
+        let wallet = address(wallet);
+        [
+            ("user_wallet", wallet.as_str()),
+            ("did_id", "0"),
+            ("verification_type", "pan"),
+            ("document_data", "{}"),
+            ("timestamp", "2025-01-01T00:00:00Z"),
+            ("status", "pending"),
+        ]
+        .iter()
+        .map(|(k, v)| (k.to_string(), Value::Data(v.as_bytes().to_vec())))
+        .collect()
+    }
+
+    #[tokio::test]
+    async fn test_redis_and_kafka_messages_feed_the_same_pipeline() {
+        let redis_message = parse_stream_fields("1700000000000-0", &stream_fields("0xabc")).unwrap();
+        let kafka_message = parse_record_payload(
+            42,
+            r#"{"user_wallet":"0xdef","did_id":"1","result":"verified","evidence_hash":"ab","verified_at":"2025-01-01T00:00:00"}"#,
+        )
+        .unwrap();
+        let failing_message = parse_stream_fields("1700000000001-0", &stream_fields("0xbad")).unwrap();
+
+        let source = RecordingSource::default();
+        let mut handler = RecordingHandler { seen: Vec::new() };
+        for message in [&redis_message, &kafka_message, &failing_message] {
+            dispatch(&source, &mut handler, message).await.unwrap();
+        }
+
+        assert_eq!(
+            handler.seen,
+            vec![
+                ("1700000000000-0".to_string(), address("0xabc")),
+                ("42".to_string(), "0xdef".to_string()),
+                ("1700000000001-0".to_string(), address("0xbad")),
+            ]
+        );
+        assert_eq!(*source.acked.lock().unwrap(), vec!["1700000000000-0", "42"]);
+        assert_eq!(*source.nacked.lock().unwrap(), vec!["1700000000001-0"]);
+    }
+
+    /// Panics on wallets ending in `bad`; with a DLQ, records what it quarantined.
+    struct PanickingHandler {
+        handled: Vec<String>,
+        quarantined: Option<Vec<(String, String)>>,
+    }
+
+    impl MessageHandler for PanickingHandler {
+        async fn handle(&mut self, message: &VerificationMessage) -> Result<()> {
+            if let MessagePayload::Request(request) = &message.payload {
+                if request.user_wallet.ends_with("bad") {
+                    panic!("unexpected payload for {}", message.id);
+                }
+            }
+            self.handled.push(message.id.clone());
+            Ok(())
+        }
+
+        async fn quarantine(&mut self, message: &VerificationMessage, reason: &str) -> Result<()> {
+            let quarantined = self.quarantined.as_mut().ok_or_else(|| anyhow!("no DLQ"))?;
+            quarantined.push((message.id.clone(), reason.to_string()));
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_panicking_message_is_quarantined_and_the_loop_continues() {
+        let poison = parse_stream_fields("1700000000000-0", &stream_fields("0xbad")).unwrap();
+        let next = parse_stream_fields("1700000000001-0", &stream_fields("0xabc")).unwrap();
+
+        let source = RecordingSource::default();
+        let mut handler = PanickingHandler { handled: Vec::new(), quarantined: Some(Vec::new()) };
+        let mut batch = AckBatch::new(10);
+        dispatch(&source, &mut handler, &poison).await.unwrap();
+        dispatch_batched(&source, &mut handler, &poison, &mut batch).await.unwrap();
+        dispatch(&source, &mut handler, &next).await.unwrap();
+        batch.flush(&source).await.unwrap();
+
+        assert_eq!(handler.handled, vec!["1700000000001-0"]);
+        let quarantined = handler.quarantined.unwrap();
+        assert_eq!(quarantined.len(), 2);
+        assert_eq!(quarantined[0].1, "handler panicked: unexpected payload for 1700000000000-0");
+        assert_eq!(*source.acked.lock().unwrap(), vec!["1700000000000-0", "1700000000001-0", "1700000000000-0"]);
+        assert!(metrics::counter("message_handler_panics_total") >= 2);
+
+        // Nowhere to put it: nacked, so it stays pending rather than being lost
+        let source = RecordingSource::default();
+        let mut handler = PanickingHandler { handled: Vec::new(), quarantined: None };
+        dispatch(&source, &mut handler, &poison).await.unwrap();
+        assert!(source.acked.lock().unwrap().is_empty());
+        assert_eq!(*source.nacked.lock().unwrap(), vec!["1700000000000-0"]);
+    }
+
+    #[test]
+    fn test_release_profile_unwinds_so_panics_are_caught() {
+        // Tests always build with unwinding, whatever the release profile says; check what ships
+        let manifest = include_str!("../Cargo.toml");
+        let release = manifest.split("[profile.release]").nth(1).expect("Cargo.toml has a release profile");
+        let release = release.split("\n[").next().unwrap();
+        let panic = release.lines().find_map(|line| line.trim().strip_prefix("panic")).map(|v| v.trim_start_matches([' ', '=']).trim());
+        assert!(matches!(panic, None | Some("\"unwind\"")), "release profile sets panic = {:?}", panic);
+    }
+
+    #[test]
+    fn test_did_id_accepts_string_or_integer() {
+        let record = |did_id: &str| {
+            serde_json::from_str::<VerifiedResult>(&format!(
+                r#"{{"user_wallet":"0xabc","did_id":{},"result":"verified","evidence_hash":"ab","verified_at":"2025-01-01T00:00:00Z"}}"#,
+                did_id
+            ))
+        };
+        for did_id in [r#""1""#, r#"" 1 ""#, "1"] {
+            assert_eq!(record(did_id).unwrap().did_id, 1, "{}", did_id);
+        }
+        for bad in [r#""abc""#, r#""256""#, "256", "-1"] {
+            assert!(record(bad).is_err(), "{}", bad);
+        }
+        // A Redis stream entry's did_id is read by the same rule
+        assert_eq!(parse_did_id(" 1 "), Ok(record(r#"" 1 ""#).unwrap().did_id));
+    }
+
+    #[test]
+    fn test_timestamp_formats_parse_to_the_same_instant() {
+        let expected = 1_735_689_600_000; // 2025-01-01T00:00:00Z
+        for timestamp in [
+            "2025-01-01T00:00:00Z",
+            "2025-01-01T05:30:00+05:30",
+            "2025-01-01T00:00:00",
+            "2025-01-01T00:00:00.000",
+            "2025-01-01 00:00:00",
+            " 1735689600 ",
+            "1735689600000",
+        ] {
+            assert_eq!(parse_timestamp_to_ms(timestamp).unwrap(), expected, "{}", timestamp);
+        }
+        assert_eq!(parse_timestamp_to_ms("2025-01-01T00:00:00.250Z").unwrap(), expected + 250);
+        for bad in ["", "yesterday", "2025-13-01T00:00:00Z", "01/01/2025", "1969-12-31T23:59:59Z", "-5"] {
+            assert!(parse_timestamp_to_ms(bad).is_err(), "{}", bad);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_crash_before_ack_flush_leaves_only_unacked_messages_pending() {
+        let wallets = ["0x1", "0x2", "0xbad", "0x4", "0x5", "0x6"];
+        let messages: Vec<_> = wallets
+            .iter()
+            .enumerate()
+            .map(|(i, wallet)| parse_stream_fields(&format!("{}-0", i + 1), &stream_fields(wallet)).unwrap())
+            .collect();
+
+        let source = RecordingSource::default();
+        let mut handler = RecordingHandler { seen: Vec::new() };
+        let mut batch = AckBatch::new(3);
+        for message in &messages {
+            dispatch_batched(&source, &mut handler, message, &mut batch).await.unwrap();
+        }
+        // Crash: the batch holding 5-0 and 6-0 is never flushed
+        drop(batch);
+
+        // The three committed messages before the crash went out in a single XACK
+        assert_eq!(*source.acked.lock().unwrap(), vec!["1-0", "2-0", "4-0"]);
+        assert_eq!(*source.ack_calls.lock().unwrap(), 1);
+        let acked = source.acked.lock().unwrap().clone();
+        let pending: Vec<&str> = messages.iter().map(|m| m.id.as_str()).filter(|id| !acked.iter().any(|a| a == id)).collect();
+        assert_eq!(pending, vec!["3-0", "5-0", "6-0"]);
+        assert_eq!(*source.nacked.lock().unwrap(), vec!["3-0"]);
+    }
+
+    /// Offset-addressed source like the Kafka one: a failed record rewinds the offset to be
+    /// fetched again until the tracker gives up, then goes to the DLQ.
+    struct ReplayingSource {
+        records: Vec<VerificationMessage>,
+        offset: StdMutex<usize>,
+        attempts: AttemptTracker,
+        dlq: StdMutex<Vec<String>>,
+    }
+
+    impl MessageSource for ReplayingSource {
+        fn name(&self) -> &str {
+            "replaying"
+        }
+
+        async fn next_batch(&self, max: usize) -> Result<Vec<VerificationMessage>> {
+            let mut offset = self.offset.lock().unwrap();
+            let batch: Vec<_> = self.records.iter().skip(*offset).take(max).cloned().collect();
+            *offset += batch.len();
+            Ok(batch)
+        }
+
+        async fn ack(&self, message: &VerificationMessage) -> Result<()> {
+            self.attempts.clear(&message.id);
+            Ok(())
+        }
+
+        async fn nack(&self, message: &VerificationMessage, _reason: &str) -> Result<()> {
+            match self.attempts.record_failure(&message.id) {
+                FailureAction::Retry { .. } => *self.offset.lock().unwrap() = message.id.parse().unwrap(),
+                FailureAction::GiveUp { .. } => self.dlq.lock().unwrap().push(message.id.clone()),
+            }
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_poison_record_is_skipped_after_max_attempts() {
+        let record = |offset: i64, wallet: &str| {
+            let payload = format!(
+                r#"{{"user_wallet":"{}","did_id":"1","result":"verified","evidence_hash":"ab","verified_at":"2025-01-01T00:00:00"}}"#,
+                wallet
+            );
+            parse_record_payload(offset, &payload).unwrap()
+        };
+        let source = ReplayingSource {
+            records: vec![record(0, "0xbad"), record(1, "0xgood")],
+            offset: StdMutex::new(0),
+            attempts: AttemptTracker::new(3),
+            dlq: StdMutex::new(Vec::new()),
+        };
+        let mut handler = RecordingHandler { seen: Vec::new() };
+
+        for _ in 0..4 {
+            for message in source.next_batch(1).await.unwrap() {
+                dispatch(&source, &mut handler, &message).await.unwrap();
+            }
+        }
+
+        let seen: Vec<&str> = handler.seen.iter().map(|(id, _)| id.as_str()).collect();
+        assert_eq!(seen, vec!["0", "0", "0", "1"]);
+        assert_eq!(*source.dlq.lock().unwrap(), vec!["0"]);
+        assert_eq!(*source.offset.lock().unwrap(), 2);
+    }
+
+    fn test_source(read_config: StreamReadConfig) -> RedisStreamSource {
+        RedisStreamSource {
+            redis: RedisConnector::new("redis://localhost:6379", "default", "secret").unwrap(),
+            read_conn: Mutex::new(None),
+            ack_conn: Mutex::new(None),
+            streams: vec!["verification_stream".to_string()],
+            label: "verification_stream".to_string(),
+            consumer_group: "attestation_processors".to_string(),
+            consumer_name: "rust_processor_1".to_string(),
+            dlq_stream: "verification_dlq".to_string(),
+            field_names: StreamFieldNames::default(),
+            current_count: AtomicUsize::new(read_config.count),
+            read_config,
+            max_entry_age: None,
+            expired_stream: None,
+            sealing_key: SealingKey::new([1u8; 32]),
+            reclaim_min_idle: None,
+            last_reclaim: std::sync::Mutex::new(None),
+        }
+    }
+
+    #[test]
+    fn test_read_command_uses_configured_count_and_block() {
+        let source = test_source(StreamReadConfig {
+            count: 25,
+            block_ms: 250,
+            ..StreamReadConfig::default()
+        });
+        let packed = String::from_utf8(source.read_command(25).get_packed_command()).unwrap();
+        assert!(packed.contains("COUNT\r\n$2\r\n25\r\n"));
+        assert!(packed.contains("BLOCK\r\n$3\r\n250\r\n"));
+    }
+
+    #[test]
+    fn test_idle_pending_entries_are_reclaimed() {
+        let source = test_source(StreamReadConfig::default());
+        let packed = String::from_utf8(
+            source.reclaim_command("verification_stream", Duration::from_secs(300), 10).get_packed_command(),
+        )
+        .unwrap();
+        assert!(packed.contains("XAUTOCLAIM\r\n$19\r\nverification_stream\r\n$22\r\nattestation_processors\r\n$16\r\nrust_processor_1\r\n$6\r\n300000\r\n$3\r\n0-0\r\n$5\r\nCOUNT\r\n$2\r\n10\r\n"), "{}", packed);
+
+        // `[cursor, [[id, [field, value, ...]]], [deleted]]`
+        let data = |text: &str| Value::Data(text.as_bytes().to_vec());
+        let reply = Value::Bulk(vec![
+            data("0-0"),
+            Value::Bulk(vec![Value::Bulk(vec![data("1-0"), Value::Bulk(vec![data("user_wallet"), data("0xa")])])]),
+            Value::Bulk(vec![]),
+        ]);
+        let entries = parse_autoclaim_reply(&reply).unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].id, "1-0");
+        assert_eq!(entries[0].map.get("user_wallet"), Some(&data("0xa")));
+        assert!(parse_autoclaim_reply(&Value::Nil).is_err());
+    }
+
+    /// A Redis that answers everything but XREADGROUP with `+OK`, and never answers that.
+    async fn hanging_redis() -> String {
+        fake_redis::serve(|args| (args[0] != "XREADGROUP").then(|| "+OK\r\n".to_string())).await
+    }
+
+    #[tokio::test]
+    async fn test_hung_read_is_abandoned_and_the_connection_dropped() {
+        let timeouts = RedisTimeouts {
+            connect: Duration::from_millis(500),
+            command: Duration::from_millis(200),
+        };
+        let mut source = test_source(StreamReadConfig {
+            block_ms: 100,
+            ..StreamReadConfig::default()
+        });
+        source.redis = RedisConnector::new(&hanging_redis().await, "default", "secret")
+            .unwrap()
+            .with_timeouts(timeouts);
+
+        let started = tokio::time::Instant::now();
+        let err = source.next_batch(10).await.unwrap_err();
+        assert!(err.to_string().contains("timed out"), "{}", err);
+        assert!(started.elapsed() < Duration::from_secs(2));
+        // The hung connection is gone, so the next read reconnects
+        assert!(source.read_conn.lock().await.is_none());
+
+        // A server that never answers AUTH fails the connect instead of hanging it
+        let silent = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let silent_url = format!("redis://{}", silent.local_addr().unwrap());
+        let connector = RedisConnector::new(&silent_url, "default", "secret").unwrap().with_timeouts(timeouts);
+        let err = connector.connect().await.err().expect("connect should time out");
+        assert!(is_redis_timeout(&err), "{}", err);
+    }
+
+    /// A Redis whose first XREADGROUP returns entry `ids.0` on `tenant_a` and `ids.1` on `tenant_b`
+    /// (and nothing after), recording every XACK and XADD it receives.
+    async fn two_stream_redis(ids: (&str, &str)) -> (String, Arc<StdMutex<Vec<String>>>) {
+        let entry = |stream: &str, id: &str, wallet: &str| {
+            let fields: Vec<String> = stream_fields(wallet)
+                .into_iter()
+                .flat_map(|(key, value)| match value {
+                    Value::Data(bytes) => [bulk(&key), bulk(&String::from_utf8(bytes).unwrap())],
+                    other => panic!("unexpected field value {:?}", other),
+                })
+                .collect();
+            format!("*2\r\n{}*1\r\n*2\r\n{}*{}\r\n{}", bulk(stream), bulk(id), fields.len(), fields.concat())
+        };
+        let reply = format!("*2\r\n{}{}", entry("tenant_a", ids.0, "0xa"), entry("tenant_b", ids.1, "0xb"));
+
+        let writes = Arc::new(StdMutex::new(Vec::new()));
+        let recorded = writes.clone();
+        let served = std::sync::atomic::AtomicBool::new(false);
+        let url = fake_redis::serve(move |args| {
+            Some(match args[0].as_str() {
+                "XREADGROUP" if served.swap(true, Ordering::SeqCst) => "*-1\r\n".to_string(),
+                "XREADGROUP" => reply.clone(),
+                "XACK" | "XADD" => {
+                    recorded.lock().unwrap().push(args.join(" "));
+                    ":1\r\n".to_string()
+                }
+                _ => "+OK\r\n".to_string(),
+            })
+        })
+        .await;
+        (url, writes)
+    }
+
+    #[tokio::test]
+    async fn test_messages_from_every_configured_stream_are_processed() {
+        let (url, acks) = two_stream_redis(("1-0", "1-0")).await;
+        let mut source = test_source(StreamReadConfig::default());
+        source.streams = vec!["tenant_a".to_string(), "tenant_b".to_string()];
+        source.redis = RedisConnector::new(&url, "default", "secret").unwrap();
+
+        // One read across both streams
+        let packed = String::from_utf8(source.read_command(10).get_packed_command()).unwrap();
+        assert!(packed.contains("STREAMS\r\n$8\r\ntenant_a\r\n$8\r\ntenant_b\r\n$1\r\n>\r\n$1\r\n>\r\n"));
+
+        let messages = source.next_batch(10).await.unwrap();
+        let streams: Vec<_> = messages.iter().map(|m| m.stream.as_deref().unwrap()).collect();
+        assert_eq!(streams, vec!["tenant_a", "tenant_b"]);
+        // Same entry id on both streams, still told apart
+        assert_eq!(messages[0].key(), "tenant_a/1-0");
+        assert_eq!(messages[1].key(), "tenant_b/1-0");
+
+        let mut handler = RecordingHandler { seen: Vec::new() };
+        let mut batch = AckBatch::new(10);
+        for message in &messages {
+            dispatch_batched(&source, &mut handler, message, &mut batch).await.unwrap();
+        }
+        batch.flush(&source).await.unwrap();
+        assert_eq!(
+            handler.seen,
+            vec![("1-0".to_string(), address("0xa")), ("1-0".to_string(), address("0xb"))]
+        );
+
+        // Each entry is acked on the stream it came from
+        let acks = acks.lock().unwrap().join("\n");
+        assert!(acks.contains("XACK tenant_a attestation_processors 1-0"), "{}", acks);
+        assert!(acks.contains("XACK tenant_b attestation_processors 1-0"), "{}", acks);
+    }
+
+    #[tokio::test]
+    async fn test_entry_older_than_the_max_age_is_discarded_unprocessed() {
+        let fresh = format!("{}-0", chrono::Utc::now().timestamp_millis() - 5_000);
+        let (url, acks) = two_stream_redis(("1-0", &fresh)).await;
+        let mut source = test_source(StreamReadConfig::default());
+        source.streams = vec!["tenant_a".to_string(), "tenant_b".to_string()];
+        source.redis = RedisConnector::new(&url, "default", "secret").unwrap();
+        source.max_entry_age = Some(Duration::from_secs(3600));
+        source.expired_stream = Some("verification_expired".to_string());
+
+        // Only the fresh entry is handed on
+        let messages = source.next_batch(10).await.unwrap();
+        let keys: Vec<String> = messages.iter().map(VerificationMessage::key).collect();
+        assert_eq!(keys, vec![format!("tenant_b/{}", fresh)]);
+
+        // The stale one is copied aside and acked, so it isn't read again
+        let acks = acks.lock().unwrap().join("\n");
+        assert!(acks.contains("XADD verification_expired"), "{}", acks);
+        assert!(acks.contains("XACK tenant_a attestation_processors 1-0"), "{}", acks);
+        assert!(!acks.contains("tenant_b"), "{}", acks);
+        assert!(metrics::counter("stream_entries_expired_total") >= 1);
+
+        assert_eq!(entry_age("1000-3", 61_000), Some(Duration::from_secs(60)));
+        assert_eq!(entry_age("not-an-id", 61_000), None);
+    }
+
+    #[test]
+    fn test_adaptive_count_grows_when_backed_up_and_shrinks_when_idle() {
+        let config = StreamReadConfig {
+            count: 10,
+            max_count: 40,
+            adaptive: true,
+            ..StreamReadConfig::default()
+        };
+        assert_eq!(config.next_count(10, 10, 10), 20);
+        assert_eq!(config.next_count(20, 20, 20), 40);
+        assert_eq!(config.next_count(40, 40, 40), 40);
+        assert_eq!(config.next_count(40, 40, 5), 40);
+        assert_eq!(config.next_count(40, 40, 0), 20);
+        assert_eq!(config.next_count(20, 20, 0), 10);
+        assert_eq!(config.next_count(10, 10, 0), 10);
+
+        let fixed = StreamReadConfig::default();
+        assert_eq!(fixed.next_count(10, 10, 10), 10);
+    }
+
+    #[test]
+    fn test_missing_group_after_create_warning_is_fatal() {
+        let err = ensure_consumer_group(
+            "verification_stream",
+            "verification_processors",
+            &["other_group".to_string()],
+            Some("WRONGTYPE Operation against a key holding the wrong kind of value"),
+        )
+        .unwrap_err()
+        .to_string();
+        assert!(err.contains("verification_processors"));
+        assert!(err.contains("WRONGTYPE"));
+
+        assert!(ensure_consumer_group("verification_stream", "verification_processors", &[], None).is_err());
+        assert!(
+            ensure_consumer_group(
+                "verification_stream",
+                "verification_processors",
+                &["verification_processors".to_string()],
+                None
+            )
+            .is_ok()
+        );
+    }
+
+    #[test]
+    fn test_stream_entry_missing_field_is_rejected() {
+        let mut fields = stream_fields("0xabc");
+        fields.remove("document_data");
+        let err = parse_stream_fields("1-0", &fields).unwrap_err();
+        assert!(is_invalid_message(&err));
+        assert_eq!(err.to_string(), "Invalid message: field 'document_data' is missing");
+    }
+
+    #[test]
+    fn test_renamed_stream_fields_are_mapped_before_parsing() {
+        let names = StreamFieldNames::parse("user_wallet=wallet, document_data = doc").unwrap();
+        let mut fields = stream_fields("0xabc");
+        for (logical, actual) in [("user_wallet", "wallet"), ("document_data", "doc")] {
+            let value = fields.remove(logical).unwrap();
+            fields.insert(actual.to_string(), value);
+        }
+        assert!(parse_stream_fields("1-0", &fields).is_err());
+
+        let message = parse_stream_fields("1-0", &names.to_logical(&fields)).unwrap();
+        match message.payload {
+            MessagePayload::Request(request) => {
+                assert_eq!(request.user_wallet, address("0xabc"));
+                assert_eq!(request.document_data, "{}");
+                assert_eq!(request.status, "pending");
+            }
+            other => panic!("expected a request, got {:?}", other),
+        }
+
+        assert!(StreamFieldNames::parse("wallet").is_err());
+        assert!(StreamFieldNames::parse("walet=wallet").is_err());
+        // Two logical fields read from the same stream field
+        assert!(StreamFieldNames::parse("user_wallet=status").is_err());
+        assert_eq!(StreamFieldNames::parse("").unwrap(), StreamFieldNames::default());
+    }
+
+    #[test]
+    fn test_stream_entry_is_checked_against_the_field_schema() {
+        // A well-formed entry, optional fields absent
+        assert!(validate_fields(VERIFICATION_REQUEST_FIELDS, &stream_fields("0xabc")).is_ok());
+
+        let with = |key: &str, value: &str| {
+            let mut fields = stream_fields("0xabc");
+            fields.insert(key.to_string(), Value::Data(value.as_bytes().to_vec()));
+            parse_stream_fields("1-0", &fields)
+        };
+        let err = with("document_data", r#"{"pan": "HJTPB9891M""#).unwrap_err();
+        assert!(is_invalid_message(&err));
+        assert!(err.to_string().contains("field 'document_data' is not valid JSON"), "{}", err);
+        assert!(with("document_data", "[1]").unwrap_err().to_string().contains("is not a JSON object"));
+        assert!(with("did_id", "300").unwrap_err().to_string().contains("field 'did_id' is invalid"));
+        assert!(with("user_wallet", "0xabc").unwrap_err().to_string().contains("field 'user_wallet' is invalid"));
+        // Unreadable OCR output is tolerated, as it is downstream
+        assert!(with("extracted_data", "not json").is_ok());
+
+        let mut fields = stream_fields("0xabc");
+        fields.insert("user_wallet".to_string(), Value::Data(vec![0xff, 0xfe]));
+        let err = parse_stream_fields("1-0", &fields).unwrap_err();
+        assert!(err.to_string().contains("field 'user_wallet' is not a UTF-8 string"));
+
+        // The rejected entry is dead-lettered and acked in one transaction, its fields sealed
+        let source = test_source(StreamReadConfig::default());
+        let mut rejected = stream_fields("0xabc");
+        rejected.insert("document_data".to_string(), Value::Data(br#"{"pan":"HJTPB9891M"}"#.to_vec()));
+        let packed = source.dead_letter_command("verification_stream", "1-0", &rejected, &err).get_packed_pipeline();
+        let packed = String::from_utf8_lossy(&packed);
+        for part in ["MULTI", "XADD", "verification_dlq", "XACK", "attestation_processors", "EXEC"] {
+            assert!(packed.contains(part), "{} missing from {}", part, packed);
+        }
+        assert!(!packed.contains("HJTPB9891M"), "{}", packed);
+        assert!(!packed.contains("field 'user_wallet'"), "{}", packed);
+    }
+
+    #[test]
+    fn test_record_did_id_accepts_string_or_integer() {
+        let message = parse_record_payload(
+            7,
+            r#"{"user_wallet":"0x1","did_id":1,"result":"verified","evidence_hash":"ab","verified_at":"t"}"#,
+        )
+        .unwrap();
+        match message.payload {
+            MessagePayload::Verified(result) => assert_eq!(result.did_id, 1),
+            other => panic!("unexpected payload: {:?}", other),
+        }
+        assert!(parse_record_payload(8, r#"{"user_wallet":"0x1","did_id":"300"}"#)
+            .unwrap_err()
+            .to_string()
+            .contains("offset 8"));
+    }
+
+    /// Handler shaped like the processor: every handling runs under the budget, checking the
+    /// deadline between its two stages, and an exhausted budget dead-letters the message and acks it.
+    struct BudgetedHandler {
+        budget: RetryBudget,
+        stage_retry: crate::retry::RetryPolicy,
+        /// Stages that ran to completion.
+        stages: Vec<&'static str>,
+        dlq: Vec<String>,
+    }
+
+    impl MessageHandler for BudgetedHandler {
+        async fn handle(&mut self, message: &VerificationMessage) -> Result<()> {
+            let (stage_retry, stages) = (&self.stage_retry, &mut self.stages);
+            let handling = |deadline: StageDeadline| async move {
+                // A slow first stage that finishes past the deadline
+                let first = crate::retry::retry_with_backoff(stage_retry, "slow stage", |attempt| async move {
+                    match attempt {
+                        1 => Err(anyhow!("proxy returned 502")),
+                        _ => Ok(()),
+                    }
+                });
+                first.await?;
+                stages.push("first");
+                deadline.check("second")?;
+                stages.push("second");
+                Err::<(), _>(anyhow!("second stage failed"))
+            };
+            match self.budget.run(&message.id, handling).await {
+                Err(e) if is_budget_exhausted(&e) => {
+                    self.dlq.push(message.id.clone());
+                    Ok(())
+                }
+                other => other,
+            }
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_message_past_its_overall_budget_is_dead_lettered() {
+        let message = parse_record_payload(
+            0,
+            r#"{"user_wallet":"0xabc","did_id":"1","result":"verified","evidence_hash":"ab","verified_at":"2025-01-01T00:00:00"}"#,
+        )
+        .unwrap();
+        let source = RecordingSource::default();
+
+        // The first stage outlives the 100ms deadline: it is let finish, and the second never starts
+        let mut handler = BudgetedHandler {
+            budget: RetryBudget::new(100, Duration::from_millis(100)),
+            stage_retry: crate::retry::RetryPolicy {
+                max_attempts: 2,
+                base_delay: Duration::from_millis(500),
+                max_delay: Duration::from_millis(500),
+            },
+            stages: Vec::new(),
+            dlq: Vec::new(),
+        };
+        dispatch(&source, &mut handler, &message).await.unwrap();
+        assert_eq!(handler.stages, vec!["first"]);
+        assert_eq!(handler.dlq, vec!["0"]);
+        assert_eq!(*source.acked.lock().unwrap(), vec!["0"]);
+        assert!(source.nacked.lock().unwrap().is_empty());
+
+        // Out of attempts before the deadline: retried until the last one, then dead-lettered
+        let source = RecordingSource::default();
+        let mut handler = BudgetedHandler {
+            budget: RetryBudget::new(3, Duration::from_secs(60)),
+            stage_retry: crate::retry::RetryPolicy { max_attempts: 1, ..crate::retry::RetryPolicy::default() },
+            stages: Vec::new(),
+            dlq: Vec::new(),
+        };
+        for _ in 0..3 {
+            dispatch(&source, &mut handler, &message).await.unwrap();
+        }
+        assert_eq!(*source.nacked.lock().unwrap(), vec!["0", "0"]);
+        assert!(handler.stages.is_empty());
+        assert_eq!(handler.dlq, vec!["0"]);
+        assert_eq!(*source.acked.lock().unwrap(), vec!["0"]);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_budget_forgets_messages_that_never_came_back() {
+        let budget = RetryBudget::new(10, Duration::from_secs(60));
+        let _ = budget.run("nacked", |_| async { Err::<(), _>(anyhow!("failed")) }).await;
+        assert!(budget.entries.lock().unwrap().contains_key("nacked"));
+        assert!(!budget.past_deadline("nacked"));
+        tokio::time::advance(Duration::from_secs(60)).await;
+        assert!(budget.past_deadline("nacked"));
+        assert!(!budget.past_deadline("never handled"));
+
+        // Twice the deadline on, the next handling of anything drops it
+        tokio::time::advance(Duration::from_secs(61)).await;
+        budget.run("other", |_| async { Ok(()) }).await.unwrap();
+        assert!(budget.entries.lock().unwrap().is_empty());
+    }
+}
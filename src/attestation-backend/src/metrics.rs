@@ -0,0 +1,138 @@
+// Lightweight in-process metrics registry, exposed in Prometheus text format on /metrics
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::{Mutex, OnceLock};
+
+/// Upper bounds, in seconds, of the histogram buckets: from a local step to a slow upstream call.
+const BUCKETS: [f64; 12] = [0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0];
+
+#[derive(Default, Clone)]
+struct Histogram {
+    /// Observations at or under each of [`BUCKETS`], cumulative as Prometheus expects.
+    buckets: [u64; BUCKETS.len()],
+    count: u64,
+    sum: f64,
+}
+
+#[derive(Default)]
+struct Registry {
+    counters: BTreeMap<String, u64>,
+    gauges: BTreeMap<String, f64>,
+    histograms: BTreeMap<String, Histogram>,
+}
+
+fn registry() -> &'static Mutex<Registry> {
+    static REGISTRY: OnceLock<Mutex<Registry>> = OnceLock::new();
+    REGISTRY.get_or_init(|| Mutex::new(Registry::default()))
+}
+
+/// Increment a counter by one.
+pub fn increment(name: &str) {
+    increment_by(name, 1);
+}
+
+/// Increment a counter by `value`.
+pub fn increment_by(name: &str, value: u64) {
+    let mut registry = registry().lock().unwrap();
+    *registry.counters.entry(name.to_string()).or_insert(0) += value;
+}
+
+/// Set a gauge to an absolute value.
+pub fn set_gauge(name: &str, value: f64) {
+    let mut registry = registry().lock().unwrap();
+    registry.gauges.insert(name.to_string(), value);
+}
+
+/// Record one observation, in seconds, in a histogram.
+pub fn observe(name: &str, seconds: f64) {
+    let mut registry = registry().lock().unwrap();
+    let histogram = registry.histograms.entry(name.to_string()).or_default();
+    for (bucket, bound) in histogram.buckets.iter_mut().zip(BUCKETS) {
+        if seconds <= bound {
+            *bucket += 1;
+        }
+    }
+    histogram.count += 1;
+    histogram.sum += seconds;
+}
+
+/// Number and sum of a histogram's observations, if it has any.
+pub fn histogram(name: &str) -> Option<(u64, f64)> {
+    registry().lock().unwrap().histograms.get(name).map(|h| (h.count, h.sum))
+}
+
+/// Current value of a counter (0 if never incremented).
+pub fn counter(name: &str) -> u64 {
+    registry().lock().unwrap().counters.get(name).copied().unwrap_or(0)
+}
+
+/// Current value of a gauge, if it has been set.
+pub fn gauge(name: &str) -> Option<f64> {
+    registry().lock().unwrap().gauges.get(name).copied()
+}
+
+/// Render every metric in Prometheus text exposition format.
+pub fn render() -> String {
+    let registry = registry().lock().unwrap();
+    let mut out = String::new();
+    for (name, value) in &registry.counters {
+        let _ = writeln!(out, "# TYPE {} counter", name);
+        let _ = writeln!(out, "{} {}", name, value);
+    }
+    for (name, value) in &registry.gauges {
+        let _ = writeln!(out, "# TYPE {} gauge", name);
+        let _ = writeln!(out, "{} {}", name, value);
+    }
+    for (name, histogram) in &registry.histograms {
+        let _ = writeln!(out, "# TYPE {} histogram", name);
+        for (count, bound) in histogram.buckets.iter().zip(BUCKETS) {
+            let _ = writeln!(out, "{}_bucket{{le=\"{}\"}} {}", name, bound, count);
+        }
+        let _ = writeln!(out, "{}_bucket{{le=\"+Inf\"}} {}", name, histogram.count);
+        let _ = writeln!(out, "{}_sum {}", name, histogram.sum);
+        let _ = writeln!(out, "{}_count {}", name, histogram.count);
+    }
+    out
+}
+
+/// Endpoint that returns all metrics.
+pub async fn metrics_handler() -> String {
+    render()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_counters_and_gauges_render() {
+        increment("metrics_test_counter_total");
+        increment_by("metrics_test_counter_total", 2);
+        set_gauge("metrics_test_gauge", 4.5);
+
+        assert_eq!(counter("metrics_test_counter_total"), 3);
+        assert_eq!(gauge("metrics_test_gauge"), Some(4.5));
+
+        let rendered = render();
+        assert!(rendered.contains("metrics_test_counter_total 3"));
+        assert!(rendered.contains("metrics_test_gauge 4.5"));
+    }
+
+    #[test]
+    fn test_histogram_renders_cumulative_buckets() {
+        observe("metrics_test_seconds", 0.003);
+        observe("metrics_test_seconds", 0.2);
+        observe("metrics_test_seconds", 60.0);
+
+        let (count, sum) = histogram("metrics_test_seconds").unwrap();
+        assert_eq!(count, 3);
+        assert!((sum - 60.203).abs() < 1e-9);
+
+        let rendered = render();
+        assert!(rendered.contains("metrics_test_seconds_bucket{le=\"0.005\"} 1"));
+        assert!(rendered.contains("metrics_test_seconds_bucket{le=\"0.25\"} 2"));
+        assert!(rendered.contains("metrics_test_seconds_bucket{le=\"30\"} 2"));
+        assert!(rendered.contains("metrics_test_seconds_bucket{le=\"+Inf\"} 3"));
+        assert!(rendered.contains("metrics_test_seconds_count 3"));
+    }
+}
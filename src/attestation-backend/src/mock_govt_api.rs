@@ -0,0 +1,203 @@
+// Mock of the government PAN API (/authenticate, /kyc/pan/verify) for offline end-to-end runs
+use anyhow::{Result, anyhow};
+use axum::extract::State;
+use axum::http::header::RETRY_AFTER;
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::routing::post;
+use axum::{Json, Router};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tokio::net::TcpListener;
+use tokio::task::JoinHandle;
+use tracing::info;
+
+/// Canned answer for a PAN verification call.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MockScenario {
+    Valid,
+    NameMismatch,
+    DobMismatch,
+    Deactivated,
+    /// 422: the API rejects the PAN outright.
+    InvalidPan,
+    /// 429 with `Retry-After: 1`.
+    RateLimited,
+    /// 503.
+    Unavailable,
+}
+
+impl MockScenario {
+    pub fn parse(name: &str) -> Result<Self> {
+        match name.trim().to_lowercase().as_str() {
+            "valid" => Ok(Self::Valid),
+            "name_mismatch" => Ok(Self::NameMismatch),
+            "dob_mismatch" => Ok(Self::DobMismatch),
+            "deactivated" => Ok(Self::Deactivated),
+            "invalid_pan" | "422" => Ok(Self::InvalidPan),
+            "rate_limited" | "429" => Ok(Self::RateLimited),
+            "unavailable" | "503" => Ok(Self::Unavailable),
+            other => Err(anyhow!("Unknown mock scenario: {}", other)),
+        }
+    }
+}
+
+/// The mock's configuration: a default scenario, optionally overridden per PAN so one server
+/// can answer differently for different test users.
+#[derive(Debug, Clone)]
+pub struct MockGovtApi {
+    default: MockScenario,
+    by_pan: HashMap<String, MockScenario>,
+}
+
+impl MockGovtApi {
+    pub fn new(default: MockScenario) -> Self {
+        Self {
+            default,
+            by_pan: HashMap::new(),
+        }
+    }
+
+    pub fn with_pan(mut self, pan: &str, scenario: MockScenario) -> Self {
+        self.by_pan.insert(pan.trim().to_uppercase(), scenario);
+        self
+    }
+
+    /// Serve on `addr` (port 0 picks a free port). Point the client at the returned address with
+    /// `GOVT_API_AUTH_URL=http://{addr}/authenticate` and `GOVT_API_BASE_URL=http://{addr}`.
+    pub async fn serve(self, addr: &str) -> Result<(SocketAddr, JoinHandle<()>)> {
+        let app = Router::new()
+            .route("/authenticate", post(authenticate))
+            .route("/kyc/pan/verify", post(verify_pan))
+            .with_state(Arc::new(self));
+        let listener = TcpListener::bind(addr).await?;
+        let local = listener.local_addr()?;
+        info!("🧪 Mock government API listening on http://{}", local);
+        let handle = tokio::spawn(async move {
+            let _ = axum::serve(listener, app).await;
+        });
+        Ok((local, handle))
+    }
+}
+
+async fn authenticate() -> Json<Value> {
+    Json(json!({ "access_token": "mock-access-token" }))
+}
+
+async fn verify_pan(State(mock): State<Arc<MockGovtApi>>, Json(request): Json<Value>) -> Response {
+    let pan = request["pan"].as_str().unwrap_or_default().trim().to_uppercase();
+    let scenario = mock.by_pan.get(&pan).copied().unwrap_or(mock.default);
+
+    let (status, name_match, dob_match) = match scenario {
+        MockScenario::Valid => ("valid", true, true),
+        MockScenario::NameMismatch => ("valid", false, true),
+        MockScenario::DobMismatch => ("valid", true, false),
+        MockScenario::Deactivated => ("deactivated", true, true),
+        MockScenario::InvalidPan => {
+            let body = json!({ "code": 422, "message": "Invalid PAN pattern", "timestamp": 0 });
+            return (StatusCode::UNPROCESSABLE_ENTITY, Json(body)).into_response();
+        }
+        MockScenario::RateLimited => {
+            return (StatusCode::TOO_MANY_REQUESTS, [(RETRY_AFTER, "1")], "rate limit exceeded").into_response();
+        }
+        MockScenario::Unavailable => {
+            return (StatusCode::SERVICE_UNAVAILABLE, "upstream unavailable").into_response();
+        }
+    };
+
+    Json(json!({
+        "code": 200,
+        "timestamp": chrono::Utc::now().timestamp_millis(),
+        "transaction_id": format!("mock-{}", pan),
+        "data": {
+            "@entity": "in.co.sandbox.kyc.pan_verification.response",
+            "pan": pan,
+            "status": status,
+            "remarks": null,
+            "name_as_per_pan_match": name_match,
+            "date_of_birth_match": dob_match,
+            "category": "individual",
+            "aadhaar_seeding_status": "y",
+        },
+    }))
+    .into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::government_api::{GovernmentApiClient, VerificationRequest};
+    use crate::metrics;
+    use crate::stage_timer::StageTimer;
+
+    fn request(pan: &str) -> VerificationRequest {
+        VerificationRequest {
+            user_wallet: "0xabc".to_string(),
+            did_id: "0".to_string(),
+            verification_type: "pan".to_string(),
+            document_data: json!({
+                "pan": pan,
+                "name_as_per_pan": "Ashwin Balaguru",
+                "date_of_birth": "27/10/2004",
+                "consent": "Y",
+                "reason": "KYC",
+            })
+            .to_string(),
+            extracted_data: None,
+            user_corrections: None,
+            timestamp: "0".to_string(),
+            status: "pending".to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_verification_request_against_mock() {
+        let mock = MockGovtApi::new(MockScenario::Valid)
+            .with_pan("ABCDE1234F", MockScenario::NameMismatch)
+            .with_pan("PQRST6789Z", MockScenario::InvalidPan);
+        let (addr, _server) = mock.serve("127.0.0.1:0").await.unwrap();
+        let mut client = GovernmentApiClient::new()
+            .unwrap()
+            .with_endpoints(&format!("http://{}/authenticate", addr), &format!("http://{}", addr));
+
+        let verified = client.process_verification_request(&request("HJTPB9891M"), &StageTimer::default()).await.unwrap();
+        assert_eq!(verified.result, "verified");
+        assert_eq!(verified.evidence.hash.len(), 64);
+
+        let rejected = client.process_verification_request(&request("ABCDE1234F"), &StageTimer::default()).await.unwrap();
+        assert_eq!(rejected.result, "failed");
+        assert!(rejected.rejection_reason.is_some());
+
+        // A PAN the API refuses is a failed verification, not a failed message
+        let refused = client.process_verification_request(&request("PQRST6789Z"), &StageTimer::default()).await.unwrap();
+        assert_eq!(refused.result, "failed");
+        assert_eq!(
+            refused.rejection_reason.as_deref(),
+            Some("PAN rejected by the government API: Invalid PAN pattern")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_each_stage_of_a_request_is_timed() {
+        let (addr, _server) = MockGovtApi::new(MockScenario::Valid).serve("127.0.0.1:0").await.unwrap();
+        let mut client = GovernmentApiClient::new()
+            .unwrap()
+            .with_endpoints(&format!("http://{}/authenticate", addr), &format!("http://{}", addr));
+        let observed = |stage: &str| {
+            metrics::histogram(&format!("message_stage_{}_seconds", stage)).map_or(0, |(count, _)| count)
+        };
+        let before = observed("govt_verify");
+
+        let timer = StageTimer::default();
+        client.process_verification_request(&request("HJTPB9891M"), &timer).await.unwrap();
+
+        let stages = timer.stages();
+        let names: Vec<&str> = stages.iter().map(|(stage, _)| *stage).collect();
+        assert_eq!(names, ["parse", "govt_auth", "govt_verify", "hash"]);
+        assert!(stages.iter().all(|(_, elapsed)| !elapsed.is_zero()), "{:?}", stages);
+        assert!(observed("govt_verify") > before);
+        assert!(timer.summary().starts_with("parse="));
+    }
+}
@@ -0,0 +1,85 @@
+// Signed negative attestations: enclave-attributable proof that a wallet was checked and failed
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::common::{to_signed_response, IntentMessage, IntentScope, ProcessedDataResponse};
+use crate::signing::{EnclaveSigner, SigningError};
+
+/// Payload of a negative attestation. The reason itself is not disclosed, only its hash,
+/// so a relying party holding the reason can check it without the enclave publishing it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NegativeAttestation {
+    pub user_wallet: String,
+    pub did_id: u8,
+    /// Always false; kept explicit so the signed bytes say what they attest to.
+    pub verified: bool,
+    /// SHA-256 hex of the rejection reason.
+    pub reason_hash: String,
+    pub evidence_hash: String,
+    pub verified_at: String,
+}
+
+pub type SignedNegativeAttestation = ProcessedDataResponse<IntentMessage<NegativeAttestation>>;
+
+/// SHA-256 hex of a rejection reason.
+pub fn reason_hash(reason: &str) -> String {
+    hex::encode(Sha256::digest(reason.as_bytes()))
+}
+
+/// Sign a negative attestation for a rejected verification under [`IntentScope::NegativeVerification`].
+pub fn sign_negative_attestation(
+    kp: &impl EnclaveSigner,
+    user_wallet: &str,
+    did_id: u8,
+    reason: &str,
+    evidence_hash: &str,
+    verified_at: &str,
+    timestamp_ms: u64,
+) -> Result<SignedNegativeAttestation, SigningError> {
+    let attestation = NegativeAttestation {
+        user_wallet: user_wallet.to_string(),
+        did_id,
+        verified: false,
+        reason_hash: reason_hash(reason),
+        evidence_hash: evidence_hash.to_string(),
+        verified_at: verified_at.to_string(),
+    };
+    to_signed_response(kp, attestation, timestamp_ms, IntentScope::NegativeVerification)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::verify_signed_response;
+    use fastcrypto::ed25519::Ed25519KeyPair;
+    use fastcrypto::traits::KeyPair;
+
+    #[test]
+    fn test_rejection_yields_valid_signed_negative_attestation() {
+        let kp = Ed25519KeyPair::generate(&mut rand::thread_rng());
+        let reason = "Date of birth does not match PAN records";
+
+        let signed = sign_negative_attestation(
+            &kp,
+            "0xabc",
+            0,
+            reason,
+            &"ab".repeat(32),
+            "2025-01-01T00:00:00+00:00",
+            1735689600000,
+        )
+        .unwrap();
+
+        assert!(!signed.response.data.verified);
+        assert_eq!(signed.response.data.reason_hash, reason_hash(reason));
+        assert_eq!(signed.response.intent, IntentScope::NegativeVerification);
+
+        // The signature covers the BCS bytes of the intent message
+        assert!(verify_signed_response(kp.public(), &signed).is_ok());
+
+        // Tampering with the attestation invalidates the signature
+        let mut tampered = signed.clone();
+        tampered.response.data.verified = true;
+        assert!(verify_signed_response(kp.public(), &tampered).is_err());
+    }
+}
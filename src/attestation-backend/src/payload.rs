@@ -0,0 +1,91 @@
+// Validation of raw message payloads before they are decoded and parsed
+use std::fmt;
+
+/// Default cap on a single record payload (1 MiB).
+pub const DEFAULT_MAX_PAYLOAD_BYTES: usize = 1024 * 1024;
+
+/// Why a raw payload was rejected. These are poison records: retrying them can never succeed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PayloadError {
+    TooLarge { size: usize, max: usize },
+    InvalidUtf8 { valid_up_to: usize },
+}
+
+impl fmt::Display for PayloadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PayloadError::TooLarge { size, max } => {
+                write!(f, "payload of {} bytes exceeds the {} byte limit", size, max)
+            }
+            PayloadError::InvalidUtf8 { valid_up_to } => {
+                write!(f, "payload is not valid UTF-8 (invalid byte at position {})", valid_up_to)
+            }
+        }
+    }
+}
+
+impl std::error::Error for PayloadError {}
+
+/// A message that can never be processed as sent, such as an unknown verification type or a
+/// malformed `did_id`. It is dead-lettered instead of being left pending for retries.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InvalidMessage {
+    pub reason: String,
+}
+
+impl fmt::Display for InvalidMessage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Invalid message: {}", self.reason)
+    }
+}
+
+impl std::error::Error for InvalidMessage {}
+
+pub fn is_invalid_message(error: &anyhow::Error) -> bool {
+    error.downcast_ref::<InvalidMessage>().is_some()
+}
+
+/// Read the payload size cap from `var`, falling back to [`DEFAULT_MAX_PAYLOAD_BYTES`].
+pub fn max_payload_bytes_from_env(var: &str) -> usize {
+    std::env::var(var)
+        .ok()
+        .and_then(|v| v.parse::<usize>().ok())
+        .unwrap_or(DEFAULT_MAX_PAYLOAD_BYTES)
+}
+
+/// Check the size cap first (so an oversized payload is never scanned) and then decode as UTF-8.
+pub fn decode_payload(payload: &[u8], max_bytes: usize) -> Result<&str, PayloadError> {
+    if payload.len() > max_bytes {
+        return Err(PayloadError::TooLarge {
+            size: payload.len(),
+            max: max_bytes,
+        });
+    }
+    std::str::from_utf8(payload).map_err(|e| PayloadError::InvalidUtf8 {
+        valid_up_to: e.valid_up_to(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_valid_payload_decodes() {
+        assert_eq!(decode_payload(br#"{"user_wallet":"0x1"}"#, 64), Ok(r#"{"user_wallet":"0x1"}"#));
+    }
+
+    #[test]
+    fn test_oversized_payload_rejected() {
+        let payload = vec![b'a'; 65];
+        let err = decode_payload(&payload, 64).unwrap_err();
+        assert_eq!(err, PayloadError::TooLarge { size: 65, max: 64 });
+        assert!(err.to_string().contains("exceeds the 64 byte limit"));
+    }
+
+    #[test]
+    fn test_non_utf8_payload_rejected() {
+        let err = decode_payload(&[b'{', 0xff, 0xfe, b'}'], 64).unwrap_err();
+        assert_eq!(err, PayloadError::InvalidUtf8 { valid_up_to: 1 });
+    }
+}
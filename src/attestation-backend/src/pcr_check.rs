@@ -0,0 +1,187 @@
+// Startup check that the enclave's own PCR measurements are ones we expect to be running
+use anyhow::{Result, anyhow};
+use std::collections::BTreeMap;
+use std::fmt;
+use tracing::{error, info, warn};
+
+use crate::metrics;
+
+/// PCRs identifying the image: 0 the enclave image, 1 the kernel and boot ramfs, 2 the application.
+pub const IMAGE_PCRS: [u16; 3] = [0, 1, 2];
+
+/// Length of a PCR value (SHA-384).
+const PCR_BYTES: usize = 48;
+
+/// Acceptable values per PCR, from `NSM_EXPECTED_PCR0`/`1`/`2`: comma separated hex, so a
+/// rollout can allow both the old and new image. PCRs without an entry are not checked.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ExpectedPcrs {
+    allowed: BTreeMap<u16, Vec<Vec<u8>>>,
+}
+
+/// PCRs whose measured value isn't in the allowlist, or that couldn't be read.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PcrMismatch {
+    /// (index, measured value as hex, empty if missing)
+    pub mismatched: Vec<(u16, String)>,
+}
+
+impl fmt::Display for PcrMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let pcrs: Vec<String> = self
+            .mismatched
+            .iter()
+            .map(|(index, value)| {
+                if value.is_empty() { format!("PCR{} missing", index) } else { format!("PCR{}={}", index, value) }
+            })
+            .collect();
+        write!(f, "unexpected enclave measurements: {}", pcrs.join(", "))
+    }
+}
+
+impl std::error::Error for PcrMismatch {}
+
+impl ExpectedPcrs {
+    /// Allow `values` (hex, comma separated) for PCR `index`.
+    pub fn allow(mut self, index: u16, values: &str) -> Result<Self> {
+        let mut parsed = Vec::new();
+        for value in values.split(',').map(str::trim).filter(|v| !v.is_empty()) {
+            let bytes = hex::decode(value.trim_start_matches("0x"))
+                .map_err(|e| anyhow!("Invalid PCR{} value '{}': {}", index, value, e))?;
+            if bytes.len() != PCR_BYTES {
+                return Err(anyhow!("PCR{} value must be {} bytes, got {}", index, PCR_BYTES, bytes.len()));
+            }
+            parsed.push(bytes);
+        }
+        if !parsed.is_empty() {
+            self.allowed.entry(index).or_default().extend(parsed);
+        }
+        Ok(self)
+    }
+
+    pub fn from_env() -> Result<Self> {
+        let mut expected = Self::default();
+        for index in IMAGE_PCRS {
+            if let Ok(values) = std::env::var(format!("NSM_EXPECTED_PCR{}", index)) {
+                expected = expected.allow(index, &values)?;
+            }
+        }
+        Ok(expected)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.allowed.is_empty()
+    }
+
+    /// Every configured PCR must be measured with one of its allowed values.
+    pub fn check(&self, measured: &BTreeMap<u16, Vec<u8>>) -> Result<(), PcrMismatch> {
+        let mismatched: Vec<(u16, String)> = self
+            .allowed
+            .iter()
+            .filter_map(|(index, allowed)| match measured.get(index) {
+                Some(value) if allowed.contains(value) => None,
+                Some(value) => Some((*index, hex::encode(value))),
+                None => Some((*index, String::new())),
+            })
+            .collect();
+        if mismatched.is_empty() {
+            Ok(())
+        } else {
+            Err(PcrMismatch { mismatched })
+        }
+    }
+}
+
+/// Whether a PCR mismatch stops startup, from `NSM_PCR_ENFORCE` (default false: log and count).
+pub fn enforce_from_env() -> bool {
+    std::env::var("NSM_PCR_ENFORCE")
+        .map(|v| v == "1" || v.eq_ignore_ascii_case("true"))
+        .unwrap_or(false)
+}
+
+/// Compare `measured` (or the failure to read it) with `expected`. A mismatch is an error when
+/// `enforce`, otherwise an error log; either way `pcr_mismatch_total` is counted and
+/// `pcr_measurements_match` shows the outcome.
+pub fn verify_measurements(
+    expected: &ExpectedPcrs,
+    measured: Result<BTreeMap<u16, Vec<u8>>>,
+    enforce: bool,
+) -> Result<()> {
+    if expected.is_empty() {
+        info!("No NSM_EXPECTED_PCR* configured, skipping the measurement check");
+        return Ok(());
+    }
+    let outcome = measured.map_err(|e| anyhow!("Could not read PCRs: {}", e)).and_then(|measured| {
+        expected.check(&measured).map_err(anyhow::Error::new)
+    });
+    match outcome {
+        Ok(()) => {
+            metrics::set_gauge("pcr_measurements_match", 1.0);
+            info!("✅ Enclave PCRs match the expected measurements");
+            Ok(())
+        }
+        Err(e) => {
+            metrics::increment("pcr_mismatch_total");
+            metrics::set_gauge("pcr_measurements_match", 0.0);
+            if enforce {
+                error!("🚨 {} and NSM_PCR_ENFORCE is set, refusing to start", e);
+                return Err(e);
+            }
+            warn!("🚨 {} (not enforced)", e);
+            Ok(())
+        }
+    }
+}
+
+/// The image PCRs as the NSM reports them, the same values its attestation documents carry.
+#[cfg(feature = "aws")]
+pub fn nsm_pcrs() -> Result<BTreeMap<u16, Vec<u8>>> {
+    use aws_nitro_enclaves_nsm_api::api::{Request, Response};
+    use aws_nitro_enclaves_nsm_api::driver;
+
+    let fd = driver::nsm_init();
+    let mut pcrs = BTreeMap::new();
+    for index in IMAGE_PCRS {
+        match driver::nsm_process_request(fd, Request::DescribePCR { index }) {
+            Response::DescribePCR { data, .. } => {
+                pcrs.insert(index, data);
+            }
+            _ => {
+                driver::nsm_exit(fd);
+                return Err(anyhow!("Unexpected NSM response to DescribePCR {}", index));
+            }
+        }
+    }
+    driver::nsm_exit(fd);
+    Ok(pcrs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_measurements_are_checked_against_the_allowlist() {
+        let pcr = |byte: u8| vec![byte; PCR_BYTES];
+        let measured: BTreeMap<u16, Vec<u8>> = [(0, pcr(0xa0)), (1, pcr(0xa1)), (2, pcr(0xa2))].into();
+        let expected = ExpectedPcrs::default()
+            .allow(0, &format!("{}, {}", hex::encode(pcr(0xb0)), hex::encode(pcr(0xa0))))
+            .unwrap()
+            .allow(2, &hex::encode(pcr(0xa2)))
+            .unwrap();
+        assert!(expected.check(&measured).is_ok());
+
+        // A tampered application image
+        let mut tampered = measured.clone();
+        tampered.insert(2, pcr(0xff));
+        let mismatch = expected.check(&tampered).unwrap_err();
+        assert_eq!(mismatch.mismatched, vec![(2, hex::encode(pcr(0xff)))]);
+        assert!(verify_measurements(&expected, Ok(tampered.clone()), true).is_err());
+        assert!(verify_measurements(&expected, Ok(tampered), false).is_ok());
+
+        // Unreadable measurements are a mismatch too; nothing configured checks nothing
+        assert!(verify_measurements(&expected, Err(anyhow!("no device")), true).is_err());
+        assert!(verify_measurements(&ExpectedPcrs::default(), Err(anyhow!("no device")), true).is_ok());
+        assert!(ExpectedPcrs::default().allow(1, "abcd").is_err());
+    }
+}
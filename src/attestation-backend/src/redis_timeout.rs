@@ -0,0 +1,89 @@
+// Deadlines for Redis calls, so a half-open connection fails over to a reconnect instead of hanging
+use anyhow::Result;
+use std::fmt;
+use std::future::Future;
+use tokio::time::Duration;
+use tracing::warn;
+
+use crate::metrics;
+
+/// Deadlines for Redis calls, from `REDIS_CONNECT_TIMEOUT_MS` (connect plus AUTH) and
+/// `REDIS_COMMAND_TIMEOUT_MS` (any other command; blocking reads get their `BLOCK` on top).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RedisTimeouts {
+    pub connect: Duration,
+    pub command: Duration,
+}
+
+impl Default for RedisTimeouts {
+    fn default() -> Self {
+        Self {
+            connect: Duration::from_secs(10),
+            command: Duration::from_secs(5),
+        }
+    }
+}
+
+impl RedisTimeouts {
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        let parse = |name: &str, default: Duration| {
+            std::env::var(name)
+                .ok()
+                .and_then(|v| v.parse::<u64>().ok())
+                .map(|ms| Duration::from_millis(ms.max(1)))
+                .unwrap_or(default)
+        };
+        Self {
+            connect: parse("REDIS_CONNECT_TIMEOUT_MS", defaults.connect),
+            command: parse("REDIS_COMMAND_TIMEOUT_MS", defaults.command),
+        }
+    }
+
+    /// Deadline for a command that may legitimately block server-side for `block_ms`.
+    pub fn blocking(&self, block_ms: u64) -> Duration {
+        self.command + Duration::from_millis(block_ms)
+    }
+}
+
+/// A Redis call that did not finish in time. The connection it ran on must be dropped:
+/// a late reply would otherwise be read as the answer to the next command.
+#[derive(Debug)]
+pub struct RedisTimeout {
+    pub operation: String,
+    pub after: Duration,
+}
+
+impl fmt::Display for RedisTimeout {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Redis {} timed out after {}ms", self.operation, self.after.as_millis())
+    }
+}
+
+impl std::error::Error for RedisTimeout {}
+
+/// Whether `error` is (or wraps) a [`RedisTimeout`].
+pub fn is_redis_timeout(error: &anyhow::Error) -> bool {
+    error.downcast_ref::<RedisTimeout>().is_some()
+}
+
+/// Run `operation` with a deadline. Its own errors pass through unchanged; running out of time
+/// is a [`RedisTimeout`], counted in `redis_timeouts_total`.
+pub async fn with_timeout<T, E, F>(operation: &str, after: Duration, fut: F) -> Result<T>
+where
+    F: Future<Output = Result<T, E>>,
+    E: Into<anyhow::Error>,
+{
+    match tokio::time::timeout(after, fut).await {
+        Ok(result) => result.map_err(Into::into),
+        Err(_) => {
+            metrics::increment("redis_timeouts_total");
+            warn!("⏱️  Redis {} timed out after {}ms, dropping the connection", operation, after.as_millis());
+            Err(RedisTimeout {
+                operation: operation.to_string(),
+                after,
+            }
+            .into())
+        }
+    }
+}
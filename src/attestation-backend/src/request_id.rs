@@ -0,0 +1,153 @@
+// Request id propagation: read or generate X-Request-Id, attach it to logs, errors and responses
+use axum::body::{to_bytes, Body, HttpBody};
+use axum::extract::Request;
+use axum::http::header::{HeaderValue, CONTENT_LENGTH};
+use axum::middleware::Next;
+use axum::response::Response;
+use rand::RngCore;
+use tracing::{info_span, warn, Instrument};
+
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+/// Largest error body that gets the request id injected; bigger bodies pass through untouched.
+const MAX_ERROR_BODY_BYTES: usize = 64 * 1024;
+
+/// Request id of the current request, available to handlers as an extension.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RequestId(pub String);
+
+/// Random (version 4) UUID.
+pub fn new_request_id() -> String {
+    let mut bytes = [0u8; 16];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    bytes[6] = (bytes[6] & 0x0f) | 0x40;
+    bytes[8] = (bytes[8] & 0x3f) | 0x80;
+    let hex = hex::encode(bytes);
+    format!("{}-{}-{}-{}-{}", &hex[..8], &hex[8..12], &hex[12..16], &hex[16..20], &hex[20..])
+}
+
+/// Accept a client-supplied id only if it is short, printable ASCII, so it is safe to log and echo.
+fn incoming_request_id(request: &Request) -> Option<String> {
+    request
+        .headers()
+        .get(REQUEST_ID_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .map(str::trim)
+        .filter(|id| !id.is_empty() && id.len() <= 128 && id.chars().all(|c| c.is_ascii_graphic()))
+        .map(str::to_string)
+}
+
+/// Middleware: run the request inside a span carrying its id, fill the id into JSON error
+/// bodies and echo it back in the `X-Request-Id` response header.
+pub async fn request_id_middleware(mut request: Request, next: Next) -> Response {
+    let request_id = incoming_request_id(&request).unwrap_or_else(new_request_id);
+    request.extensions_mut().insert(RequestId(request_id.clone()));
+
+    let span = info_span!("request", request_id = %request_id, method = %request.method(), path = %request.uri().path());
+    let mut response = next.run(request).instrument(span).await;
+
+    if response.status().is_client_error() || response.status().is_server_error() {
+        response = with_request_id_in_error(response, &request_id).await;
+    }
+
+    if let Ok(value) = HeaderValue::from_str(&request_id) {
+        response.headers_mut().insert(REQUEST_ID_HEADER, value);
+    }
+    response
+}
+
+async fn with_request_id_in_error(response: Response, request_id: &str) -> Response {
+    // Only a body known to fit is buffered; anything larger or of unknown length streams as it was
+    let fits = response.body().size_hint().upper().is_some_and(|len| len <= MAX_ERROR_BODY_BYTES as u64);
+    if !fits {
+        return response;
+    }
+    let (mut parts, body) = response.into_parts();
+    let bytes = match to_bytes(body, MAX_ERROR_BODY_BYTES).await {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            warn!("Could not buffer error body for request {}: {}", request_id, e);
+            return Response::from_parts(parts, Body::empty());
+        }
+    };
+
+    let body = match serde_json::from_slice::<serde_json::Value>(&bytes) {
+        Ok(serde_json::Value::Object(mut map)) => {
+            let entry = map.entry("request_id").or_insert(serde_json::Value::Null);
+            if entry.is_null() {
+                *entry = request_id.into();
+            }
+            let encoded = serde_json::to_vec(&map).unwrap_or_else(|_| bytes.to_vec());
+            parts.headers.remove(CONTENT_LENGTH);
+            Body::from(encoded)
+        }
+        // Not a JSON object: leave the body as it was
+        _ => Body::from(bytes),
+    };
+    Response::from_parts(parts, body)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::EnclaveError;
+    use axum::{middleware::from_fn, routing::get, Router};
+    use tower::Service;
+
+    fn app() -> Router {
+        Router::new()
+            .route("/ok", get(|| async { "ok" }))
+            .route("/fail", get(|| async { Err::<(), _>(EnclaveError::GenericError("boom".to_string())) }))
+            .route("/fail-large", get(|| async { Err::<(), _>(EnclaveError::GenericError("x".repeat(MAX_ERROR_BODY_BYTES))) }))
+            .layer(from_fn(request_id_middleware))
+    }
+
+    #[tokio::test]
+    async fn test_provided_request_id_is_echoed() {
+        let request = Request::builder()
+            .uri("/ok")
+            .header(REQUEST_ID_HEADER, "client-123")
+            .body(Body::empty())
+            .unwrap();
+        let response = app().call(request).await.unwrap();
+        assert_eq!(response.headers()[REQUEST_ID_HEADER], "client-123");
+    }
+
+    #[tokio::test]
+    async fn test_missing_request_id_is_generated() {
+        let request = Request::builder().uri("/ok").body(Body::empty()).unwrap();
+        let response = app().call(request).await.unwrap();
+        let id = response.headers()[REQUEST_ID_HEADER].to_str().unwrap();
+        assert_eq!(id.len(), 36);
+        assert_eq!(&id[14..15], "4");
+    }
+
+    #[tokio::test]
+    async fn test_error_body_carries_request_id() {
+        let request = Request::builder()
+            .uri("/fail")
+            .header(REQUEST_ID_HEADER, "client-456")
+            .body(Body::empty())
+            .unwrap();
+        let response = app().call(request).await.unwrap();
+        let body = to_bytes(response.into_body(), MAX_ERROR_BODY_BYTES).await.unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["message"], "boom");
+        assert_eq!(json["request_id"], "client-456");
+    }
+
+    #[tokio::test]
+    async fn test_large_error_body_passes_through_untouched() {
+        let request = Request::builder()
+            .uri("/fail-large")
+            .header(REQUEST_ID_HEADER, "client-789")
+            .body(Body::empty())
+            .unwrap();
+        let response = app().call(request).await.unwrap();
+        assert_eq!(response.headers()[REQUEST_ID_HEADER], "client-789");
+        let body = to_bytes(response.into_body(), 2 * MAX_ERROR_BODY_BYTES).await.unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["message"], "x".repeat(MAX_ERROR_BODY_BYTES));
+        assert!(json["request_id"].is_null());
+    }
+}
@@ -0,0 +1,204 @@
+// Durable log of processed verification results, behind a trait so the backend is swappable
+use anyhow::{Result, anyhow};
+use axum::async_trait;
+use redis::aio::Connection;
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, Mutex};
+use tracing::info;
+
+use crate::canonical_json::to_canonical_string;
+use crate::redis_timeout::with_timeout;
+use crate::results::VerificationResultEvent;
+use crate::verification_processor::RedisConnector;
+
+/// A result in the log, with its position. Sequence numbers start at 1 and only grow.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ResultRecord {
+    pub seq: u64,
+    pub recorded_at: String,
+    pub event: VerificationResultEvent,
+}
+
+/// Append-only store of every published result. Records are never updated or removed here;
+/// retention is the backend's business.
+#[async_trait]
+pub trait ResultStore: Send + Sync {
+    /// Record `event`, returning its sequence number.
+    async fn append(&self, event: &VerificationResultEvent) -> Result<u64>;
+    /// Every record for `wallet` (case-insensitive), oldest first.
+    async fn get_by_wallet(&self, wallet: &str) -> Result<Vec<ResultRecord>>;
+    /// Up to `limit` records with a sequence number above `seq`, oldest first. Readers resume
+    /// from the last `seq` they saw.
+    async fn list_since(&self, seq: u64, limit: usize) -> Result<Vec<ResultRecord>>;
+}
+
+fn wallet_key(wallet: &str) -> String {
+    wallet.trim().to_lowercase()
+}
+
+fn new_record(seq: u64, event: &VerificationResultEvent) -> ResultRecord {
+    ResultRecord {
+        seq,
+        recorded_at: chrono::Utc::now().to_rfc3339(),
+        event: event.clone(),
+    }
+}
+
+/// The store named by `RESULTS_STORE`: `redis`, `memory`, or unset/`none` for no result log.
+pub fn result_store_from_env(redis: &RedisConnector) -> Result<Option<Arc<dyn ResultStore>>> {
+    let backend = std::env::var("RESULTS_STORE").unwrap_or_default().trim().to_lowercase();
+    let store: Option<Arc<dyn ResultStore>> = match backend.as_str() {
+        "" | "none" => None,
+        "memory" => Some(Arc::new(MemoryResultStore::default())),
+        "redis" => Some(Arc::new(RedisResultStore::from_env(redis.clone()))),
+        other => return Err(anyhow!("Unknown RESULTS_STORE '{}' (expected redis, memory or none)", other)),
+    };
+    info!("Result store: {}", if backend.is_empty() { "none" } else { backend.as_str() });
+    Ok(store)
+}
+
+/// In-process store, for tests and runs without Redis. Lost on restart.
+#[derive(Default)]
+pub struct MemoryResultStore {
+    records: Mutex<Vec<ResultRecord>>,
+}
+
+#[async_trait]
+impl ResultStore for MemoryResultStore {
+    async fn append(&self, event: &VerificationResultEvent) -> Result<u64> {
+        let mut records = self.records.lock().unwrap();
+        let seq = records.len() as u64 + 1;
+        records.push(new_record(seq, event));
+        Ok(seq)
+    }
+
+    async fn get_by_wallet(&self, wallet: &str) -> Result<Vec<ResultRecord>> {
+        let wallet = wallet_key(wallet);
+        let records = self.records.lock().unwrap();
+        Ok(records.iter().filter(|r| wallet_key(&r.event.user_wallet) == wallet).cloned().collect())
+    }
+
+    async fn list_since(&self, seq: u64, limit: usize) -> Result<Vec<ResultRecord>> {
+        let records = self.records.lock().unwrap();
+        Ok(records.iter().filter(|r| r.seq > seq).take(limit).cloned().collect())
+    }
+}
+
+/// Redis layout under `RESULTS_STORE_KEY_PREFIX` (default `results_store`): a `:seq` counter,
+/// a `:log` sorted set of every record scored by sequence number, and a `:wallet:<wallet>`
+/// sorted set per wallet.
+pub struct RedisResultStore {
+    redis: RedisConnector,
+    conn: tokio::sync::Mutex<Option<Connection>>,
+    prefix: String,
+}
+
+impl RedisResultStore {
+    pub fn from_env(redis: RedisConnector) -> Self {
+        Self {
+            redis,
+            conn: tokio::sync::Mutex::new(None),
+            prefix: std::env::var("RESULTS_STORE_KEY_PREFIX").unwrap_or_else(|_| "results_store".to_string()),
+        }
+    }
+
+    fn key(&self, suffix: &str) -> String {
+        format!("{}:{}", self.prefix, suffix)
+    }
+
+    /// Run `pipe` on the shared connection, dropping the connection if Redis fails.
+    async fn query<T: redis::FromRedisValue>(&self, pipe: &redis::Pipeline) -> Result<T> {
+        let mut guard = self.conn.lock().await;
+        if guard.is_none() {
+            *guard = Some(self.redis.connect().await?);
+        }
+        let conn = guard.as_mut().expect("connection was just established");
+        let result = with_timeout("result store", self.redis.timeouts().command, pipe.query_async(conn)).await;
+        if result.is_err() {
+            *guard = None;
+        }
+        result
+    }
+
+    async fn range(&self, key: String, min: String, limit: Option<usize>) -> Result<Vec<ResultRecord>> {
+        let mut pipe = redis::pipe();
+        let command = pipe.cmd("ZRANGEBYSCORE").arg(key).arg(min).arg("+inf");
+        if let Some(limit) = limit {
+            command.arg("LIMIT").arg(0).arg(limit);
+        }
+        let (members,): (Vec<String>,) = self.query(&pipe).await?;
+        members
+            .iter()
+            .map(|json| serde_json::from_str(json).map_err(|e| anyhow!("Corrupt result record: {}", e)))
+            .collect()
+    }
+}
+
+#[async_trait]
+impl ResultStore for RedisResultStore {
+    async fn append(&self, event: &VerificationResultEvent) -> Result<u64> {
+        let mut next = redis::pipe();
+        next.cmd("INCR").arg(self.key("seq"));
+        let (seq,): (u64,) = self.query(&next).await?;
+
+        let record = to_canonical_string(&new_record(seq, event))?;
+        let mut pipe = redis::pipe();
+        pipe.atomic()
+            .cmd("ZADD").arg(self.key("log")).arg(seq).arg(&record).ignore()
+            .cmd("ZADD")
+            .arg(self.key(&format!("wallet:{}", wallet_key(&event.user_wallet))))
+            .arg(seq)
+            .arg(&record)
+            .ignore();
+        self.query::<()>(&pipe).await?;
+        Ok(seq)
+    }
+
+    async fn get_by_wallet(&self, wallet: &str) -> Result<Vec<ResultRecord>> {
+        self.range(self.key(&format!("wallet:{}", wallet_key(wallet))), "-inf".to_string(), None).await
+    }
+
+    async fn list_since(&self, seq: u64, limit: usize) -> Result<Vec<ResultRecord>> {
+        self.range(self.key("log"), format!("({}", seq), Some(limit)).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::results::sample_event;
+
+    fn event(wallet: &str, message_id: &str) -> VerificationResultEvent {
+        VerificationResultEvent {
+            message_id: message_id.to_string(),
+            user_wallet: wallet.to_string(),
+            did_id: 1,
+            verification_type: "citizenship".to_string(),
+            ..sample_event()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_results_are_appended_and_read_back_by_wallet_and_sequence() {
+        // As the processor holds it
+        let store: Arc<dyn ResultStore> = Arc::new(MemoryResultStore::default());
+        let alice = format!("0x{}", "a".repeat(64));
+        let bob = format!("0x{}", "b".repeat(64));
+
+        assert_eq!(store.append(&event(&alice, "1-0")).await.unwrap(), 1);
+        assert_eq!(store.append(&event(&bob, "2-0")).await.unwrap(), 2);
+        assert_eq!(store.append(&event(&alice, "3-0")).await.unwrap(), 3);
+
+        let alices = store.get_by_wallet(&alice.to_uppercase().replace("0X", "0x")).await.unwrap();
+        let ids: Vec<&str> = alices.iter().map(|r| r.event.message_id.as_str()).collect();
+        assert_eq!(ids, vec!["1-0", "3-0"]);
+        assert!(store.get_by_wallet("0xc").await.unwrap().is_empty());
+
+        // A reader that saw seq 1 resumes after it, a page at a time
+        let page = store.list_since(1, 1).await.unwrap();
+        assert_eq!(page.iter().map(|r| r.seq).collect::<Vec<_>>(), vec![2]);
+        let rest = store.list_since(page[0].seq, 10).await.unwrap();
+        assert_eq!(rest.iter().map(|r| r.seq).collect::<Vec<_>>(), vec![3]);
+        assert!(store.list_since(3, 10).await.unwrap().is_empty());
+    }
+}
@@ -0,0 +1,289 @@
+// Delivery of verification results to the results stream and optional webhook
+use anyhow::{Result, anyhow};
+use redis::aio::Connection;
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use std::future::Future;
+use tokio::sync::Mutex;
+use tracing::{error, info};
+
+use crate::canonical_json::to_canonical_string;
+use crate::live_results::ResultFeed;
+use crate::metrics;
+use crate::redis_timeout::{with_timeout, RedisTimeouts};
+use crate::negative_attestation::SignedNegativeAttestation;
+use crate::retry::{RetryPolicy, retry_with_backoff};
+
+/// Result of a processed verification, published once the Sui calls have completed.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct VerificationResultEvent {
+    /// [`crate::message_source::VerificationMessage::key`]: the entry id with its stream, as
+    /// entry ids alone can repeat across streams.
+    pub message_id: String,
+    pub user_wallet: String,
+    pub did_id: u8,
+    pub verification_type: String,
+    pub result: String,
+    pub evidence_hash: String,
+    pub evidence_schema: String,
+    /// Evidence fields the hash commits to (see [`crate::evidence::EvidenceProfile`]); empty for upstream results.
+    #[serde(default)]
+    pub evidence_profile: String,
+    pub verified_at: String,
+    /// Enclave-signed proof of a rejection; only present when `result` is not "verified".
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub negative_attestation: Option<SignedNegativeAttestation>,
+    /// The stream the message came from, when the processor reads several.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source_stream: Option<String>,
+}
+
+/// What happened to a result after all delivery attempts.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DeliveryOutcome {
+    Delivered,
+    /// Delivery failed but the result was preserved in the results DLQ.
+    DeadLettered,
+    /// Both delivery and the DLQ write failed. This is only ever logged loudly.
+    Lost,
+}
+
+/// Try `deliver` under `policy`; once attempts are exhausted hand the error to `dead_letter`
+/// so a successful verification's notification is never silently dropped.
+pub async fn deliver_with_dead_letter<F, Fut, G, GFut>(
+    policy: &RetryPolicy,
+    target: &str,
+    deliver: F,
+    dead_letter: G,
+) -> DeliveryOutcome
+where
+    F: FnMut(u32) -> Fut,
+    Fut: Future<Output = Result<()>>,
+    G: FnOnce(String) -> GFut,
+    GFut: Future<Output = Result<()>>,
+{
+    match retry_with_backoff(policy, target, deliver).await {
+        Ok(()) => {
+            metrics::increment("results_delivered_total");
+            DeliveryOutcome::Delivered
+        }
+        Err(e) => {
+            error!("Result delivery to {} exhausted retries, moving to results DLQ: {}", target, e);
+            match dead_letter(e.to_string()).await {
+                Ok(()) => {
+                    metrics::increment("results_dlq_total");
+                    DeliveryOutcome::DeadLettered
+                }
+                Err(dlq_err) => {
+                    error!("🚨 Failed to write result to results DLQ, result LOST for {}: {}", target, dlq_err);
+                    metrics::increment("results_lost_total");
+                    DeliveryOutcome::Lost
+                }
+            }
+        }
+    }
+}
+
+/// POST a result to a webhook, treating any non-2xx status as a failure.
+pub async fn post_webhook(client: &Client, url: &str, event: &VerificationResultEvent) -> Result<()> {
+    let response = client.post(url).json(event).send().await?;
+    if !response.status().is_success() {
+        return Err(anyhow!("Webhook returned status {}", response.status()));
+    }
+    Ok(())
+}
+
+pub struct ResultPublisher {
+    http: Client,
+    results_stream: String,
+    dlq_stream: String,
+    webhook_url: Option<String>,
+    retry_policy: RetryPolicy,
+    live: Option<ResultFeed>,
+    command_timeout: std::time::Duration,
+}
+
+impl ResultPublisher {
+    pub fn from_env() -> Result<Self> {
+        let http = Client::builder()
+            .timeout(std::time::Duration::from_secs(10))
+            .build()?;
+
+        let publisher = Self {
+            http,
+            results_stream: std::env::var("RESULTS_STREAM_NAME")
+                .unwrap_or_else(|_| "verification_results".to_string()),
+            dlq_stream: std::env::var("RESULTS_DLQ_STREAM")
+                .unwrap_or_else(|_| "verification_results_dlq".to_string()),
+            webhook_url: std::env::var("RESULTS_WEBHOOK_URL").ok().filter(|u| !u.is_empty()),
+            retry_policy: RetryPolicy::from_env("RESULTS_DELIVERY"),
+            live: None,
+            command_timeout: RedisTimeouts::from_env().command,
+        };
+
+        info!("Results stream: {}, results DLQ: {}, webhook: {}",
+              publisher.results_stream,
+              publisher.dlq_stream,
+              if publisher.webhook_url.is_some() { "configured" } else { "disabled" });
+        Ok(publisher)
+    }
+
+    /// Also push every published result to `/ws/results` subscribers.
+    pub fn with_live_feed(mut self, feed: ResultFeed) -> Self {
+        self.live = Some(feed);
+        self
+    }
+
+    /// Publish a result to the results stream, live subscribers and (if configured) the webhook.
+    /// Each target retries independently and falls back to the results DLQ.
+    pub async fn publish(&self, conn: &mut Connection, event: &VerificationResultEvent) -> Vec<DeliveryOutcome> {
+        let conn = Mutex::new(conn);
+        let conn = &conn;
+        let payload = to_canonical_string(event).unwrap_or_default();
+        let payload = payload.as_str();
+        let mut outcomes = Vec::new();
+
+        let stream = self.results_stream.as_str();
+        let command_timeout = self.command_timeout;
+        outcomes.push(
+            deliver_with_dead_letter(
+                &self.retry_policy,
+                stream,
+                move |_| async move {
+                    let mut guard = conn.lock().await;
+                    let mut xadd = redis::cmd("XADD");
+                    xadd.arg(stream).arg("*").arg("event").arg(payload);
+                    with_timeout("XADD", command_timeout, xadd.query_async::<_, String>(&mut **guard))
+                        .await
+                        .map(|_| ())
+                        .map_err(|e| anyhow!("XADD to {} failed: {}", stream, e))
+                },
+                move |err| self.dead_letter(conn, stream, payload, err),
+            )
+            .await,
+        );
+
+        if let Some(feed) = &self.live {
+            feed.publish(event);
+        }
+
+        if let Some(url) = self.webhook_url.as_deref() {
+            let http = &self.http;
+            outcomes.push(
+                deliver_with_dead_letter(
+                    &self.retry_policy,
+                    "webhook",
+                    move |_| post_webhook(http, url, event),
+                    move |err| self.dead_letter(conn, "webhook", payload, err),
+                )
+                .await,
+            );
+        }
+
+        outcomes
+    }
+
+    async fn dead_letter(&self, conn: &Mutex<&mut Connection>, target: &str, payload: &str, error: String) -> Result<()> {
+        let mut guard = conn.lock().await;
+        let mut xadd = redis::cmd("XADD");
+        xadd.arg(&self.dlq_stream)
+            .arg("*")
+            .arg("event")
+            .arg(payload)
+            .arg("target")
+            .arg(target)
+            .arg("error")
+            .arg(error)
+            .arg("failed_at")
+            .arg(chrono::Utc::now().to_rfc3339());
+        with_timeout("XADD", self.command_timeout, xadd.query_async::<_, String>(&mut **guard)).await?;
+        Ok(())
+    }
+}
+
+/// A verified PAN result, for tests to adjust with struct update syntax.
+#[cfg(test)]
+pub(crate) fn sample_event() -> VerificationResultEvent {
+    VerificationResultEvent {
+        message_id: "1700000000000-0".to_string(),
+        user_wallet: format!("0x{}", "a".repeat(64)),
+        did_id: 0,
+        verification_type: "pan".to_string(),
+        result: "verified".to_string(),
+        evidence_hash: "ab".repeat(32),
+        evidence_schema: "pan_v3".to_string(),
+        evidence_profile: "full".to_string(),
+        verified_at: "2025-01-01T00:00:00+00:00".to_string(),
+        negative_attestation: None,
+        source_stream: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use std::sync::Mutex as StdMutex;
+    use tokio::time::Duration;
+
+    fn fast_policy(max_attempts: u32) -> RetryPolicy {
+        RetryPolicy {
+            max_attempts,
+            base_delay: Duration::ZERO,
+            max_delay: Duration::ZERO,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_undeliverable_webhook_lands_in_dlq() {
+        let client = Client::builder().timeout(Duration::from_secs(2)).build().unwrap();
+        let event = sample_event();
+        let attempts = AtomicU32::new(0);
+        let dlq: StdMutex<Vec<String>> = StdMutex::new(Vec::new());
+        let dlq_before = metrics::counter("results_dlq_total");
+
+        let outcome = deliver_with_dead_letter(
+            &fast_policy(3),
+            "webhook",
+            |_| {
+                attempts.fetch_add(1, Ordering::SeqCst);
+                // Nothing listens on the discard port, so every attempt is refused
+                post_webhook(&client, "http://127.0.0.1:9/results", &event)
+            },
+            |err| async {
+                dlq.lock().unwrap().push(err);
+                Ok(())
+            },
+        )
+        .await;
+
+        assert_eq!(outcome, DeliveryOutcome::DeadLettered);
+        assert_eq!(attempts.load(Ordering::SeqCst), 3);
+        assert_eq!(dlq.lock().unwrap().len(), 1);
+        assert!(metrics::counter("results_dlq_total") > dlq_before);
+    }
+
+    #[tokio::test]
+    async fn test_delivered_result_skips_dlq() {
+        let outcome = deliver_with_dead_letter(
+            &fast_policy(3),
+            "stream",
+            |_| async { Ok(()) },
+            |_| async { panic!("must not dead-letter a delivered result") },
+        )
+        .await;
+        assert_eq!(outcome, DeliveryOutcome::Delivered);
+    }
+
+    #[tokio::test]
+    async fn test_failed_dlq_write_is_reported_as_lost() {
+        let outcome = deliver_with_dead_letter(
+            &fast_policy(1),
+            "stream",
+            |_| async { Err(anyhow!("down")) },
+            |_| async { Err(anyhow!("dlq down")) },
+        )
+        .await;
+        assert_eq!(outcome, DeliveryOutcome::Lost);
+    }
+}
@@ -0,0 +1,243 @@
+// Bounded retry with exponential backoff and jitter, shared by the pipeline and delivery paths
+use anyhow::Result;
+use rand::Rng;
+use std::fmt;
+use std::future::Future;
+use tokio::time::{Duration, sleep};
+use tracing::warn;
+
+/// How many times to attempt an operation and how long to wait between attempts.
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    pub max_attempts: u32,
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            base_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(10),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Read `{PREFIX}_MAX_ATTEMPTS`, `{PREFIX}_BASE_DELAY_MS` and `{PREFIX}_MAX_DELAY_MS`,
+    /// falling back to the defaults for anything unset.
+    pub fn from_env(prefix: &str) -> Self {
+        let defaults = Self::default();
+        let read = |suffix: &str| {
+            std::env::var(format!("{}_{}", prefix, suffix))
+                .ok()
+                .and_then(|v| v.parse::<u64>().ok())
+        };
+
+        Self {
+            max_attempts: read("MAX_ATTEMPTS")
+                .map(|v| v.max(1) as u32)
+                .unwrap_or(defaults.max_attempts),
+            base_delay: read("BASE_DELAY_MS")
+                .map(Duration::from_millis)
+                .unwrap_or(defaults.base_delay),
+            max_delay: read("MAX_DELAY_MS")
+                .map(Duration::from_millis)
+                .unwrap_or(defaults.max_delay),
+        }
+    }
+
+    /// Exponential backoff for the given (1-based) attempt, capped at `max_delay`, with equal
+    /// jitter: a random delay between half of it and all of it.
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let exp = self
+            .base_delay
+            .saturating_mul(2u32.saturating_pow(attempt.saturating_sub(1)))
+            .min(self.max_delay);
+        let max_ms = exp.as_millis() as u64;
+        if max_ms == 0 {
+            return Duration::ZERO;
+        }
+        Duration::from_millis(rand::thread_rng().gen_range(max_ms / 2..=max_ms))
+    }
+}
+
+/// Longest server-requested wait that is honored; a larger `Retry-After` is clamped to this.
+pub const MAX_RETRY_AFTER: Duration = Duration::from_secs(300);
+
+/// A failure that may succeed on retry: a connection error, a 5xx or a 429 from an HTTP dependency.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TransientError {
+    pub reason: String,
+    /// Wait the server asked for (`Retry-After`), used instead of the policy's backoff.
+    pub retry_after: Option<Duration>,
+}
+
+impl fmt::Display for TransientError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Transient failure: {}", self.reason)
+    }
+}
+
+impl std::error::Error for TransientError {}
+
+pub fn is_transient(error: &anyhow::Error) -> bool {
+    error.downcast_ref::<TransientError>().is_some()
+}
+
+/// Parse a `Retry-After` value: delay-seconds or an HTTP-date (a date already past means now).
+pub fn parse_retry_after(value: &str, now: chrono::DateTime<chrono::Utc>) -> Option<Duration> {
+    let value = value.trim();
+    let delay = match value.parse::<u64>() {
+        Ok(secs) => Duration::from_secs(secs),
+        Err(_) => {
+            let at = chrono::DateTime::parse_from_rfc2822(value).ok()?;
+            (at.with_timezone(&chrono::Utc) - now).to_std().unwrap_or(Duration::ZERO)
+        }
+    };
+    Some(delay.min(MAX_RETRY_AFTER))
+}
+
+/// The `Retry-After` of an HTTP response, if present and well-formed.
+pub fn retry_after_header(headers: &reqwest::header::HeaderMap) -> Option<Duration> {
+    headers
+        .get(reqwest::header::RETRY_AFTER)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| parse_retry_after(v, chrono::Utc::now()))
+}
+
+/// Run `op` until it succeeds or `policy.max_attempts` is exhausted, returning the last error.
+/// The attempt number (starting at 1) is passed to `op`.
+pub async fn retry_with_backoff<T, F, Fut>(policy: &RetryPolicy, operation: &str, op: F) -> Result<T>
+where
+    F: FnMut(u32) -> Fut,
+    Fut: Future<Output = Result<T>>,
+{
+    retry_with_backoff_if(policy, operation, |_| true, op).await
+}
+
+/// Like [`retry_with_backoff`], but an error `should_retry` rejects is returned at once.
+/// Errors keep their type either way, so callers can still downcast them. A [`TransientError`]
+/// carrying `retry_after` waits that long instead of the policy's backoff.
+pub async fn retry_with_backoff_if<T, F, Fut, P>(
+    policy: &RetryPolicy,
+    operation: &str,
+    should_retry: P,
+    mut op: F,
+) -> Result<T>
+where
+    F: FnMut(u32) -> Fut,
+    Fut: Future<Output = Result<T>>,
+    P: Fn(&anyhow::Error) -> bool,
+{
+    let mut attempt = 1;
+    loop {
+        match op(attempt).await {
+            Ok(value) => return Ok(value),
+            Err(e) if !should_retry(&e) => return Err(e),
+            Err(e) if attempt < policy.max_attempts => {
+                let delay = e
+                    .downcast_ref::<TransientError>()
+                    .and_then(|t| t.retry_after)
+                    .unwrap_or_else(|| policy.backoff(attempt));
+                warn!("{} failed (attempt {}/{}): {} - retrying in {:?}",
+                      operation, attempt, policy.max_attempts, e, delay);
+                sleep(delay).await;
+                attempt += 1;
+            }
+            Err(e) => {
+                let message = format!("{} failed after {} attempts: {}", operation, attempt, e);
+                return Err(e.context(message));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::anyhow;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    fn fast_policy(max_attempts: u32) -> RetryPolicy {
+        RetryPolicy {
+            max_attempts,
+            base_delay: Duration::ZERO,
+            max_delay: Duration::ZERO,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_retry_succeeds_after_transient_failures() {
+        let calls = AtomicU32::new(0);
+        let result = retry_with_backoff(&fast_policy(3), "op", |attempt| {
+            calls.fetch_add(1, Ordering::SeqCst);
+            async move {
+                if attempt < 3 {
+                    Err(anyhow!("transient"))
+                } else {
+                    Ok(attempt)
+                }
+            }
+        })
+        .await;
+
+        assert_eq!(result.unwrap(), 3);
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_retry_gives_up_after_max_attempts() {
+        let calls = AtomicU32::new(0);
+        let result: Result<()> = retry_with_backoff(&fast_policy(2), "op", |_| {
+            calls.fetch_add(1, Ordering::SeqCst);
+            async { Err(anyhow!("down")) }
+        })
+        .await;
+
+        assert!(result.unwrap_err().to_string().contains("after 2 attempts"));
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_non_retryable_error_is_returned_at_once() {
+        let calls = AtomicU32::new(0);
+        let result: Result<()> = retry_with_backoff_if(&fast_policy(3), "op", is_transient, |attempt| {
+            calls.fetch_add(1, Ordering::SeqCst);
+            async move {
+                if attempt == 1 {
+                    Err(TransientError { reason: "502".to_string(), retry_after: None }.into())
+                } else {
+                    Err(anyhow!("transaction failed"))
+                }
+            }
+        })
+        .await;
+
+        assert_eq!(result.unwrap_err().to_string(), "transaction failed");
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn test_retry_after_accepts_seconds_and_http_dates() {
+        let now = chrono::DateTime::parse_from_rfc2822("Wed, 21 Oct 2015 07:28:00 GMT").unwrap().with_timezone(&chrono::Utc);
+        assert_eq!(parse_retry_after("2", now), Some(Duration::from_secs(2)));
+        assert_eq!(parse_retry_after("Wed, 21 Oct 2015 07:28:30 GMT", now), Some(Duration::from_secs(30)));
+        assert_eq!(parse_retry_after("Wed, 21 Oct 2015 07:00:00 GMT", now), Some(Duration::ZERO));
+        assert_eq!(parse_retry_after("86400", now), Some(MAX_RETRY_AFTER));
+        assert_eq!(parse_retry_after("soon", now), None);
+    }
+
+    #[test]
+    fn test_backoff_is_capped() {
+        let policy = RetryPolicy {
+            max_attempts: 10,
+            base_delay: Duration::from_millis(100),
+            max_delay: Duration::from_millis(400),
+        };
+        assert!(policy.backoff(1) <= Duration::from_millis(100));
+        assert!(policy.backoff(8) <= Duration::from_millis(400));
+        assert!(policy.backoff(8) >= Duration::from_millis(200));
+    }
+}
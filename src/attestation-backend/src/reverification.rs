@@ -0,0 +1,183 @@
+// Scheduled re-verification: re-check old verifications so on-chain status doesn't go stale
+use anyhow::Result;
+use redis::aio::Connection;
+use serde::{Deserialize, Serialize};
+use tokio::time::Duration;
+use tracing::warn;
+
+use crate::government_api::VerificationRequest;
+use crate::redis_timeout::{with_timeout, RedisTimeouts};
+use crate::sealed_blob::{SealedBlob, SealingKey};
+
+/// A verified wallet kept in the re-verification index, with what is needed to re-check it
+/// and to update the existing UserDID.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IndexedVerification {
+    pub request: VerificationRequest,
+    pub user_did_id: String,
+    pub verified_at_ms: u64,
+}
+
+impl IndexedVerification {
+    pub fn member(&self) -> String {
+        format!("{}:{}", self.request.user_wallet, self.request.did_id)
+    }
+}
+
+/// Members whose last verification is older than `max_age`, oldest first, at most `limit`.
+/// `entries` are (member, verified_at_ms) pairs.
+pub fn select_stale(mut entries: Vec<(String, u64)>, now_ms: u64, max_age: Duration, limit: usize) -> Vec<String> {
+    let cutoff = now_ms.saturating_sub(max_age.as_millis() as u64);
+    entries.sort_by_key(|(_, verified_at_ms)| *verified_at_ms);
+    entries
+        .into_iter()
+        .filter(|(_, verified_at_ms)| *verified_at_ms <= cutoff)
+        .take(limit)
+        .map(|(member, _)| member)
+        .collect()
+}
+
+/// Re-verification settings: `REVERIFY_ENABLED`, `REVERIFY_MAX_AGE_SECS` (default 30 days),
+/// `REVERIFY_INTERVAL_SECS` (default 1h), `REVERIFY_BATCH_SIZE` (re-checks per interval, default 10),
+/// `REVERIFY_INDEX_KEY` and `REVERIFY_TTL_SECS` (default 90 days, never less than one max age plus
+/// one interval). Records hold the request's document data, so they are sealed (which needs a
+/// `KEY_SEALING` key that outlives a restart), and the index expires once nothing has been written
+/// to it for the TTL.
+#[derive(Debug, Clone)]
+pub struct Reverification {
+    pub enabled: bool,
+    pub max_age: Duration,
+    pub interval: Duration,
+    pub batch_size: usize,
+    pub ttl: Duration,
+    index_key: String,
+    sealing_key: SealingKey,
+    command_timeout: Duration,
+}
+
+impl Reverification {
+    pub fn from_env(sealing_key: SealingKey) -> Self {
+        let parse = |name: &str, default: u64| {
+            std::env::var(name)
+                .ok()
+                .and_then(|v| v.parse::<u64>().ok())
+                .unwrap_or(default)
+        };
+        let max_age = Duration::from_secs(parse("REVERIFY_MAX_AGE_SECS", 30 * 24 * 3600));
+        let interval = Duration::from_secs(parse("REVERIFY_INTERVAL_SECS", 3600).max(1));
+        Self {
+            enabled: std::env::var("REVERIFY_ENABLED")
+                .map(|v| v == "1" || v.eq_ignore_ascii_case("true"))
+                .unwrap_or(false),
+            max_age,
+            interval,
+            batch_size: parse("REVERIFY_BATCH_SIZE", 10) as usize,
+            // An entry must outlive the wait until it is due and the run that re-checks it
+            ttl: Duration::from_secs(parse("REVERIFY_TTL_SECS", 90 * 24 * 3600)).max(max_age + interval),
+            index_key: std::env::var("REVERIFY_INDEX_KEY").unwrap_or_else(|_| "verification_index".to_string()),
+            sealing_key,
+            command_timeout: RedisTimeouts::from_env().command,
+        }
+    }
+
+    fn records_key(&self) -> String {
+        format!("{}:records", self.index_key)
+    }
+
+    /// Add or refresh a wallet in the index: a sorted set scored by verification time,
+    /// plus a hash holding each member's record. Both keys get a fresh TTL.
+    pub async fn record(&self, conn: &mut Connection, entry: &IndexedVerification) -> Result<()> {
+        let member = entry.member();
+        let records_key = self.records_key();
+        let ttl_ms = self.ttl.as_millis() as u64;
+        let mut pipe = redis::pipe();
+        pipe.atomic()
+            .cmd("ZADD")
+            .arg(&self.index_key)
+            .arg(entry.verified_at_ms)
+            .arg(&member)
+            .ignore()
+            .cmd("HSET")
+            .arg(&records_key)
+            .arg(&member)
+            .arg(serde_json::to_string(&SealedBlob::seal_json(&self.sealing_key, entry)?)?)
+            .ignore()
+            .cmd("PEXPIRE")
+            .arg(&self.index_key)
+            .arg(ttl_ms)
+            .ignore()
+            .cmd("PEXPIRE")
+            .arg(&records_key)
+            .arg(ttl_ms)
+            .ignore();
+        with_timeout("HSET", self.command_timeout, pipe.query_async::<_, ()>(conn)).await?;
+        Ok(())
+    }
+
+    /// Drop a wallet from the index, e.g. once it has been revoked.
+    pub async fn remove(&self, conn: &mut Connection, member: &str) -> Result<()> {
+        let mut zrem = redis::cmd("ZREM");
+        zrem.arg(&self.index_key).arg(member);
+        with_timeout("ZREM", self.command_timeout, zrem.query_async::<_, i64>(conn)).await?;
+        let mut hdel = redis::cmd("HDEL");
+        hdel.arg(self.records_key()).arg(member);
+        with_timeout("HDEL", self.command_timeout, hdel.query_async::<_, i64>(conn)).await?;
+        Ok(())
+    }
+
+    /// Up to `batch_size` stale entries, oldest first.
+    pub async fn due(&self, conn: &mut Connection, now_ms: u64) -> Result<Vec<IndexedVerification>> {
+        let cutoff = now_ms.saturating_sub(self.max_age.as_millis() as u64);
+        let mut range = redis::cmd("ZRANGEBYSCORE");
+        range.arg(&self.index_key)
+            .arg("-inf")
+            .arg(cutoff)
+            .arg("WITHSCORES")
+            .arg("LIMIT")
+            .arg(0)
+            .arg(self.batch_size);
+        let scored: Vec<(String, u64)> = with_timeout("ZRANGEBYSCORE", self.command_timeout, range.query_async(conn)).await?;
+
+        let mut due = Vec::new();
+        for member in select_stale(scored, now_ms, self.max_age, self.batch_size) {
+            let mut hget = redis::cmd("HGET");
+            hget.arg(self.records_key()).arg(&member);
+            let record: Option<String> = with_timeout("HGET", self.command_timeout, hget.query_async(conn)).await?;
+            let entry = record.and_then(|r| {
+                let sealed: SealedBlob = serde_json::from_str(&r).ok()?;
+                sealed.open_json::<IndexedVerification>(&self.sealing_key).ok()
+            });
+            match entry {
+                Some(entry) => due.push(entry),
+                // Index entry without a usable record (or sealed under a lost key) can never be re-checked
+                None => {
+                    warn!("Dropping {} from the re-verification index: its record doesn't open", member);
+                    self.remove(conn, &member).await?
+                }
+            }
+        }
+        Ok(due)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DAY_MS: u64 = 24 * 3600 * 1000;
+
+    #[test]
+    fn test_stale_wallet_is_selected_for_reverification() {
+        let now_ms = 100 * DAY_MS;
+        let entries = vec![
+            ("0xfresh:0".to_string(), now_ms - DAY_MS),
+            ("0xstale:0".to_string(), now_ms - 45 * DAY_MS),
+            ("0xstaler:1".to_string(), now_ms - 90 * DAY_MS),
+        ];
+
+        let max_age = Duration::from_secs(30 * 24 * 3600);
+        assert_eq!(select_stale(entries.clone(), now_ms, max_age, 10), vec!["0xstaler:1", "0xstale:0"]);
+        // The batch size caps re-checks per run, oldest first
+        assert_eq!(select_stale(entries, now_ms, max_age, 1), vec!["0xstaler:1"]);
+    }
+}
@@ -0,0 +1,155 @@
+// Encryption at rest for cached KYC document data, under a key only the enclave holds
+use aes_gcm::aead::{Aead, KeyInit};
+use aes_gcm::{Aes256Gcm, Nonce};
+use anyhow::{Result, anyhow};
+use fastcrypto::ed25519::Ed25519KeyPair;
+use fastcrypto::traits::ToFromBytes;
+use hkdf::Hkdf;
+use rand::RngCore;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::fmt;
+
+/// Stream and request fields holding document data: sealed wherever we copy them to Redis.
+pub const PII_FIELDS: [&str; 3] = ["document_data", "extracted_data", "user_corrections"];
+
+/// AES-GCM nonce length; random per blob, so a key may seal about 2^32 blobs.
+const NONCE_BYTES: usize = 12;
+
+/// Key for [`SealedBlob`]s. Derived from the enclave keypair, so it never leaves the enclave and
+/// is as stable as the keypair: blobs outlive a restart only when `KEY_SEALING` keeps the key.
+#[derive(Clone)]
+pub struct SealingKey([u8; 32]);
+
+impl SealingKey {
+    pub fn new(key: [u8; 32]) -> Self {
+        Self(key)
+    }
+
+    pub fn from_keypair(kp: &Ed25519KeyPair) -> Self {
+        let mut key = [0u8; 32];
+        Hkdf::<Sha256>::new(None, kp.as_bytes())
+            .expand(b"enclave-pii-sealing", &mut key)
+            .expect("32 bytes is a valid HKDF-SHA256 output length");
+        Self(key)
+    }
+
+    fn cipher(&self) -> Aes256Gcm {
+        Aes256Gcm::new(&self.0.into())
+    }
+}
+
+impl fmt::Debug for SealingKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("SealingKey(***)")
+    }
+}
+
+/// PII encrypted with AES-256-GCM under a [`SealingKey`], the only form in which it may be
+/// written to Redis or kept in a cache. All fields are hex.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SealedBlob {
+    pub nonce: String,
+    /// The ciphertext followed by the GCM tag.
+    pub ciphertext: String,
+}
+
+impl SealedBlob {
+    pub fn seal(key: &SealingKey, plaintext: &[u8]) -> Self {
+        let mut nonce = [0u8; NONCE_BYTES];
+        rand::thread_rng().fill_bytes(&mut nonce);
+        let ciphertext = key
+            .cipher()
+            .encrypt(&Nonce::from(nonce), plaintext)
+            .expect("AES-GCM encryption only fails on inputs over 64 GiB");
+        Self { nonce: hex::encode(nonce), ciphertext: hex::encode(ciphertext) }
+    }
+
+    /// The plaintext, failing on a wrong key or any tampering.
+    pub fn open(&self, key: &SealingKey) -> Result<Vec<u8>> {
+        let nonce: [u8; NONCE_BYTES] = hex::decode(&self.nonce)?
+            .try_into()
+            .map_err(|nonce: Vec<u8>| anyhow!("Sealed blob nonce must be {} bytes, got {}", NONCE_BYTES, nonce.len()))?;
+        key.cipher()
+            .decrypt(&Nonce::from(nonce), hex::decode(&self.ciphertext)?.as_slice())
+            .map_err(|_| anyhow!("Sealed blob failed authentication"))
+    }
+
+    pub fn seal_json<T: Serialize>(key: &SealingKey, value: &T) -> Result<Self> {
+        Ok(Self::seal(key, &serde_json::to_vec(value)?))
+    }
+
+    pub fn open_json<T: DeserializeOwned>(&self, key: &SealingKey) -> Result<T> {
+        Ok(serde_json::from_slice(&self.open(key)?)?)
+    }
+}
+
+/// `text` sealed and encoded as one Redis field value.
+pub fn seal_text(key: &SealingKey, text: &str) -> String {
+    serde_json::to_string(&SealedBlob::seal(key, text.as_bytes())).expect("a sealed blob always serializes")
+}
+
+/// The text in a field value written by [`seal_text`].
+pub fn open_text(key: &SealingKey, sealed: &str) -> Result<String> {
+    let blob: SealedBlob = serde_json::from_str(sealed).map_err(|e| anyhow!("Field is not a sealed blob: {}", e))?;
+    Ok(String::from_utf8(blob.open(key)?)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::government_api::DocumentData;
+    use fastcrypto::traits::KeyPair;
+
+    fn document() -> DocumentData {
+        DocumentData {
+            entity: None,
+            pan: "HJTPB9891M".to_string(),
+            name_as_per_pan: "Ravi Kumar".to_string(),
+            date_of_birth: "15/08/1990".to_string(),
+            phone_number: Some("9876543210".to_string()),
+            consent: "Y".to_string(),
+            reason: "KYC onboarding".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_sealed_document_round_trips_and_is_not_plaintext() {
+        let key = SealingKey::from_keypair(&Ed25519KeyPair::generate(&mut rand::thread_rng()));
+        let sealed = SealedBlob::seal_json(&key, &document()).unwrap();
+
+        // What would be written to Redis carries none of the document's fields
+        let stored = serde_json::to_string(&sealed).unwrap();
+        for field in ["HJTPB9891M", "Ravi Kumar", "15/08/1990", "9876543210", "KYC onboarding"] {
+            assert!(!stored.contains(field), "{} readable in {}", field, stored);
+            assert!(!stored.contains(&hex::encode(field)), "{} readable in {}", field, stored);
+        }
+
+        let restored: SealedBlob = serde_json::from_str(&stored).unwrap();
+        let opened: DocumentData = restored.open_json(&key).unwrap();
+        assert_eq!(opened.pan, "HJTPB9891M");
+        assert_eq!(opened.name_as_per_pan, "Ravi Kumar");
+
+        // Same plaintext, fresh nonce: equal documents are not linkable at rest
+        assert_ne!(SealedBlob::seal_json(&key, &document()).unwrap().ciphertext, sealed.ciphertext);
+    }
+
+    #[test]
+    fn test_sealed_blob_rejects_wrong_key_and_tampering() {
+        let key = SealingKey::new([7u8; 32]);
+        let sealed = SealedBlob::seal(&key, &[0x42; 100]);
+        assert_eq!(sealed.open(&key).unwrap(), vec![0x42; 100]);
+
+        assert!(sealed.open(&SealingKey::new([8u8; 32])).is_err());
+        let mut tampered = sealed.clone();
+        tampered.ciphertext.replace_range(0..2, if sealed.ciphertext.starts_with("00") { "01" } else { "00" });
+        assert!(tampered.open(&key).is_err());
+
+        // As a field value
+        let field = seal_text(&key, r#"{"pan":"HJTPB9891M"}"#);
+        assert!(!field.contains("HJTPB9891M"));
+        assert_eq!(open_text(&key, &field).unwrap(), r#"{"pan":"HJTPB9891M"}"#);
+        assert!(open_text(&key, r#"{"pan":"HJTPB9891M"}"#).is_err());
+    }
+}
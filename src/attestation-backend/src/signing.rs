@@ -0,0 +1,30 @@
+// Fallible signing, so hardware-backed or rotating keys can report failures instead of panicking
+use fastcrypto::ed25519::{Ed25519KeyPair, Ed25519Signature};
+use fastcrypto::traits::Signer;
+use std::fmt;
+
+/// The enclave key could not produce a signature. Nothing was signed; retrying later can succeed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SigningError {
+    pub reason: String,
+}
+
+impl fmt::Display for SigningError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Signing failed: {}", self.reason)
+    }
+}
+
+impl std::error::Error for SigningError {}
+
+/// A key the enclave signs responses and verifications with.
+pub trait EnclaveSigner {
+    fn try_sign(&self, msg: &[u8]) -> Result<Ed25519Signature, SigningError>;
+}
+
+/// In-memory keys cannot fail to sign.
+impl EnclaveSigner for Ed25519KeyPair {
+    fn try_sign(&self, msg: &[u8]) -> Result<Ed25519Signature, SigningError> {
+        Ok(self.sign(msg))
+    }
+}
@@ -0,0 +1,53 @@
+// Per-message latency of each pipeline stage, as histograms and one log line per message
+use std::future::Future;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::metrics;
+
+/// Times the stages of one message. Each stage is observed in the `message_stage_{stage}_seconds`
+/// histogram as it finishes, and kept so the whole breakdown can be logged with the message.
+/// Shared by reference, so a stage can be timed from inside a closure.
+#[derive(Debug, Default)]
+pub struct StageTimer {
+    stages: Mutex<Vec<(&'static str, Duration)>>,
+}
+
+impl StageTimer {
+    /// Await `stage`, recording how long it took whether or not it succeeded.
+    pub async fn time<F: Future>(&self, stage: &'static str, future: F) -> F::Output {
+        let started = Instant::now();
+        let output = future.await;
+        self.record(stage, started.elapsed());
+        output
+    }
+
+    /// Run a synchronous `stage`.
+    pub fn time_sync<T>(&self, stage: &'static str, run: impl FnOnce() -> T) -> T {
+        let started = Instant::now();
+        let output = run();
+        self.record(stage, started.elapsed());
+        output
+    }
+
+    pub fn record(&self, stage: &'static str, elapsed: Duration) {
+        metrics::observe(&format!("message_stage_{}_seconds", stage), elapsed.as_secs_f64());
+        self.stages.lock().unwrap().push((stage, elapsed));
+    }
+
+    /// Stages timed so far, in the order they finished.
+    pub fn stages(&self) -> Vec<(&'static str, Duration)> {
+        self.stages.lock().unwrap().clone()
+    }
+
+    /// `stage=1.2ms` pairs, for the log.
+    pub fn summary(&self) -> String {
+        self.stages
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(stage, elapsed)| format!("{}={:.1}ms", stage, elapsed.as_secs_f64() * 1000.0))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
@@ -0,0 +1,279 @@
+// Periodic XTRIM of the verification stream, never past an entry a consumer group still needs
+use anyhow::{Result, anyhow};
+use redis::aio::Connection;
+use redis::streams::{StreamInfoGroupsReply, StreamPendingReply, StreamRangeReply};
+use std::fmt;
+use tokio::time::{Duration, sleep};
+use tracing::{info, warn};
+
+use crate::message_source::stream_names_from_env;
+use crate::metrics;
+use crate::redis_timeout::{with_timeout, RedisTimeouts};
+use crate::verification_processor::RedisConnector;
+
+/// A stream entry id, `<ms>-<seq>`, ordered the way Redis orders them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct EntryId {
+    pub ms: u64,
+    pub seq: u64,
+}
+
+impl EntryId {
+    pub fn parse(id: &str) -> Option<Self> {
+        let (ms, seq) = id.split_once('-')?;
+        Some(Self {
+            ms: ms.parse().ok()?,
+            seq: seq.parse().ok()?,
+        })
+    }
+
+    /// The smallest id after this one.
+    pub fn next(self) -> Self {
+        match self.seq.checked_add(1) {
+            Some(seq) => Self { ms: self.ms, seq },
+            None => Self { ms: self.ms + 1, seq: 0 },
+        }
+    }
+}
+
+impl fmt::Display for EntryId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}-{}", self.ms, self.seq)
+    }
+}
+
+/// What one consumer group still needs from the stream.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GroupProgress {
+    pub last_delivered: EntryId,
+    /// Oldest entry delivered but not yet acked, if any.
+    pub lowest_pending: Option<EntryId>,
+}
+
+impl GroupProgress {
+    /// Oldest entry the group may still read: its oldest pending entry, or else the first undelivered one.
+    pub fn needed_from(&self) -> EntryId {
+        self.lowest_pending.unwrap_or_else(|| self.last_delivered.next())
+    }
+}
+
+/// Oldest id that may be trimmed up to, keeping `margin_ms` of history before the entry the
+/// furthest-behind group still needs. `None` (trim nothing) when the stream has no groups.
+pub fn trim_floor(groups: &[GroupProgress], margin_ms: u64) -> Option<EntryId> {
+    let needed = groups.iter().map(GroupProgress::needed_from).min()?;
+    if margin_ms == 0 {
+        return Some(needed);
+    }
+    Some(EntryId {
+        ms: needed.ms.saturating_sub(margin_ms),
+        seq: 0,
+    })
+}
+
+/// `MINID` to trim to: what retention asks for, but never past the floor.
+pub fn trim_target(retention: Option<EntryId>, floor: Option<EntryId>) -> Option<EntryId> {
+    Some(retention?.min(floor?))
+}
+
+/// Most entries one run reads to find where `MaxLen` cuts; a larger excess is trimmed over several runs.
+const MAXLEN_SCAN_COUNT: u64 = 10_000;
+
+/// How many of the oldest entries to read to find the `MaxLen` cut: the ones over `max_len`,
+/// at most [`MAXLEN_SCAN_COUNT`]. `None` when the stream is within its length.
+pub fn maxlen_excess(len: u64, max_len: usize) -> Option<u64> {
+    let excess = len.saturating_sub(max_len as u64);
+    (excess > 0).then(|| excess.min(MAXLEN_SCAN_COUNT))
+}
+
+/// How much of the stream to keep, from `STREAM_TRIM_POLICY` (`maxlen`, `maxage` or `off`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TrimPolicy {
+    Off,
+    /// Keep about the newest N entries (`STREAM_TRIM_MAXLEN`).
+    MaxLen(usize),
+    /// Keep entries newer than this (`STREAM_TRIM_MAX_AGE_SECS`).
+    MaxAge(Duration),
+}
+
+/// Trim settings: policy, `STREAM_TRIM_INTERVAL_SECS` and `STREAM_TRIM_SAFETY_MARGIN_MS`.
+#[derive(Debug, Clone)]
+pub struct StreamTrimConfig {
+    /// Every stream the processor consumes.
+    pub streams: Vec<String>,
+    pub policy: TrimPolicy,
+    pub interval: Duration,
+    pub margin_ms: u64,
+    pub command_timeout: Duration,
+}
+
+impl StreamTrimConfig {
+    pub fn from_env() -> Result<Self> {
+        let parse = |name: &str, default: u64| {
+            std::env::var(name)
+                .ok()
+                .and_then(|v| v.parse::<u64>().ok())
+                .unwrap_or(default)
+        };
+        let policy = match std::env::var("STREAM_TRIM_POLICY").unwrap_or_else(|_| "maxlen".to_string()).to_lowercase().as_str() {
+            "off" => TrimPolicy::Off,
+            "maxlen" => TrimPolicy::MaxLen(parse("STREAM_TRIM_MAXLEN", 100_000).max(1) as usize),
+            "maxage" => TrimPolicy::MaxAge(Duration::from_secs(parse("STREAM_TRIM_MAX_AGE_SECS", 7 * 24 * 3600))),
+            other => return Err(anyhow!("Unknown STREAM_TRIM_POLICY: {}", other)),
+        };
+        Ok(Self {
+            streams: stream_names_from_env(),
+            policy,
+            interval: Duration::from_secs(parse("STREAM_TRIM_INTERVAL_SECS", 300).max(1)),
+            margin_ms: parse("STREAM_TRIM_SAFETY_MARGIN_MS", 60_000),
+            command_timeout: RedisTimeouts::from_env().command,
+        })
+    }
+}
+
+/// Where every consumer group on the stream is.
+async fn group_progress(conn: &mut Connection, stream_name: &str, timeout: Duration) -> Result<Vec<GroupProgress>> {
+    let mut xinfo = redis::cmd("XINFO");
+    xinfo.arg("GROUPS").arg(stream_name);
+    let reply: StreamInfoGroupsReply = with_timeout("XINFO", timeout, xinfo.query_async(conn)).await?;
+    let mut groups = Vec::new();
+    for group in reply.groups {
+        let last_delivered = EntryId::parse(&group.last_delivered_id)
+            .ok_or_else(|| anyhow!("Bad last-delivered-id {} for group {}", group.last_delivered_id, group.name))?;
+        let mut xpending = redis::cmd("XPENDING");
+        xpending.arg(stream_name).arg(&group.name);
+        let pending: StreamPendingReply = with_timeout("XPENDING", timeout, xpending.query_async(conn)).await?;
+        let lowest_pending = match pending {
+            StreamPendingReply::Data(data) => Some(
+                EntryId::parse(&data.start_id)
+                    .ok_or_else(|| anyhow!("Bad pending id {} for group {}", data.start_id, group.name))?,
+            ),
+            StreamPendingReply::Empty => None,
+        };
+        groups.push(GroupProgress { last_delivered, lowest_pending });
+    }
+    Ok(groups)
+}
+
+/// Oldest id the retention policy keeps, or `None` if it keeps everything.
+async fn retention_id(conn: &mut Connection, config: &StreamTrimConfig, stream_name: &str) -> Result<Option<EntryId>> {
+    match config.policy {
+        TrimPolicy::Off => Ok(None),
+        TrimPolicy::MaxAge(age) => {
+            let now_ms = chrono::Utc::now().timestamp_millis() as u64;
+            Ok(Some(EntryId { ms: now_ms.saturating_sub(age.as_millis() as u64), seq: 0 }))
+        }
+        TrimPolicy::MaxLen(max_len) => {
+            // Read only the entries past the limit, from the old end, not the ones being kept
+            let mut xlen = redis::cmd("XLEN");
+            xlen.arg(stream_name);
+            let len: u64 = with_timeout("XLEN", config.command_timeout, xlen.query_async(conn)).await?;
+            let Some(excess) = maxlen_excess(len, max_len) else {
+                return Ok(None);
+            };
+            let mut range = redis::cmd("XRANGE");
+            range.arg(stream_name).arg("-").arg("+").arg("COUNT").arg(excess);
+            let oldest: StreamRangeReply = with_timeout("XRANGE", config.command_timeout, range.query_async(conn)).await?;
+            Ok(oldest.ids.last().and_then(|entry| EntryId::parse(&entry.id)).map(EntryId::next))
+        }
+    }
+}
+
+/// Trim every stream once; returns how many entries were removed.
+pub async fn trim_once(conn: &mut Connection, config: &StreamTrimConfig) -> Result<u64> {
+    let mut trimmed = 0;
+    for stream_name in &config.streams {
+        trimmed += trim_stream(conn, config, stream_name).await?;
+    }
+    Ok(trimmed)
+}
+
+async fn trim_stream(conn: &mut Connection, config: &StreamTrimConfig, stream_name: &str) -> Result<u64> {
+    let groups = group_progress(conn, stream_name, config.command_timeout).await?;
+    let floor = trim_floor(&groups, config.margin_ms);
+    let Some(target) = trim_target(retention_id(conn, config, stream_name).await?, floor) else {
+        return Ok(0);
+    };
+    // Approximate trimming only drops whole radix-tree nodes, so it never goes past the target
+    let mut xtrim = redis::cmd("XTRIM");
+    xtrim.arg(stream_name).arg("MINID").arg("~").arg(target.to_string());
+    let trimmed: u64 = with_timeout("XTRIM", config.command_timeout, xtrim.query_async(conn)).await?;
+    Ok(trimmed)
+}
+
+/// Background task: trim the stream every interval. Failures are logged and retried next interval.
+pub async fn run_stream_trim_task(redis: RedisConnector) -> Result<()> {
+    let config = StreamTrimConfig::from_env()?;
+    if config.policy == TrimPolicy::Off {
+        info!("Stream trimming is off");
+        return Ok(());
+    }
+    let streams = config.streams.join(",");
+    info!("✂️  Trimming {} every {}s ({:?})", streams, config.interval.as_secs(), config.policy);
+
+    let mut conn = None;
+    loop {
+        sleep(config.interval).await;
+        if conn.is_none() {
+            conn = redis.connect().await.map_err(|e| warn!("Stream trim Redis connect failed: {}", e)).ok();
+        }
+        let Some(c) = conn.as_mut() else { continue };
+        match trim_once(c, &config).await {
+            Ok(trimmed) => {
+                metrics::increment_by("stream_entries_trimmed_total", trimmed);
+                if trimmed > 0 {
+                    info!("✂️  Trimmed {} entries from {}", trimmed, streams);
+                }
+            }
+            Err(e) => {
+                warn!("Failed to trim {}: {}", streams, e);
+                conn = None;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn id(ms: u64) -> EntryId {
+        EntryId { ms, seq: 0 }
+    }
+
+    #[test]
+    fn test_trim_keeps_pending_and_removes_acked_entries() {
+        // Entries 1..=10; group A acked 1..=5, has 6 pending and has been delivered up to 8.
+        // Group B is caught up and acked everything.
+        let stream: Vec<EntryId> = (1..=10).map(id).collect();
+        let groups = [
+            GroupProgress { last_delivered: id(8), lowest_pending: Some(id(6)) },
+            GroupProgress { last_delivered: id(10), lowest_pending: None },
+        ];
+
+        // Retention alone would keep only the newest two entries
+        let target = trim_target(Some(id(9)), trim_floor(&groups, 0)).unwrap();
+        let kept: Vec<EntryId> = stream.iter().copied().filter(|e| *e >= target).collect();
+        assert_eq!(kept, (6..=10).map(id).collect::<Vec<_>>());
+
+        // With nothing pending, undelivered entries are still kept
+        let caught_up = [GroupProgress { last_delivered: id(8), lowest_pending: None }];
+        assert_eq!(trim_target(Some(id(10)), trim_floor(&caught_up, 0)), Some(EntryId { ms: 8, seq: 1 }));
+
+        // No groups or no retention limit: nothing is trimmed
+        assert_eq!(trim_target(Some(id(9)), trim_floor(&[], 0)), None);
+        assert_eq!(trim_target(None, trim_floor(&groups, 0)), None);
+
+        // The safety margin keeps extra history before the pending entry
+        assert_eq!(trim_floor(&groups, 4), Some(id(2)));
+        assert_eq!(EntryId::parse("1700000000000-3"), Some(EntryId { ms: 1_700_000_000_000, seq: 3 }));
+    }
+
+    #[test]
+    fn test_maxlen_reads_only_the_excess() {
+        assert_eq!(maxlen_excess(90, 100), None);
+        assert_eq!(maxlen_excess(100, 100), None);
+        assert_eq!(maxlen_excess(130, 100), Some(30));
+        // A huge backlog is trimmed a bounded chunk at a time
+        assert_eq!(maxlen_excess(1_000_000, 100), Some(MAXLEN_SCAN_COUNT));
+    }
+}
@@ -0,0 +1,283 @@
+// Guard for signature timestamps against the on-chain Clock, so enclave clock drift can't get
+// update_verification_status rejected for a timestamp in the future
+use anyhow::{Result, anyhow};
+use std::fmt;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+
+use crate::metrics;
+use crate::sui_proxy::{proxy_base_url, read_json, UpstreamUnavailable};
+use crate::verification_processor::DEFAULT_SUI_CLOCK_ID;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClockDriftConfig {
+    /// Timestamps up to this far ahead of the Sui clock are clamped to it; further ahead is refused.
+    pub max_ahead_ms: u64,
+    /// Drift above this is logged, so a misconfigured enclave clock gets noticed.
+    pub warn_drift_ms: u64,
+    /// How long a Clock reading is reused (advanced by local elapsed time) before re-fetching.
+    pub cache_ttl: Duration,
+    /// At startup the Clock must read within this of wall-clock time.
+    pub startup_max_skew_ms: u64,
+}
+
+impl Default for ClockDriftConfig {
+    fn default() -> Self {
+        Self {
+            max_ahead_ms: 30_000,
+            warn_drift_ms: 2_000,
+            cache_ttl: Duration::from_secs(60),
+            startup_max_skew_ms: 300_000,
+        }
+    }
+}
+
+impl ClockDriftConfig {
+    /// `SUI_CLOCK_MAX_AHEAD_MS` (default 30000), `SUI_CLOCK_WARN_DRIFT_MS` (default 2000),
+    /// `SUI_CLOCK_CACHE_SECS` (default 60) and `SUI_CLOCK_STARTUP_MAX_SKEW_SECS` (default 300).
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        let var = |name: &str| std::env::var(name).ok().and_then(|v| v.parse::<u64>().ok());
+        Self {
+            max_ahead_ms: var("SUI_CLOCK_MAX_AHEAD_MS").unwrap_or(defaults.max_ahead_ms),
+            warn_drift_ms: var("SUI_CLOCK_WARN_DRIFT_MS").unwrap_or(defaults.warn_drift_ms),
+            cache_ttl: var("SUI_CLOCK_CACHE_SECS").map(Duration::from_secs).unwrap_or(defaults.cache_ttl),
+            startup_max_skew_ms: var("SUI_CLOCK_STARTUP_MAX_SKEW_SECS")
+                .map(|secs| secs.saturating_mul(1_000))
+                .unwrap_or(defaults.startup_max_skew_ms),
+        }
+    }
+
+    /// The timestamp to submit for `timestamp_ms`, given the Sui clock at `sui_now_ms`.
+    pub fn check(&self, timestamp_ms: u64, sui_now_ms: u64) -> Result<u64, ClockSkewError> {
+        let drift_ms = timestamp_ms.abs_diff(sui_now_ms);
+        if drift_ms > self.warn_drift_ms {
+            warn!("🕰️ Signature timestamp {} is {}ms {} the Sui clock ({}); check the enclave clock",
+                  timestamp_ms, drift_ms, if timestamp_ms > sui_now_ms { "ahead of" } else { "behind" }, sui_now_ms);
+        }
+        if timestamp_ms <= sui_now_ms {
+            return Ok(timestamp_ms);
+        }
+        if drift_ms > self.max_ahead_ms {
+            metrics::increment("sui_clock_skew_rejections_total");
+            return Err(ClockSkewError { ahead_ms: drift_ms, max_ahead_ms: self.max_ahead_ms });
+        }
+        metrics::increment("sui_clock_skew_clamped_total");
+        Ok(sui_now_ms)
+    }
+}
+
+/// A signature timestamp is further ahead of the Sui clock than `SUI_CLOCK_MAX_AHEAD_MS`.
+/// Retrying later can succeed once Sui time catches up.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClockSkewError {
+    pub ahead_ms: u64,
+    pub max_ahead_ms: u64,
+}
+
+impl fmt::Display for ClockSkewError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Signature timestamp is {}ms ahead of the Sui clock, more than the allowed {}ms",
+               self.ahead_ms, self.max_ahead_ms)
+    }
+}
+
+impl std::error::Error for ClockSkewError {}
+
+/// A Clock reading `sui_now_ms` is plausible at wall-clock `wall_now_ms`. Anything further off
+/// means `SUI_CLOCK_ID` is not the live Clock, or the node or enclave clock is badly wrong.
+pub fn check_clock_reading(sui_now_ms: u64, wall_now_ms: u64, max_skew_ms: u64) -> Result<()> {
+    let skew_ms = sui_now_ms.abs_diff(wall_now_ms);
+    if skew_ms > max_skew_ms {
+        return Err(anyhow!(
+            "Sui clock reads {} but wall-clock time is {} ({}s apart, at most {}s allowed)",
+            sui_now_ms, wall_now_ms, skew_ms / 1_000, max_skew_ms / 1_000
+        ));
+    }
+    Ok(())
+}
+
+/// Move type of the system Clock object.
+pub const CLOCK_TYPE: &str = "0x2::clock::Clock";
+
+/// `timestamp_ms` from the `sui client object <clock> --json` output. An object of another
+/// type is refused, even if it happens to have a `timestamp_ms` field.
+pub fn parse_clock_timestamp(stdout: &str) -> Result<u64> {
+    let object: serde_json::Value = serde_json::from_str(stdout)?;
+    if let Some(object_type) = object["content"]["type"].as_str() {
+        if object_type != CLOCK_TYPE {
+            return Err(anyhow!("Object is a {}, not a {}", object_type, CLOCK_TYPE));
+        }
+    }
+    let fields = &object["content"]["fields"];
+    let timestamp = &fields["timestamp_ms"];
+    timestamp
+        .as_u64()
+        .or_else(|| timestamp.as_str().and_then(|s| s.parse().ok()))
+        .ok_or_else(|| anyhow!("Clock object has no timestamp_ms: {}", fields))
+}
+
+/// The Sui Clock, read through the proxy and cached for [`ClockDriftConfig::cache_ttl`].
+pub struct SuiClock {
+    config: ClockDriftConfig,
+    clock_id: String,
+    base_url: String,
+    client: reqwest::Client,
+    // Last reading and when it was taken
+    cached: Mutex<Option<(u64, Instant)>>,
+}
+
+impl SuiClock {
+    pub fn new(config: ClockDriftConfig, clock_id: &str, base_url: &str) -> Self {
+        Self {
+            config,
+            clock_id: clock_id.to_string(),
+            base_url: base_url.to_string(),
+            client: reqwest::Client::new(),
+            cached: Mutex::new(None),
+        }
+    }
+
+    pub fn from_env() -> Self {
+        let clock_id = std::env::var("SUI_CLOCK_ID").unwrap_or_else(|_| DEFAULT_SUI_CLOCK_ID.to_string());
+        Self::new(ClockDriftConfig::from_env(), &clock_id, &proxy_base_url())
+    }
+
+    async fn fetch(&self) -> Result<u64> {
+        let url = format!("{}/sui/client/object", self.base_url);
+        let response = self
+            .client
+            .get(&url)
+            .query(&[("id", self.clock_id.as_str()), ("json", "1")])
+            .send()
+            .await?;
+        let result = read_json(&url, response).await?;
+        if !result["success"].as_bool().unwrap_or(false) {
+            return Err(anyhow!(
+                "sui client object failed: {}",
+                result["stderr"].as_str().or(result["error"].as_str()).unwrap_or("unknown error")
+            ));
+        }
+        parse_clock_timestamp(result["stdout"].as_str().unwrap_or(""))
+    }
+
+    /// Current Sui time: the cached reading advanced by the time since it was taken.
+    pub async fn now_ms(&self) -> Result<u64> {
+        if let Some((reading, taken)) = *self.cached.lock().unwrap() {
+            if taken.elapsed() < self.config.cache_ttl {
+                return Ok(reading + taken.elapsed().as_millis() as u64);
+            }
+        }
+        let reading = self.fetch().await?;
+        *self.cached.lock().unwrap() = Some((reading, Instant::now()));
+        Ok(reading)
+    }
+
+    /// Startup check that the configured clock object is the live Clock. Fails on a wrong
+    /// object or an implausible reading; an unreachable proxy is only logged.
+    pub async fn check_at_startup(&self) -> Result<()> {
+        let reading = match self.fetch().await {
+            Ok(reading) => reading,
+            Err(e) if e.downcast_ref::<reqwest::Error>().is_some() || e.downcast_ref::<UpstreamUnavailable>().is_some() => {
+                warn!("🕰️ Could not reach the Sui proxy to check the clock: {}", e);
+                return Ok(());
+            }
+            Err(e) => return Err(anyhow!("SUI_CLOCK_ID {} is not a readable Clock: {}", self.clock_id, e)),
+        };
+        let wall_now_ms = chrono::Utc::now().timestamp_millis().max(0) as u64;
+        check_clock_reading(reading, wall_now_ms, self.config.startup_max_skew_ms)
+            .map_err(|e| anyhow!("SUI_CLOCK_ID {}: {}", self.clock_id, e))?;
+        info!("🕰️ Sui clock {} reads {} ({}ms from wall-clock)", self.clock_id, reading, reading.abs_diff(wall_now_ms));
+        *self.cached.lock().unwrap() = Some((reading, Instant::now()));
+        Ok(())
+    }
+
+    /// The timestamp to submit for `timestamp_ms`. When the clock can't be read the timestamp
+    /// is submitted unchanged; a failed read shouldn't stop every update.
+    pub async fn guard(&self, timestamp_ms: u64) -> Result<u64> {
+        match self.now_ms().await {
+            Ok(sui_now_ms) => Ok(self.config.check(timestamp_ms, sui_now_ms)?),
+            Err(e) => {
+                warn!("🕰️ Could not read the Sui clock, submitting timestamp unchecked: {}", e);
+                Ok(timestamp_ms)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::routing::get;
+    use axum::{Json, Router};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    const SUI_NOW_MS: u64 = 1_735_689_600_000;
+
+    #[test]
+    fn test_future_timestamps_are_clamped_or_refused() {
+        let config = ClockDriftConfig { max_ahead_ms: 5_000, ..ClockDriftConfig::default() };
+
+        // Behind the chain is what the contract expects
+        assert_eq!(config.check(SUI_NOW_MS - 60_000, SUI_NOW_MS), Ok(SUI_NOW_MS - 60_000));
+        // A little ahead is pulled back to Sui time
+        assert_eq!(config.check(SUI_NOW_MS + 4_000, SUI_NOW_MS), Ok(SUI_NOW_MS));
+        // Too far ahead is refused rather than submitted
+        assert_eq!(
+            config.check(SUI_NOW_MS + 60_000, SUI_NOW_MS),
+            Err(ClockSkewError { ahead_ms: 60_000, max_ahead_ms: 5_000 })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_clock_is_read_once_and_cached() {
+        let reads = Arc::new(AtomicUsize::new(0));
+        let counter = reads.clone();
+        let app = Router::new().route(
+            "/sui/client/object",
+            get(move || {
+                counter.fetch_add(1, Ordering::SeqCst);
+                let object = serde_json::json!({
+                    "objectId": "0x6",
+                    "content": { "dataType": "moveObject", "fields": { "timestamp_ms": SUI_NOW_MS.to_string() } }
+                });
+                async move { Json(serde_json::json!({ "success": true, "stdout": object.to_string() })) }
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        let config = ClockDriftConfig { max_ahead_ms: 5_000, ..ClockDriftConfig::default() };
+        let clock = SuiClock::new(config, DEFAULT_SUI_CLOCK_ID, &format!("http://{}", addr));
+        assert_eq!(clock.guard(SUI_NOW_MS - 1).await.unwrap(), SUI_NOW_MS - 1);
+        let clamped = clock.guard(SUI_NOW_MS + 1_000).await.unwrap();
+        assert!((SUI_NOW_MS..SUI_NOW_MS + 1_000).contains(&clamped), "{}", clamped);
+        let error = clock.guard(SUI_NOW_MS + 600_000).await.unwrap_err();
+        assert!(error.downcast_ref::<ClockSkewError>().is_some(), "{}", error);
+        assert_eq!(reads.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn test_implausible_clock_readings_fail_the_startup_check() {
+        let wall_now_ms = SUI_NOW_MS;
+        let max_skew_ms = ClockDriftConfig::default().startup_max_skew_ms;
+        assert!(check_clock_reading(SUI_NOW_MS - 2_000, wall_now_ms, max_skew_ms).is_ok());
+
+        // A stale or wrong object, far from now either way
+        let stale = check_clock_reading(SUI_NOW_MS - 86_400_000, wall_now_ms, max_skew_ms).unwrap_err();
+        assert!(stale.to_string().contains("86400s apart"), "{}", stale);
+        assert!(check_clock_reading(0, wall_now_ms, max_skew_ms).is_err());
+        assert!(check_clock_reading(SUI_NOW_MS + 600_000, wall_now_ms, max_skew_ms).is_err());
+
+        // Some other object with a timestamp_ms field is not the Clock
+        let other = serde_json::json!({
+            "content": { "type": "0xabc::oracle::Feed", "fields": { "timestamp_ms": SUI_NOW_MS.to_string() } }
+        });
+        assert!(parse_clock_timestamp(&other.to_string()).unwrap_err().to_string().contains(CLOCK_TYPE));
+        let clock = serde_json::json!({ "content": { "type": CLOCK_TYPE, "fields": { "timestamp_ms": SUI_NOW_MS } } });
+        assert_eq!(parse_clock_timestamp(&clock.to_string()).unwrap(), SUI_NOW_MS);
+    }
+}
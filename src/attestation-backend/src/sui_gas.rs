@@ -0,0 +1,487 @@
+// Gas coins for the Sui signer: balance pre-flight from `sui client gas --json` and a per-transaction coin pool
+use anyhow::{Result, anyhow};
+use serde::{Deserialize, Deserializer, Serialize};
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use tokio::sync::{watch, Semaphore, SemaphorePermit};
+use tokio::time::{sleep, Duration};
+use tracing::{error, info, warn};
+
+use crate::metrics;
+use crate::retry::RetryPolicy;
+use crate::sui_proxy::{post_with_retry, proxy_base_url, read_json, SuiCallRequest};
+
+/// Gas budget for a contract call when none is configured, in MIST.
+pub const CALL_GAS_BUDGET_MIST: u64 = 10_000_000;
+
+/// Gas budget per contract call. `SUI_GAS_BUDGET_DEFAULT` (default 10000000) applies unless
+/// `SUI_GAS_BUDGETS` has an entry for the function, or for the function and verification type:
+/// a comma separated list like `start_verification=20000000,start_verification:pan=15000000`.
+/// With `SUI_GAS_DRY_RUN_MARGIN_PERCENT` set, calls without an entry are dry-run first and
+/// budgeted at the dry run's cost plus that margin.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GasBudgets {
+    pub default_mist: u64,
+    /// `function` or `function:verification_type` -> budget in MIST.
+    pub overrides: BTreeMap<String, u64>,
+    pub dry_run_margin_percent: Option<u64>,
+}
+
+impl Default for GasBudgets {
+    fn default() -> Self {
+        Self {
+            default_mist: CALL_GAS_BUDGET_MIST,
+            overrides: BTreeMap::new(),
+            dry_run_margin_percent: None,
+        }
+    }
+}
+
+impl GasBudgets {
+    pub fn from_env() -> Result<Self> {
+        let mut budgets = Self::parse(&std::env::var("SUI_GAS_BUDGETS").unwrap_or_default())?;
+        if let Ok(value) = std::env::var("SUI_GAS_BUDGET_DEFAULT") {
+            budgets.default_mist = value
+                .trim()
+                .parse()
+                .map_err(|_| anyhow!("Invalid SUI_GAS_BUDGET_DEFAULT: {}", value))?;
+        }
+        budgets.dry_run_margin_percent = std::env::var("SUI_GAS_DRY_RUN_MARGIN_PERCENT")
+            .ok()
+            .and_then(|v| v.trim().parse().ok());
+        Ok(budgets)
+    }
+
+    /// Parse a comma separated `function[:verification_type]=mist` list.
+    pub fn parse(table: &str) -> Result<Self> {
+        let mut overrides = BTreeMap::new();
+        for entry in table.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+            let (target, mist) = entry
+                .split_once('=')
+                .ok_or_else(|| anyhow!("Invalid gas budget entry (expected function[:type]=mist): {}", entry))?;
+            let mist = mist
+                .trim()
+                .parse::<u64>()
+                .map_err(|_| anyhow!("Invalid gas budget in entry: {}", entry))?;
+            overrides.insert(target.trim().to_lowercase(), mist);
+        }
+        Ok(Self { overrides, ..Self::default() })
+    }
+
+    /// The budget set for `function` and `verification_type`, if any.
+    pub fn configured(&self, function: &str, verification_type: &str) -> Option<u64> {
+        let function = function.to_lowercase();
+        self.overrides
+            .get(&format!("{}:{}", function, verification_type.trim().to_lowercase()))
+            .or_else(|| self.overrides.get(&function))
+            .copied()
+    }
+
+    /// The configured budget, or the default.
+    pub fn budget_for(&self, function: &str, verification_type: &str) -> u64 {
+        self.configured(function, verification_type).unwrap_or(self.default_mist)
+    }
+
+    /// `call` with its budget set for `verification_type`. A dry run through the proxy at `url`
+    /// only happens when enabled and nothing is configured; if it fails, the default is used.
+    pub async fn apply(
+        &self,
+        client: &reqwest::Client,
+        policy: &RetryPolicy,
+        url: &str,
+        mut call: SuiCallRequest,
+        verification_type: &str,
+    ) -> SuiCallRequest {
+        call.gas_budget = self.budget_for(&call.function, verification_type);
+        let Some(margin) = self.dry_run_margin_percent else { return call };
+        if self.configured(&call.function, verification_type).is_some() {
+            return call;
+        }
+        match dry_run_cost(client, policy, url, &call).await {
+            Ok(cost) => {
+                call.gas_budget = cost.saturating_mul(100 + margin) / 100;
+                info!("⛽ {} dry run cost {} MIST, budgeting {}", call.function, cost, call.gas_budget);
+            }
+            Err(e) => warn!("⛽ {} dry run failed, using the default budget: {}", call.function, e),
+        }
+        call
+    }
+}
+
+/// Gas cost of a dry run: computation plus storage, before any rebate.
+pub fn parse_dry_run_cost(stdout: &str) -> Result<u64> {
+    let response: serde_json::Value = serde_json::from_str(stdout)?;
+    let gas_used = &response["effects"]["gasUsed"];
+    let cost = |field: &str| {
+        let value = &gas_used[field];
+        value
+            .as_u64()
+            .or_else(|| value.as_str().and_then(|s| s.parse().ok()))
+            .ok_or_else(|| anyhow!("Dry run output has no gasUsed.{}", field))
+    };
+    Ok(cost("computationCost")? + cost("storageCost")?)
+}
+
+async fn dry_run_cost(client: &reqwest::Client, policy: &RetryPolicy, url: &str, call: &SuiCallRequest) -> Result<u64> {
+    let body = serde_json::to_value(call.clone().dry_run())?;
+    let result = post_with_retry(client, policy, url, &body).await?;
+    if !result["success"].as_bool().unwrap_or(false) {
+        return Err(anyhow!("{}", result["stderr"].as_str().unwrap_or("unknown error")));
+    }
+    parse_dry_run_cost(result["stdout"].as_str().unwrap_or(""))
+}
+
+/// One gas coin as listed by `sui client gas --json`.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GasCoin {
+    pub gas_coin_id: String,
+    #[serde(deserialize_with = "deserialize_mist")]
+    pub mist_balance: u64,
+}
+
+/// Older CLI versions print the balance as a string, newer ones as a number.
+fn deserialize_mist<'de, D>(deserializer: D) -> Result<u64, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Mist {
+        Number(u64),
+        Text(String),
+    }
+
+    match Mist::deserialize(deserializer)? {
+        Mist::Number(n) => Ok(n),
+        Mist::Text(s) => s.parse().map_err(serde::de::Error::custom),
+    }
+}
+
+pub fn parse_gas_coins(json: &str) -> Result<Vec<GasCoin>> {
+    serde_json::from_str(json).map_err(|e| anyhow!("Failed to parse gas coin listing: {}", e))
+}
+
+pub fn total_balance(coins: &[GasCoin]) -> u64 {
+    coins.iter().map(|c| c.mist_balance).sum()
+}
+
+/// Pre-flight gas check settings: `SUI_MIN_GAS_BALANCE_MIST` (default: budget for 5 calls)
+/// and `SUI_GAS_CHECK_STRICT`, which fails startup instead of warning.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GasCheckConfig {
+    pub min_balance_mist: u64,
+    pub strict: bool,
+}
+
+impl GasCheckConfig {
+    pub fn from_env() -> Self {
+        Self {
+            min_balance_mist: std::env::var("SUI_MIN_GAS_BALANCE_MIST")
+                .ok()
+                .and_then(|v| v.parse::<u64>().ok())
+                .unwrap_or(5 * CALL_GAS_BUDGET_MIST),
+            strict: std::env::var("SUI_GAS_CHECK_STRICT")
+                .map(|v| v == "1" || v.eq_ignore_ascii_case("true"))
+                .unwrap_or(false),
+        }
+    }
+
+    /// Compare a gas listing against the minimum; an error only when strict.
+    pub fn evaluate(&self, coins: &[GasCoin]) -> Result<u64> {
+        let total = total_balance(coins);
+        metrics::set_gauge("sui_gas_balance_mist", total as f64);
+        if total >= self.min_balance_mist {
+            info!("⛽ Gas balance {} MIST across {} coins", total, coins.len());
+            return Ok(total);
+        }
+
+        let message = format!(
+            "Gas balance {} MIST across {} coins is below the minimum of {} MIST",
+            total,
+            coins.len(),
+            self.min_balance_mist
+        );
+        if self.strict {
+            return Err(anyhow!(message));
+        }
+        warn!("⛽ {}", message);
+        Ok(total)
+    }
+}
+
+/// Fetch the signer's gas coins from the Sui proxy.
+pub async fn fetch_gas_coins() -> Result<Vec<GasCoin>> {
+    let url = format!("{}/sui/client/gas", proxy_base_url());
+    let response = reqwest::Client::new().get(&url).query(&[("json", "1")]).send().await?;
+    let result = read_json(&url, response).await?;
+
+    if !result["success"].as_bool().unwrap_or(false) {
+        return Err(anyhow!(
+            "sui client gas failed: {}",
+            result["stderr"].as_str().or(result["error"].as_str()).unwrap_or("unknown error")
+        ));
+    }
+    parse_gas_coins(result["stdout"].as_str().unwrap_or("[]"))
+}
+
+/// Startup pre-flight: check the signer can afford a few transactions.
+pub async fn check_gas_balance() -> Result<()> {
+    let config = GasCheckConfig::from_env();
+    match fetch_gas_coins().await {
+        Ok(coins) => config.evaluate(&coins).map(|_| ()),
+        Err(e) if config.strict => Err(e),
+        Err(e) => {
+            warn!("⛽ Could not check gas balance: {}", e);
+            Ok(())
+        }
+    }
+}
+
+/// Low-gas pause for the running processor: fetching stops while the balance is below
+/// `SUI_GAS_PAUSE_BELOW_MIST` (default: one call's budget; 0 disables), checked every
+/// `SUI_GAS_CHECK_INTERVAL_SECS` (default 60).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GasPauseConfig {
+    pub floor_mist: u64,
+    pub interval: Duration,
+}
+
+impl GasPauseConfig {
+    pub fn from_env() -> Self {
+        let parse = |name: &str, default: u64| {
+            std::env::var(name)
+                .ok()
+                .and_then(|v| v.parse::<u64>().ok())
+                .unwrap_or(default)
+        };
+        Self {
+            floor_mist: parse("SUI_GAS_PAUSE_BELOW_MIST", CALL_GAS_BUDGET_MIST),
+            interval: Duration::from_secs(parse("SUI_GAS_CHECK_INTERVAL_SECS", 60).max(1)),
+        }
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.floor_mist > 0
+    }
+}
+
+/// Whether message fetching is paused for low gas. Clones share one state.
+#[derive(Debug, Clone)]
+pub struct GasGate {
+    paused: Arc<watch::Sender<bool>>,
+}
+
+impl Default for GasGate {
+    fn default() -> Self {
+        Self { paused: Arc::new(watch::Sender::new(false)) }
+    }
+}
+
+impl GasGate {
+    pub fn is_paused(&self) -> bool {
+        *self.paused.borrow()
+    }
+
+    /// Pause or resume for a fresh `balance` reading. Pausing is counted in
+    /// `sui_gas_pauses_total`; `sui_gas_paused` is 1 while paused.
+    pub fn observe(&self, balance: u64, floor_mist: u64) -> bool {
+        let paused = balance < floor_mist;
+        let was_paused = self.paused.send_replace(paused);
+        metrics::set_gauge("sui_gas_paused", if paused { 1.0 } else { 0.0 });
+        match (was_paused, paused) {
+            (false, true) => {
+                metrics::increment("sui_gas_pauses_total");
+                error!("⛽ Gas balance {} MIST is below {} MIST, pausing message fetching", balance, floor_mist);
+            }
+            (true, false) => info!("⛽ Gas balance back to {} MIST, resuming message fetching", balance),
+            _ => {}
+        }
+        paused
+    }
+
+    /// Wait until the gate is not paused.
+    pub async fn wait_until_funded(&self) {
+        let mut paused = self.paused.subscribe();
+        let _ = paused.wait_for(|paused| !*paused).await;
+    }
+}
+
+/// Poll the signer's balance and pause `gate` while it is below the floor. A failed check
+/// leaves the gate as it was.
+pub async fn run_gas_monitor(gate: GasGate, config: GasPauseConfig) {
+    loop {
+        match fetch_gas_coins().await {
+            Ok(coins) => {
+                let total = total_balance(&coins);
+                metrics::set_gauge("sui_gas_balance_mist", total as f64);
+                gate.observe(total, config.floor_mist);
+            }
+            Err(e) => warn!("⛽ Could not check gas balance: {}", e),
+        }
+        sleep(config.interval).await;
+    }
+}
+
+/// Gas coins handed out one per in-flight transaction, so concurrent calls never
+/// contend for (or equivocate on) the same gas object. Read from `SUI_GAS_COINS`;
+/// an empty pool leaves gas selection to the CLI.
+#[derive(Debug)]
+pub struct GasCoinPool {
+    coins: Mutex<VecDeque<String>>,
+    available: Semaphore,
+    size: usize,
+}
+
+impl GasCoinPool {
+    pub fn new(coins: Vec<String>) -> Self {
+        metrics::set_gauge("sui_gas_coins_available", coins.len() as f64);
+        Self {
+            available: Semaphore::new(coins.len()),
+            size: coins.len(),
+            coins: Mutex::new(coins.into()),
+        }
+    }
+
+    pub fn from_env() -> Self {
+        Self::from_var("SUI_GAS_COINS")
+    }
+
+    /// A pool of the comma separated coin IDs in `var`.
+    pub fn from_var(var: &str) -> Self {
+        let coins: Vec<String> = std::env::var(var)
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|c| !c.is_empty())
+            .map(str::to_string)
+            .collect();
+        if !coins.is_empty() {
+            info!("⛽ Rotating across {} gas coins", coins.len());
+        }
+        Self::new(coins)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.size == 0
+    }
+
+    /// Take a coin for one transaction, waiting while all coins are in use.
+    /// With an empty pool this returns immediately with no coin.
+    pub async fn acquire(&self) -> GasCoinLease<'_> {
+        if self.is_empty() {
+            return GasCoinLease { pool: self, coin: None, _permit: None };
+        }
+        let permit = self.available.acquire().await.expect("gas coin semaphore is never closed");
+        let mut coins = self.coins.lock().unwrap();
+        let coin = coins.pop_front();
+        metrics::set_gauge("sui_gas_coins_available", coins.len() as f64);
+        GasCoinLease { pool: self, coin, _permit: Some(permit) }
+    }
+}
+
+/// A gas coin checked out of a [`GasCoinPool`]; returned to the pool on drop.
+#[derive(Debug)]
+pub struct GasCoinLease<'a> {
+    pool: &'a GasCoinPool,
+    coin: Option<String>,
+    _permit: Option<SemaphorePermit<'a>>,
+}
+
+impl GasCoinLease<'_> {
+    pub fn coin(&self) -> Option<&str> {
+        self.coin.as_deref()
+    }
+}
+
+impl Drop for GasCoinLease<'_> {
+    fn drop(&mut self) {
+        // Runs before the permit is released, so a woken waiter always finds a coin
+        if let Some(coin) = self.coin.take() {
+            let mut coins = self.pool.coins.lock().unwrap();
+            coins.push_back(coin);
+            metrics::set_gauge("sui_gas_coins_available", coins.len() as f64);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_concurrent_submissions_draw_distinct_coins() {
+        let pool = GasCoinPool::new(vec!["0xa".to_string(), "0xb".to_string(), "0xc".to_string()]);
+        let (a, b, c) = tokio::join!(pool.acquire(), pool.acquire(), pool.acquire());
+        let coins: HashSet<&str> = [a.coin(), b.coin(), c.coin()].into_iter().flatten().collect();
+        assert_eq!(coins.len(), 3);
+
+        // A fourth submission waits until a coin comes back
+        assert!(tokio::time::timeout(Duration::from_millis(20), pool.acquire()).await.is_err());
+        let released = b.coin().unwrap().to_string();
+        drop(b);
+        let d = pool.acquire().await;
+        assert_eq!(d.coin(), Some(released.as_str()));
+
+        // No pool configured: calls proceed without pinning a coin
+        assert_eq!(GasCoinPool::new(Vec::new()).acquire().await.coin(), None);
+    }
+
+    const GAS_JSON: &str = r#"[
+        {"gasCoinId": "0x1a2b", "mistBalance": 30000000, "suiBalance": "0.03"},
+        {"gasCoinId": "0x3c4d", "mistBalance": "12000000", "suiBalance": "0.012"}
+    ]"#;
+
+    #[test]
+    fn test_parse_gas_listing_and_total() {
+        let coins = parse_gas_coins(GAS_JSON).unwrap();
+        assert_eq!(coins.len(), 2);
+        assert_eq!(coins[1].gas_coin_id, "0x3c4d");
+        assert_eq!(total_balance(&coins), 42_000_000);
+
+        let lenient = GasCheckConfig { min_balance_mist: 50_000_000, strict: false };
+        assert_eq!(lenient.evaluate(&coins).unwrap(), 42_000_000);
+        let strict = GasCheckConfig { min_balance_mist: 50_000_000, strict: true };
+        assert!(strict.evaluate(&coins).unwrap_err().to_string().contains("42000000"));
+        assert!(parse_gas_coins("No gas coins").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_configured_budget_is_used_for_the_operation() {
+        let budgets = GasBudgets::parse("start_verification=20000000, start_verification:PAN=15000000").unwrap();
+        assert_eq!(budgets.budget_for("start_verification", "pan"), 15_000_000);
+        assert_eq!(budgets.budget_for("start_verification", "citizenship"), 20_000_000);
+        assert_eq!(budgets.budget_for("update_verification_status", "pan"), CALL_GAS_BUDGET_MIST);
+        assert!(GasBudgets::parse("start_verification").is_err());
+
+        // Dry runs only fill in for calls without a configured budget
+        let dry_runs = Arc::new(Mutex::new(Vec::new()));
+        let seen = dry_runs.clone();
+        let app = axum::Router::new().route(
+            "/sui/client/call",
+            axum::routing::post(move |axum::Json(body): axum::Json<serde_json::Value>| {
+                seen.lock().unwrap().push(body["function"].as_str().unwrap().to_string());
+                let effects = serde_json::json!({
+                    "effects": { "gasUsed": { "computationCost": "1000000", "storageCost": "3000000", "storageRebate": "900000" } }
+                });
+                async move { axum::Json(serde_json::json!({ "success": true, "stdout": effects.to_string() })) }
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/sui/client/call", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        let budgets = GasBudgets { dry_run_margin_percent: Some(50), ..budgets };
+        let client = reqwest::Client::new();
+        let policy = RetryPolicy::default();
+        let call = |function: &str| SuiCallRequest::new("0xpkg", "did_registry", function, Vec::new(), 0);
+        let start = budgets.apply(&client, &policy, &url, call("start_verification"), "pan").await;
+        assert_eq!(start.gas_budget, 15_000_000);
+        let update = budgets.apply(&client, &policy, &url, call("update_verification_status"), "pan").await;
+        assert_eq!(update.gas_budget, 6_000_000);
+        assert!(!update.dry_run);
+        assert_eq!(*dry_runs.lock().unwrap(), vec!["update_verification_status"]);
+    }
+}
@@ -0,0 +1,169 @@
+// Pure parsers for `sui client call` output, which is untrusted text from the proxy
+use serde_json::Value;
+use tracing::info;
+
+/// Move type suffix of the object `start_verification` creates.
+const USER_DID_TYPE: &str = "::did_registry::UserDID";
+
+/// Lines after an `ObjectID:` searched for its `ObjectType:`.
+const OBJECT_TYPE_LOOKAHEAD: usize = 4;
+
+/// `0x` followed by 1 to 64 hex digits.
+pub fn is_object_id(value: &str) -> bool {
+    value
+        .strip_prefix("0x")
+        .is_some_and(|hex| (1..=64).contains(&hex.len()) && hex.chars().all(|c| c.is_ascii_hexdigit()))
+}
+
+/// The UserDID object created by a `start_verification` call, from either the CLI's `--json`
+/// output or its human-readable table. Never panics; anything returned is an [`is_object_id`].
+pub fn extract_user_did_id(output: &str) -> Option<String> {
+    let user_did_id = match serde_json::from_str::<Value>(output.trim()) {
+        Ok(json) => user_did_from_json(&json),
+        Err(_) => user_did_from_text(output),
+    };
+    if let Some(id) = &user_did_id {
+        info!("Found UserDID object: {}", id);
+    }
+    user_did_id
+}
+
+/// `objectChanges` entry of type `created` whose `objectType` is a UserDID.
+fn user_did_from_json(json: &Value) -> Option<String> {
+    json.get("objectChanges")?.as_array()?.iter().find_map(|change| {
+        let created = change.get("type")?.as_str()? == "created";
+        let object_type = change.get("objectType")?.as_str()?;
+        let object_id = change.get("objectId")?.as_str()?;
+        (created && object_type.ends_with(USER_DID_TYPE) && is_object_id(object_id)).then(|| object_id.to_string())
+    })
+}
+
+/// Digest of the transaction in `sui client call` output: the `digest` of the `--json` output, or
+/// the `Transaction Digest:` line of the table. Only base58 characters are accepted.
+pub fn extract_transaction_digest(output: &str) -> Option<String> {
+    let digest = match serde_json::from_str::<Value>(output.trim()) {
+        Ok(json) => json.get("digest")?.as_str()?.to_string(),
+        Err(_) => output
+            .lines()
+            .find_map(|line| line.split_once("Transaction Digest:"))
+            .and_then(|(_, rest)| rest.split_whitespace().next())?
+            .to_string(),
+    };
+    (!digest.is_empty() && digest.chars().all(|c| c.is_ascii_alphanumeric())).then_some(digest)
+}
+
+/// An `ObjectID: 0x…` line followed within a few lines, and before the next object, by an
+/// `ObjectType:` line naming a UserDID.
+fn user_did_from_text(output: &str) -> Option<String> {
+    let lines: Vec<&str> = output.lines().collect();
+    lines.iter().enumerate().find_map(|(i, line)| {
+        let (_, rest) = line.split_once("ObjectID:")?;
+        let object_id = rest.split_whitespace().next().filter(|token| is_object_id(token))?;
+        lines
+            .iter()
+            .skip(i + 1)
+            .take(OBJECT_TYPE_LOOKAHEAD)
+            .take_while(|next| !next.contains("ObjectID:"))
+            .any(|next| next.contains("ObjectType:") && next.contains(USER_DID_TYPE))
+            .then(|| object_id.to_string())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    const USER_DID: &str = "0x6ec40d30e636afb906e621748ee60a9b72bc59a39325adda43deadd28dc89e09::did_registry::UserDID";
+
+    fn table(object_id: &str, object_type: &str) -> String {
+        format!(
+            "╭──────────╮\n│ Created Objects: │\n│  ┌──\n│  │ ObjectID: {} │\n│  │ Sender: 0x1 │\n│  │ Owner: Account Address ( 0x1 ) │\n│  │ ObjectType: {} │\n│  └──\n╰──────────╯",
+            object_id, object_type
+        )
+    }
+
+    #[test]
+    fn test_user_did_is_found_in_text_and_json_output() {
+        let id = format!("0x{}", "ab".repeat(32));
+        assert_eq!(extract_user_did_id(&table(&id, USER_DID)), Some(id.clone()));
+        assert_eq!(extract_user_did_id(&table(&id, "0x2::coin::Coin<0x2::sui::SUI>")), None);
+        // Not an object id, however it looks
+        assert_eq!(extract_user_did_id(&table("0xzz", USER_DID)), None);
+
+        let json = serde_json::json!({ "objectChanges": [
+            { "type": "mutated", "objectType": USER_DID, "objectId": "0x1" },
+            { "type": "created", "objectType": USER_DID, "objectId": id },
+        ]});
+        assert_eq!(extract_user_did_id(&json.to_string()), Some(id));
+    }
+
+    #[test]
+    fn test_transaction_digest_is_found_in_text_and_json_output() {
+        let text = "╭──╮\n│ Transaction Digest: 9xQeWvG816bUx9EPjHmaT23yvVM2ZWbrrpZb9PusVFin │\n╰──╯";
+        assert_eq!(extract_transaction_digest(text).as_deref(), Some("9xQeWvG816bUx9EPjHmaT23yvVM2ZWbrrpZb9PusVFin"));
+        let json = serde_json::json!({ "digest": "Fq3Hd1", "objectChanges": [] });
+        assert_eq!(extract_transaction_digest(&json.to_string()).as_deref(), Some("Fq3Hd1"));
+        assert_eq!(extract_transaction_digest("Transaction Digest: <none>"), None);
+        assert_eq!(extract_transaction_digest("no digest here"), None);
+    }
+
+    /// Text made of the pieces the parser looks for, in any order, plus multibyte noise.
+    fn adversarial_output() -> impl Strategy<Value = String> {
+        let fragment = prop_oneof![
+            Just("ObjectID:".to_string()),
+            Just("ObjectType:".to_string()),
+            Just(USER_DID.to_string()),
+            Just("0x".to_string()),
+            Just("\n".to_string()),
+            Just(" ".to_string()),
+            Just("│".to_string()),
+            Just("é\u{200b}".to_string()),
+            "[0-9a-fA-Fg-z]{0,70}",
+        ];
+        prop::collection::vec(fragment, 0..40).prop_map(|fragments| fragments.concat())
+    }
+
+    fn json_output() -> impl Strategy<Value = String> {
+        let change = (
+            prop_oneof![Just("created".to_string()), ".{0,8}"],
+            prop_oneof![Just(USER_DID.to_string()), ".{0,40}"],
+            prop_oneof!["0x[0-9a-f]{1,64}", ".{0,70}"],
+        )
+            .prop_map(|(kind, object_type, object_id)| {
+                serde_json::json!({ "type": kind, "objectType": object_type, "objectId": object_id })
+            });
+        prop::collection::vec(change, 0..5)
+            .prop_map(|changes| serde_json::json!({ "objectChanges": changes }).to_string())
+    }
+
+    proptest! {
+        #[test]
+        fn prop_arbitrary_output_never_yields_a_malformed_id(output in any::<String>()) {
+            if let Some(id) = extract_user_did_id(&output) {
+                prop_assert!(is_object_id(&id));
+            }
+        }
+
+        #[test]
+        fn prop_adversarial_text_only_yields_ids_it_contains(output in adversarial_output()) {
+            if let Some(id) = extract_user_did_id(&output) {
+                prop_assert!(is_object_id(&id));
+                prop_assert!(output.contains(&id));
+            }
+        }
+
+        #[test]
+        fn prop_json_output_only_yields_well_formed_ids(output in json_output()) {
+            if let Some(id) = extract_user_did_id(&output) {
+                prop_assert!(is_object_id(&id));
+                prop_assert!(output.contains(&id));
+            }
+        }
+
+        #[test]
+        fn prop_any_valid_id_is_found_in_a_table(id in "0x[0-9a-fA-F]{1,64}") {
+            prop_assert_eq!(extract_user_did_id(&table(&id, USER_DID)), Some(id));
+        }
+    }
+}
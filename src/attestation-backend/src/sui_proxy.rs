@@ -0,0 +1,543 @@
+// HTTP calls to the host-side Sui CLI proxy (sui_proxy.py), retried on transient failures
+use anyhow::Result;
+use reqwest::Client;
+use serde::{Serialize, Serializer};
+use serde_json::Value;
+use std::fmt;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+
+use crate::call_limit::CallLimit;
+use crate::metrics;
+use crate::retry::{is_transient, retry_after_header, retry_with_backoff_if, RetryPolicy, TransientError};
+
+/// Base URL of the proxy, from `SUI_PROXY_URL` (default `http://localhost:9999`).
+pub fn proxy_base_url() -> String {
+    std::env::var("SUI_PROXY_URL").unwrap_or_else(|_| "http://localhost:9999".to_string())
+}
+
+/// Path of the proxy's `sui client call` endpoint.
+pub const CALL_PATH: &str = "/sui/client/call";
+
+/// A Move call argument, serialized the way the proxy passes it to `sui client call`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SuiArg {
+    ObjectId(String),
+    Address(String),
+    U8(u8),
+    /// Sent as `"true"`/`"false"`.
+    Bool(bool),
+    /// Sent as an array of numbers, i.e. a `vector<u8>`.
+    Bytes(Vec<u8>),
+    /// Sent as a decimal string, so large values survive JSON.
+    U64(u64),
+}
+
+impl Serialize for SuiArg {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            SuiArg::ObjectId(id) | SuiArg::Address(id) => serializer.serialize_str(id),
+            SuiArg::U8(value) => serializer.serialize_u8(*value),
+            SuiArg::Bool(value) => serializer.serialize_str(if *value { "true" } else { "false" }),
+            SuiArg::Bytes(bytes) => bytes.serialize(serializer),
+            SuiArg::U64(value) => serializer.serialize_str(&value.to_string()),
+        }
+    }
+}
+
+fn serialize_u64_as_string<S: Serializer>(value: &u64, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&value.to_string())
+}
+
+/// Body of `POST /sui/client/call`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SuiCallRequest {
+    #[serde(rename = "package_id")]
+    pub package: String,
+    pub module: String,
+    pub function: String,
+    pub args: Vec<SuiArg>,
+    #[serde(serialize_with = "serialize_u64_as_string")]
+    pub gas_budget: u64,
+    /// Gas coin to pay with; the CLI picks one when unset.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub gas: Option<String>,
+    /// Return the unsigned transaction bytes instead of executing the call.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub serialize_unsigned: bool,
+    /// Only estimate the call's effects and gas cost, as JSON.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub dry_run: bool,
+    /// Transaction sender, when it isn't the proxy's active address.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sender: Option<String>,
+    /// Address that owns the gas coin and pays, for a sponsored transaction.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub gas_sponsor: Option<String>,
+}
+
+impl SuiCallRequest {
+    pub fn new(package: &str, module: &str, function: &str, args: Vec<SuiArg>, gas_budget: u64) -> Self {
+        Self {
+            package: package.to_string(),
+            module: module.to_string(),
+            function: function.to_string(),
+            args,
+            gas_budget,
+            gas: None,
+            serialize_unsigned: false,
+            dry_run: false,
+            sender: None,
+            gas_sponsor: None,
+        }
+    }
+
+    pub fn with_gas(mut self, coin: Option<&str>) -> Self {
+        self.gas = coin.map(str::to_string);
+        self
+    }
+
+    pub fn serialize_unsigned(mut self) -> Self {
+        self.serialize_unsigned = true;
+        self
+    }
+
+    pub fn dry_run(mut self) -> Self {
+        self.dry_run = true;
+        self
+    }
+
+    /// Sent by `sender`, which also pays unless the call is [`Self::sponsored`].
+    pub fn sent_by(mut self, sender: &str) -> Self {
+        self.sender = Some(sender.to_string());
+        self
+    }
+
+    /// Sent by `sender`, with gas owned and paid by `sponsor`.
+    pub fn sponsored(mut self, sender: &str, sponsor: &str) -> Self {
+        self.sender = Some(sender.to_string());
+        self.gas_sponsor = Some(sponsor.to_string());
+        self
+    }
+}
+
+/// Longest part of an unexpected body kept in an [`UpstreamUnavailable`].
+const BODY_SNIPPET_CHARS: usize = 200;
+
+/// The proxy (or a load balancer in front of it) answered with an error status or with
+/// something other than the expected JSON, e.g. an HTML 502 page or an empty body.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UpstreamUnavailable {
+    pub url: String,
+    pub status: u16,
+    /// The start of the body, whitespace collapsed.
+    pub snippet: String,
+}
+
+impl UpstreamUnavailable {
+    pub fn new(url: &str, status: reqwest::StatusCode, body: &str) -> Self {
+        let collapsed = body.split_whitespace().collect::<Vec<_>>().join(" ");
+        let snippet = if collapsed.is_empty() {
+            "<empty body>".to_string()
+        } else if collapsed.chars().count() > BODY_SNIPPET_CHARS {
+            format!("{}...", collapsed.chars().take(BODY_SNIPPET_CHARS).collect::<String>())
+        } else {
+            collapsed
+        };
+        Self { url: url.to_string(), status: status.as_u16(), snippet }
+    }
+}
+
+impl fmt::Display for UpstreamUnavailable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} returned {}: {}", self.url, self.status, self.snippet)
+    }
+}
+
+impl std::error::Error for UpstreamUnavailable {}
+
+/// The request never reached the proxy (connection refused, name not resolved), so nothing was
+/// submitted and sending it again, here or to another endpoint, can't run anything twice.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NotSent {
+    pub url: String,
+    pub reason: String,
+}
+
+impl fmt::Display for NotSent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} unreachable: {}", self.url, self.reason)
+    }
+}
+
+impl std::error::Error for NotSent {}
+
+pub fn is_not_sent(error: &anyhow::Error) -> bool {
+    error.downcast_ref::<NotSent>().is_some()
+}
+
+/// A transaction submission whose outcome is unknown: the proxy had the request when it failed,
+/// timed out or dropped the connection (it gives up on the CLI after 30s, possibly after the
+/// transaction went out). Sending it again could execute it twice, so it never is; the message
+/// is set aside for someone to check the chain.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SubmissionInDoubt {
+    pub reason: String,
+}
+
+impl fmt::Display for SubmissionInDoubt {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "transaction outcome unknown, not resubmitted: {}", self.reason)
+    }
+}
+
+impl std::error::Error for SubmissionInDoubt {}
+
+pub fn is_submission_in_doubt(error: &anyhow::Error) -> bool {
+    error.downcast_ref::<SubmissionInDoubt>().is_some()
+}
+
+/// The proxy's JSON reply, whatever the status. A body that isn't JSON is an [`UpstreamUnavailable`].
+pub async fn read_json(url: &str, response: reqwest::Response) -> Result<Value> {
+    let status = response.status();
+    let text = response
+        .text()
+        .await
+        .map_err(|e| TransientError { reason: format!("{}: {}", url, e), retry_after: None })?;
+    serde_json::from_str(&text).map_err(|_| UpstreamUnavailable::new(url, status, &text).into())
+}
+
+/// One POST to the proxy. Connection failures, 5xx and 429 are [`TransientError`]s (with a
+/// [`NotSent`] or an [`UpstreamUnavailable`] describing the body underneath); any other response
+/// is returned as its JSON body, including a CLI failure reported with `success: false`.
+pub async fn post_once(client: &Client, url: &str, body: &Value) -> Result<Value> {
+    let response = client.post(url).json(body).send().await.map_err(|e| {
+        let transient = TransientError { reason: format!("{}: {}", url, e), retry_after: None };
+        match e.is_connect() {
+            true => anyhow::Error::new(NotSent { url: url.to_string(), reason: e.to_string() }).context(transient),
+            false => transient.into(),
+        }
+    })?;
+
+    let status = response.status();
+    if status.is_server_error() || status == reqwest::StatusCode::TOO_MANY_REQUESTS {
+        let retry_after = retry_after_header(response.headers());
+        let text = response.text().await.unwrap_or_default();
+        let upstream = UpstreamUnavailable::new(url, status, &text);
+        let transient = TransientError { reason: upstream.to_string(), retry_after };
+        return Err(anyhow::Error::new(upstream).context(transient));
+    }
+    if !status.is_success() {
+        let text = response.text().await.unwrap_or_default();
+        return Err(UpstreamUnavailable::new(url, status, &text).into());
+    }
+    read_json(url, response).await
+}
+
+/// [`post_once`] under `policy`, retrying only transient failures. Only for calls that change
+/// nothing on chain (queries, dry runs, unsigned bytes); transactions go through [`submit_with_retry`].
+pub async fn post_with_retry(client: &Client, policy: &RetryPolicy, url: &str, body: &Value) -> Result<Value> {
+    retry_with_backoff_if(policy, url, is_transient, |_| post_once(client, url, body)).await
+}
+
+/// [`post_once`] for a call that executes a transaction, retried under `policy` only while the
+/// request was [`NotSent`]. Any other transient failure came after the proxy had it, so it is a
+/// [`SubmissionInDoubt`] rather than something to retry.
+pub async fn submit_with_retry(client: &Client, policy: &RetryPolicy, url: &str, body: &Value) -> Result<Value> {
+    retry_with_backoff_if(policy, url, is_not_sent, |_| post_once(client, url, body))
+        .await
+        .map_err(|e| match is_transient(&e) && !is_not_sent(&e) {
+            true => SubmissionInDoubt { reason: e.to_string() }.into(),
+            false => e,
+        })
+}
+
+/// Which endpoint calls go to, and when the primary was last tried.
+struct ActiveEndpoint {
+    index: usize,
+    last_probe: Instant,
+}
+
+/// Proxy base URLs in failover order, from `SUI_PROXY_URLS` (comma separated; default
+/// [`proxy_base_url`] alone), each in front of its own Sui node. Calls go to the active one and
+/// move on to the next only when it can't be reached at all ([`NotSent`]): once a node has the
+/// body, sending it to another could execute the transaction twice. While on a fallback, the
+/// primary is tried again first every `SUI_PROXY_REPROBE_SECS` (default 60).
+pub struct SuiEndpoints {
+    urls: Vec<String>,
+    reprobe_after: Duration,
+    active: Mutex<ActiveEndpoint>,
+    // Sui submissions in flight, whichever endpoint they go to
+    call_limit: CallLimit,
+}
+
+impl SuiEndpoints {
+    pub fn new(urls: Vec<String>, reprobe_after: Duration) -> Result<Self> {
+        if urls.is_empty() {
+            return Err(anyhow::anyhow!("No Sui proxy endpoints configured"));
+        }
+        metrics::set_gauge("sui_active_endpoint", 0.0);
+        Ok(Self {
+            urls,
+            reprobe_after,
+            active: Mutex::new(ActiveEndpoint { index: 0, last_probe: Instant::now() }),
+            call_limit: CallLimit::new("sui", 1),
+        })
+    }
+
+    /// Cap on concurrent Sui calls (`SUI_MAX_CONCURRENCY`, default 1: every call uses the cap object).
+    pub fn with_call_limit(mut self, limit: CallLimit) -> Self {
+        self.call_limit = limit;
+        self
+    }
+
+    pub fn from_env() -> Result<Self> {
+        let urls: Vec<String> = std::env::var("SUI_PROXY_URLS")
+            .unwrap_or_default()
+            .split(',')
+            .map(|url| url.trim().trim_end_matches('/').to_string())
+            .filter(|url| !url.is_empty())
+            .collect();
+        let urls = if urls.is_empty() { vec![proxy_base_url()] } else { urls };
+        let reprobe_secs = std::env::var("SUI_PROXY_REPROBE_SECS").ok().and_then(|v| v.parse().ok()).unwrap_or(60);
+        info!("Sui proxy endpoints: {} (re-probe primary every {}s)", urls.join(", "), reprobe_secs);
+        Ok(Self::new(urls, Duration::from_secs(reprobe_secs))?.with_call_limit(CallLimit::from_env("sui", "SUI", 1)))
+    }
+
+    /// Base URL calls currently go to.
+    pub fn active(&self) -> &str {
+        &self.urls[self.active.lock().unwrap().index]
+    }
+
+    /// Endpoint indices to try, starting with the primary when it is due a re-probe.
+    fn order(&self) -> Vec<usize> {
+        let mut active = self.active.lock().unwrap();
+        let start = if active.index != 0 && active.last_probe.elapsed() >= self.reprobe_after {
+            active.last_probe = Instant::now();
+            0
+        } else {
+            active.index
+        };
+        (0..self.urls.len()).map(|i| (start + i) % self.urls.len()).collect()
+    }
+
+    fn activate(&self, index: usize) {
+        let mut active = self.active.lock().unwrap();
+        if active.index == index {
+            return;
+        }
+        if index == 0 {
+            info!("✅ Sui proxy back on the primary {}", self.urls[index]);
+        } else {
+            warn!("🔀 Sui proxy failed over from {} to {}", self.urls[active.index], self.urls[index]);
+            metrics::increment("sui_endpoint_failovers_total");
+        }
+        *active = ActiveEndpoint { index, last_probe: Instant::now() };
+        metrics::set_gauge("sui_active_endpoint", index as f64);
+    }
+
+    /// [`submit_with_retry`] to `path` on the active endpoint, then on each of the others while the
+    /// request couldn't be sent. Whichever endpoint answers becomes the active one. Waits for a
+    /// slot under the call limit first.
+    pub async fn post(&self, client: &Client, policy: &RetryPolicy, path: &str, body: &Value) -> Result<Value> {
+        let _permit = self.call_limit.acquire().await;
+        let mut last_error = None;
+        for index in self.order() {
+            let url = format!("{}{}", self.urls[index], path);
+            match submit_with_retry(client, policy, &url, body).await {
+                Err(e) if is_not_sent(&e) => {
+                    warn!("Sui proxy {} unavailable: {}", self.urls[index], e);
+                    last_error = Some(e);
+                }
+                result => {
+                    self.activate(index);
+                    return result;
+                }
+            }
+        }
+        Err(last_error.expect("at least one endpoint"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::StatusCode;
+    use axum::response::IntoResponse;
+    use axum::routing::post;
+    use axum::{Json, Router};
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use std::sync::Arc;
+    use tokio::time::Duration;
+
+    /// Serve a proxy stub that answers 502 for the first `failures` calls.
+    async fn flaky_proxy(failures: u32, calls: Arc<AtomicU32>) -> String {
+        let app = Router::new().route(
+            "/sui/client/call",
+            post(move || {
+                let calls = calls.clone();
+                async move {
+                    if calls.fetch_add(1, Ordering::SeqCst) < failures {
+                        StatusCode::BAD_GATEWAY.into_response()
+                    } else {
+                        Json(serde_json::json!({ "success": true, "stdout": "ok" })).into_response()
+                    }
+                }
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        format!("http://{}/sui/client/call", addr)
+    }
+
+    #[test]
+    fn test_call_request_serializes_to_the_proxy_shape() {
+        let request = SuiCallRequest::new(
+            "0xpkg",
+            "did_registry",
+            "update_verification_status",
+            vec![
+                SuiArg::ObjectId("0xregistry".to_string()),
+                SuiArg::Address("0xabc".to_string()),
+                SuiArg::U8(1),
+                SuiArg::Bool(true),
+                SuiArg::Bytes(vec![0xde, 0xad]),
+                SuiArg::U64(u64::MAX),
+            ],
+            10_000_000,
+        )
+        .with_gas(Some("0xcoin"));
+
+        assert_eq!(
+            serde_json::to_value(&request).unwrap(),
+            serde_json::json!({
+                "package_id": "0xpkg",
+                "module": "did_registry",
+                "function": "update_verification_status",
+                "args": ["0xregistry", "0xabc", 1, "true", [222, 173], "18446744073709551615"],
+                "gas_budget": "10000000",
+                "gas": "0xcoin"
+            })
+        );
+        let without_gas = serde_json::to_value(request.with_gas(None)).unwrap();
+        assert!(without_gas.get("gas").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_transient_502_is_retried_then_succeeds() {
+        let calls = Arc::new(AtomicU32::new(0));
+        let url = flaky_proxy(1, calls.clone()).await;
+        let policy = RetryPolicy {
+            max_attempts: 3,
+            base_delay: Duration::ZERO,
+            max_delay: Duration::ZERO,
+        };
+
+        let result = post_with_retry(&Client::new(), &policy, &url, &serde_json::json!({})).await.unwrap();
+        assert_eq!(result["success"], true);
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_submission_is_retried_only_while_unsent() {
+        let policy = RetryPolicy { max_attempts: 3, base_delay: Duration::ZERO, max_delay: Duration::ZERO };
+        let body = serde_json::json!({});
+
+        // The proxy had it when it failed: one attempt, and the outcome is left in doubt
+        let calls = Arc::new(AtomicU32::new(0));
+        let url = flaky_proxy(1, calls.clone()).await;
+        let error = submit_with_retry(&Client::new(), &policy, &url, &body).await.unwrap_err();
+        assert!(is_submission_in_doubt(&error), "{}", error);
+        assert!(!is_transient(&error));
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+        // Nothing listening: never sent, so retried and still transient
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let unreachable = format!("http://{}/sui/client/call", listener.local_addr().unwrap());
+        drop(listener);
+        let error = submit_with_retry(&Client::new(), &policy, &unreachable, &body).await.unwrap_err();
+        assert!(is_not_sent(&error) && is_transient(&error), "{}", error);
+    }
+
+    #[tokio::test]
+    async fn test_non_json_502_surfaces_status_and_body() {
+        let page = format!("<html>\n  <head><title>502 Bad Gateway</title></head>\n{}</html>", "x".repeat(500));
+        let app = Router::new()
+            .route("/sui/client/call", post(move || async move { (StatusCode::BAD_GATEWAY, page) }))
+            .route("/empty", post(|| async { StatusCode::OK }));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        let policy = RetryPolicy {
+            max_attempts: 2,
+            base_delay: Duration::ZERO,
+            max_delay: Duration::ZERO,
+        };
+
+        let url = format!("http://{}/sui/client/call", addr);
+        let error = post_with_retry(&Client::new(), &policy, &url, &serde_json::json!({})).await.unwrap_err();
+        assert!(is_transient(&error));
+        let upstream = error.downcast_ref::<UpstreamUnavailable>().unwrap();
+        assert_eq!(upstream.status, 502);
+        assert!(upstream.snippet.starts_with("<html> <head><title>502 Bad Gateway</title></head> xxx"), "{}", upstream.snippet);
+        assert!(upstream.snippet.ends_with("...") && upstream.snippet.len() < 220);
+        assert!(error.to_string().contains("returned 502: <html>"), "{}", error);
+
+        // A success status with no JSON is just as unusable
+        let url = format!("http://{}/empty", addr);
+        let error = post_once(&Client::new(), &url, &serde_json::json!({})).await.unwrap_err();
+        assert_eq!(error.downcast_ref::<UpstreamUnavailable>().unwrap().snippet, "<empty body>");
+    }
+
+    #[tokio::test]
+    async fn test_unreachable_primary_fails_over_to_the_secondary() {
+        // A port nothing listens on until the primary comes back
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let primary_addr = listener.local_addr().unwrap();
+        drop(listener);
+        let primary_url = format!("http://{}", primary_addr);
+        let secondary_calls = Arc::new(AtomicU32::new(0));
+        let secondary_url = flaky_proxy(0, secondary_calls.clone()).await.trim_end_matches("/sui/client/call").to_string();
+
+        let endpoints = SuiEndpoints::new(vec![primary_url.clone(), secondary_url.clone()], Duration::from_millis(50)).unwrap();
+        let policy = RetryPolicy { max_attempts: 1, base_delay: Duration::ZERO, max_delay: Duration::ZERO };
+        let (client, body) = (Client::new(), serde_json::json!({}));
+        let call = || endpoints.post(&client, &policy, CALL_PATH, &body);
+
+        assert_eq!(call().await.unwrap()["stdout"], "ok");
+        assert_eq!(endpoints.active(), secondary_url);
+        assert_eq!(metrics::gauge("sui_active_endpoint"), Some(1.0));
+        // Stays on the secondary until the primary is due a re-probe
+        call().await.unwrap();
+        assert_eq!(secondary_calls.load(Ordering::SeqCst), 2);
+
+        let primary = Router::new().route(
+            "/sui/client/call",
+            post(|| async { Json(serde_json::json!({ "success": true, "stdout": "primary" })) }),
+        );
+        let listener = tokio::net::TcpListener::bind(primary_addr).await.unwrap();
+        tokio::spawn(async move { axum::serve(listener, primary).await.unwrap() });
+        tokio::time::sleep(Duration::from_millis(60)).await;
+        assert_eq!(call().await.unwrap()["stdout"], "primary");
+        assert_eq!(endpoints.active(), primary_url);
+    }
+
+    #[tokio::test]
+    async fn test_node_that_received_the_call_is_not_failed_over_from() {
+        // The primary has the body when it fails: the secondary must never see it
+        let primary_calls = Arc::new(AtomicU32::new(0));
+        let primary_url = flaky_proxy(u32::MAX, primary_calls.clone()).await.trim_end_matches("/sui/client/call").to_string();
+        let secondary_calls = Arc::new(AtomicU32::new(0));
+        let secondary_url = flaky_proxy(0, secondary_calls.clone()).await.trim_end_matches("/sui/client/call").to_string();
+
+        let endpoints = SuiEndpoints::new(vec![primary_url.clone(), secondary_url], Duration::from_secs(60)).unwrap();
+        let policy = RetryPolicy { max_attempts: 1, base_delay: Duration::ZERO, max_delay: Duration::ZERO };
+        let error = endpoints.post(&Client::new(), &policy, CALL_PATH, &serde_json::json!({})).await.unwrap_err();
+        assert!(is_submission_in_doubt(&error), "{}", error);
+        assert_eq!(primary_calls.load(Ordering::SeqCst), 1);
+        assert_eq!(secondary_calls.load(Ordering::SeqCst), 0);
+        assert_eq!(endpoints.active(), primary_url);
+    }
+}
@@ -0,0 +1,707 @@
+// update_verification_status as an unsigned, serialized Sui transaction for the caller to submit
+use axum::extract::State;
+use axum::http::HeaderMap;
+use axum::Json;
+use fastcrypto::ed25519::Ed25519PublicKey;
+use fastcrypto::encoding::{Encoding, Hex};
+use fastcrypto::traits::ToFromBytes;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+
+use crate::api_error::ApiJson;
+use crate::attestation_store::SignedVerificationAttestation;
+use crate::common::verify_signed_response;
+use crate::diagnostics::SuiTarget;
+use crate::evidence::decode_evidence_hash;
+use crate::message_source::{parse_timestamp_to_ms, VerifiedResult};
+use crate::metrics;
+use crate::retry::RetryPolicy;
+use crate::sui_gas::GasBudgets;
+use crate::sui_proxy::{post_with_retry, proxy_base_url, SuiArg, SuiCallRequest};
+use crate::verification_processor::sign_verification;
+use crate::{AppState, EnclaveError};
+
+/// Who pays for the transactions `/verification_transaction` builds, from `SUI_GAS_MODE`.
+#[derive(Debug, Clone)]
+pub enum GasMode {
+    /// `self` (default): the wallet sends and pays with its own gas.
+    SelfGas,
+    /// `sponsor`: the wallet sends and `sponsor` (`SUI_SPONSOR_ADDRESS`) owns the gas, paying
+    /// with coins from `SUI_SPONSOR_GAS_COINS`. Users need no SUI.
+    Sponsored { sponsor: String, coins: Arc<SponsorCoins> },
+}
+
+impl GasMode {
+    pub fn from_env() -> anyhow::Result<Self> {
+        match std::env::var("SUI_GAS_MODE").unwrap_or_default().trim().to_lowercase().as_str() {
+            "" | "self" => Ok(GasMode::SelfGas),
+            "sponsor" => {
+                let sponsor = std::env::var("SUI_SPONSOR_ADDRESS")
+                    .ok()
+                    .filter(|a| !a.trim().is_empty())
+                    .ok_or_else(|| anyhow::anyhow!("SUI_GAS_MODE=sponsor requires SUI_SPONSOR_ADDRESS"))?;
+                info!("⛽ Sponsoring verification transactions from {}", sponsor);
+                Ok(GasMode::Sponsored {
+                    sponsor: sponsor.trim().to_string(),
+                    coins: Arc::new(SponsorCoins::from_env()?),
+                })
+            }
+            other => Err(anyhow::anyhow!("Unknown SUI_GAS_MODE '{}' (expected self or sponsor)", other)),
+        }
+    }
+}
+
+/// The sponsor's gas coins. Built transaction bytes pin their coin's version, so a coin stays
+/// reserved for its transaction until that is submitted or `ttl` passes; while every coin is
+/// reserved no more transactions are sponsored.
+#[derive(Debug)]
+pub struct SponsorCoins {
+    inner: Mutex<SponsorCoinsInner>,
+    ttl: Duration,
+}
+
+#[derive(Debug, Default)]
+struct SponsorCoinsInner {
+    free: VecDeque<String>,
+    /// `tx_bytes` -> the coin paying for it and when its reservation ends.
+    reserved: HashMap<String, (String, Instant)>,
+}
+
+impl SponsorCoins {
+    pub fn new(coins: Vec<String>, ttl: Duration) -> Self {
+        metrics::set_gauge("sui_sponsor_coins_free", coins.len() as f64);
+        Self {
+            inner: Mutex::new(SponsorCoinsInner { free: coins.into(), reserved: HashMap::new() }),
+            ttl,
+        }
+    }
+
+    /// Comma separated coin IDs from `SUI_SPONSOR_GAS_COINS`, each reserved for up to
+    /// `SUI_SPONSOR_RESERVATION_SECS` (default 120).
+    pub fn from_env() -> anyhow::Result<Self> {
+        let coins: Vec<String> = std::env::var("SUI_SPONSOR_GAS_COINS")
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|c| !c.is_empty())
+            .map(str::to_string)
+            .collect();
+        if coins.is_empty() {
+            return Err(anyhow::anyhow!("SUI_GAS_MODE=sponsor requires SUI_SPONSOR_GAS_COINS"));
+        }
+        let ttl = std::env::var("SUI_SPONSOR_RESERVATION_SECS")
+            .ok()
+            .and_then(|v| v.parse::<u64>().ok())
+            .unwrap_or(120);
+        Ok(Self::new(coins, Duration::from_secs(ttl)))
+    }
+
+    /// A free coin, after taking back the ones whose reservation ran out.
+    pub fn take(&self) -> Option<String> {
+        let mut inner = self.inner.lock().unwrap();
+        let now = Instant::now();
+        let expired: Vec<String> = inner
+            .reserved
+            .iter()
+            .filter(|(_, (_, until))| *until <= now)
+            .map(|(tx_bytes, _)| tx_bytes.clone())
+            .collect();
+        for tx_bytes in expired {
+            if let Some((coin, _)) = inner.reserved.remove(&tx_bytes) {
+                inner.free.push_back(coin);
+            }
+        }
+        let coin = inner.free.pop_front();
+        metrics::set_gauge("sui_sponsor_coins_free", inner.free.len() as f64);
+        coin
+    }
+
+    /// Return a coin that never made it into a transaction.
+    pub fn put_back(&self, coin: String) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.free.push_back(coin);
+        metrics::set_gauge("sui_sponsor_coins_free", inner.free.len() as f64);
+    }
+
+    /// Hold `coin` for the transaction `tx_bytes` until it is released or the reservation expires.
+    pub fn reserve(&self, tx_bytes: &str, coin: String) {
+        let until = Instant::now() + self.ttl;
+        self.inner.lock().unwrap().reserved.insert(tx_bytes.to_string(), (coin, until));
+    }
+
+    /// Whether `tx_bytes` was sponsored here and its reservation is still live.
+    pub fn is_reserved(&self, tx_bytes: &str) -> bool {
+        let inner = self.inner.lock().unwrap();
+        inner.reserved.get(tx_bytes).is_some_and(|(_, until)| *until > Instant::now())
+    }
+
+    /// Free the coin of `tx_bytes`, once the transaction was executed (or failed to be).
+    pub fn release(&self, tx_bytes: &str) {
+        let mut inner = self.inner.lock().unwrap();
+        if let Some((coin, _)) = inner.reserved.remove(tx_bytes) {
+            inner.free.push_back(coin);
+            metrics::set_gauge("sui_sponsor_coins_free", inner.free.len() as f64);
+        }
+    }
+}
+
+/// Who may call the transaction endpoints, and how often. Every call needs one of
+/// `TRANSACTION_API_KEYS` (comma separated) in `x-api-key`; with none configured both endpoints
+/// refuse every call. A wallet gets at most `TRANSACTION_RATE_LIMIT` (default 5) transactions
+/// built per `TRANSACTION_RATE_WINDOW_SECS` (default 60).
+#[derive(Debug)]
+pub struct TransactionAccess {
+    api_keys: Vec<String>,
+    limit: u32,
+    window: Duration,
+    /// Wallet -> start of its current window and the transactions built in it.
+    built: Mutex<HashMap<String, (Instant, u32)>>,
+}
+
+impl Default for TransactionAccess {
+    fn default() -> Self {
+        Self::new(Vec::new(), 5, Duration::from_secs(60))
+    }
+}
+
+impl TransactionAccess {
+    pub fn new(api_keys: Vec<String>, limit: u32, window: Duration) -> Self {
+        Self { api_keys, limit: limit.max(1), window, built: Mutex::new(HashMap::new()) }
+    }
+
+    pub fn from_env() -> Self {
+        let api_keys: Vec<String> = std::env::var("TRANSACTION_API_KEYS")
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|k| !k.is_empty())
+            .map(str::to_string)
+            .collect();
+        if api_keys.is_empty() {
+            warn!("TRANSACTION_API_KEYS is unset; /verification_transaction and /submit_sponsored_transaction refuse every call");
+        }
+        let var = |name: &str, default: u64| std::env::var(name).ok().and_then(|v| v.parse::<u64>().ok()).unwrap_or(default);
+        Self::new(
+            api_keys,
+            var("TRANSACTION_RATE_LIMIT", 5) as u32,
+            Duration::from_secs(var("TRANSACTION_RATE_WINDOW_SECS", 60)),
+        )
+    }
+
+    /// The call carries a configured API key.
+    pub fn authenticate(&self, headers: &HeaderMap) -> Result<(), EnclaveError> {
+        let presented = headers.get("x-api-key").map(|v| v.as_bytes()).unwrap_or_default();
+        // Compared in full every time, so the time taken says nothing about the key
+        let matches = |key: &String| {
+            key.len() == presented.len() && key.bytes().zip(presented).fold(0u8, |diff, (a, b)| diff | (a ^ b)) == 0
+        };
+        if presented.is_empty() || !self.api_keys.iter().any(matches) {
+            return Err(EnclaveError::Unauthorized("A valid x-api-key is required".to_string()));
+        }
+        Ok(())
+    }
+
+    /// Count one transaction built for `wallet`, refusing it past the limit.
+    pub fn admit(&self, wallet: &str) -> Result<(), EnclaveError> {
+        let mut built = self.built.lock().unwrap();
+        let now = Instant::now();
+        built.retain(|_, (started, _)| now.duration_since(*started) < self.window);
+        let (_, count) = built.entry(wallet.to_lowercase()).or_insert((now, 0));
+        if *count >= self.limit {
+            metrics::increment("verification_transaction_rate_limited_total");
+            return Err(EnclaveError::RateLimited(format!(
+                "At most {} transactions per {:?} for a wallet",
+                self.limit, self.window
+            )));
+        }
+        *count += 1;
+        Ok(())
+    }
+}
+
+/// The arguments of `did_registry::update_verification_status`, besides the registry objects.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VerificationStatusUpdate {
+    pub user_did_id: String,
+    pub verified: bool,
+    pub signature: Vec<u8>,
+    pub signature_timestamp_ms: u64,
+    pub evidence_hash: Vec<u8>,
+}
+
+impl VerificationStatusUpdate {
+    /// The call's arguments in contract order.
+    pub fn args(&self, registry_id: &str, cap_id: &str, clock_id: &str) -> Vec<SuiArg> {
+        vec![
+            SuiArg::ObjectId(registry_id.to_string()),
+            SuiArg::ObjectId(cap_id.to_string()),
+            SuiArg::ObjectId(self.user_did_id.clone()),
+            SuiArg::Bool(self.verified),
+            SuiArg::Bytes(self.signature.clone()),
+            SuiArg::U64(self.signature_timestamp_ms),
+            SuiArg::Bytes(self.evidence_hash.clone()),
+            SuiArg::ObjectId(clock_id.to_string()),
+        ]
+    }
+
+    pub fn call(&self, target: &SuiTarget, gas_budget: u64) -> SuiCallRequest {
+        SuiCallRequest::new(
+            &target.package_id,
+            "did_registry",
+            "update_verification_status",
+            self.args(&target.registry_id, &target.cap_id, &target.clock_id),
+            gas_budget,
+        )
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct VerificationTransactionRequest {
+    pub wallet: String,
+    #[serde(rename = "type")]
+    pub verification_type: String,
+    /// The wallet's UserDID object, created by `start_verification`.
+    pub user_did_id: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct VerificationTransaction {
+    /// Base64 BCS `TransactionData`, as printed by `sui client call --serialize-unsigned-transaction`.
+    pub tx_bytes: String,
+    /// The Move call the transaction holds.
+    pub call: SuiCallRequest,
+    /// The stored attestation the call was built from.
+    pub attestation: SignedVerificationAttestation,
+    /// Present in sponsored mode: the wallet signs `tx_bytes` too and submits both signatures.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sponsorship: Option<Sponsorship>,
+}
+
+/// The gas side of a sponsored transaction.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Sponsorship {
+    pub sender: String,
+    pub sponsor: String,
+    /// The sponsor's coin paying for gas, when one was taken from the pool.
+    pub gas: Option<String>,
+    /// The sponsor's Sui signature over `tx_bytes`, base64 as printed by `sui keytool sign`.
+    pub sponsor_signature: String,
+}
+
+/// A result reply from the Sui proxy, or an [`EnclaveError::Upstream`] naming `action`.
+async fn proxy_call(url: &str, body: &serde_json::Value, action: &str) -> Result<serde_json::Value, EnclaveError> {
+    let reply = post_with_retry(&reqwest::Client::new(), &RetryPolicy::from_env("SUI_PROXY_RETRY"), url, body)
+        .await
+        .map_err(|e| EnclaveError::Upstream(format!("Sui proxy unavailable: {}", e)))?;
+    if !reply["success"].as_bool().unwrap_or(false) {
+        return Err(EnclaveError::Upstream(format!(
+            "{} failed: {}",
+            action,
+            reply["stderr"].as_str().or(reply["error"].as_str()).unwrap_or("unknown error")
+        )));
+    }
+    Ok(reply)
+}
+
+/// The unsigned transaction bytes of `call`, serialized by the proxy.
+async fn serialize_call(proxy_url: &str, call: &SuiCallRequest) -> Result<String, EnclaveError> {
+    let url = format!("{}/sui/client/call", proxy_url);
+    let body = serde_json::to_value(call).map_err(|e| EnclaveError::Internal(e.to_string()))?;
+    let reply = proxy_call(&url, &body, "Serializing update_verification_status").await?;
+    Ok(reply["stdout"].as_str().unwrap_or("").trim().to_string())
+}
+
+/// Sign `tx_bytes` with `address`'s key in the proxy's keystore.
+async fn sign_as_sponsor(proxy_url: &str, address: &str, tx_bytes: &str) -> Result<String, EnclaveError> {
+    let url = format!("{}/sui/keytool/sign", proxy_url);
+    let body = serde_json::json!({ "address": address, "data": tx_bytes });
+    let reply = proxy_call(&url, &body, "Signing as gas sponsor").await?;
+    let output: serde_json::Value = serde_json::from_str(reply["stdout"].as_str().unwrap_or(""))
+        .map_err(|e| EnclaveError::Upstream(format!("Unreadable sui keytool sign output: {}", e)))?;
+    output["suiSignature"]
+        .as_str()
+        .map(str::to_string)
+        .ok_or_else(|| EnclaveError::Upstream("sui keytool sign printed no suiSignature".to_string()))
+}
+
+/// Build the `update_verification_status` transaction for a stored attestation, through the
+/// Sui proxy at `proxy_url`. Only results this enclave signed and stored are used; a caller can
+/// never supply the result itself. The wallet sends it, so it passes `target`'s shared cap.
+pub async fn build_verification_transaction(
+    state: &AppState,
+    request: &VerificationTransactionRequest,
+    target: &SuiTarget,
+    proxy_url: &str,
+) -> Result<VerificationTransaction, EnclaveError> {
+    // Only the backend's own address can pass the owned cap, so a wallet-sent call needs the shared one
+    let cap_id = target.shared_cap_id.clone().ok_or_else(|| {
+        EnclaveError::Internal("SUI_SHARED_CAP_ID is unset; a wallet can't send with the backend-owned cap".to_string())
+    })?;
+    let target = SuiTarget { cap_id, ..target.clone() };
+    let stored = state
+        .attestations
+        .get(&request.wallet, &request.verification_type)
+        .await
+        .map_err(|e| EnclaveError::Upstream(format!("Attestation store unavailable: {}", e)))?
+        .ok_or_else(|| {
+            EnclaveError::NotFound(format!(
+                "No {} attestation for wallet {}",
+                request.verification_type, request.wallet
+            ))
+        })?;
+    let signer = Hex::decode(&stored.public_key)
+        .ok()
+        .and_then(|bytes| Ed25519PublicKey::from_bytes(&bytes).ok())
+        .ok_or_else(|| EnclaveError::Internal("Stored attestation has an unreadable public key".to_string()))?;
+    verify_signed_response(&signer, &stored.attestation)
+        .map_err(|_| EnclaveError::Internal("Stored attestation failed its signature check".to_string()))?;
+
+    let attestation = &stored.attestation.response.data;
+    let result = VerifiedResult {
+        user_wallet: attestation.user_wallet.clone(),
+        did_id: attestation.did_id,
+        result: attestation.result.clone(),
+        evidence_hash: attestation.evidence_hash.clone(),
+        verified_at: attestation.verified_at.clone(),
+        rejection_reason: None,
+    };
+    let internal = |e: anyhow::Error| EnclaveError::Internal(e.to_string());
+    let update = VerificationStatusUpdate {
+        user_did_id: request.user_did_id.clone(),
+        verified: result.result == "verified",
+        signature: sign_verification(&state.eph_kp, &result)?,
+        signature_timestamp_ms: parse_timestamp_to_ms(&result.verified_at).map_err(internal)?,
+        evidence_hash: decode_evidence_hash(&result.evidence_hash).map_err(internal)?,
+    };
+    let gas_budget = GasBudgets::from_env()
+        .map_err(internal)?
+        .budget_for("update_verification_status", &request.verification_type);
+    let call = update.call(&target, gas_budget).serialize_unsigned();
+
+    // The wallet always sends; nothing the backend owns pays unless it sponsors the gas
+    let (call, tx_bytes, sponsorship) = match &state.gas_mode {
+        GasMode::Sponsored { sponsor, coins } => {
+            let coin = coins.take().ok_or_else(|| {
+                EnclaveError::RateLimited("Every sponsor gas coin is reserved; try again shortly".to_string())
+            })?;
+            let call = call.sponsored(&attestation.user_wallet, sponsor).with_gas(Some(&coin));
+            let sponsored = async {
+                let tx_bytes = serialize_call(proxy_url, &call).await?;
+                let sponsor_signature = sign_as_sponsor(proxy_url, sponsor, &tx_bytes).await?;
+                Ok::<_, EnclaveError>((tx_bytes, sponsor_signature))
+            };
+            // The coin stays with the bytes until they are submitted or the reservation runs out
+            let (tx_bytes, sponsor_signature) = match sponsored.await {
+                Ok(built) => built,
+                Err(e) => {
+                    coins.put_back(coin);
+                    return Err(e);
+                }
+            };
+            coins.reserve(&tx_bytes, coin.clone());
+            let sponsorship = Sponsorship {
+                sender: attestation.user_wallet.clone(),
+                sponsor: sponsor.clone(),
+                gas: Some(coin),
+                sponsor_signature,
+            };
+            (call, tx_bytes, Some(sponsorship))
+        }
+        GasMode::SelfGas => {
+            let call = call.sent_by(&attestation.user_wallet);
+            let tx_bytes = serialize_call(proxy_url, &call).await?;
+            (call, tx_bytes, None)
+        }
+    };
+    info!("Built update_verification_status transaction for wallet: {} (sponsored: {})",
+          attestation.user_wallet, sponsorship.is_some());
+
+    Ok(VerificationTransaction {
+        tx_bytes,
+        call,
+        attestation: stored.attestation,
+        sponsorship,
+    })
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SponsoredSubmission {
+    pub tx_bytes: String,
+    /// The sender's signature over `tx_bytes`.
+    pub user_signature: String,
+    /// From the [`Sponsorship`] the transaction was built with.
+    pub sponsor_signature: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SubmittedTransaction {
+    /// `sui client execute-signed-tx` output.
+    pub output: String,
+}
+
+/// Execute a sponsored transaction with both signatures, through the Sui proxy. Only bytes
+/// sponsored here whose coin is still reserved are accepted; the coin is freed afterwards,
+/// whether the transaction executed or not.
+pub async fn submit_sponsored(
+    proxy_url: &str,
+    coins: &SponsorCoins,
+    submission: &SponsoredSubmission,
+) -> Result<SubmittedTransaction, EnclaveError> {
+    if !coins.is_reserved(&submission.tx_bytes) {
+        return Err(EnclaveError::NotFound(
+            "No sponsorship for this transaction: it expired or wasn't built here".to_string(),
+        ));
+    }
+    let url = format!("{}/sui/client/execute-signed-tx", proxy_url);
+    let body = serde_json::json!({
+        "tx_bytes": submission.tx_bytes,
+        "signatures": [submission.user_signature, submission.sponsor_signature],
+    });
+    let reply = proxy_call(&url, &body, "Executing sponsored transaction").await;
+    coins.release(&submission.tx_bytes);
+    let reply = reply?;
+    metrics::increment("sui_sponsored_transactions_total");
+    Ok(SubmittedTransaction { output: reply["stdout"].as_str().unwrap_or("").to_string() })
+}
+
+/// Endpoint `/verification_transaction`: the unsigned `update_verification_status` transaction
+/// for a stored attestation, with the enclave signature among its arguments. The attested wallet
+/// is the sender and pays its own gas, then signs and submits it. With `SUI_GAS_MODE=sponsor`
+/// the enclave's sponsor pays and signs for gas instead; the wallet co-signs and posts both
+/// signatures to `/submit_sponsored_transaction`. The call passes the shared verifier cap
+/// (`SUI_SHARED_CAP_ID`), and needs an API key and is rate limited per wallet ([`TransactionAccess`]).
+pub async fn serialize_verification_transaction(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    ApiJson(request): ApiJson<VerificationTransactionRequest>,
+) -> Result<Json<VerificationTransaction>, EnclaveError> {
+    state.transaction_access.authenticate(&headers)?;
+    state.transaction_access.admit(&request.wallet)?;
+    Ok(Json(build_verification_transaction(&state, &request, &SuiTarget::from_env(), &proxy_base_url()).await?))
+}
+
+/// Endpoint `/submit_sponsored_transaction`: execute a transaction from `/verification_transaction`
+/// once the wallet has signed it.
+pub async fn submit_sponsored_transaction(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    ApiJson(submission): ApiJson<SponsoredSubmission>,
+) -> Result<Json<SubmittedTransaction>, EnclaveError> {
+    state.transaction_access.authenticate(&headers)?;
+    let GasMode::Sponsored { coins, .. } = &state.gas_mode else {
+        return Err(EnclaveError::NotFound("Transactions aren't sponsored here (SUI_GAS_MODE=self)".to_string()));
+    };
+    if submission.tx_bytes.trim().is_empty() || submission.user_signature.trim().is_empty() {
+        return Err(EnclaveError::InvalidBody("tx_bytes and user_signature are required".to_string()));
+    }
+    Ok(Json(submit_sponsored(&proxy_base_url(), coins, &submission).await?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::attestation_store::{sign_verification_attestation, StoredAttestation};
+    use crate::results::{sample_event, VerificationResultEvent};
+    use axum::routing::post;
+    use axum::Router;
+    use fastcrypto::ed25519::Ed25519KeyPair;
+    use fastcrypto::traits::{KeyPair, VerifyingKey};
+    use std::sync::Mutex;
+
+    /// Sui proxy stub that records the call and answers with fixed transaction bytes, and
+    /// signs as `0xsponsor` only.
+    async fn serializing_proxy(received: Arc<Mutex<Option<serde_json::Value>>>) -> String {
+        let app = Router::new()
+            .route(
+                "/sui/client/call",
+                post(move |Json(body): Json<serde_json::Value>| {
+                    let received = received.clone();
+                    async move {
+                        *received.lock().unwrap() = Some(body);
+                        Json(serde_json::json!({ "success": true, "stdout": "AAACAQ==\n" }))
+                    }
+                }),
+            )
+            .route(
+                "/sui/keytool/sign",
+                post(|Json(body): Json<serde_json::Value>| async move {
+                    let signed = body["address"] == "0xsponsor" && body["data"] == "AAACAQ==";
+                    let stdout = serde_json::json!({ "suiSignature": "AHNwb25zb3I=" }).to_string();
+                    Json(serde_json::json!({ "success": signed, "stdout": stdout }))
+                }),
+            )
+            .route(
+                "/sui/client/execute-signed-tx",
+                post(|| async { Json(serde_json::json!({ "success": true, "stdout": "executed" })) }),
+            );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        format!("http://{}", addr)
+    }
+
+    fn target() -> SuiTarget {
+        SuiTarget {
+            proxy_url: String::new(),
+            package_id: "0xpackage".to_string(),
+            registry_id: "0xregistry".to_string(),
+            cap_id: "0xowned_cap".to_string(),
+            shared_cap_id: Some("0xshared_cap".to_string()),
+            clock_id: "0x6".to_string(),
+        }
+    }
+
+    async fn store_attestation(state: &AppState) {
+        let event = VerificationResultEvent {
+            user_wallet: "0xabc".to_string(),
+            did_id: 1,
+            verification_type: "citizenship".to_string(),
+            ..sample_event()
+        };
+        let attestation = sign_verification_attestation(&state.eph_kp, &event, "citizenship", 1_000).unwrap();
+        state
+            .attestations
+            .put(&StoredAttestation {
+                public_key: Hex::encode(state.eph_kp.public().as_bytes()),
+                attestation,
+            })
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_transaction_is_built_from_the_stored_attestation() {
+        let state = AppState::new(Ed25519KeyPair::generate(&mut rand::thread_rng()));
+        store_attestation(&state).await;
+
+        let received = Arc::new(Mutex::new(None));
+        let proxy_url = serializing_proxy(received.clone()).await;
+        let request = VerificationTransactionRequest {
+            wallet: "0xABC".to_string(),
+            verification_type: "citizenship".to_string(),
+            user_did_id: "0xdid".to_string(),
+        };
+        let built = build_verification_transaction(&state, &request, &target(), &proxy_url).await.unwrap();
+        assert_eq!(built.tx_bytes, "AAACAQ==");
+        assert_eq!(built.sponsorship, None);
+
+        // The proxy was asked to serialize exactly the contract call, signature included,
+        // sent by the attested wallet with its own gas
+        let sent = received.lock().unwrap().take().unwrap();
+        assert_eq!(sent["function"], "update_verification_status");
+        assert_eq!(sent["serialize_unsigned"], true);
+        assert_eq!(sent["sender"], "0xabc");
+        assert!(sent.get("gas").is_none() && sent.get("gas_sponsor").is_none());
+        assert_eq!(sent, serde_json::to_value(&built.call).unwrap());
+        let args = sent["args"].as_array().unwrap();
+        assert_eq!(args.len(), 8);
+        // The wallet can only pass the shared cap
+        assert_eq!(args[1], "0xshared_cap");
+        assert_eq!(args[2], "0xdid");
+        assert_eq!(args[3], "true");
+        assert_eq!(args[5], "1735689600000");
+        assert_eq!(args[6], serde_json::json!(vec![0xab; 32]));
+        let signature: Vec<u8> = serde_json::from_value(args[4].clone()).unwrap();
+        let payload = format!("0xabc:1:verified:{}:2025-01-01T00:00:00+00:00", "ab".repeat(32));
+        let signature = fastcrypto::ed25519::Ed25519Signature::from_bytes(&signature).unwrap();
+        assert!(state.eph_kp.public().verify(payload.as_bytes(), &signature).is_ok());
+
+        // Nothing stored, nothing built
+        let unknown = VerificationTransactionRequest { wallet: "0xdef".to_string(), ..request };
+        assert!(matches!(
+            build_verification_transaction(&state, &unknown, &target(), &proxy_url).await,
+            Err(EnclaveError::NotFound(_))
+        ));
+
+        // Without a shared cap the wallet couldn't execute it, so it isn't built
+        let owned_only = SuiTarget { shared_cap_id: None, ..target() };
+        let request = VerificationTransactionRequest { wallet: "0xabc".to_string(), ..unknown };
+        assert!(matches!(
+            build_verification_transaction(&state, &request, &owned_only, &proxy_url).await,
+            Err(EnclaveError::Internal(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_sponsored_transaction_is_sent_by_the_wallet_and_paid_by_the_sponsor() {
+        let mut state = AppState::new(Ed25519KeyPair::generate(&mut rand::thread_rng()));
+        let coins = Arc::new(SponsorCoins::new(vec!["0xcoin".to_string()], Duration::from_secs(60)));
+        state.gas_mode = GasMode::Sponsored { sponsor: "0xsponsor".to_string(), coins: coins.clone() };
+        store_attestation(&state).await;
+
+        let received = Arc::new(Mutex::new(None));
+        let proxy_url = serializing_proxy(received.clone()).await;
+        let request = VerificationTransactionRequest {
+            wallet: "0xabc".to_string(),
+            verification_type: "citizenship".to_string(),
+            user_did_id: "0xdid".to_string(),
+        };
+        let built = build_verification_transaction(&state, &request, &target(), &proxy_url).await.unwrap();
+
+        // The wallet sends, the sponsor's pooled coin pays
+        let sent = received.lock().unwrap().take().unwrap();
+        assert_eq!(sent["sender"], "0xabc");
+        assert_eq!(sent["gas_sponsor"], "0xsponsor");
+        assert_eq!(sent["gas"], "0xcoin");
+        assert_eq!(sent["serialize_unsigned"], true);
+        assert_eq!(
+            built.sponsorship,
+            Some(Sponsorship {
+                sender: "0xabc".to_string(),
+                sponsor: "0xsponsor".to_string(),
+                gas: Some("0xcoin".to_string()),
+                sponsor_signature: "AHNwb25zb3I=".to_string(),
+            })
+        );
+
+        // The coin stays with the bytes: nothing else is sponsored until they are submitted
+        assert!(matches!(
+            build_verification_transaction(&state, &request, &target(), &proxy_url).await,
+            Err(EnclaveError::RateLimited(_))
+        ));
+        let submission = |tx_bytes: &str| SponsoredSubmission {
+            tx_bytes: tx_bytes.to_string(),
+            user_signature: "AHVzZXI=".to_string(),
+            sponsor_signature: "AHNwb25zb3I=".to_string(),
+        };
+        assert!(matches!(
+            submit_sponsored(&proxy_url, &coins, &submission("AAAA")).await,
+            Err(EnclaveError::NotFound(_))
+        ));
+        let submitted = submit_sponsored(&proxy_url, &coins, &submission(&built.tx_bytes)).await.unwrap();
+        assert_eq!(submitted.output, "executed");
+        assert_eq!(coins.take().as_deref(), Some("0xcoin"));
+    }
+
+    #[test]
+    fn test_unsubmitted_sponsorship_frees_its_coin_once_expired() {
+        let coins = SponsorCoins::new(vec!["0xcoin".to_string()], Duration::ZERO);
+        let coin = coins.take().unwrap();
+        coins.reserve("AAACAQ==", coin);
+        assert!(!coins.is_reserved("AAACAQ=="));
+        assert_eq!(coins.take().as_deref(), Some("0xcoin"));
+    }
+
+    #[test]
+    fn test_transaction_endpoints_need_a_key_and_are_rate_limited_per_wallet() {
+        let access = TransactionAccess::new(vec!["k1".to_string()], 2, Duration::from_secs(60));
+        let headers = |key: &str| {
+            let mut headers = HeaderMap::new();
+            headers.insert("x-api-key", key.parse().unwrap());
+            headers
+        };
+        assert!(access.authenticate(&headers("k1")).is_ok());
+        assert!(matches!(access.authenticate(&headers("k2")), Err(EnclaveError::Unauthorized(_))));
+        assert!(matches!(access.authenticate(&HeaderMap::new()), Err(EnclaveError::Unauthorized(_))));
+        // No keys configured: nobody gets in
+        assert!(TransactionAccess::default().authenticate(&headers("k1")).is_err());
+
+        assert!(access.admit("0xabc").is_ok());
+        assert!(access.admit("0xABC").is_ok());
+        assert!(matches!(access.admit("0xabc"), Err(EnclaveError::RateLimited(_))));
+        assert!(access.admit("0xdef").is_ok());
+    }
+}
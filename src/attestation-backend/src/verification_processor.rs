@@ -1,26 +1,46 @@
 // New verification processor that integrates government API with Redis and Sui
 use anyhow::{Result, anyhow};
-use redis::{Client, RedisResult, Value, streams::StreamReadReply};
-use serde::{Deserialize, Serialize};
+use redis::{Client, RedisResult};
 use tokio::time::{Duration, Instant, sleep};
-use tracing::{error, info, warn};
+use tracing::{debug, error, info, info_span, warn, Instrument};
 use fastcrypto::ed25519::Ed25519KeyPair;
-use std::collections::HashMap;
+use fastcrypto::encoding::{Encoding, Hex};
+use fastcrypto::traits::{KeyPair, ToFromBytes};
+use std::sync::Arc;
 
-use super::government_api::{GovernmentApiClient, VerificationRequest};
-
-// DID type constants (matching your Move contract)
-const DID_PAN_VERIFY: u8 = 0; // PAN covers all verification types now
-
-// Verification result message for Sui contract
-#[derive(Debug, Clone, Deserialize, Serialize)]
-struct SuiVerificationMessage {
-    user_wallet: String,
-    did_id: u8,
-    result: String,
-    evidence_hash: String,
-    verified_at: String,
-}
+use super::attestation_store::{sign_verification_attestation, AttestationStore, RedisAttestationStore, StoredAttestation};
+use super::circuit_breaker::is_unavailable;
+use super::commit_log::{CommitLog, ResumePoint};
+use super::deferred::{self, DeferredEntry, DeferredQueue};
+use super::did_cache::UserDidCache;
+use super::evidence::decode_evidence_hash;
+use super::government_api::{gov_api_error, is_consent_missing, GovApiError, GovernmentApiClient, VerificationRequest};
+use super::lifetime_stats::LifetimeStats;
+use super::live_results::ResultFeed;
+use super::metrics;
+use super::negative_attestation::sign_negative_attestation;
+use super::payload::is_invalid_message;
+use super::message_source::{
+    AckBatch, MessageHandler, MessagePayload, MessageSource, RedisStreamSource, VerificationMessage, VerifiedResult,
+    dead_letter_entry, dispatch_batched, is_budget_exhausted, parse_timestamp_to_ms, RetryBudget, StageDeadline,
+};
+use super::results::{ResultPublisher, VerificationResultEvent};
+use super::result_store::{result_store_from_env, ResultStore};
+use super::signing::{EnclaveSigner, SigningError};
+use super::reverification::{IndexedVerification, Reverification};
+use super::key_sealing::require_persistent_key;
+use super::sealed_blob::SealingKey;
+use super::verification_types::{VerificationTypeSpec, VerificationTypes};
+use super::redis_timeout::{is_redis_timeout, with_timeout, RedisTimeouts};
+use super::retry::RetryPolicy;
+use super::sui_proxy::{is_submission_in_doubt, SubmissionInDoubt, SuiArg, SuiCallRequest, SuiEndpoints, CALL_PATH};
+use super::sui_transaction::VerificationStatusUpdate;
+use super::sui_clock::SuiClock;
+use super::stage_timer::StageTimer;
+pub use super::sui_output::extract_user_did_id;
+use super::sui_output::extract_transaction_digest;
+use super::sui_gas::{check_gas_balance, run_gas_monitor, GasBudgets, GasCoinPool, GasGate, GasPauseConfig, CALL_GAS_BUDGET_MIST};
+use super::work_queue::{self, WorkQueueConfig, WorkQueueSender};
 
 // Throughput tracker
 #[derive(Debug)]
@@ -30,6 +50,12 @@ pub struct ThroughputTracker {
     last_report_time: Instant,
 }
 
+impl Default for ThroughputTracker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl ThroughputTracker {
     pub fn new() -> Self {
         let now = Instant::now();
@@ -67,29 +93,17 @@ impl ThroughputTracker {
     }
 }
 
-pub struct VerificationProcessor {
-    keypair: Ed25519KeyPair,
-    redis_client: Client,
-    government_api: GovernmentApiClient,
-    stream_name: String,
-    consumer_group: String,
-    consumer_name: String,
-    throughput_tracker: ThroughputTracker,
-    // Sui contract parameters
-    package_id: String,
-    registry_id: String,
-    cap_id: String,
-    clock_id: String,
-    // Redis authentication
-    redis_username: String,
-    redis_password: String,
+/// Opens authenticated async connections to Redis. Shared by the fetch and execute stages.
+#[derive(Clone)]
+pub struct RedisConnector {
+    client: Client,
+    username: String,
+    password: String,
+    timeouts: RedisTimeouts,
 }
 
-impl VerificationProcessor {
-    const REPORT_INTERVAL_SECS: u64 = 10;
-    const POLL_INTERVAL_MS: u64 = 1000; // 1 second polling
-
-    pub fn new(keypair: Ed25519KeyPair) -> Result<Self> {
+impl RedisConnector {
+    pub fn from_env() -> Result<Self> {
         // Redis configuration
         let redis_url = std::env::var("REDIS_URL")
             .unwrap_or_else(|_| "redis://localhost:6379".to_string());
@@ -102,53 +116,50 @@ impl VerificationProcessor {
                   &redis_url 
               });
         
-        let client = Client::open(redis_url.as_str())
-            .map_err(|e| anyhow!("Failed to create Redis client: {}", e))?;
-
         // Get Redis authentication credentials
-        let redis_username = std::env::var("REDIS_USERNAME")
+        let username = std::env::var("REDIS_USERNAME")
             .unwrap_or_else(|_| "default".to_string());
-        let redis_password = std::env::var("REDIS_PASSWORD")
+        let password = std::env::var("REDIS_PASSWORD")
             .map_err(|_| anyhow!("REDIS_PASSWORD environment variable is required"))?;
 
-        // Initialize government API client
-        let government_api = GovernmentApiClient::new()
-            .map_err(|e| anyhow!("Failed to initialize government API client: {}", e))?;
+        Self::new(&redis_url, &username, &password)
+    }
 
-        Ok(VerificationProcessor {
-            keypair,
-            redis_client: client,
-            government_api,
-            stream_name: std::env::var("REDIS_STREAM_NAME")
-                .unwrap_or_else(|_| "verification_stream".to_string()),
-            consumer_group: std::env::var("REDIS_CONSUMER_GROUP")
-                .unwrap_or_else(|_| "attestation_processors".to_string()),
-            consumer_name: std::env::var("REDIS_CONSUMER_NAME")
-                .unwrap_or_else(|_| "rust_processor_1".to_string()),
-            throughput_tracker: ThroughputTracker::new(),
-            package_id: std::env::var("SUI_PACKAGE_ID")
-                .unwrap_or_else(|_| "0x6ec40d30e636afb906e621748ee60a9b72bc59a39325adda43deadd28dc89e09".to_string()),
-            registry_id: std::env::var("SUI_REGISTRY_ID")
-                .unwrap_or_else(|_| "0x2c6962f40c84a7df1d40c74ab05c7f60c9afdbae8129cfe507ced948a02cbdc4".to_string()),
-            cap_id: std::env::var("SUI_CAP_ID")
-                .unwrap_or_else(|_| "0x9aa20287121e2d325405097c54b5a2519a5d3f745ca74d47358a490dc94914cc".to_string()),
-            clock_id: std::env::var("SUI_CLOCK_ID")
-                .unwrap_or_else(|_| "0x0000000000000000000000000000000000000000000000000000000000000006".to_string()),
-            redis_username,
-            redis_password,
+    pub fn new(redis_url: &str, username: &str, password: &str) -> Result<Self> {
+        let client = Client::open(redis_url)
+            .map_err(|e| anyhow!("Failed to create Redis client: {}", e))?;
+
+        Ok(Self {
+            client,
+            username: username.to_string(),
+            password: password.to_string(),
+            timeouts: RedisTimeouts::from_env(),
         })
     }
 
-    /// Helper method to get an authenticated Redis connection
-    async fn get_authenticated_connection(&self) -> Result<redis::aio::Connection> {
-        let mut conn = self.redis_client.get_async_connection().await
+    pub fn with_timeouts(mut self, timeouts: RedisTimeouts) -> Self {
+        self.timeouts = timeouts;
+        self
+    }
+
+    pub fn timeouts(&self) -> RedisTimeouts {
+        self.timeouts
+    }
+
+    /// Get an authenticated Redis connection. Connecting and AUTH share `REDIS_CONNECT_TIMEOUT_MS`.
+    pub async fn connect(&self) -> Result<redis::aio::Connection> {
+        with_timeout("connect", self.timeouts.connect, self.connect_and_auth()).await
+    }
+
+    async fn connect_and_auth(&self) -> Result<redis::aio::Connection> {
+        let mut conn = self.client.get_async_connection().await
             .map_err(|e| anyhow!("Failed to connect to Redis: {}", e))?;
         
         // Explicit authentication required for Redis Cloud
-        info!("Authenticating with Redis using username: {}", self.redis_username);
+        info!("Authenticating with Redis using username: {}", self.username);
         let auth_result: RedisResult<String> = redis::cmd("AUTH")
-            .arg(&self.redis_username)
-            .arg(&self.redis_password)
+            .arg(&self.username)
+            .arg(&self.password)
             .query_async(&mut conn)
             .await;
 
@@ -164,347 +175,681 @@ impl VerificationProcessor {
         
         Ok(conn)
     }
+}
 
-    pub async fn start_processing(&mut self) -> Result<()> {
-        info!("Starting Verification Processor with Government API integration...");
-        info!("Contract parameters:");
-        info!("   Package: {}", self.package_id);
-        info!("   Registry: {}", self.registry_id);
-        info!("   Cap: {}", self.cap_id);
-        info!("   Stream: {}", self.stream_name);
-        info!("   Consumer Group: {}", self.consumer_group);
-        info!("   Consumer Name: {}", self.consumer_name);
-        
-        // Create consumer group if it doesn't exist
-        self.create_consumer_group().await?;
-        
-        // Main processing loop
-        loop {
-            match self.process_pending_messages().await {
-                Ok(processed_count) => {
-                    if processed_count == 0 {
-                        // No messages, sleep briefly
-                        sleep(Duration::from_millis(Self::POLL_INTERVAL_MS)).await;
-                    }
-                    
-                    // Report throughput periodically
-                    self.throughput_tracker.maybe_report(Self::REPORT_INTERVAL_SECS);
+/// Fetch stage: pulls new messages from the source into the worker queue.
+/// It only asks for as many messages as the queue has free slots for, and parks
+/// while the queue is full instead of polling the source.
+async fn run_fetcher<S: MessageSource>(source: Arc<S>, queue: WorkQueueSender<VerificationMessage>, gas: GasGate) -> Result<()> {
+    const POLL_INTERVAL_MS: u64 = 1000; // 1 second polling
+
+    loop {
+        // Messages stay in the stream while there is no gas to process them with
+        if gas.is_paused() {
+            warn!("⛽ Not fetching from {} until the gas balance is topped up", source.name());
+            gas.wait_until_funded().await;
+        }
+        let free_slots = queue.wait_for_capacity().await?;
+
+        // The source picks its own batch size, never more than the queue can take
+        match source.next_batch(free_slots).await {
+            Ok(messages) => {
+                if messages.is_empty() {
+                    // No messages, sleep briefly
+                    sleep(Duration::from_millis(POLL_INTERVAL_MS)).await;
                 }
-                Err(e) => {
-                    error!("Error processing messages: {}", e);
-                    sleep(Duration::from_secs(5)).await; // Back off on error
+
+                for message in messages {
+                    if let Err(rejected) = queue.push(message).await {
+                        // Left pending, not dropped: the source reclaims it once it has sat idle
+                        source.nack(&rejected, "worker queue full").await?;
+                    }
                 }
             }
+            Err(e) => {
+                error!("Error reading messages from {}: {}", source.name(), e);
+                sleep(Duration::from_secs(5)).await; // Back off on error
+            }
         }
     }
+}
 
-    async fn create_consumer_group(&mut self) -> Result<()> {
-        let mut conn = self.get_authenticated_connection().await?;
+/// Contract objects used when `SUI_PACKAGE_ID`, `SUI_REGISTRY_ID`, `SUI_CAP_ID` and `SUI_CLOCK_ID` are unset.
+pub const DEFAULT_SUI_PACKAGE_ID: &str = "0x6ec40d30e636afb906e621748ee60a9b72bc59a39325adda43deadd28dc89e09";
+pub const DEFAULT_SUI_REGISTRY_ID: &str = "0x2c6962f40c84a7df1d40c74ab05c7f60c9afdbae8129cfe507ced948a02cbdc4";
+pub const DEFAULT_SUI_CAP_ID: &str = "0x9aa20287121e2d325405097c54b5a2519a5d3f745ca74d47358a490dc94914cc";
+pub const DEFAULT_SUI_CLOCK_ID: &str = "0x0000000000000000000000000000000000000000000000000000000000000006";
 
-        // Try to create consumer group (ignore if it already exists)
-        let result: RedisResult<String> = redis::cmd("XGROUP")
-            .arg("CREATE")
-            .arg(&self.stream_name)
-            .arg(&self.consumer_group)
-            .arg("0")
-            .arg("MKSTREAM")
-            .query_async(&mut conn)
-            .await;
+pub struct VerificationProcessor {
+    keypair: Ed25519KeyPair,
+    // Seals document data before it is copied anywhere in Redis
+    sealing_key: SealingKey,
+    redis: RedisConnector,
+    government_api: GovernmentApiClient,
+    result_publisher: ResultPublisher,
+    // Latest signed result per wallet and type, served by /attestation
+    attestations: Arc<dyn AttestationStore>,
+    // Append-only log of every result, when RESULTS_STORE names a backend
+    result_store: Option<Arc<dyn ResultStore>>,
+    commit_log: CommitLog,
+    deferred: DeferredQueue,
+    verification_types: VerificationTypes,
+    reverification: Reverification,
+    // Messages that can never succeed are moved here instead of being retried
+    message_dlq_stream: String,
+    // Overall attempts and time per message, across every stage
+    retry_budget: Arc<RetryBudget>,
+    // Redis connection for the commit log and results, independent of the message source
+    conn: Option<redis::aio::Connection>,
+    work_queue_config: WorkQueueConfig,
+    throughput_tracker: ThroughputTracker,
+    // Processed/succeeded/failed counts that survive restarts
+    lifetime_stats: LifetimeStats,
+    // Also record rejections on-chain (verified=false) instead of only signing them
+    record_negative_on_chain: bool,
+    gas_pool: Arc<GasCoinPool>,
+    gas_budgets: GasBudgets,
+    // Keeps signature timestamps from running ahead of the on-chain Clock
+    sui_clock: SuiClock,
+    // Known UserDID objects, so hot wallets don't re-run start_verification
+    did_cache: UserDidCache,
+    // Counts start_verification outputs the UserDID can't be read from
+    did_extraction: DidExtractionMonitor,
+    proxy_client: reqwest::Client,
+    proxy_retry: RetryPolicy,
+    // Proxies to submit through, in failover order
+    sui_endpoints: Arc<SuiEndpoints>,
+    // Sui contract parameters
+    package_id: String,
+    registry_id: String,
+    cap_id: String,
+    clock_id: String,
+}
 
-        match result {
-            Ok(_) => info!("Created consumer group: {}", self.consumer_group),
-            Err(e) => {
-                if e.to_string().contains("BUSYGROUP") {
-                    info!("Consumer group already exists: {}", self.consumer_group);
-                } else {
-                    warn!("Failed to create consumer group: {}", e);
-                }
-            }
+impl VerificationProcessor {
+    const REPORT_INTERVAL_SECS: u64 = 10;
+
+    pub fn new(keypair: Ed25519KeyPair) -> Result<Self> {
+        Self::with_redis(keypair, RedisConnector::from_env()?)
+    }
+
+    /// As [`Self::new`], on an already configured Redis connection.
+    fn with_redis(keypair: Ed25519KeyPair, redis: RedisConnector) -> Result<Self> {
+        let verification_types = VerificationTypes::from_env()?;
+
+        // Initialize government API client
+        let government_api = GovernmentApiClient::new()
+            .map_err(|e| anyhow!("Failed to initialize government API client: {}", e))?
+            .with_verification_types(&verification_types);
+
+        let result_publisher = ResultPublisher::from_env()?;
+
+        let gas_budgets = GasBudgets::from_env()?;
+        info!("⛽ Gas budgets: default={} overrides={:?} dry_run_margin={:?}",
+              gas_budgets.default_mist, gas_budgets.overrides, gas_budgets.dry_run_margin_percent);
+
+        let sealing_key = SealingKey::from_keypair(&keypair);
+        let deferred = DeferredQueue::from_env(sealing_key.clone());
+        if deferred.enabled {
+            require_persistent_key("GOVT_API_DEFER_ENABLED")?;
         }
+        let reverification = Reverification::from_env(sealing_key.clone());
+        if reverification.enabled {
+            require_persistent_key("REVERIFY_ENABLED")?;
+        }
+        Ok(VerificationProcessor {
+            keypair,
+            sealing_key,
+            redis: redis.clone(),
+            government_api,
+            result_publisher,
+            result_store: result_store_from_env(&redis)?,
+            attestations: Arc::new(RedisAttestationStore::from_env(redis)),
+            commit_log: CommitLog::from_env(),
+            deferred,
+            verification_types,
+            reverification,
+            message_dlq_stream: std::env::var("VERIFICATION_DLQ_STREAM")
+                .unwrap_or_else(|_| "verification_dlq".to_string()),
+            retry_budget: Arc::new(RetryBudget::from_env()),
+            conn: None,
+            work_queue_config: WorkQueueConfig::from_env()?,
+            throughput_tracker: ThroughputTracker::new(),
+            lifetime_stats: LifetimeStats::from_env(),
+            record_negative_on_chain: std::env::var("RECORD_NEGATIVE_ATTESTATIONS")
+                .map(|v| v == "1" || v.eq_ignore_ascii_case("true"))
+                .unwrap_or(false),
+            gas_pool: Arc::new(GasCoinPool::from_env()),
+            gas_budgets,
+            sui_clock: SuiClock::from_env(),
+            did_cache: UserDidCache::from_env(),
+            did_extraction: DidExtractionMonitor::from_env(),
+            proxy_client: reqwest::Client::new(),
+            proxy_retry: RetryPolicy::from_env("SUI_PROXY_RETRY"),
+            sui_endpoints: Arc::new(SuiEndpoints::from_env()?),
+            package_id: std::env::var("SUI_PACKAGE_ID")
+                .unwrap_or_else(|_| DEFAULT_SUI_PACKAGE_ID.to_string()),
+            registry_id: std::env::var("SUI_REGISTRY_ID")
+                .unwrap_or_else(|_| DEFAULT_SUI_REGISTRY_ID.to_string()),
+            cap_id: std::env::var("SUI_CAP_ID")
+                .unwrap_or_else(|_| DEFAULT_SUI_CAP_ID.to_string()),
+            clock_id: std::env::var("SUI_CLOCK_ID")
+                .unwrap_or_else(|_| DEFAULT_SUI_CLOCK_ID.to_string()),
+        })
+    }
 
-        Ok(())
+    pub fn redis(&self) -> &RedisConnector {
+        &self.redis
     }
 
-    async fn process_pending_messages(&mut self) -> Result<usize> {
-        let mut conn = self.get_authenticated_connection().await?;
-
-        // Read messages from the stream
-        let result: RedisResult<StreamReadReply> = redis::cmd("XREADGROUP")
-            .arg("GROUP")
-            .arg(&self.consumer_group)
-            .arg(&self.consumer_name)
-            .arg("COUNT")
-            .arg("10") // Process up to 10 messages at once
-            .arg("BLOCK")
-            .arg("1000") // Block for 1 second
-            .arg("STREAMS")
-            .arg(&self.stream_name)
-            .arg(">") // Only new messages
-            .query_async(&mut conn)
-            .await;
+    pub async fn start_processing<S: MessageSource + 'static>(&mut self, source: Arc<S>) -> Result<()> {
+        info!("Starting Verification Processor with Government API integration...");
+        info!("Contract parameters:");
+        info!("   Package: {}", self.package_id);
+        info!("   Registry: {}", self.registry_id);
+        info!("   Cap: {}", self.cap_id);
+        info!("   Source: {}", source.name());
+
+        // Fetch stage runs in its own task, feeding the bounded worker queue
+        let (queue_tx, mut queue_rx) = work_queue::channel("worker_queue", &self.work_queue_config);
+        let gas_gate = GasGate::default();
+        let gas_pause = GasPauseConfig::from_env();
+        let gas_monitor = gas_pause.enabled().then(|| tokio::spawn(run_gas_monitor(gas_gate.clone(), gas_pause)));
+        let fetch_handle = tokio::spawn(run_fetcher(source.clone(), queue_tx, gas_gate));
+        // Re-verification waits on the same slow APIs, so it runs beside the queue, not in it
+        let reverifier = if self.reverification.enabled {
+            Some(tokio::spawn(self.reverifier()?.run_reverification()))
+        } else {
+            None
+        };
 
-        match result {
-            Ok(reply) => {
-                let mut processed_count = 0;
-                
-                for stream_key in reply.keys {
-                    for stream_id in stream_key.ids {
-                        match self.process_verification_message(&stream_id.id, &stream_id.map).await {
-                            Ok(_) => {
-                                // Acknowledge the message
-                                let _: RedisResult<i32> = redis::cmd("XACK")
-                                    .arg(&self.stream_name)
-                                    .arg(&self.consumer_group)
-                                    .arg(&stream_id.id)
-                                    .query_async(&mut conn)
-                                    .await;
-                                
-                                processed_count += 1;
-                                self.throughput_tracker.record_message();
-                            }
-                            Err(e) => {
-                                error!("Failed to process message {}: {}", stream_id.id, e);
-                                // Don't acknowledge failed messages - they'll be retried
-                            }
-                        }
+        // Execute stage: process queued messages in order
+        // Processed messages are acked together, once the batch fills or the queue runs dry
+        let mut acks = AckBatch::from_env();
+        while let Some(message) = queue_rx.recv().await {
+            let mut messages = vec![message];
+            let batch = self.government_api.batch_config();
+            if batch.enabled() {
+                // Give the queue a short window to fill a batch of PAN calls
+                while messages.len() < batch.size {
+                    match tokio::time::timeout(batch.window, queue_rx.recv()).await {
+                        Ok(Some(message)) => messages.push(message),
+                        _ => break,
                     }
                 }
-                
-                Ok(processed_count)
             }
-            Err(e) => {
-                if e.to_string().contains("NOGROUP") {
-                    warn!("Consumer group doesn't exist, recreating...");
-                    self.create_consumer_group().await?;
-                    Ok(0)
-                } else {
-                    Err(anyhow!("Redis stream read error: {}", e))
+            // With whatever else is queued already, as many PANs as the API may verify at once
+            let in_flight = batch.size.max(self.government_api.call_limit().max());
+            while messages.len() < in_flight {
+                match queue_rx.try_recv() {
+                    Some(message) => messages.push(message),
+                    None => break,
                 }
             }
+            if messages.len() > 1 {
+                self.prefetch_pan_batch(&messages).await;
+            }
+            for message in &messages {
+                let mut result = dispatch_batched(source.as_ref(), self, message, &mut acks).await;
+                if result.is_ok() && queue_rx.is_empty() {
+                    result = acks.flush(source.as_ref()).await;
+                }
+                if let Err(e) = result {
+                    error!("Error while finalizing message {}: {}", message.id, e);
+                    sleep(Duration::from_secs(5)).await; // Back off on error
+                }
+            }
+
+            // Report throughput periodically
+            self.throughput_tracker.maybe_report(Self::REPORT_INTERVAL_SECS);
+        }
+
+        if let Err(e) = acks.flush(source.as_ref()).await {
+            error!("Failed to ack the last processed messages: {}", e);
+        }
+
+        if let Some(monitor) = gas_monitor {
+            monitor.abort();
+        }
+        if let Some(reverifier) = reverifier {
+            reverifier.abort();
+        }
+
+        // The queue only closes when the fetcher exits
+        match fetch_handle.await {
+            Ok(Ok(())) => Err(anyhow!("Message fetcher stopped unexpectedly")),
+            Ok(Err(e)) => Err(anyhow!("Message fetcher failed: {}", e)),
+            Err(e) => Err(anyhow!("Message fetcher task panicked: {}", e)),
         }
     }
 
-    async fn process_verification_message(&mut self, message_id: &str, fields: &HashMap<String, Value>) -> Result<()> {
-        info!("Processing verification message: {}", message_id);
+    /// Verify the PANs of the raw requests among `messages` together, in one batch call or in
+    /// concurrent ones. Only
+    /// requests their own handling would send to the API are included: not ones of an unknown
+    /// type or already past their retry deadline.
+    async fn prefetch_pan_batch(&mut self, messages: &[VerificationMessage]) {
+        let requests: Vec<(String, VerificationRequest)> = messages
+            .iter()
+            .filter_map(|message| match &message.payload {
+                MessagePayload::Request(request) => Some((message, request)),
+                MessagePayload::Verified(_) => None,
+            })
+            .filter(|(message, request)| {
+                self.verification_types.for_request(request).is_ok() && !self.retry_budget.past_deadline(&message.key())
+            })
+            .map(|(message, request)| (message.id.clone(), request.clone()))
+            .collect();
+        self.government_api.prefetch(&requests).await;
+    }
 
-        // Parse Redis message into VerificationRequest
-        let verification_request = self.parse_verification_request(fields)?;
+    /// Copy a message that can never be processed to the DLQ stream, with why it was rejected.
+    async fn dead_letter_message(
+        &self,
+        conn: &mut redis::aio::Connection,
+        message: &VerificationMessage,
+        error: &anyhow::Error,
+    ) -> Result<()> {
+        let payload = match &message.payload {
+            MessagePayload::Request(request) => serde_json::to_string(request)?,
+            MessagePayload::Verified(result) => serde_json::to_string(result)?,
+        };
+        let stream = message.stream.as_deref().unwrap_or_default();
+        let xadd = dead_letter_entry(&self.message_dlq_stream, &message.id, stream, &self.sealing_key, &payload, error);
+        with_timeout("XADD", self.redis.timeouts().command, xadd.query_async::<_, String>(conn)).await?;
+        metrics::increment("verification_dlq_total");
+        warn!("☠️  Message {} moved to {}: {}", message.id, self.message_dlq_stream, error);
+        Ok(())
+    }
 
-        info!("Processing verification for wallet: {} - Type: {}", 
-              verification_request.user_wallet, verification_request.verification_type);
+    async fn process_verification_message(
+        &mut self,
+        conn: &mut redis::aio::Connection,
+        message: &VerificationMessage,
+        timer: &StageTimer,
+        deadline: StageDeadline,
+    ) -> Result<VerificationResultEvent> {
+        info!("Processing verification message: {} from {}", message.id, message.stream.as_deref().unwrap_or("-"));
 
-        // Process with government API
-        let (verification_result, evidence_hash) = self.government_api
-            .process_verification_request(&verification_request)
-            .await?;
+        let (verified, verification_type, evidence_schema, evidence_profile) = match &message.payload {
+            MessagePayload::Request(verification_request) => {
+                info!("Processing verification for wallet: {} - Type: {}", 
+                      verification_request.user_wallet, verification_request.verification_type);
 
-        // Convert DID string to u8
-        let did_id = verification_request.did_id.parse::<u8>()
-            .unwrap_or(DID_PAN_VERIFY); // Default to PAN verification
+                // Unknown types and mismatched DID IDs fail before the API is called
+                let spec = self.verification_types.for_request(verification_request)?.clone();
 
-        // Create Sui verification message
-        let sui_message = SuiVerificationMessage {
-            user_wallet: verification_request.user_wallet.clone(),
-            did_id,
-            result: verification_result,
-            evidence_hash,
-            verified_at: chrono::Utc::now().to_rfc3339(),
+                // Process with government API
+                deadline.check("government_api")?;
+                let outcome = self.government_api
+                    .process_verification_request(verification_request, timer)
+                    .await?;
+                check_evidence_schema(&spec, outcome.evidence.schema)?;
+                let did_id = spec.did_id;
+
+                let verified = VerifiedResult {
+                    user_wallet: verification_request.user_wallet.clone(),
+                    did_id,
+                    result: outcome.result,
+                    evidence_hash: outcome.evidence.hash,
+                    verified_at: chrono::Utc::now().to_rfc3339(),
+                    rejection_reason: outcome.rejection_reason,
+                };
+                (
+                    verified,
+                    verification_request.verification_type.clone(),
+                    outcome.evidence.schema.to_string(),
+                    outcome.evidence.profile.to_string(),
+                )
+            }
+            // Decided upstream; only the Sui calls are left
+            MessagePayload::Verified(result) => (result.clone(), "external".to_string(), String::new(), String::new()),
         };
 
         // Execute Sui contract call
-        self.execute_sui_contract(&sui_message).await?;
+        deadline.check("sui_contract")?;
+        let sui_step = self.execute_sui_contract(conn, &verified, timer).await;
+        let user_did_id = settle_sui_step(&verified, self.record_negative_on_chain, sui_step)?;
 
-        info!("Successfully processed verification for wallet: {}", verification_request.user_wallet);
+        info!("Successfully processed verification for wallet: {}", verified.user_wallet);
 
-        Ok(())
-    }
+        // Index verified wallets so they are re-checked once stale
+        if let (true, MessagePayload::Request(request), Some(user_did_id)) =
+            (self.reverification.enabled, &message.payload, user_did_id)
+        {
+            if verified.result == "verified" {
+                let entry = IndexedVerification {
+                    request: request.clone(),
+                    user_did_id,
+                    verified_at_ms: parse_timestamp_to_ms(&verified.verified_at)?,
+                };
+                if let Err(e) = self.reverification.record(conn, &entry).await {
+                    warn!("Failed to index {} for re-verification: {}", entry.member(), e);
+                }
+            }
+        }
 
-    fn parse_verification_request(&self, fields: &HashMap<String, Value>) -> Result<VerificationRequest> {
-        let get_field = |key: &str| -> Result<String> {
-            fields.get(key)
-                .and_then(|v| {
-                    // Convert Redis Value to String
-                    match v {
-                        Value::Data(bytes) => String::from_utf8(bytes.clone()).ok(),
-                        Value::Int(i) => Some(i.to_string()),
-                        Value::Status(s) => Some(s.clone()),
-                        _ => {
-                            // For other types, try to use Debug formatting as fallback
-                            Some(format!("{:?}", v))
-                        }
-                    }
-                })
-                .ok_or_else(|| anyhow!("Missing or invalid field: {}", key))
+        // Rejections get a signed negative attestation so they are attributable to the enclave;
+        // a result left for review isn't a rejection yet
+        let negative_attestation = if verified.result != "verified" && verified.result != "review" {
+            let reason = verified.rejection_reason.clone()
+                .unwrap_or_else(|| verified.result.clone());
+            Some(sign_negative_attestation(
+                &self.keypair,
+                &verified.user_wallet,
+                verified.did_id,
+                &reason,
+                &verified.evidence_hash,
+                &verified.verified_at,
+                parse_timestamp_to_ms(&verified.verified_at)?,
+            )?)
+        } else {
+            None
         };
 
-        Ok(VerificationRequest {
-            user_wallet: get_field("user_wallet")?,
-            did_id: get_field("did_id")?,
-            verification_type: get_field("verification_type")?,
-            document_data: get_field("document_data")?,
-            extracted_data: get_field("extracted_data").ok(),
-            user_corrections: get_field("user_corrections").ok(),
-            timestamp: get_field("timestamp")?,
-            status: get_field("status")?,
+        Ok(VerificationResultEvent {
+            message_id: message.key(),
+            user_wallet: verified.user_wallet,
+            did_id: verified.did_id,
+            verification_type,
+            result: verified.result,
+            evidence_hash: verified.evidence_hash,
+            evidence_schema,
+            evidence_profile,
+            verified_at: verified.verified_at,
+            negative_attestation,
+            source_stream: message.stream.clone(),
         })
     }
 
-    async fn execute_sui_contract(&self, message: &SuiVerificationMessage) -> Result<()> {
+    /// Run the Sui calls for a decided result. Returns the UserDID object id once its status is on-chain.
+    async fn execute_sui_contract(
+        &self,
+        conn: &mut redis::aio::Connection,
+        message: &VerifiedResult,
+        timer: &StageTimer,
+    ) -> Result<Option<String>> {
         info!("Executing Sui contract for wallet: {} using HTTP calls to Flask proxy", message.user_wallet);
 
-        // Step 1: Execute start_verification via HTTP call to Flask proxy
-        let user_did_id = self.call_start_verification(
-            &message.user_wallet,
-            message.did_id,
-        ).await?;
+        // A malformed hash would only fail at the contract, after start_verification; it is
+        // an invalid message, so dead-lettered rather than retried
+        let evidence_hash = decode_evidence_hash(&message.evidence_hash)?;
 
-        if let Some(did_id) = user_did_id {
-            info!("✅ Step 1: start_verification successful for wallet: {} with DID ID: {}", 
-                  message.user_wallet, did_id);
-            
-            // Step 2: Execute update_verification_status with evidence hash (only if verified)
-            if message.result == "verified" {
-                info!("✅ Step 2: Executing update_verification_status with evidence hash");
-                
-                // Generate signature for the verification
-                let signature = self.generate_verification_signature(message)?;
-                
-                // Parse the original verification timestamp to milliseconds
-                let verification_timestamp_ms = chrono::DateTime::parse_from_rfc3339(&message.verified_at)
-                    .map_err(|e| anyhow!("Failed to parse verified_at timestamp: {}", e))?
-                    .timestamp_millis() as u64;
-                
-                self.call_update_verification_status(
-                    &message.user_wallet,
-                    &did_id,
-                    true, // is_verified = true
-                    signature,
-                    verification_timestamp_ms,
-                    &message.evidence_hash,
-                ).await?;
-                
-                info!("🎉 Complete Sui contract execution successful for wallet: {}", message.user_wallet);
-                info!("Evidence hash recorded on-chain: {}", message.evidence_hash);
-            } else {
-                info!("⚠️ Verification result is '{}', skipping update_verification_status", message.result);
+        // Consult the commit log so a redelivered message resumes where it left off
+        let commit_key = self.commit_log.key(&message.user_wallet, message.did_id, &message.evidence_hash);
+        let user_did_id = match self.commit_log.load(conn, &commit_key).await?.resume_point() {
+            ResumePoint::Completed => {
+                info!("⏭️ Sui calls already committed for wallet: {}, skipping", message.user_wallet);
+                return Ok(None);
+            }
+            ResumePoint::InDoubt { call } => {
+                warn!("⚠️ {} for wallet: {} has an unknown outcome, not resubmitting", call, message.user_wallet);
+                return Err(SubmissionInDoubt { reason: format!("{} submitted earlier", call) }.into());
+            }
+            ResumePoint::UpdateVerificationStatus { user_did_id } => {
+                info!("🔁 Resuming at update_verification_status for wallet: {} with DID ID: {}",
+                      message.user_wallet, user_did_id);
+                user_did_id
             }
+            ResumePoint::StartVerification => {
+                // Step 1: Execute start_verification via HTTP call to Flask proxy, unless the object is cached
+                // A failed call is an error, so the message is retried rather than acked half-done
+                let user_did_id = self.did_cache
+                    .get_or_start(&message.user_wallet, message.did_id, || {
+                        timer.time("start_verification", self.call_start_verification(&message.user_wallet, message.did_id))
+                    })
+                    .await;
+                let user_did_id = self.record_in_doubt(conn, &commit_key, "start_verification", user_did_id).await?;
+                self.commit_log.mark_started(conn, &commit_key, &user_did_id).await?;
+                user_did_id
+            }
+        };
+
+        info!("✅ Step 1: start_verification successful for wallet: {} with DID ID: {}", 
+              message.user_wallet, user_did_id);
+        
+        // Step 2: Execute update_verification_status with evidence hash (only if verified)
+        if message.result == "verified" {
+            info!("✅ Step 2: Executing update_verification_status with evidence hash");
+            
+            let updated = timer.time("update_verification_status", self.call_update_verification_status(
+                message,
+                &user_did_id,
+                true, // is_verified = true
+                &evidence_hash,
+            )).await;
+            self.record_in_doubt(conn, &commit_key, "update_verification_status", updated).await?;
+            self.commit_log.mark_updated(conn, &commit_key).await?;
+            
+            info!("🎉 Complete Sui contract execution successful for wallet: {}", message.user_wallet);
+            info!("Evidence hash recorded on-chain: {}", message.evidence_hash);
+            return Ok(Some(user_did_id));
+        } else if self.record_negative_on_chain && message.result != "review" {
+            info!("✅ Step 2: Recording rejected verification on-chain (verified=false)");
+
+            let updated = timer.time("update_verification_status", self.call_update_verification_status(
+                message,
+                &user_did_id,
+                false,
+                &evidence_hash,
+            )).await;
+            self.record_in_doubt(conn, &commit_key, "update_verification_status", updated).await?;
+            self.commit_log.mark_updated(conn, &commit_key).await?;
         } else {
-            warn!("❌ start_verification returned None for wallet: {}", message.user_wallet);
+            info!("⚠️ Verification result is '{}', skipping update_verification_status", message.result);
+        }
+
+        Ok(None)
+    }
+
+    /// Pass `result` through, first noting in the commit log when `call` was submitted with an
+    /// unknown outcome so a redelivery doesn't submit it a second time.
+    async fn record_in_doubt<T>(
+        &self,
+        conn: &mut redis::aio::Connection,
+        commit_key: &str,
+        call: &str,
+        result: Result<T>,
+    ) -> Result<T> {
+        if let Err(e) = &result {
+            if is_submission_in_doubt(e) {
+                error!("❓ {} outcome unknown for {}: {}", call, commit_key, e);
+                self.commit_log.mark_in_doubt(conn, commit_key, call).await?;
+            }
+        }
+        result
+    }
+
+    /// A processor of its own for scheduled re-verification, with its own Redis connection. It
+    /// shares the gas coin pool, so its transactions never use a coin one of ours holds, and the
+    /// Sui endpoints and government API limits, so it is held to the same caps and circuit.
+    fn reverifier(&self) -> Result<Self> {
+        let mut reverifier = Self::with_redis(Ed25519KeyPair::from_bytes(self.keypair.as_bytes())?, self.redis.clone())?;
+        reverifier.gas_pool = self.gas_pool.clone();
+        reverifier.sui_endpoints = self.sui_endpoints.clone();
+        reverifier.government_api = reverifier
+            .government_api
+            .with_circuit_breaker(self.government_api.circuit_breaker())
+            .with_call_limit(self.government_api.call_limit());
+        Ok(reverifier)
+    }
+
+    /// Background task: re-check stale verifications every interval. Failures are logged and
+    /// retried next interval.
+    async fn run_reverification(mut self) {
+        let mut tick = tokio::time::interval(self.reverification.interval);
+        loop {
+            tick.tick().await;
+            if let Err(e) = self.reverify_due().await {
+                error!("Re-verification run failed: {}", e);
+            }
+        }
+    }
+
+    /// Re-check stale verified wallets and refresh their on-chain status. A wallet that no longer
+    /// verifies is recorded as verified=false on its existing UserDID and dropped from the index;
+    /// one sent to review keeps its status and is checked again once stale.
+    async fn reverify_due(&mut self) -> Result<()> {
+        let mut conn = match self.conn.take() {
+            Some(conn) => conn,
+            None => self.redis.connect().await?,
+        };
+        let now_ms = chrono::Utc::now().timestamp_millis() as u64;
+        // On error the connection is dropped and re-established on next use
+        let due = self.reverification.due(&mut conn, now_ms).await?;
+        if !due.is_empty() {
+            info!("🔄 Re-verifying {} stale verifications", due.len());
         }
 
+        for entry in due {
+            match self.reverify(&mut conn, &entry).await {
+                Ok(Reverified::Confirmed) => {
+                    info!("🔄 Re-verified {}", entry.member());
+                    metrics::increment("reverifications_total");
+                }
+                Ok(Reverified::Revoked) => {
+                    warn!("🚫 {} no longer verifies, status revoked on-chain", entry.member());
+                    metrics::increment("reverification_revocations_total");
+                }
+                Ok(Reverified::Unchanged) => {
+                    warn!("🔍 {} needs review, on-chain status left as it is", entry.member());
+                    metrics::increment("reverification_reviews_total");
+                }
+                Err(e) => {
+                    error!("Re-verification of {} failed: {}", entry.member(), e);
+                    // No point hammering an API that is down; the rest stay due for next run
+                    if is_unavailable(&e) {
+                        break;
+                    }
+                }
+            }
+        }
+
+        self.conn = Some(conn);
         Ok(())
     }
 
+    async fn reverify(&mut self, conn: &mut redis::aio::Connection, entry: &IndexedVerification) -> Result<Reverified> {
+        let spec = self.verification_types.for_request(&entry.request)?.clone();
+        // An update submitted in an earlier run of this round may already be on chain
+        let commit_key = self.commit_log.reverification_key(&entry.request.user_wallet, spec.did_id, entry.verified_at_ms);
+        let resume_point = self.commit_log.load(conn, &commit_key).await?.resume_point();
+        if let ResumePoint::InDoubt { call } = resume_point {
+            warn!("⚠️ {} for {} has an unknown outcome, not resubmitting", call, entry.member());
+            return Err(SubmissionInDoubt { reason: format!("{} submitted earlier", call) }.into());
+        }
+        let outcome = self.government_api.process_verification_request(&entry.request, &StageTimer::default()).await?;
+        check_evidence_schema(&spec, outcome.evidence.schema)?;
+        let verified = VerifiedResult {
+            user_wallet: entry.request.user_wallet.clone(),
+            did_id: spec.did_id,
+            result: outcome.result,
+            evidence_hash: outcome.evidence.hash,
+            verified_at: chrono::Utc::now().to_rfc3339(),
+            rejection_reason: outcome.rejection_reason,
+        };
+        let verified_at_ms = parse_timestamp_to_ms(&verified.verified_at)?;
+        if verified.result == "review" {
+            // Nobody has decided yet, so the status stands; checked again once stale
+            let deferred = IndexedVerification { verified_at_ms, ..entry.clone() };
+            self.reverification.record(conn, &deferred).await?;
+            return Ok(Reverified::Unchanged);
+        }
+        let still_verified = verified.result == "verified";
+
+        if resume_point != ResumePoint::Completed {
+            let updated = self.call_update_verification_status(
+                &verified,
+                &entry.user_did_id,
+                still_verified,
+                &decode_evidence_hash(&verified.evidence_hash)?,
+            ).await;
+            self.record_in_doubt(conn, &commit_key, "update_verification_status", updated).await?;
+            self.commit_log.mark_updated(conn, &commit_key).await?;
+        }
+
+        if still_verified {
+            let refreshed = IndexedVerification { verified_at_ms, ..entry.clone() };
+            self.reverification.record(conn, &refreshed).await?;
+            Ok(Reverified::Confirmed)
+        } else {
+            self.reverification.remove(conn, &entry.member()).await?;
+            Ok(Reverified::Revoked)
+        }
+    }
+
     async fn call_start_verification(
         &self,
         user_address: &str,
         redis_did_id: u8,
-    ) -> Result<Option<String>> {
+    ) -> Result<String> {
         info!("Calling start_verification via HTTP for user: {}", user_address);
         
         // Map Redis DID ID to contract DID type
-        let contract_did_type = match redis_did_id {
-            0 => 1, // DID_AGE_VERIFY
-            1 => 2, // DID_CITIZENSHIP_VERIFY
-            _ => {
-                warn!("Unknown DID ID: {}, defaulting to age verification", redis_did_id);
-                1
-            }
-        };
-
-        let call_data = serde_json::json!({
-            "package_id": self.package_id,
-            "module": "did_registry",
-            "function": "start_verification",
-            "args": [
-                self.registry_id,
-                self.cap_id,
-                user_address,
-                contract_did_type,
-                "0x0000000000000000000000000000000000000000000000000000000000000006"  // Clock object ID
-            ],
-            "gas_budget": "10000000"
-        });
-
-        let client = reqwest::Client::new();
-        let response = client
-            .post("http://localhost:9999/sui/client/call")
-            .json(&call_data)
-            .send()
-            .await?;
-
-        let result: serde_json::Value = response.json().await?;
+        let spec = self.verification_types.by_did_id(redis_did_id)?;
+        let (contract_did_type, verification_type) = (spec.contract_did_type, spec.verification_type.clone());
 
-        if result["success"].as_bool().unwrap_or(false) {
-            info!("start_verification executed successfully for user: {}", user_address);
-            let output_str = result["stdout"].as_str().unwrap_or("");
-            info!("Output: {}", output_str);
-            
-            // Extract UserDID object ID from the transaction output using the same logic as redis_sui_processor
-            if let Some(user_did_id) = self.extract_user_did_id(output_str) {
-                info!("Extracted UserDID ID: {}", user_did_id);
-                return Ok(Some(user_did_id));
-            } else {
-                warn!("Could not extract UserDID ID from transaction output");
-            }
-            
-            let stderr = result["stderr"].as_str().unwrap_or("");
-            if !stderr.is_empty() {
-                warn!("Warnings: {}", stderr);
-            }
-        } else {
-            let stderr = result["stderr"].as_str().unwrap_or("unknown error");
-            let stdout = result["stdout"].as_str().unwrap_or("");
-            let returncode = result["returncode"].as_i64().unwrap_or(-1);
-            
-            error!("start_verification failed for user: {}", user_address);
-            error!("Exit code: {}", returncode);
-            error!("STDERR: {}", stderr);
-            error!("STDOUT: {}", stdout);
-        }
+        let args = vec![
+            SuiArg::ObjectId(self.registry_id.clone()),
+            SuiArg::ObjectId(self.cap_id.clone()),
+            SuiArg::Address(user_address.to_string()),
+            SuiArg::U8(contract_did_type),
+            SuiArg::ObjectId(self.clock_id.clone()),
+        ];
+        // Held until the call returns so no concurrent transaction uses the same coin
+        let gas_lease = self.gas_pool.acquire().await;
+        let url = format!("{}{}", self.sui_endpoints.active(), CALL_PATH);
+        let call = SuiCallRequest::new(&self.package_id, "did_registry", "start_verification", args, CALL_GAS_BUDGET_MIST)
+            .with_gas(gas_lease.coin());
+        let call = self.gas_budgets.apply(&self.proxy_client, &self.proxy_retry, &url, call, &verification_type).await;
+        let call_data = serde_json::to_value(&call)?;
 
-        Ok(None)
+        let result = self.sui_endpoints.post(&self.proxy_client, &self.proxy_retry, CALL_PATH, &call_data).await?;
+        user_did_from_call(&result, &self.did_extraction)
+            .inspect_err(|e| error!("start_verification failed for user: {}: {}", user_address, e))
     }
 
     async fn call_update_verification_status(
         &self,
-        user_address: &str,
+        message: &VerifiedResult,
         user_did_id: &str,
         verified: bool,
-        nautilus_signature: Vec<u8>,
-        signature_timestamp_ms: u64,
-        evidence_hash: &str,
+        evidence_hash: &[u8],
     ) -> Result<()> {
-        info!("Calling update_verification_status via HTTP for user: {}", user_address);
-
-        let call_data = serde_json::json!({
-            "package_id": self.package_id,
-            "module": "did_registry",
-            "function": "update_verification_status",
-            "args": [
-                self.registry_id,
-                self.cap_id,
-                user_did_id,
-                verified.to_string().to_lowercase(),
-                nautilus_signature,
-                signature_timestamp_ms.to_string(),
-                evidence_hash,
-                "0x0000000000000000000000000000000000000000000000000000000000000006"  // Clock object ID
-            ],
-            "gas_budget": "10000000"
-        });
-
-        let client = reqwest::Client::new();
-        let response = client
-            .post("http://localhost:9999/sui/client/call")
-            .json(&call_data)
-            .send()
-            .await?;
-
-        let result: serde_json::Value = response.json().await?;
+        info!("Calling update_verification_status via HTTP for user: {}", message.user_wallet);
+        // The contract checks the signature against the timestamp it is given, so the clamp to
+        // the Sui clock comes first and a clamped time is what gets signed
+        let verified_at_ms = parse_timestamp_to_ms(&message.verified_at)?;
+        let signature_timestamp_ms = self.sui_clock.guard(verified_at_ms).await?;
+        let restamped;
+        let message = if signature_timestamp_ms == verified_at_ms {
+            message
+        } else {
+            restamped = restamp(message, signature_timestamp_ms)?;
+            &restamped
+        };
+        let nautilus_signature = self.generate_verification_signature(message)?;
+
+        let update = VerificationStatusUpdate {
+            user_did_id: user_did_id.to_string(),
+            verified,
+            signature: nautilus_signature,
+            signature_timestamp_ms,
+            evidence_hash: evidence_hash.to_vec(),
+        };
+        let args = update.args(&self.registry_id, &self.cap_id, &self.clock_id);
+        // Held until the call returns so no concurrent transaction uses the same coin
+        let gas_lease = self.gas_pool.acquire().await;
+        let url = format!("{}{}", self.sui_endpoints.active(), CALL_PATH);
+        let call = SuiCallRequest::new(&self.package_id, "did_registry", "update_verification_status", args, CALL_GAS_BUDGET_MIST)
+            .with_gas(gas_lease.coin());
+        let verification_type = self.verification_types.by_did_id(message.did_id)?.verification_type.clone();
+        let call = self.gas_budgets.apply(&self.proxy_client, &self.proxy_retry, &url, call, &verification_type).await;
+        let call_data = serde_json::to_value(&call)?;
+
+        let result = self.sui_endpoints.post(&self.proxy_client, &self.proxy_retry, CALL_PATH, &call_data).await?;
 
         if result["success"].as_bool().unwrap_or(false) {
-            info!("update_verification_status executed successfully for user: {}", user_address);
+            info!("update_verification_status executed successfully for user: {}", message.user_wallet);
             let output_str = result["stdout"].as_str().unwrap_or("");
             info!("Output: {}", output_str);
         } else {
@@ -515,71 +860,632 @@ impl VerificationProcessor {
         Ok(())
     }
 
-    /// Extract UserDID object ID from Sui transaction output (replicated from redis_sui_processor.rs)
-    fn extract_user_did_id(&self, output: &str) -> Option<String> {
-        let lines: Vec<&str> = output.lines().collect();
-        let mut i = 0;
-        
-        // Look for Created Objects section and find the UserDID object
-        while i < lines.len() {
-            let line = lines[i];
-            
-            // Look for ObjectID line
-            if line.contains("ObjectID:") && line.contains("0x") {
-                // Extract the object ID
-                if let Some(start) = line.find("0x") {
-                    let id_part = &line[start..];
-                    let object_id = if let Some(end) = id_part.find(char::is_whitespace) {
-                        &id_part[..end]
-                    } else {
-                        id_part.trim()
-                    };
-                    
-                    // Look ahead for ObjectType line to check if this is a UserDID
-                    for j in (i+1)..(i+5).min(lines.len()) {
-                        let next_line = lines[j];
-                        if next_line.contains("ObjectType:") && next_line.contains("::did_registry::UserDID") {
-                            info!("Found UserDID object: {}", object_id);
-                            return Some(object_id.to_string());
-                        }
-                        // Stop looking if we hit another ObjectID (next object)
-                        if next_line.contains("ObjectID:") {
-                            break;
-                        }
-                    }
+    fn generate_verification_signature(&self, message: &VerifiedResult) -> Result<Vec<u8>> {
+        // A signing failure leaves the message pending; it is reclaimed once idle and retried within its budget
+        let signature = sign_verification(&self.keypair, message)?;
+        info!("Generated verification signature for wallet: {}", message.user_wallet);
+        Ok(signature)
+    }
+}
+
+/// What re-checking a stale verification did.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Reverified {
+    /// Still verified: the on-chain status was refreshed.
+    Confirmed,
+    /// No longer verified: recorded as verified=false and dropped from the index.
+    Revoked,
+    /// The API wants a person to decide; nothing on-chain was touched.
+    Unchanged,
+}
+
+/// The evidence must have been hashed over the schema the type table expects.
+fn check_evidence_schema(spec: &VerificationTypeSpec, schema: &str) -> Result<()> {
+    if spec.evidence_schema != schema {
+        return Err(anyhow!(
+            "Evidence schema '{}' does not match '{}' configured for verification type '{}'",
+            schema, spec.evidence_schema, spec.verification_type
+        ));
+    }
+    Ok(())
+}
+
+/// A decided rejection that isn't recorded on-chain: it is final, and nothing on Sui waits for it.
+/// A result sent to review isn't decided, so it never is.
+pub fn is_terminal_rejection(verified: &VerifiedResult, record_negative_on_chain: bool) -> bool {
+    verified.result != "verified" && verified.result != "review" && !record_negative_on_chain
+}
+
+/// The Sui step's outcome for `verified`. A terminal rejection is done once decided, so a Sui
+/// failure is logged and counted, and the rejection still published and acked, instead of the
+/// message being retried as if it couldn't be finished.
+pub fn settle_sui_step(
+    verified: &VerifiedResult,
+    record_negative_on_chain: bool,
+    step: Result<Option<String>>,
+) -> Result<Option<String>> {
+    match step {
+        Err(e) if is_terminal_rejection(verified, record_negative_on_chain) && !is_invalid_message(&e) => {
+            metrics::increment("rejection_sui_failures_total");
+            warn!("Sui calls failed for rejected wallet {}, recording the rejection anyway: {}", verified.user_wallet, e);
+            Ok(None)
+        }
+        step => step,
+    }
+}
+
+/// Signature passed to `update_verification_status` over `wallet:did_id:result:evidence_hash:verified_at`,
+/// using the original verification timestamp rather than the current time.
+pub fn sign_verification<S: EnclaveSigner + ?Sized>(signer: &S, message: &VerifiedResult) -> Result<Vec<u8>, SigningError> {
+    let payload = format!(
+        "{}:{}:{}:{}:{}",
+        message.user_wallet,
+        message.did_id,
+        message.result,
+        message.evidence_hash,
+        message.verified_at
+    );
+    let signature = signer.try_sign(payload.as_bytes())?;
+    Ok(signature.as_ref().to_vec())
+}
+
+/// `message` with `verified_at` moved to `timestamp_ms`, for signing a timestamp clamped to the Sui clock.
+pub fn restamp(message: &VerifiedResult, timestamp_ms: u64) -> Result<VerifiedResult> {
+    let verified_at = i64::try_from(timestamp_ms)
+        .ok()
+        .and_then(chrono::DateTime::from_timestamp_millis)
+        .ok_or_else(|| anyhow!("Timestamp {} out of range", timestamp_ms))?;
+    Ok(VerifiedResult {
+        verified_at: verified_at.to_rfc3339_opts(chrono::SecondsFormat::Millis, true),
+        ..message.clone()
+    })
+}
+
+/// Tracks [`extract_user_did_id`] failures. Each one skips `update_verification_status`, so a
+/// change in the CLI output or the contract would leave every verification half-done; a run of
+/// `USER_DID_EXTRACTION_ALERT_THRESHOLD` (default 3) consecutive failures raises
+/// `user_did_extraction_alert` until an extraction succeeds again.
+pub struct DidExtractionMonitor {
+    alert_threshold: u32,
+    consecutive_failures: std::sync::atomic::AtomicU32,
+}
+
+impl DidExtractionMonitor {
+    pub fn new(alert_threshold: u32) -> Self {
+        Self {
+            alert_threshold: alert_threshold.max(1),
+            consecutive_failures: std::sync::atomic::AtomicU32::new(0),
+        }
+    }
+
+    pub fn from_env() -> Self {
+        Self::new(
+            std::env::var("USER_DID_EXTRACTION_ALERT_THRESHOLD")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(3),
+        )
+    }
+
+    /// [`extract_user_did_id`], recording the outcome.
+    pub fn extract(&self, output: &str) -> Option<String> {
+        use std::sync::atomic::Ordering;
+        match extract_user_did_id(output) {
+            Some(user_did_id) => {
+                if self.consecutive_failures.swap(0, Ordering::SeqCst) >= self.alert_threshold {
+                    info!("✅ UserDID extraction recovered");
                 }
+                metrics::set_gauge("user_did_extraction_consecutive_failures", 0.0);
+                metrics::set_gauge("user_did_extraction_alert", 0.0);
+                Some(user_did_id)
+            }
+            None => {
+                let failures = self.consecutive_failures.fetch_add(1, Ordering::SeqCst) + 1;
+                metrics::increment("user_did_extraction_failures_total");
+                metrics::set_gauge("user_did_extraction_consecutive_failures", failures as f64);
+                error!("❌ Could not extract UserDID ID from transaction output");
+                if failures >= self.alert_threshold {
+                    metrics::set_gauge("user_did_extraction_alert", 1.0);
+                    error!("🚨 {} consecutive UserDID extraction failures: the CLI output or contract may have changed",
+                           failures);
+                }
+                None
             }
-            i += 1;
         }
-        
-        warn!("Could not find UserDID object in transaction output");
-        None
-    }
-
-    fn generate_verification_signature(&self, message: &SuiVerificationMessage) -> Result<Vec<u8>> {
-        // Create a payload to sign (matching the format expected by the contract)
-        // Use the original verification timestamp, not current time
-        let payload = format!(
-            "{}:{}:{}:{}:{}",
-            message.user_wallet,
-            message.did_id,
-            message.result,
-            message.evidence_hash,
-            message.verified_at  // Use original verification timestamp
-        );
-        
-        // Sign the payload with the enclave keypair
-        use fastcrypto::traits::Signer;
-        let signature = self.keypair.sign(payload.as_bytes());
-        
-        info!("Generated verification signature for wallet: {}", message.user_wallet);
-        
-        Ok(signature.as_ref().to_vec())
+    }
+}
+
+/// `start_verification` succeeded as a transaction but created no UserDID, e.g. because the
+/// contract returned early. Sending it again would only repeat that, so the message is
+/// dead-lettered for review with the transaction's digest.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ObjectNotCreated {
+    pub digest: Option<String>,
+}
+
+impl std::fmt::Display for ObjectNotCreated {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "start_verification transaction {} succeeded but created no UserDID object",
+               self.digest.as_deref().unwrap_or("(digest unknown)"))
+    }
+}
+
+impl std::error::Error for ObjectNotCreated {}
+
+pub fn is_object_not_created(error: &anyhow::Error) -> bool {
+    error.downcast_ref::<ObjectNotCreated>().is_some()
+}
+
+/// Errors retrying can't fix: the message is moved to the DLQ and acked instead of left pending.
+pub fn is_dead_letter_error(error: &anyhow::Error) -> bool {
+    let bad_request = matches!(gov_api_error(error), Some(GovApiError::ClientError { .. }));
+    is_invalid_message(error)
+        || is_consent_missing(error)
+        || bad_request
+        || is_budget_exhausted(error)
+        || is_object_not_created(error)
+        || is_submission_in_doubt(error)
+}
+
+/// The UserDID created by a `start_verification` call, from the proxy's response. A failed call
+/// is an error that leaves the message pending, as the transaction didn't commit; one that
+/// committed without a UserDID is an [`ObjectNotCreated`].
+pub fn user_did_from_call(result: &serde_json::Value, monitor: &DidExtractionMonitor) -> Result<String> {
+    let stdout = result["stdout"].as_str().unwrap_or("");
+    let stderr = result["stderr"].as_str().unwrap_or("");
+    if !result["success"].as_bool().unwrap_or(false) {
+        let returncode = result["returncode"].as_i64().unwrap_or(-1);
+        error!("Exit code: {}", returncode);
+        error!("STDERR: {}", stderr);
+        error!("STDOUT: {}", stdout);
+        return Err(anyhow!("start_verification failed (exit code {}): {}", returncode,
+                           if stderr.is_empty() { "unknown error" } else { stderr }));
+    }
+    info!("start_verification executed successfully");
+    info!("Output: {}", stdout);
+    if !stderr.is_empty() {
+        warn!("Warnings: {}", stderr);
+    }
+    monitor
+        .extract(stdout)
+        .inspect(|user_did_id| info!("Extracted UserDID ID: {}", user_did_id))
+        .ok_or_else(|| ObjectNotCreated { digest: extract_transaction_digest(stdout) }.into())
+}
+
+impl VerificationProcessor {
+    /// Append the result to the result store, if one is configured. Failures are logged.
+    async fn record_result(&self, event: &VerificationResultEvent) {
+        let Some(store) = &self.result_store else { return };
+        match store.append(event).await {
+            Ok(seq) => debug!("Recorded result {} as #{}", event.message_id, seq),
+            Err(e) => {
+                warn!("Failed to record result {} in the result store: {}", event.message_id, e);
+                metrics::increment("result_store_errors_total");
+            }
+        }
+    }
+
+    /// Sign and store the result for `/attestation`. Failures are logged; the result is already published.
+    async fn store_attestation(&self, event: &VerificationResultEvent) {
+        let verification_type = match self.verification_types.by_did_id(event.did_id) {
+            Ok(spec) => spec.verification_type.clone(),
+            Err(_) => event.verification_type.clone(),
+        };
+        let now_ms = chrono::Utc::now().timestamp_millis() as u64;
+        let stored = match sign_verification_attestation(&self.keypair, event, &verification_type, now_ms) {
+            Ok(attestation) => StoredAttestation {
+                public_key: Hex::encode(self.keypair.public().as_bytes()),
+                attestation,
+            },
+            Err(e) => {
+                warn!("Failed to sign attestation for {}: {}", event.user_wallet, e);
+                return;
+            }
+        };
+        if let Err(e) = self.attestations.put(&stored).await {
+            warn!("Failed to store attestation for {}: {}", event.user_wallet, e);
+        }
+    }
+
+    /// Count the message in the lifetime totals. Failures are logged; they never fail the message.
+    async fn record_lifetime(&self, conn: &mut redis::aio::Connection, succeeded: bool) {
+        if let Err(e) = self.lifetime_stats.record(conn, succeeded).await {
+            warn!("Failed to update lifetime totals: {}", e);
+        }
+    }
+}
+
+impl MessageHandler for VerificationProcessor {
+    /// Process one message and publish its result. Returning Ok acks it at the source.
+    async fn handle(&mut self, message: &VerificationMessage) -> Result<()> {
+        let mut conn = match self.conn.take() {
+            Some(conn) => conn,
+            None => self.redis.connect().await?,
+        };
+
+        let budget = self.retry_budget.clone();
+        let timer = StageTimer::default();
+        let span = info_span!("message", id = %message.key());
+        let result = budget
+            .run(&message.key(), |deadline| self.process_verification_message(&mut conn, message, &timer, deadline))
+            .instrument(span.clone())
+            .await;
+        span.in_scope(|| info!("⏱️ Stage timings: {}", timer.summary()));
+        if let Err(e) = &result {
+            if let (true, MessagePayload::Request(request)) = (self.deferred.enabled && is_unavailable(e), &message.payload) {
+                // Park it instead of failing it; it is re-enqueued once the API is back
+                let now_ms = chrono::Utc::now().timestamp_millis() as u64;
+                let entry = DeferredEntry::from_request(&message.id, request, self.deferred.retry_after_ms(now_ms))
+                    .with_origin_stream(message.stream.as_deref());
+                self.deferred.defer(&mut conn, &entry).await?;
+                budget.clear(&message.key());
+                self.conn = Some(conn);
+                return Ok(());
+            }
+        }
+        if let Err(e) = &result {
+            if is_dead_letter_error(e) {
+                // Retrying can't help; move it aside and ack it (a failed XADD leaves it pending)
+                self.dead_letter_message(&mut conn, message, e).await?;
+                budget.clear(&message.key());
+                self.record_lifetime(&mut conn, false).await;
+                self.conn = Some(conn);
+                return Ok(());
+            }
+        }
+        if let Ok(event) = &result {
+            if event.result != "verified" && event.result != "review" {
+                metrics::increment("verification_terminal_rejections_total");
+            }
+            // Publish the result before acknowledging; delivery failures
+            // end up in the results DLQ rather than failing the message
+            self.result_publisher.publish(&mut conn, event).await;
+            self.store_attestation(event).await;
+            self.record_result(event).await;
+            self.throughput_tracker.record_message();
+        }
+        self.record_lifetime(&mut conn, result.is_ok()).await;
+
+        // Keep the connection unless Redis itself failed
+        match &result {
+            Err(e) if e.downcast_ref::<redis::RedisError>().is_some() || is_redis_timeout(e) => {}
+            _ => self.conn = Some(conn),
+        }
+        result.map(|_| ())
+    }
+
+    /// A message that panicked the processor goes straight to the DLQ; the connection it was
+    /// using went down with the panic, so this one is new.
+    async fn quarantine(&mut self, message: &VerificationMessage, reason: &str) -> Result<()> {
+        let mut conn = match self.conn.take() {
+            Some(conn) => conn,
+            None => self.redis.connect().await?,
+        };
+        self.dead_letter_message(&mut conn, message, &anyhow!("{}", reason)).await?;
+        self.retry_budget.clear(&message.key());
+        self.record_lifetime(&mut conn, false).await;
+        self.conn = Some(conn);
+        Ok(())
     }
 }
 
 // Main entry point for the verification processor
-pub async fn start_verification_processor(keypair: Ed25519KeyPair) -> Result<()> {
+pub async fn start_verification_processor(keypair: Ed25519KeyPair, result_feed: ResultFeed) -> Result<()> {
     let mut processor = VerificationProcessor::new(keypair)?;
-    processor.start_processing().await
-}
\ No newline at end of file
+    processor.result_publisher = processor.result_publisher.with_live_feed(result_feed);
+    let source = RedisStreamSource::from_env(processor.redis().clone(), processor.sealing_key.clone())?;
+    source.init().await?;
+    check_gas_balance().await?;
+    processor.sui_clock.check_at_startup().await?;
+    // Show the lifetime totals on /metrics before the first message
+    match processor.redis().connect().await {
+        Ok(mut conn) => {
+            if let Err(e) = processor.lifetime_stats.load(&mut conn).await {
+                warn!("Failed to load lifetime totals: {}", e);
+            }
+        }
+        Err(e) => warn!("Failed to load lifetime totals: {}", e),
+    }
+
+    if processor.deferred.enabled {
+        tokio::spawn(deferred::run_requeue_task(
+            processor.deferred.clone(),
+            processor.redis().clone(),
+            processor.government_api.circuit_breaker(),
+        ));
+    }
+    processor.start_processing(Arc::new(source)).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::message_source::{dispatch, RecordingSource};
+    use crate::work_queue::OverflowPolicy;
+    use fastcrypto::ed25519::Ed25519Signature;
+    use fastcrypto::traits::VerifyingKey;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// Source with an endless supply of messages, counting its fetches.
+    struct EndlessSource {
+        fetches: AtomicUsize,
+    }
+
+    impl MessageSource for EndlessSource {
+        fn name(&self) -> &str {
+            "endless"
+        }
+
+        async fn next_batch(&self, _max: usize) -> Result<Vec<VerificationMessage>> {
+            let n = self.fetches.fetch_add(1, Ordering::SeqCst);
+            sleep(Duration::from_millis(5)).await;
+            let payload = r#"{"user_wallet":"0xabc","did_id":"1","result":"verified","evidence_hash":"ab","verified_at":"2025-01-01T00:00:00Z"}"#;
+            Ok(vec![crate::message_source::parse_record_payload(n as i64, payload)?])
+        }
+
+        async fn ack(&self, _message: &VerificationMessage) -> Result<()> {
+            Ok(())
+        }
+
+        async fn nack(&self, _message: &VerificationMessage, _reason: &str) -> Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_unparseable_output_counts_as_extraction_failure() {
+        let monitor = DidExtractionMonitor::new(2);
+        let before = metrics::counter("user_did_extraction_failures_total");
+
+        assert_eq!(monitor.extract("Transaction Digest: abc\n(no created objects)"), None);
+        assert!(metrics::counter("user_did_extraction_failures_total") > before);
+        assert_eq!(monitor.consecutive_failures.load(std::sync::atomic::Ordering::SeqCst), 1);
+
+        assert_eq!(monitor.extract("garbage"), None);
+        assert_eq!(monitor.consecutive_failures.load(std::sync::atomic::Ordering::SeqCst), 2);
+
+        let output = "ObjectID: 0xabc\n ObjectType: 0x6ec::did_registry::UserDID";
+        assert_eq!(monitor.extract(output).as_deref(), Some("0xabc"));
+        assert_eq!(monitor.consecutive_failures.load(std::sync::atomic::Ordering::SeqCst), 0);
+    }
+
+    /// The real processor, configured from the (test) environment.
+    fn test_processor() -> VerificationProcessor {
+        let keypair = Ed25519KeyPair::generate(&mut rand::thread_rng());
+        let redis = RedisConnector::new("redis://localhost:6379", "default", "secret").unwrap();
+        VerificationProcessor::with_redis(keypair, redis).unwrap()
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_reverifier_shares_sui_endpoints_and_government_limits() {
+        let processor = test_processor();
+        let reverifier = processor.reverifier().unwrap();
+        assert!(Arc::ptr_eq(&processor.sui_endpoints, &reverifier.sui_endpoints));
+        assert!(Arc::ptr_eq(&processor.government_api.circuit_breaker(), &reverifier.government_api.circuit_breaker()));
+
+        // With every government call slot taken by the pipeline, the re-verifier waits too
+        let limit = processor.government_api.call_limit();
+        let mut permits = Vec::new();
+        for _ in 0..limit.max() {
+            permits.push(limit.acquire().await);
+        }
+        let shared = reverifier.government_api.call_limit();
+        assert!(tokio::time::timeout(Duration::from_secs(1), shared.acquire()).await.is_err());
+    }
+
+    /// Handles a message as far as `start_verification` on the real processor, and dead-letters
+    /// what the processor would.
+    struct StartVerificationHandler {
+        processor: VerificationProcessor,
+        dead_lettered: Vec<String>,
+    }
+
+    impl MessageHandler for StartVerificationHandler {
+        async fn handle(&mut self, _message: &VerificationMessage) -> Result<()> {
+            match self.processor.call_start_verification("0xabc", 1).await {
+                Err(e) if is_dead_letter_error(&e) => {
+                    self.dead_lettered.push(e.to_string());
+                    Ok(())
+                }
+                result => result.map(|_| ()),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_failed_start_verification_stays_pending_and_objectless_one_is_dead_lettered() {
+        let payload = r#"{"user_wallet":"0xabc","did_id":"1","result":"verified","evidence_hash":"ab","verified_at":"2025-01-01T00:00:00Z"}"#;
+        let message = crate::message_source::parse_record_payload(1, payload).unwrap();
+        let created = "ObjectID: 0xabc\n ObjectType: 0x6ec::did_registry::UserDID";
+        let objectless = serde_json::json!({ "digest": "Fq3Hd1", "objectChanges": [] }).to_string();
+        let responses = [
+            // The CLI call failed, e.g. the transaction aborted
+            serde_json::json!({ "success": false, "stdout": "", "stderr": "MoveAbort", "returncode": 1 }),
+            // It committed, but no UserDID was created
+            serde_json::json!({ "success": true, "stdout": "Transaction Digest: abc", "stderr": "", "returncode": 0 }),
+            serde_json::json!({ "success": true, "stdout": objectless, "stderr": "", "returncode": 0 }),
+            serde_json::json!({ "success": true, "stdout": created, "stderr": "", "returncode": 0 }),
+        ];
+
+        let mut outcomes = Vec::new();
+        for response in responses {
+            // The proxy answers every call with this response
+            let app = axum::Router::new().route(
+                CALL_PATH,
+                axum::routing::post(move || async move { axum::Json(response) }),
+            );
+            let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let addr = listener.local_addr().unwrap();
+            tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+            let mut processor = test_processor();
+            let endpoints = SuiEndpoints::new(vec![format!("http://{}", addr)], Duration::from_secs(60)).unwrap();
+            processor.sui_endpoints = Arc::new(endpoints);
+            let source = RecordingSource::default();
+            let mut handler = StartVerificationHandler { processor, dead_lettered: Vec::new() };
+            let mut acks = AckBatch::new(10);
+            dispatch_batched(&source, &mut handler, &message, &mut acks).await.unwrap();
+            acks.flush(&source).await.unwrap();
+            let acked = source.acked.lock().unwrap().len();
+            let nacked = source.nacked.lock().unwrap().len();
+            outcomes.push((acked, nacked, handler.dead_lettered));
+        }
+        let dead_letter = |digest: &str| {
+            vec![format!("start_verification transaction {} succeeded but created no UserDID object", digest)]
+        };
+        assert_eq!(
+            outcomes,
+            vec![(0, 1, vec![]), (1, 0, dead_letter("abc")), (1, 0, dead_letter("Fq3Hd1")), (1, 0, vec![])]
+        );
+    }
+
+    /// Decides requests with the real government client and publishes what it decided; the Sui
+    /// proxy is down throughout.
+    struct SuiDownHandler {
+        government_api: GovernmentApiClient,
+        published: Vec<(String, String)>,
+    }
+
+    impl MessageHandler for SuiDownHandler {
+        async fn handle(&mut self, message: &VerificationMessage) -> Result<()> {
+            let MessagePayload::Request(request) = &message.payload else { unreachable!() };
+            let outcome = self.government_api.process_verification_request(request, &StageTimer::default()).await?;
+            let verified = VerifiedResult {
+                user_wallet: request.user_wallet.clone(),
+                did_id: 0,
+                result: outcome.result,
+                evidence_hash: outcome.evidence.hash,
+                verified_at: chrono::Utc::now().to_rfc3339(),
+                rejection_reason: outcome.rejection_reason,
+            };
+            settle_sui_step(&verified, false, Err(anyhow!("Sui proxy unavailable")))?;
+            self.published.push((message.id.clone(), verified.result));
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_terminal_rejection_is_acked_and_recorded_but_outage_is_not() {
+        use axum::http::StatusCode;
+        use axum::response::IntoResponse;
+
+        // 422 is the API's verdict on a PAN; 503 is the API failing to give one
+        let app = axum::Router::new()
+            .route("/authenticate", axum::routing::post(|| async { r#"{"access_token":"jwt"}"# }))
+            .route(
+                "/kyc/pan/verify",
+                axum::routing::post(|axum::Json(body): axum::Json<serde_json::Value>| async move {
+                    if body["pan"] == "HJTPB9891M" {
+                        (StatusCode::UNPROCESSABLE_ENTITY, r#"{"message":"PAN is deactivated"}"#).into_response()
+                    } else {
+                        StatusCode::SERVICE_UNAVAILABLE.into_response()
+                    }
+                }),
+            );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        let mut handler = SuiDownHandler {
+            government_api: GovernmentApiClient::new()
+                .unwrap()
+                .with_endpoints(&format!("http://{}/authenticate", addr), &format!("http://{}", addr)),
+            published: Vec::new(),
+        };
+        let request = |id: &str, pan: &str| VerificationMessage {
+            id: id.to_string(),
+            payload: MessagePayload::Request(VerificationRequest {
+                user_wallet: "0xabc".to_string(),
+                did_id: "0".to_string(),
+                verification_type: "pan".to_string(),
+                document_data: serde_json::json!({
+                    "pan": pan,
+                    "name_as_per_pan": "ASHWIN BALAGURU",
+                    "date_of_birth": "27/10/2004",
+                    "consent": "Y",
+                    "reason": "KYC",
+                })
+                .to_string(),
+                extracted_data: None,
+                user_corrections: None,
+                timestamp: "2025-01-01T00:00:00Z".to_string(),
+                status: "pending".to_string(),
+            }),
+            stream: None,
+        };
+
+        let source = RecordingSource::default();
+        dispatch(&source, &mut handler, &request("1-0", "HJTPB9891M")).await.unwrap();
+        dispatch(&source, &mut handler, &request("2-0", "ABCDE1234F")).await.unwrap();
+
+        assert_eq!(handler.published, vec![("1-0".to_string(), "failed".to_string())]);
+        assert_eq!(*source.acked.lock().unwrap(), vec!["1-0".to_string()]);
+        assert_eq!(*source.nacked.lock().unwrap(), vec!["2-0".to_string()]);
+
+        // A rejection that must reach the chain still waits for Sui
+        let rejected = VerifiedResult {
+            user_wallet: "0xabc".to_string(),
+            did_id: 0,
+            result: "failed".to_string(),
+            evidence_hash: "ab".repeat(32),
+            verified_at: "2025-01-01T00:00:00Z".to_string(),
+            rejection_reason: None,
+        };
+        assert!(settle_sui_step(&rejected, true, Err(anyhow!("Sui proxy unavailable"))).is_err());
+        let review = VerifiedResult { result: "review".to_string(), ..rejected.clone() };
+        assert!(!is_terminal_rejection(&review, false));
+        assert!(settle_sui_step(&review, false, Err(anyhow!("Sui proxy unavailable"))).is_err());
+        let verified = VerifiedResult { result: "verified".to_string(), ..rejected };
+        assert!(settle_sui_step(&verified, false, Err(anyhow!("Sui proxy unavailable"))).is_err());
+    }
+
+    #[test]
+    fn test_clamped_timestamp_is_the_one_signed() {
+        let keypair = Ed25519KeyPair::generate(&mut rand::thread_rng());
+        let message = VerifiedResult {
+            user_wallet: "0xabc".to_string(),
+            did_id: 0,
+            result: "verified".to_string(),
+            evidence_hash: "ab".repeat(32),
+            verified_at: "2025-01-01T00:00:05Z".to_string(),
+            rejection_reason: None,
+        };
+        // Clamped back to the Sui clock, 5s earlier
+        let clamped_ms = parse_timestamp_to_ms("2025-01-01T00:00:00Z").unwrap();
+        let restamped = restamp(&message, clamped_ms).unwrap();
+        assert_eq!(parse_timestamp_to_ms(&restamped.verified_at).unwrap(), clamped_ms);
+        assert_eq!(VerifiedResult { verified_at: message.verified_at.clone(), ..restamped.clone() }, message);
+
+        // The signature covers the clamped time, not the original one
+        let payload = format!("0xabc:0:verified:{}:{}", "ab".repeat(32), restamped.verified_at);
+        let signature = Ed25519Signature::from_bytes(&sign_verification(&keypair, &restamped).unwrap()).unwrap();
+        assert!(keypair.public().verify(payload.as_bytes(), &signature).is_ok());
+        assert_ne!(sign_verification(&keypair, &restamped).unwrap(), sign_verification(&keypair, &message).unwrap());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_low_gas_halts_fetching_until_restored() {
+        let source = Arc::new(EndlessSource { fetches: AtomicUsize::new(0) });
+        let config = WorkQueueConfig { capacity: 4, overflow_policy: OverflowPolicy::Block };
+        let (queue_tx, mut queue_rx) = work_queue::channel("gas_gate_test_queue", &config);
+        tokio::spawn(async move { while queue_rx.recv().await.is_some() {} });
+
+        let gate = GasGate::default();
+        let fetcher = tokio::spawn(run_fetcher(source.clone(), queue_tx, gate.clone()));
+        sleep(Duration::from_millis(50)).await;
+        assert!(source.fetches.load(Ordering::SeqCst) > 0);
+
+        // Below the floor: the fetch in flight completes, then nothing more is pulled
+        assert!(gate.observe(1_000, CALL_GAS_BUDGET_MIST));
+        sleep(Duration::from_millis(20)).await;
+        let paused_at = source.fetches.load(Ordering::SeqCst);
+        sleep(Duration::from_millis(100)).await;
+        assert_eq!(source.fetches.load(Ordering::SeqCst), paused_at);
+
+        // Topped up: fetching resumes
+        assert!(!gate.observe(5 * CALL_GAS_BUDGET_MIST, CALL_GAS_BUDGET_MIST));
+        sleep(Duration::from_millis(50)).await;
+        assert!(source.fetches.load(Ordering::SeqCst) > paused_at);
+        fetcher.abort();
+    }
+}
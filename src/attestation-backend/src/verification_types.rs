@@ -0,0 +1,343 @@
+// Per-verification-type table: verification_type -> did_id -> contract DID type -> evidence schema -> decision policy
+use anyhow::{Result, anyhow};
+use serde::Deserialize;
+use std::collections::HashMap;
+use tracing::info;
+
+use crate::decision_policy::DecisionPolicy;
+use crate::government_api::VerificationRequest;
+use crate::payload::InvalidMessage;
+
+/// Evidence schemas the government API integration can produce (see [`crate::evidence::EvidenceInput`]).
+pub const KNOWN_EVIDENCE_SCHEMAS: [&str; 4] = ["pan_v3", "aadhaar_v2", "voter_id_v2", "driving_licence_v2"];
+
+/// Used when no table file is present; matches the behavior before the table existed.
+const DEFAULT_TABLE: &str = r#"
+- verification_type: pan
+  aliases: [age]
+  did_id: 0
+  contract_did_type: 1
+  evidence_schema: pan_v3
+  decision_policy: full
+- verification_type: citizenship
+  did_id: 1
+  contract_did_type: 2
+  evidence_schema: pan_v3
+  decision_policy: full
+"#;
+
+/// Everything that varies by verification type.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VerificationTypeSpec {
+    pub verification_type: String,
+    pub did_id: u8,
+    /// DID type argument of `did_registry::start_verification`.
+    pub contract_did_type: u8,
+    pub evidence_schema: String,
+    pub decision_policy: DecisionPolicy,
+}
+
+#[derive(Debug, Deserialize)]
+struct RawSpec {
+    verification_type: String,
+    #[serde(default)]
+    aliases: Vec<String>,
+    did_id: u8,
+    contract_did_type: u8,
+    evidence_schema: String,
+    decision_policy: String,
+}
+
+/// The loaded table, validated so no two types share a name, `did_id` or contract DID type.
+#[derive(Debug, Clone)]
+pub struct VerificationTypes {
+    specs: Vec<VerificationTypeSpec>,
+    /// Lowercased type name or alias -> index into `specs`.
+    by_name: HashMap<String, usize>,
+}
+
+impl VerificationTypes {
+    /// Load `VERIFICATION_TYPES_FILE` (default `verification_types.yaml`), or the built-in table if it is absent,
+    /// narrowed to `VERIFICATION_TYPES_ALLOWED` when set.
+    pub fn from_env() -> Result<Self> {
+        let path = std::env::var("VERIFICATION_TYPES_FILE").unwrap_or_else(|_| "verification_types.yaml".to_string());
+        let mut types = match std::fs::read_to_string(&path) {
+            Ok(yaml) => Self::from_yaml(&yaml).map_err(|e| anyhow!("Invalid verification types in {}: {}", path, e))?,
+            Err(_) => {
+                info!("No {} found, using built-in verification types", path);
+                Self::default_table()
+            }
+        };
+        if let Ok(allowed) = std::env::var("VERIFICATION_TYPES_ALLOWED") {
+            if !allowed.trim().is_empty() {
+                types = types.with_allowed(&allowed)?;
+            }
+        }
+        info!("Accepted verification types: {}", types.accepted().join(", "));
+        for spec in &types.specs {
+            info!(
+                "Verification type '{}': did_id={} contract_did_type={} schema={} policy={}",
+                spec.verification_type,
+                spec.did_id,
+                spec.contract_did_type,
+                spec.evidence_schema,
+                spec.decision_policy.as_str()
+            );
+        }
+        Ok(types)
+    }
+
+    pub fn default_table() -> Self {
+        Self::from_yaml(DEFAULT_TABLE).expect("built-in verification types are valid")
+    }
+
+    pub fn from_yaml(yaml: &str) -> Result<Self> {
+        let raw: Vec<RawSpec> = serde_yaml::from_str(yaml)?;
+        let mut specs = Vec::new();
+        let mut by_name = HashMap::new();
+
+        for entry in raw {
+            if let Some(other) = specs.iter().find(|s: &&VerificationTypeSpec| s.did_id == entry.did_id) {
+                return Err(anyhow!(
+                    "did_id {} is used by both '{}' and '{}'",
+                    entry.did_id, other.verification_type, entry.verification_type
+                ));
+            }
+            if let Some(other) = specs.iter().find(|s| s.contract_did_type == entry.contract_did_type) {
+                return Err(anyhow!(
+                    "contract_did_type {} is used by both '{}' and '{}'",
+                    entry.contract_did_type, other.verification_type, entry.verification_type
+                ));
+            }
+            if !KNOWN_EVIDENCE_SCHEMAS.contains(&entry.evidence_schema.as_str()) {
+                return Err(anyhow!(
+                    "Unknown evidence schema '{}' for '{}'",
+                    entry.evidence_schema, entry.verification_type
+                ));
+            }
+
+            let index = specs.len();
+            for name in std::iter::once(&entry.verification_type).chain(&entry.aliases) {
+                if by_name.insert(name.trim().to_lowercase(), index).is_some() {
+                    return Err(anyhow!("Verification type '{}' is defined twice", name));
+                }
+            }
+            specs.push(VerificationTypeSpec {
+                verification_type: entry.verification_type,
+                did_id: entry.did_id,
+                contract_did_type: entry.contract_did_type,
+                evidence_schema: entry.evidence_schema,
+                decision_policy: DecisionPolicy::parse(&entry.decision_policy)?,
+            });
+        }
+
+        Ok(Self { specs, by_name })
+    }
+
+    /// Accept only the comma separated type names and aliases in `allowed`; the rest of the
+    /// table stays defined but is rejected like an unknown type. Naming a type the table
+    /// doesn't define is an error, so a typo can't silently disable everything.
+    pub fn with_allowed(mut self, allowed: &str) -> Result<Self> {
+        let allowed: Vec<String> = allowed
+            .split(',')
+            .map(|name| name.trim().to_lowercase())
+            .filter(|name| !name.is_empty())
+            .collect();
+        if let Some(unknown) = allowed.iter().find(|name| !self.by_name.contains_key(*name)) {
+            return Err(anyhow!("VERIFICATION_TYPES_ALLOWED names '{}', which is not in the verification type table", unknown));
+        }
+        self.by_name.retain(|name, _| allowed.contains(name));
+        Ok(self)
+    }
+
+    /// Every accepted type name and alias, sorted.
+    pub fn accepted(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = self.by_name.keys().map(String::as_str).collect();
+        names.sort_unstable();
+        names
+    }
+
+    /// The spec for a `verification_type` (or one of its aliases). Anything else is an
+    /// [`InvalidMessage`] naming the value, never a fallback to some default type.
+    pub fn get(&self, verification_type: &str) -> Result<&VerificationTypeSpec> {
+        self.by_name
+            .get(&verification_type.trim().to_lowercase())
+            .map(|&index| &self.specs[index])
+            .ok_or_else(|| {
+                InvalidMessage {
+                    reason: format!(
+                        "Unsupported verification_type '{}' (accepted: {})",
+                        verification_type,
+                        self.accepted().join(", ")
+                    ),
+                }
+                .into()
+            })
+    }
+
+    /// The spec for a request's `verification_type`, checking its `did_id` agrees with the table.
+    /// Any mismatch is an [`InvalidMessage`]: the request can never succeed as sent.
+    pub fn for_request(&self, request: &VerificationRequest) -> Result<&VerificationTypeSpec> {
+        let spec = self.get(&request.verification_type)?;
+        let did_id = parse_did_id(&request.did_id)?;
+        if did_id != spec.did_id {
+            return Err(InvalidMessage {
+                reason: format!(
+                    "did_id {} does not match verification type '{}' (expected {})",
+                    did_id, request.verification_type, spec.did_id
+                ),
+            }
+            .into());
+        }
+        Ok(spec)
+    }
+
+    /// The spec with `did_id`, if its type is accepted; a type left out by [`Self::with_allowed`]
+    /// is rejected like an unknown id.
+    pub fn by_did_id(&self, did_id: u8) -> Result<&VerificationTypeSpec> {
+        self.specs
+            .iter()
+            .position(|s| s.did_id == did_id)
+            .filter(|index| self.by_name.values().any(|accepted| accepted == index))
+            .map(|index| &self.specs[index])
+            .ok_or_else(|| InvalidMessage { reason: format!("Unknown DID ID: {}", did_id) }.into())
+    }
+
+    /// Every type name and alias with its decision policy.
+    pub fn policies(&self) -> impl Iterator<Item = (&str, DecisionPolicy)> + '_ {
+        self.by_name
+            .iter()
+            .map(|(name, &index)| (name.as_str(), self.specs[index].decision_policy))
+    }
+}
+
+/// A `did_id` as sent in a request; anything but an integer in 0..=255 is rejected, never defaulted.
+pub fn parse_did_id(did_id: &str) -> Result<u8, InvalidMessage> {
+    did_id.trim().parse::<u8>().map_err(|_| InvalidMessage {
+        reason: format!("did_id '{}' is not an integer in 0..=255", did_id),
+    })
+}
+
+/// Length of a Sui address: `0x` and 32 bytes of hex.
+pub const SUI_ADDRESS_LEN: usize = 66;
+
+/// A wallet as sent in a request must be a full Sui address, `0x` followed by 64 hex digits.
+/// Checked on ingestion, so a malformed address never costs a government API call.
+pub fn validate_sui_address(address: &str) -> Result<(), InvalidMessage> {
+    let problem = match address.strip_prefix("0x") {
+        None => "does not start with 0x",
+        Some(_) if address.len() != SUI_ADDRESS_LEN => "is not 64 hex digits after 0x",
+        Some(digits) if !digits.bytes().all(|b| b.is_ascii_hexdigit()) => "is not hex",
+        Some(_) => return Ok(()),
+    };
+    Err(InvalidMessage { reason: format!("wallet address '{}' {}", address, problem) })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_table_resolves_types_and_aliases() {
+        let types = VerificationTypes::default_table();
+        assert_eq!(types.get("PAN").unwrap().contract_did_type, 1);
+        assert_eq!(types.get("age").unwrap().did_id, 0);
+        assert_eq!(types.by_did_id(1).unwrap().verification_type, "citizenship");
+        assert!(types.get("passport").is_err());
+        assert!(types.by_did_id(7).is_err());
+    }
+
+    #[test]
+    fn test_malformed_did_id_is_an_invalid_message() {
+        assert!(parse_did_id("abc").is_err());
+        assert!(parse_did_id("999").is_err());
+        assert_eq!(parse_did_id(" 1 "), Ok(1));
+
+        let types = VerificationTypes::default_table();
+        let request = |did_id: &str| VerificationRequest {
+            user_wallet: "0xabc".to_string(),
+            did_id: did_id.to_string(),
+            verification_type: "pan".to_string(),
+            document_data: "{}".to_string(),
+            extracted_data: None,
+            user_corrections: None,
+            timestamp: "0".to_string(),
+            status: "pending".to_string(),
+        };
+        for bad in ["abc", "999", "1"] {
+            let err = types.for_request(&request(bad)).unwrap_err();
+            assert!(crate::payload::is_invalid_message(&err), "{}: {}", bad, err);
+        }
+        assert_eq!(types.for_request(&request("0")).unwrap().verification_type, "pan");
+    }
+
+    #[test]
+    fn test_sui_addresses_are_validated() {
+        let valid = format!("0x{}", "a1B2".repeat(16));
+        assert_eq!(validate_sui_address(&valid), Ok(()));
+
+        let invalid = [
+            (String::new(), "does not start with 0x"),
+            ("a1b2".repeat(16), "does not start with 0x"),
+            ("0xabc".to_string(), "64 hex digits"),
+            (format!("0x{}", "a".repeat(63)), "64 hex digits"),
+            (format!("0x{}", "a".repeat(65)), "64 hex digits"),
+            (format!(" 0x{}", "a".repeat(64)), "does not start with 0x"),
+            (format!("0x{}g", "a".repeat(63)), "is not hex"),
+            // 66 bytes, but not 66 characters
+            (format!("0x{}é", "a".repeat(62)), "is not hex"),
+        ];
+        for (address, problem) in invalid {
+            let err = validate_sui_address(&address).unwrap_err();
+            assert!(err.reason.contains(problem), "{:?}: {}", address, err);
+        }
+    }
+
+    #[test]
+    fn test_duplicate_did_id_is_rejected_at_load() {
+        let yaml = r#"
+- verification_type: pan
+  did_id: 0
+  contract_did_type: 1
+  evidence_schema: pan_v3
+  decision_policy: full
+- verification_type: age
+  did_id: 0
+  contract_did_type: 2
+  evidence_schema: pan_v3
+  decision_policy: dob_only
+"#;
+        let err = VerificationTypes::from_yaml(yaml).unwrap_err().to_string();
+        assert!(err.contains("did_id 0"), "{}", err);
+
+        let unknown_schema = yaml.replace(
+            "did_id: 0\n  contract_did_type: 2\n  evidence_schema: pan_v3",
+            "did_id: 1\n  contract_did_type: 2\n  evidence_schema: passport_v1",
+        );
+        assert!(VerificationTypes::from_yaml(&unknown_schema).unwrap_err().to_string().contains("passport_v1"));
+    }
+
+    #[test]
+    fn test_only_accepted_verification_types_pass() {
+        let types = VerificationTypes::default_table();
+        assert_eq!(types.accepted(), vec!["age", "citizenship", "pan"]);
+        assert_eq!(types.get(" Citizenship ").unwrap().did_id, 1);
+
+        // A producer typo is an invalid message naming what was received, so it goes to the DLQ
+        let err = types.get("citizenshp").unwrap_err();
+        assert!(crate::payload::is_invalid_message(&err));
+        assert!(err.to_string().contains("'citizenshp' (accepted: age, citizenship, pan)"), "{}", err);
+
+        // Narrowed by configuration: defined but not allowed is rejected the same way
+        let narrowed = VerificationTypes::default_table().with_allowed("pan, CITIZENSHIP").unwrap();
+        assert_eq!(narrowed.accepted(), vec!["citizenship", "pan"]);
+        assert!(crate::payload::is_invalid_message(&narrowed.get("age").unwrap_err()));
+        assert_eq!(narrowed.by_did_id(1).unwrap().verification_type, "citizenship");
+
+        // A type allowed by neither its name nor an alias is refused by did_id too
+        let pan_only = VerificationTypes::default_table().with_allowed("pan").unwrap();
+        assert_eq!(pan_only.by_did_id(0).unwrap().verification_type, "pan");
+        assert!(crate::payload::is_invalid_message(&pan_only.by_did_id(1).unwrap_err()));
+        assert!(VerificationTypes::default_table().with_allowed("pan,passport").is_err());
+    }
+}
@@ -0,0 +1,186 @@
+// Server-side check of an attestation we signed, for integrators without Ed25519/BCS verification
+use anyhow::{Result, anyhow};
+use axum::extract::State;
+use axum::Json;
+use fastcrypto::ed25519::Ed25519PublicKey;
+use fastcrypto::encoding::{Encoding, Hex};
+use fastcrypto::traits::{KeyPair, ToFromBytes};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+use crate::api_error::ApiJson;
+use crate::app::KYCResponse;
+use crate::attestation_store::VerificationAttestation;
+use crate::common::{
+    key_id, verify_signed_response, IntentMessage, IntentScope, ProcessedDataResponse, SkewWindow, TimestampError,
+};
+use crate::heartbeat::Heartbeat;
+use crate::lag_alert::LagAlert;
+use crate::metrics;
+use crate::negative_attestation::NegativeAttestation;
+use crate::{AppState, EnclaveError};
+
+/// Public keys of earlier boots whose attestations are still accepted, from
+/// `ATTESTATION_RETIRED_KEYS` (comma separated hex). There is no rotation inside a process:
+/// each boot has its own key (or the sealed one), so the previous keys are listed here.
+pub fn retired_keys_from_env() -> Result<Vec<Ed25519PublicKey>> {
+    let Ok(keys) = std::env::var("ATTESTATION_RETIRED_KEYS") else { return Ok(Vec::new()) };
+    keys.split(',')
+        .map(str::trim)
+        .filter(|key| !key.is_empty())
+        .map(|key| {
+            Hex::decode(key.trim_start_matches("0x"))
+                .ok()
+                .and_then(|bytes| Ed25519PublicKey::from_bytes(&bytes).ok())
+                .ok_or_else(|| anyhow!("Invalid retired public key '{}'", key))
+        })
+        .collect()
+}
+
+/// Where the signed timestamp falls relative to the [`SkewWindow`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Freshness {
+    Fresh,
+    TooOld,
+    InFuture,
+}
+
+/// Result of `/verify_attestation`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AttestationCheck {
+    /// The signature is by the current key or a retired one.
+    pub valid: bool,
+    pub intent: IntentScope,
+    /// Key id of the key that signed it, when `valid`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub key_id: Option<String>,
+    /// Whether that key is the one this enclave holds now.
+    pub current_key: bool,
+    pub freshness: Freshness,
+}
+
+/// Re-type `data` as the payload `T` signed under its intent and try every accepted key.
+fn check_as<T: Serialize + DeserializeOwned>(
+    signed: &ProcessedDataResponse<IntentMessage<serde_json::Value>>,
+    keys: &[&Ed25519PublicKey],
+) -> Result<Option<usize>, EnclaveError> {
+    let data: T = serde_json::from_value(signed.response.data.clone()).map_err(|e| {
+        EnclaveError::InvalidBody(format!("data is not a {:?} payload: {}", signed.response.intent, e))
+    })?;
+    let typed = ProcessedDataResponse {
+        response: IntentMessage {
+            intent: signed.response.intent,
+            timestamp_ms: signed.response.timestamp_ms,
+            data,
+        },
+        signature: signed.signature.clone(),
+        request_id: None,
+    };
+    Ok(keys.iter().position(|pk| verify_signed_response(pk, &typed).is_ok()))
+}
+
+/// Endpoint `/verify_attestation`: whether a signed response from this service is genuine, by
+/// which key, and whether it is still fresh. A bad signature is a 200 with `valid: false`; only
+/// a body that isn't a signed payload of a known kind is an error.
+pub async fn verify_attestation(
+    State(state): State<Arc<AppState>>,
+    ApiJson(signed): ApiJson<ProcessedDataResponse<IntentMessage<serde_json::Value>>>,
+) -> Result<Json<AttestationCheck>, EnclaveError> {
+    let keys: Vec<&Ed25519PublicKey> = std::iter::once(state.eph_kp.public()).chain(&state.retired_keys).collect();
+    let intent = signed.response.intent;
+    let signer = match intent {
+        IntentScope::KYCVerification => check_as::<KYCResponse>(&signed, &keys)?,
+        IntentScope::NegativeVerification => check_as::<NegativeAttestation>(&signed, &keys)?,
+        IntentScope::Heartbeat => check_as::<Heartbeat>(&signed, &keys)?,
+        IntentScope::VerificationResult => check_as::<VerificationAttestation>(&signed, &keys)?,
+        IntentScope::LagAlert => check_as::<LagAlert>(&signed, &keys)?,
+        IntentScope::Generic | IntentScope::BatchVerification => {
+            return Err(EnclaveError::InvalidBody(format!("{:?} payloads can't be checked here", intent)));
+        }
+    };
+    metrics::increment(if signer.is_some() { "attestation_checks_valid_total" } else { "attestation_checks_invalid_total" });
+
+    let now_ms = chrono::Utc::now().timestamp_millis() as u64;
+    let freshness = match SkewWindow::from_env().check(signed.response.timestamp_ms, now_ms) {
+        Ok(()) => Freshness::Fresh,
+        Err(TimestampError::TooOld { .. }) => Freshness::TooOld,
+        Err(TimestampError::InFuture { .. }) => Freshness::InFuture,
+    };
+    Ok(Json(AttestationCheck {
+        valid: signer.is_some(),
+        intent,
+        key_id: signer.map(|index| key_id(keys[index])),
+        current_key: signer == Some(0),
+        freshness,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::negative_attestation::sign_negative_attestation;
+    use axum::body::{to_bytes, Body};
+    use axum::http::{Request, StatusCode};
+    use axum::routing::post;
+    use axum::Router;
+    use fastcrypto::ed25519::Ed25519KeyPair;
+    use tower::Service;
+
+    async fn check(app: &mut Router, body: &serde_json::Value) -> (StatusCode, serde_json::Value) {
+        let request = Request::post("/verify_attestation")
+            .header("content-type", "application/json")
+            .body(Body::from(body.to_string()))
+            .unwrap();
+        let response = app.call(request).await.unwrap();
+        let status = response.status();
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (status, serde_json::from_slice(&body).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_genuine_attestation_verifies_and_tampered_one_fails() {
+        let retired = Ed25519KeyPair::generate(&mut rand::thread_rng());
+        let mut state = AppState::new(Ed25519KeyPair::generate(&mut rand::thread_rng()));
+        state.retired_keys = vec![retired.public().clone()];
+        let state = Arc::new(state);
+        let mut app = Router::new().route("/verify_attestation", post(verify_attestation)).with_state(state.clone());
+
+        let now_ms = chrono::Utc::now().timestamp_millis() as u64;
+        let sign = |kp: &Ed25519KeyPair, timestamp_ms: u64| {
+            let evidence_hash = "ab".repeat(32);
+            let signed =
+                sign_negative_attestation(kp, "0xabc", 0, "PAN mismatch", &evidence_hash, "2025-01-01T00:00:00Z", timestamp_ms);
+            serde_json::to_value(signed.unwrap()).unwrap()
+        };
+
+        let (status, genuine) = check(&mut app, &sign(&state.eph_kp, now_ms)).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(genuine["valid"], true);
+        assert_eq!(genuine["current_key"], true);
+        assert_eq!(genuine["key_id"], key_id(state.eph_kp.public()));
+        assert_eq!(genuine["freshness"], "fresh");
+
+        // Point it at another wallet
+        let mut tampered = sign(&state.eph_kp, now_ms);
+        tampered["response"]["data"]["user_wallet"] = "0xdef".into();
+        let (status, tampered) = check(&mut app, &tampered).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(tampered["valid"], false);
+        assert!(tampered.get("key_id").is_none());
+
+        // Signed by the previous boot's key, a day ago
+        let (_, old) = check(&mut app, &sign(&retired, now_ms - 24 * 3600 * 1000)).await;
+        assert_eq!((old["valid"].clone(), old["current_key"].clone()), (true.into(), false.into()));
+        assert_eq!(old["key_id"], key_id(retired.public()));
+        assert_eq!(old["freshness"], "too_old");
+
+        // An unknown key, and data that isn't the intent's payload
+        let (_, stranger) = check(&mut app, &sign(&Ed25519KeyPair::generate(&mut rand::thread_rng()), now_ms)).await;
+        assert_eq!(stranger["valid"], false);
+        let mut malformed = sign(&state.eph_kp, now_ms);
+        malformed["response"]["data"] = serde_json::json!({ "unexpected": 1 });
+        assert_eq!(check(&mut app, &malformed).await.0, StatusCode::BAD_REQUEST);
+    }
+}
@@ -0,0 +1,193 @@
+// Bounded in-memory queue between the Redis fetch stage and the execute stage
+use anyhow::{Result, anyhow};
+use tokio::sync::mpsc;
+use tracing::info;
+
+use crate::metrics;
+
+/// What the fetch stage does when the queue has no free slot for an entry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OverflowPolicy {
+    /// Wait until the execute stage drains a slot (backpressure). Default.
+    Block,
+    /// Hand the entry back to the caller, which leaves it pending in Redis to be reclaimed
+    /// once idle (`REDIS_RECLAIM_MIN_IDLE_MS`) and offered to the queue again.
+    Reject,
+}
+
+impl OverflowPolicy {
+    pub fn parse(value: &str) -> Result<Self> {
+        match value.trim().to_lowercase().as_str() {
+            "block" => Ok(OverflowPolicy::Block),
+            "reject" => Ok(OverflowPolicy::Reject),
+            other => Err(anyhow!("Unknown worker queue overflow policy: {}", other)),
+        }
+    }
+}
+
+/// Queue settings, read from `WORKER_QUEUE_CAPACITY` and `WORKER_QUEUE_OVERFLOW_POLICY`.
+#[derive(Debug, Clone)]
+pub struct WorkQueueConfig {
+    pub capacity: usize,
+    pub overflow_policy: OverflowPolicy,
+}
+
+impl WorkQueueConfig {
+    pub fn from_env() -> Result<Self> {
+        let capacity = std::env::var("WORKER_QUEUE_CAPACITY")
+            .ok()
+            .and_then(|v| v.parse::<usize>().ok())
+            .unwrap_or(32)
+            .max(1);
+        let overflow_policy = match std::env::var("WORKER_QUEUE_OVERFLOW_POLICY") {
+            Ok(value) => OverflowPolicy::parse(&value)?,
+            Err(_) => OverflowPolicy::Block,
+        };
+        info!("Worker queue: capacity={} overflow_policy={:?}", capacity, overflow_policy);
+
+        Ok(Self {
+            capacity,
+            overflow_policy,
+        })
+    }
+}
+
+/// Create a bounded queue. Its depth is exported as the `{name}_depth` gauge.
+pub fn channel<T>(name: &str, config: &WorkQueueConfig) -> (WorkQueueSender<T>, WorkQueueReceiver<T>) {
+    let (tx, rx) = mpsc::channel(config.capacity);
+    metrics::set_gauge(&format!("{}_capacity", name), config.capacity as f64);
+    metrics::set_gauge(&format!("{}_depth", name), 0.0);
+
+    (
+        WorkQueueSender {
+            tx,
+            policy: config.overflow_policy,
+            name: name.to_string(),
+        },
+        WorkQueueReceiver {
+            rx,
+            name: name.to_string(),
+        },
+    )
+}
+
+pub struct WorkQueueSender<T> {
+    tx: mpsc::Sender<T>,
+    policy: OverflowPolicy,
+    name: String,
+}
+
+impl<T> WorkQueueSender<T> {
+    /// Number of entries currently queued.
+    pub fn depth(&self) -> usize {
+        self.tx.max_capacity() - self.tx.capacity()
+    }
+
+    /// Wait (without polling) until at least one slot is free and return the number of free slots.
+    /// The fetch stage calls this before reading so it never pulls more than it can queue.
+    pub async fn wait_for_capacity(&self) -> Result<usize> {
+        if self.tx.capacity() == 0 {
+            // Reserving and immediately releasing a permit parks us until a slot frees up
+            let permit = self.tx.reserve().await
+                .map_err(|_| anyhow!("{} closed", self.name))?;
+            drop(permit);
+        }
+        Ok(self.tx.capacity())
+    }
+
+    /// Queue an entry according to the overflow policy. Under `Reject` a full queue
+    /// hands the entry back so the caller can leave it unacked.
+    pub async fn push(&self, item: T) -> std::result::Result<(), T> {
+        let result = match self.policy {
+            OverflowPolicy::Block => self.tx.send(item).await.map_err(|e| e.0),
+            OverflowPolicy::Reject => self.tx.try_send(item).map_err(|e| match e {
+                mpsc::error::TrySendError::Full(item) => {
+                    metrics::increment(&format!("{}_rejected_total", self.name));
+                    item
+                }
+                mpsc::error::TrySendError::Closed(item) => item,
+            }),
+        };
+        metrics::set_gauge(&format!("{}_depth", self.name), self.depth() as f64);
+        result
+    }
+}
+
+pub struct WorkQueueReceiver<T> {
+    rx: mpsc::Receiver<T>,
+    name: String,
+}
+
+impl<T> WorkQueueReceiver<T> {
+    pub async fn recv(&mut self) -> Option<T> {
+        let item = self.rx.recv().await;
+        metrics::set_gauge(&format!("{}_depth", self.name), self.rx.len() as f64);
+        item
+    }
+
+    /// The next item if one is already waiting, without waiting for one.
+    pub fn try_recv(&mut self) -> Option<T> {
+        let item = self.rx.try_recv().ok()?;
+        metrics::set_gauge(&format!("{}_depth", self.name), self.rx.len() as f64);
+        Some(item)
+    }
+
+    /// Whether nothing is waiting to be received.
+    pub fn is_empty(&self) -> bool {
+        self.rx.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::time::{Duration, timeout};
+
+    fn config(capacity: usize, overflow_policy: OverflowPolicy) -> WorkQueueConfig {
+        WorkQueueConfig {
+            capacity,
+            overflow_policy,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_full_queue_halts_fetching_until_drained() {
+        let (tx, mut rx) = channel::<u32>("test_block_queue", &config(2, OverflowPolicy::Block));
+
+        assert_eq!(tx.wait_for_capacity().await.unwrap(), 2);
+        tx.push(1).await.unwrap();
+        tx.push(2).await.unwrap();
+        assert_eq!(tx.depth(), 2);
+        assert_eq!(metrics::gauge("test_block_queue_depth"), Some(2.0));
+
+        // Fetcher must park while the queue is full
+        assert!(timeout(Duration::from_millis(50), tx.wait_for_capacity()).await.is_err());
+
+        // Draining one entry lets the fetcher resume
+        assert_eq!(rx.recv().await, Some(1));
+        let free = timeout(Duration::from_millis(50), tx.wait_for_capacity()).await
+            .expect("fetcher should resume after drain")
+            .unwrap();
+        assert_eq!(free, 1);
+        assert_eq!(metrics::gauge("test_block_queue_depth"), Some(1.0));
+    }
+
+    #[tokio::test]
+    async fn test_reject_policy_hands_back_entry_when_full() {
+        let (tx, mut rx) = channel::<u32>("test_reject_queue", &config(1, OverflowPolicy::Reject));
+
+        tx.push(1).await.unwrap();
+        assert_eq!(tx.push(2).await, Err(2));
+        assert_eq!(metrics::counter("test_reject_queue_rejected_total"), 1);
+
+        assert_eq!(rx.recv().await, Some(1));
+        assert!(tx.push(3).await.is_ok());
+    }
+
+    #[test]
+    fn test_overflow_policy_parse() {
+        assert_eq!(OverflowPolicy::parse("Block").unwrap(), OverflowPolicy::Block);
+        assert_eq!(OverflowPolicy::parse("reject").unwrap(), OverflowPolicy::Reject);
+        assert!(OverflowPolicy::parse("drop").is_err());
+    }
+}
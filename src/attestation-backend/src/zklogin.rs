@@ -1,5 +1,6 @@
 /*
 // zklogin.rs - COMMENTED OUT - No longer using zkLogin functionality
+use crate::api_error::ApiError;
 use crate::AppState;
 use axum::extract::State;
 use axum::http::StatusCode;
@@ -124,11 +125,12 @@ fn get_enoki_token() -> Result<String, &'static str> {
 // Helper function to create error responses with logging
 fn error_response(status: StatusCode, message: &str) -> Response {
     error!("Request failed: {}", message);
-    let body = Json(json!({
-        "error": message,
-        "status": status.as_u16()
-    }));
-    (status, body).into_response()
+    let code = match status {
+        StatusCode::BAD_REQUEST => "bad_request",
+        StatusCode::BAD_GATEWAY => "upstream_unavailable",
+        _ => "internal_error",
+    };
+    ApiError::new(status, code, message).into_response()
 }
 
 /// Generate user salt using HKDF derivation
@@ -44,8 +44,10 @@ def get_active_address():
 def get_gas():
     """Get gas coins"""
     try:
-        result = subprocess.run(['sui', 'client', 'gas'], 
-                              capture_output=True, text=True, timeout=10)
+        cmd = ['sui', 'client', 'gas']
+        if request.args.get('json'):
+            cmd.append('--json')
+        result = subprocess.run(cmd, capture_output=True, text=True, timeout=10)
         return jsonify({
             'success': result.returncode == 0,
             'stdout': result.stdout.strip(),
@@ -56,6 +58,27 @@ def get_gas():
         logger.error(f"Error getting gas: {e}")
         return jsonify({'success': False, 'error': str(e)}), 500
 
+@app.route('/sui/client/object', methods=['GET'])
+def get_object():
+    """Read an object (e.g. the Clock at 0x6)"""
+    try:
+        object_id = request.args.get('id')
+        if not object_id:
+            return jsonify({'success': False, 'error': 'id is required'}), 400
+        cmd = ['sui', 'client', 'object', object_id]
+        if request.args.get('json'):
+            cmd.append('--json')
+        result = subprocess.run(cmd, capture_output=True, text=True, timeout=10)
+        return jsonify({
+            'success': result.returncode == 0,
+            'stdout': result.stdout.strip(),
+            'stderr': result.stderr.strip(),
+            'returncode': result.returncode
+        })
+    except Exception as e:
+        logger.error(f"Error getting object: {e}")
+        return jsonify({'success': False, 'error': str(e)}), 500
+
 @app.route('/sui/client/call', methods=['POST'])
 def call_contract():
     """Execute a contract call"""
@@ -67,6 +90,7 @@ def call_contract():
         args = data.get('args', [])
         type_args = data.get('type_args', [])
         gas_budget = data.get('gas_budget', '10000000')
+        gas = data.get('gas')
         
         # Build sui client call command
         cmd = ['sui', 'client', 'call', 
@@ -75,6 +99,24 @@ def call_contract():
                '--function', function,
                '--gas-budget', gas_budget]
         
+        # Pin the gas coin when the caller manages a coin pool
+        if gas:
+            cmd.extend(['--gas', gas])
+        
+        # Sponsored transaction: another sender, gas owned by the sponsor
+        if data.get('sender'):
+            cmd.extend(['--sender', data['sender']])
+        if data.get('gas_sponsor'):
+            cmd.extend(['--gas-sponsor', data['gas_sponsor']])
+        
+        # Print the unsigned transaction bytes (base64) instead of executing
+        if data.get('serialize_unsigned'):
+            cmd.append('--serialize-unsigned-transaction')
+        
+        # Estimate effects and gas cost without executing
+        if data.get('dry_run'):
+            cmd.extend(['--dry-run', '--json'])
+        
         # Add type arguments if provided
         for type_arg in type_args:
             cmd.extend(['--type-args', type_arg])
@@ -99,6 +141,45 @@ def call_contract():
         logger.error(f"Error executing contract call: {e}")
         return jsonify({'success': False, 'error': str(e)}), 500
 
+@app.route('/sui/keytool/sign', methods=['POST'])
+def keytool_sign():
+    """Sign base64 transaction bytes with a keystore address (e.g. as gas sponsor)"""
+    try:
+        data = request.json
+        cmd = ['sui', 'keytool', 'sign', '--address', data.get('address'), '--data', data.get('data'), '--json']
+        result = subprocess.run(cmd, capture_output=True, text=True, timeout=10)
+        return jsonify({
+            'success': result.returncode == 0,
+            'stdout': result.stdout.strip(),
+            'stderr': result.stderr.strip(),
+            'returncode': result.returncode
+        })
+    except Exception as e:
+        logger.error(f"Error signing transaction: {e}")
+        return jsonify({'success': False, 'error': str(e)}), 500
+
+@app.route('/sui/client/execute-signed-tx', methods=['POST'])
+def execute_signed_tx():
+    """Execute transaction bytes with every required signature (sender and sponsor)"""
+    try:
+        data = request.json
+        cmd = ['sui', 'client', 'execute-signed-tx', '--tx-bytes', data.get('tx_bytes')]
+        for signature in data.get('signatures', []):
+            cmd.extend(['--signatures', signature])
+        
+        logger.info("Executing signed transaction")
+        
+        result = subprocess.run(cmd, capture_output=True, text=True, timeout=30)
+        return jsonify({
+            'success': result.returncode == 0,
+            'stdout': result.stdout.strip(),
+            'stderr': result.stderr.strip(),
+            'returncode': result.returncode
+        })
+    except Exception as e:
+        logger.error(f"Error executing signed transaction: {e}")
+        return jsonify({'success': False, 'error': str(e)}), 500
+
 @app.route('/sui/client/ptb', methods=['POST'])
 def execute_ptb():
     """Execute a Programmable Transaction Block"""